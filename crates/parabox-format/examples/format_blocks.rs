@@ -4,7 +4,7 @@ use anstyle::{AnsiColor, RgbColor, Style};
 use parabox::{BlockKey, World};
 use parabox_format::{Formatter, MetaFmt};
 use parabox_parser::{Executor, MetaTable, StringSource};
-use std::rc::Rc;
+use std::sync::Arc;
 
 const SCRIPT: &str = r#"
 DEFINE BOX #container size (5, 5)
@@ -52,7 +52,7 @@ impl MetaFmt for Wrapper<'_> {
 fn main() {
     let mut executor = Executor::new();
     let source = StringSource::new(SCRIPT.to_string());
-    executor.push_source(Rc::new(source)).unwrap();
+    executor.push_source(Arc::new(source)).unwrap();
     executor.run_all().unwrap();
     let (world, meta) = executor.take();
 
@@ -3,9 +3,8 @@ extern crate core;
 use anstyle::{RgbColor, Style};
 use parabox::{BlockKey, ProtoType, World};
 use parabox_format::{Formatter, MetaFmt};
-use parabox_parser::{Executor, MetaTable, ParseResult, StringSource};
+use parabox_parser::{Executor, MetaTable, ParseResult};
 use std::io::Write;
-use std::rc::Rc;
 
 fn brighten(color: RgbColor) -> RgbColor {
     let factor = 1.5;
@@ -30,15 +29,12 @@ impl Wrapper {
     }
 
     fn execute(&mut self, text: &str) -> ParseResult<()> {
-        let source = StringSource::new(text.to_string());
-        self.executor.push_source(Rc::new(source))?;
+        self.executor.push_line(text)?;
         self.executor.run_all()?;
 
-        for (key, block) in self.executor.world().blocks() {
-            if block.proto.is_hollow() {
-                if !self.names.contains(&key) {
-                    self.names.push(key);
-                }
+        for (key, block) in self.executor.world().blocks_ordered() {
+            if block.proto.is_hollow() && !self.names.contains(&key) {
+                self.names.push(key);
             }
         }
 
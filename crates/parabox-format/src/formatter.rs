@@ -1,6 +1,6 @@
 use crate::matrix::{Cell, Matrix, MatrixBuilder};
 use anstyle::{Color, Style};
-use parabox::{BlockKey, World};
+use parabox::{BlockKey, Orientation, World};
 use slotmap::SlotMap;
 use std::collections::HashMap;
 
@@ -90,7 +90,13 @@ where
         let interior = &block.state.interior;
         let (width, height) = block.proto.size();
 
-        for y in (0..height).rev() {
+        // Print the rows from top to bottom.
+        let rows: Vec<usize> = match self.world.orientation() {
+            Orientation::YUp => (0..height).rev().collect(),
+            Orientation::YDown => (0..height).collect(),
+        };
+
+        for y in rows {
             builder.push_newline();
 
             if y == height / 2 {
@@ -101,8 +107,8 @@ where
                 builder.push_space();
             }
 
-            for x in 0..width {
-                if let Some(cell) = interior[x][y] {
+            for column in interior.iter().take(width) {
+                if let Some(cell) = column[y] {
                     builder.push_cell(self.format_cell(cell));
                 } else {
                     builder.push_cell(Cell::new(' ', Style::new().bg_color(self.background)));
@@ -115,8 +121,7 @@ where
 
     pub fn format(&self, space_between: usize) -> Matrix {
         self.world
-            .blocks()
-            .iter()
+            .blocks_ordered()
             .filter(|(_, block)| block.proto.is_hollow())
             .map(|(key, _)| self.format_block(key))
             .reduce(|matrix1, matrix2| matrix1 + Matrix::new(space_between, 0) + matrix2)
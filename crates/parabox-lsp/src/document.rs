@@ -0,0 +1,386 @@
+use ecow::EcoString;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, Diagnostic, DiagnosticSeverity, NumberOrString, Position,
+    Range,
+};
+use parabox_parser::{
+    parse_ast, Argument, Budget, Executor, ParseError, Source, Span, Spanned, Statement,
+    StatementKind, StringSource, KEYWORDS,
+};
+use std::sync::Arc;
+use std::time::Duration;
+
+/// An open script, analyzed once for the requests of the editor.
+pub struct Document {
+    source: Arc<dyn Source>,
+    /// The diagnostics of the parsing and the execution.
+    diagnostics: Vec<Diagnostic>,
+    /// The identifiers, with the definitions first.
+    idents: Vec<Ident>,
+    /// The blocks of the world after the execution, with their prototypes.
+    blocks: Vec<(EcoString, String)>,
+}
+
+/// An identifier in the script.
+struct Ident {
+    name: EcoString,
+    span: Span,
+    /// The text defining the block if the identifier is a definition, e.g.
+    /// `DEFINE BOX #room SIZE (3, 3)`.
+    definition: Option<EcoString>,
+}
+
+impl Document {
+    /// Parses and executes the text of a script.
+    pub fn new(text: String) -> Self {
+        let source: Arc<dyn Source> = Arc::new(StringSource::new(text));
+        let (statements, _) = parse_ast(source.clone());
+        let mut idents = vec![];
+        collect_idents(&statements, &mut idents);
+        idents.sort_by_key(|ident| ident.definition.is_none());
+
+        let mut executor = Executor::new();
+        executor.set_output(|_| {});
+        // The document is analyzed on each change, so that a script pushing
+        // blocks for a long time must not block the editor.
+        executor.set_budget(Budget {
+            timeout: Some(Duration::from_secs(1)),
+            ..Budget::default()
+        });
+        let mut diagnostics = vec![];
+        match executor.push_source_all(source.clone()) {
+            Ok(()) => {
+                for warning in executor.warnings() {
+                    diagnostics.push(diagnostic(
+                        &warning.span(),
+                        DiagnosticSeverity::WARNING,
+                        None,
+                        warning.message(),
+                    ));
+                }
+                if let Err(error) = executor.run_all() {
+                    diagnostics.push(error_diagnostic(&error.into()));
+                }
+            }
+            Err(errors) => diagnostics.extend(errors.iter().map(error_diagnostic)),
+        }
+
+        let blocks = executor
+            .to_level()
+            .blocks
+            .into_iter()
+            .map(|block| (block.name, block.proto.to_string()))
+            .collect();
+
+        Self {
+            source,
+            diagnostics,
+            idents,
+            blocks,
+        }
+    }
+
+    /// Returns the errors and the warnings of the script.
+    pub fn diagnostics(&self) -> Vec<Diagnostic> {
+        self.diagnostics.clone()
+    }
+
+    /// Returns the range of the definition of the identifier at the position,
+    /// i.e. its `DEFINE`, its `MAP` block, its `COPY` or its `WORLD CREATE`.
+    pub fn definition(&self, position: Position) -> Option<Range> {
+        let offset = offset(&*self.source, position)?;
+        let ident = self.idents.iter().find(|ident| {
+            let range = ident.span.range();
+            range.start <= offset && offset <= range.end
+        })?;
+        self.idents
+            .iter()
+            .find(|other| other.definition.is_some() && other.name == ident.name)
+            .map(|definition| range(&definition.span))
+    }
+
+    /// Returns the completions at the position, i.e. the names of the blocks
+    /// after `#`, or the keywords.
+    pub fn completion(&self, position: Position) -> Vec<CompletionItem> {
+        let Some(offset) = offset(&*self.source, position) else {
+            return vec![];
+        };
+        let (line, column) = self.source.locate(offset);
+        let before = &self.source.line(line).unwrap_or_default()[..column];
+        let word = before.trim_end_matches(|c: char| c.is_alphanumeric() || c == '_' || c == ':');
+
+        if word.ends_with('#') {
+            // The blocks defined in the script are known even if the script
+            // cannot be executed, e.g. while writing a command.
+            let mut names: Vec<_> = self.blocks.clone();
+            for ident in &self.idents {
+                if let Some(definition) = &ident.definition {
+                    if !names.iter().any(|(name, _)| *name == ident.name) {
+                        names.push((ident.name.clone(), definition.to_string()));
+                    }
+                }
+            }
+            names.sort();
+            names
+                .into_iter()
+                .map(|(name, detail)| CompletionItem {
+                    label: name.into(),
+                    kind: Some(CompletionItemKind::VARIABLE),
+                    detail: Some(detail),
+                    ..Default::default()
+                })
+                .collect()
+        } else if word.ends_with('$') {
+            vec![]
+        } else {
+            KEYWORDS
+                .iter()
+                .map(|keyword| CompletionItem {
+                    label: keyword.to_uppercase(),
+                    kind: Some(CompletionItemKind::KEYWORD),
+                    ..Default::default()
+                })
+                .collect()
+        }
+    }
+}
+
+/// Collects the identifiers of the statements, marking those defined by
+/// `DEFINE`, `MAP`, `COPY` and `WORLD CREATE`.
+fn collect_idents(statements: &[Statement], idents: &mut Vec<Ident>) {
+    for statement in statements {
+        let mut arguments: Vec<_> = statement.arguments().iter().collect();
+        for property in statement.properties() {
+            arguments.extend(property.arguments());
+        }
+        let defined = match statement.kind() {
+            // The block follows the prototype, e.g. `DEFINE BOX #room`.
+            StatementKind::Define => statement
+                .properties()
+                .first()
+                .and_then(|property| property.arguments().first()),
+            StatementKind::Map => statement.arguments().first(),
+            StatementKind::Copy => statement
+                .property("as")
+                .and_then(|property| property.arguments().first()),
+            StatementKind::World => statement
+                .property("create")
+                .and_then(|property| property.arguments().first()),
+            _ => None,
+        };
+        let text = statement.span().text().lines().next().unwrap_or_default();
+        for argument in arguments {
+            collect_argument(argument, defined.map(|defined| (defined, text)), idents);
+        }
+
+        // The legend of a map defines the blocks with identifiers, e.g.
+        // `b = BOX #box`.
+        let legend = statement.kind() == StatementKind::Map;
+        for line in statement.lines() {
+            let definition = (legend && line.value().contains('=')).then(|| line.value().clone());
+            idents.extend(line_idents(line).into_iter().map(|(name, span)| Ident {
+                name,
+                span,
+                definition: definition.clone(),
+            }));
+        }
+
+        collect_idents(statement.body(), idents);
+    }
+}
+
+fn collect_argument(
+    argument: &Spanned<Argument>,
+    defined: Option<(&Spanned<Argument>, &str)>,
+    idents: &mut Vec<Ident>,
+) {
+    match argument.value() {
+        Argument::Ident(name) => idents.push(Ident {
+            name: name.clone(),
+            span: argument.span().clone(),
+            definition: defined
+                .filter(|(defined, _)| std::ptr::eq(*defined, argument))
+                .map(|(_, text)| text.into()),
+        }),
+        Argument::Tuple(elements) => {
+            for element in elements {
+                collect_argument(element, None, idents);
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Returns the identifiers written in a line of a `MAP` block, without `#`.
+fn line_idents(line: &Spanned<EcoString>) -> Vec<(EcoString, Span)> {
+    let span = line.span();
+    let start = span.range().start;
+    let text = line.value().as_str();
+
+    let mut idents = vec![];
+    let mut chars = text.char_indices().peekable();
+    while let Some((index, c)) = chars.next() {
+        if c != '#' {
+            continue;
+        }
+        let mut end = index + 1;
+        while let Some(&(next, c)) = chars.peek() {
+            if !(c.is_alphanumeric() || c == '_' || c == ':') {
+                break;
+            }
+            end = next + c.len_utf8();
+            chars.next();
+        }
+        if end > index + 1 {
+            let range = (start + index)..(start + end);
+            idents.push((text[index + 1..end].into(), Span::new(span.source(), range)));
+        }
+    }
+    idents
+}
+
+fn error_diagnostic(error: &ParseError) -> Diagnostic {
+    diagnostic(
+        &error.span(),
+        DiagnosticSeverity::ERROR,
+        Some(error.kind().code()),
+        error.message(),
+    )
+}
+
+fn diagnostic(
+    span: &Span,
+    severity: DiagnosticSeverity,
+    code: Option<&str>,
+    message: EcoString,
+) -> Diagnostic {
+    Diagnostic {
+        range: range(span),
+        severity: Some(severity),
+        code: code.map(|code| NumberOrString::String(code.into())),
+        source: Some("parabox".into()),
+        message: message.into(),
+        ..Default::default()
+    }
+}
+
+/// Converts a span to a range, whose characters are counted in UTF-16.
+fn range(span: &Span) -> Range {
+    let source = span.source();
+    let range = span.range();
+    Range::new(
+        position(&*source, range.start),
+        position(&*source, range.end),
+    )
+}
+
+fn position(source: &dyn Source, offset: usize) -> Position {
+    let (line, column) = source.locate(offset);
+    let text = source.line(line).unwrap_or_default();
+    let character = text[..column.min(text.len())].encode_utf16().count();
+    Position::new(line as u32, character as u32)
+}
+
+/// Converts a position to the offset in the source.
+fn offset(source: &dyn Source, position: Position) -> Option<usize> {
+    let line = position.line as usize;
+    let range = source.line_range(line)?;
+    let text = source.line(line)?;
+
+    let mut units = 0;
+    for (index, c) in text.char_indices() {
+        if units >= position.character as usize {
+            return Some(range.start + index);
+        }
+        units += c.len_utf16();
+    }
+    Some(range.end)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SCRIPT: &str = "define box #room size (3, 1)\n\
+                          define box #box size (1, 1)\n\
+                          place #box at (0, 0) in #room\n\
+                          push #box east\n\
+                          map #map\n\
+                          \x20 wx\n\
+                          \x20 w = wall #wall\n\
+                          \x20 x = wall\n\
+                          end\n\
+                          expect #wall at (0, 0) in #map";
+
+    #[test]
+    fn test_diagnostics() {
+        let document = Document::new(SCRIPT.to_string());
+        assert!(document.diagnostics().is_empty());
+
+        let document = Document::new("define box #room size (3, 1)\nundo $x\nfoo".to_string());
+        let diagnostics = document.diagnostics();
+        assert_eq!(diagnostics.len(), 2);
+        assert_eq!(diagnostics[0].message, "undefined variable `$x`");
+        assert_eq!(
+            diagnostics[0].range,
+            Range::new(Position::new(1, 5), Position::new(1, 7))
+        );
+        assert_eq!(
+            diagnostics[1].code,
+            Some(NumberOrString::String("unknown-keyword".into()))
+        );
+
+        let document = Document::new(
+            "define box #room size (3, 1)\nexpect #room at (0, 0) in #room".to_string(),
+        );
+        let diagnostics = document.diagnostics();
+        assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostics[0].range.start.line, 1);
+    }
+
+    #[test]
+    fn test_definition() {
+        let document = Document::new(SCRIPT.to_string());
+        // `#room` of the `PLACE`.
+        let definition = document.definition(Position::new(2, 26)).unwrap();
+        assert_eq!(
+            definition,
+            Range::new(Position::new(0, 11), Position::new(0, 16))
+        );
+        // `#wall` and `#map` of the `EXPECT`, defined by the map.
+        let definition = document.definition(Position::new(9, 8)).unwrap();
+        assert_eq!(
+            definition,
+            Range::new(Position::new(6, 11), Position::new(6, 16))
+        );
+        let definition = document.definition(Position::new(9, 28)).unwrap();
+        assert_eq!(
+            definition,
+            Range::new(Position::new(4, 4), Position::new(4, 8))
+        );
+        assert!(document.definition(Position::new(3, 0)).is_none());
+    }
+
+    #[test]
+    fn test_completion() {
+        let document = Document::new(SCRIPT.to_string());
+        let items = document.completion(Position::new(3, 6));
+        let labels: Vec<_> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["box", "map", "map_1_0", "room", "wall"]);
+        assert_eq!(items[0].detail.as_deref(), Some("BOX size (1, 1)"));
+
+        // The script cannot be parsed while writing the identifier.
+        let document = Document::new(format!("{}\npush #", SCRIPT));
+        let items = document.completion(Position::new(10, 6));
+        let labels: Vec<_> = items.iter().map(|item| item.label.as_str()).collect();
+        assert_eq!(labels, vec!["box", "map", "room", "wall"]);
+        assert_eq!(
+            items[0].detail.as_deref(),
+            Some("define box #box size (1, 1)")
+        );
+        assert_eq!(items[3].detail.as_deref(), Some("w = wall #wall"));
+
+        let items = document.completion(Position::new(10, 2));
+        assert!(items.iter().any(|item| item.label == "PUSH"));
+    }
+}
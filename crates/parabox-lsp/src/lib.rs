@@ -0,0 +1,20 @@
+//! The language server for the Parabox script.
+//!
+//! # Overview
+//!
+//! The server speaks the Language Server Protocol over the standard input
+//! and output, see [run]. For each open script, it provides
+//! - _Diagnostics_ of the parse errors, the execution errors and the warnings.
+//! - _Go to definition_ of the `#identifiers`, to their `DEFINE` or `MAP`.
+//! - _Completion_ of the keywords, and of the block names after `#`.
+//!
+//! The analysis of a script is done by [Document], with the syntax tree of
+//! [`parabox_parser::parse_ast`] and the blocks of the executed world.
+
+#![warn(missing_docs)]
+
+mod document;
+mod server;
+
+pub use document::Document;
+pub use server::run;
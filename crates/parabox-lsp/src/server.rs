@@ -0,0 +1,201 @@
+use crate::document::Document;
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument,
+    Notification as NotificationTrait, PublishDiagnostics,
+};
+use lsp_types::request::{Completion, GotoDefinition, Request as RequestTrait};
+use lsp_types::{
+    CompletionOptions, CompletionParams, CompletionResponse, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, GotoDefinitionParams,
+    GotoDefinitionResponse, Location, OneOf, PublishDiagnosticsParams, ServerCapabilities,
+    TextDocumentSyncCapability, TextDocumentSyncKind, Url,
+};
+use std::collections::HashMap;
+use std::error::Error;
+
+/// Runs the language server on the connection until the client exits, e.g.
+/// on [Connection::stdio].
+pub fn run(connection: Connection) -> Result<(), Box<dyn Error + Send + Sync>> {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Kind(TextDocumentSyncKind::FULL)),
+        definition_provider: Some(OneOf::Left(true)),
+        completion_provider: Some(CompletionOptions {
+            trigger_characters: Some(vec!["#".into()]),
+            ..Default::default()
+        }),
+        ..Default::default()
+    };
+    connection.initialize(serde_json::to_value(capabilities)?)?;
+
+    let mut documents = HashMap::new();
+    for message in &connection.receiver {
+        match message {
+            Message::Request(request) => {
+                if connection.handle_shutdown(&request)? {
+                    return Ok(());
+                }
+                let response = respond(&documents, request);
+                connection.sender.send(Message::Response(response))?;
+            }
+            Message::Notification(notification) => {
+                if let Some(params) = update(&mut documents, notification)? {
+                    connection
+                        .sender
+                        .send(Message::Notification(Notification::new(
+                            PublishDiagnostics::METHOD.into(),
+                            params,
+                        )))?;
+                }
+            }
+            Message::Response(_) => {}
+        }
+    }
+
+    Ok(())
+}
+
+/// Responds to a request on the documents.
+fn respond(documents: &HashMap<Url, Document>, request: Request) -> Response {
+    let id = request.id.clone();
+    let result = match request.method.as_str() {
+        GotoDefinition::METHOD => {
+            serde_json::from_value(request.params).map(|params: GotoDefinitionParams| {
+                let params = params.text_document_position_params;
+                let uri = params.text_document.uri;
+                let range = documents
+                    .get(&uri)
+                    .and_then(|document| document.definition(params.position));
+                let response =
+                    range.map(|range| GotoDefinitionResponse::Scalar(Location::new(uri, range)));
+                serde_json::to_value(response).unwrap()
+            })
+        }
+        Completion::METHOD => {
+            serde_json::from_value(request.params).map(|params: CompletionParams| {
+                let params = params.text_document_position;
+                let items = documents
+                    .get(&params.text_document.uri)
+                    .map(|document| document.completion(params.position));
+                serde_json::to_value(items.map(CompletionResponse::Array)).unwrap()
+            })
+        }
+        method => {
+            return Response::new_err(
+                id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request `{}`", method),
+            );
+        }
+    };
+
+    match result {
+        Ok(result) => Response::new_ok(id, result),
+        Err(error) => Response::new_err(id, ErrorCode::InvalidParams as i32, error.to_string()),
+    }
+}
+
+/// Updates the documents on a notification. Returns the diagnostics to
+/// publish if a document is changed.
+fn update(
+    documents: &mut HashMap<Url, Document>,
+    notification: Notification,
+) -> Result<Option<PublishDiagnosticsParams>, serde_json::Error> {
+    let (uri, document) = match notification.method.as_str() {
+        DidOpenTextDocument::METHOD => {
+            let params: DidOpenTextDocumentParams = serde_json::from_value(notification.params)?;
+            let document = params.text_document;
+            (document.uri, Some(Document::new(document.text)))
+        }
+        DidChangeTextDocument::METHOD => {
+            let params: DidChangeTextDocumentParams = serde_json::from_value(notification.params)?;
+            // The whole text is sent on each change, see the capabilities.
+            let Some(change) = params.content_changes.into_iter().last() else {
+                return Ok(None);
+            };
+            (params.text_document.uri, Some(Document::new(change.text)))
+        }
+        DidCloseTextDocument::METHOD => {
+            let params: DidCloseTextDocumentParams = serde_json::from_value(notification.params)?;
+            (params.text_document.uri, None)
+        }
+        _ => return Ok(None),
+    };
+
+    let diagnostics = document
+        .as_ref()
+        .map(Document::diagnostics)
+        .unwrap_or_default();
+    match document {
+        Some(document) => documents.insert(uri.clone(), document),
+        None => documents.remove(&uri),
+    };
+    Ok(Some(PublishDiagnosticsParams::new(uri, diagnostics, None)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use lsp_server::RequestId;
+    use lsp_types::notification::{Exit, Initialized};
+    use lsp_types::request::{Initialize, Shutdown};
+    use lsp_types::{InitializeParams, Position, TextDocumentItem};
+    use serde_json::json;
+
+    fn request(client: &Connection, id: i32, method: &str, params: serde_json::Value) -> Response {
+        let request = Request::new(RequestId::from(id), method.into(), params);
+        client.sender.send(request.into()).unwrap();
+        match client.receiver.recv().unwrap() {
+            Message::Response(response) => response,
+            message => panic!("unexpected message {:?}", message),
+        }
+    }
+
+    fn notify(client: &Connection, method: &str, params: impl serde::Serialize) {
+        let notification = Notification::new(method.into(), params);
+        client.sender.send(notification.into()).unwrap();
+    }
+
+    #[test]
+    fn test_server() {
+        let (server, client) = Connection::memory();
+        let thread = std::thread::spawn(move || run(server).unwrap());
+
+        let params = serde_json::to_value(InitializeParams::default()).unwrap();
+        let response = request(&client, 1, Initialize::METHOD, params);
+        assert!(
+            response.result.unwrap()["capabilities"]["definitionProvider"]
+                .as_bool()
+                .unwrap()
+        );
+        notify(&client, Initialized::METHOD, json!({}));
+
+        let uri = Url::parse("file:///level.pbs").unwrap();
+        let text = "define box #room size (3, 1)\nexpect #room at (0, 0) in #room";
+        let params = DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(uri.clone(), "pbs".into(), 0, text.into()),
+        };
+        notify(&client, DidOpenTextDocument::METHOD, params);
+        let Message::Notification(notification) = client.receiver.recv().unwrap() else {
+            panic!("expected diagnostics");
+        };
+        let params: PublishDiagnosticsParams = serde_json::from_value(notification.params).unwrap();
+        assert_eq!(params.uri, uri);
+        assert_eq!(params.diagnostics.len(), 1);
+
+        let params = json!({
+            "textDocument": { "uri": uri },
+            "position": Position::new(1, 9),
+        });
+        let response = request(&client, 2, GotoDefinition::METHOD, params);
+        let location: Location = serde_json::from_value(response.result.unwrap()).unwrap();
+        assert_eq!(location.range.start, Position::new(0, 11));
+
+        let response = request(&client, 3, "parabox/unknown", json!({}));
+        assert!(response.error.is_some());
+
+        request(&client, 4, Shutdown::METHOD, json!(null));
+        notify(&client, Exit::METHOD, json!(null));
+        thread.join().unwrap();
+    }
+}
@@ -56,7 +56,7 @@ pub fn scan_tests(attr: TokenStream, item: TokenStream) -> TokenStream {
     let func_block = &func.block;
 
     let functions = names.iter().zip(contents.iter()).map(|(name, content)| {
-        let name = syn::Ident::new(&name, proc_macro2::Span::call_site());
+        let name = syn::Ident::new(name, proc_macro2::Span::call_site());
         let content = syn::LitStr::new(content, proc_macro2::Span::call_site());
         quote! {
             #( #func_attrs )*
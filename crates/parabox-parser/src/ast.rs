@@ -0,0 +1,406 @@
+use crate::error::Span;
+use crate::kind::SyntaxKind;
+use ecow::EcoString;
+
+/// A node of the syntax tree with its span.
+#[derive(Clone, Debug)]
+pub struct Spanned<T> {
+    value: T,
+    span: Span,
+}
+
+impl<T> Spanned<T> {
+    pub(crate) fn new(value: T, span: Span) -> Self {
+        Self { value, span }
+    }
+
+    /// Returns the value of the node.
+    pub fn value(&self) -> &T {
+        &self.value
+    }
+
+    /// Returns the span of the node.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+/// The kind of a [Statement], given by its keyword.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum StatementKind {
+    Define,
+    Place,
+    Push,
+    Player,
+    Move,
+    Expect,
+    Undo,
+    Checkpoint,
+    Restore,
+    Let,
+    Goal,
+    Show,
+    List,
+    Where,
+    Info,
+    Seed,
+    Macro,
+    Template,
+    Call,
+    Spawn,
+    Copy,
+    World,
+    Version,
+    /// The `MAP` block of a grid, not the `MAP` of `EXPECT MAP`.
+    Map,
+}
+
+impl StatementKind {
+    /// Returns the kind of the statement started by the token, if it is a
+    /// statement keyword.
+    pub(crate) fn from_syntax(kind: SyntaxKind) -> Option<Self> {
+        match kind {
+            SyntaxKind::Define => Some(StatementKind::Define),
+            SyntaxKind::Place => Some(StatementKind::Place),
+            SyntaxKind::Push => Some(StatementKind::Push),
+            SyntaxKind::Player => Some(StatementKind::Player),
+            SyntaxKind::Move => Some(StatementKind::Move),
+            SyntaxKind::Expect => Some(StatementKind::Expect),
+            SyntaxKind::Undo => Some(StatementKind::Undo),
+            SyntaxKind::Checkpoint => Some(StatementKind::Checkpoint),
+            SyntaxKind::Restore => Some(StatementKind::Restore),
+            SyntaxKind::Let => Some(StatementKind::Let),
+            SyntaxKind::Goal => Some(StatementKind::Goal),
+            SyntaxKind::Show => Some(StatementKind::Show),
+            SyntaxKind::List => Some(StatementKind::List),
+            SyntaxKind::Where => Some(StatementKind::Where),
+            SyntaxKind::Info => Some(StatementKind::Info),
+            SyntaxKind::Seed => Some(StatementKind::Seed),
+            SyntaxKind::Macro => Some(StatementKind::Macro),
+            SyntaxKind::Template => Some(StatementKind::Template),
+            SyntaxKind::Call => Some(StatementKind::Call),
+            SyntaxKind::Spawn => Some(StatementKind::Spawn),
+            SyntaxKind::Copy => Some(StatementKind::Copy),
+            SyntaxKind::World => Some(StatementKind::World),
+            SyntaxKind::Version => Some(StatementKind::Version),
+            SyntaxKind::Map => Some(StatementKind::Map),
+            _ => None,
+        }
+    }
+
+    /// Returns the keyword of the statement, in uppercase, e.g. `DEFINE`.
+    pub fn keyword(self) -> &'static str {
+        match self {
+            StatementKind::Define => "DEFINE",
+            StatementKind::Place => "PLACE",
+            StatementKind::Push => "PUSH",
+            StatementKind::Player => "PLAYER",
+            StatementKind::Move => "MOVE",
+            StatementKind::Expect => "EXPECT",
+            StatementKind::Undo => "UNDO",
+            StatementKind::Checkpoint => "CHECKPOINT",
+            StatementKind::Restore => "RESTORE",
+            StatementKind::Let => "LET",
+            StatementKind::Goal => "GOAL",
+            StatementKind::Show => "SHOW",
+            StatementKind::List => "LIST",
+            StatementKind::Where => "WHERE",
+            StatementKind::Info => "INFO",
+            StatementKind::Seed => "SEED",
+            StatementKind::Macro => "MACRO",
+            StatementKind::Template => "TEMPLATE",
+            StatementKind::Call => "CALL",
+            StatementKind::Spawn => "SPAWN",
+            StatementKind::Copy => "COPY",
+            StatementKind::World => "WORLD",
+            StatementKind::Version => "VERSION",
+            StatementKind::Map => "MAP",
+        }
+    }
+
+    /// Returns whether the statement reads the following lines until `END`,
+    /// i.e. `MAP`, `MACRO` and `TEMPLATE`.
+    pub fn is_block(self) -> bool {
+        matches!(
+            self,
+            StatementKind::Map | StatementKind::Macro | StatementKind::Template
+        )
+    }
+
+    /// Returns whether the statement is followed by a name instead of an
+    /// argument, e.g. `CHECKPOINT start`.
+    pub(crate) fn is_named(self) -> bool {
+        matches!(
+            self,
+            StatementKind::Macro
+                | StatementKind::Template
+                | StatementKind::Call
+                | StatementKind::Spawn
+                | StatementKind::Checkpoint
+                | StatementKind::Restore
+        )
+    }
+}
+
+/// A statement of a script, e.g. `DEFINE BOX #room SIZE (3, 3)`.
+///
+/// A statement is made up of its keyword, the arguments following the keyword,
+/// e.g. `#room` of `PUSH #room EAST`, and the properties, each started by a
+/// keyword, e.g. `BOX #room` and `SIZE (3, 3)`.
+#[derive(Clone, Debug)]
+pub struct Statement {
+    pub(crate) kind: StatementKind,
+    pub(crate) keyword: Spanned<EcoString>,
+    pub(crate) arguments: Vec<Spanned<Argument>>,
+    pub(crate) properties: Vec<Property>,
+    pub(crate) lines: Vec<Spanned<EcoString>>,
+    pub(crate) body: Vec<Statement>,
+    pub(crate) span: Span,
+}
+
+impl Statement {
+    /// Returns the kind of the statement.
+    pub fn kind(&self) -> StatementKind {
+        self.kind
+    }
+
+    /// Returns the keyword of the statement, in uppercase, e.g. `DEFINE`.
+    pub fn keyword(&self) -> &Spanned<EcoString> {
+        &self.keyword
+    }
+
+    /// Returns the arguments between the keyword and the first property.
+    pub fn arguments(&self) -> &[Spanned<Argument>] {
+        &self.arguments
+    }
+
+    /// Returns the properties, in order.
+    pub fn properties(&self) -> &[Property] {
+        &self.properties
+    }
+
+    /// Returns the first property started by the keyword, which is
+    /// case-insensitive.
+    pub fn property(&self, keyword: &str) -> Option<&Property> {
+        self.properties
+            .iter()
+            .find(|property| property.keyword.value.eq_ignore_ascii_case(keyword))
+    }
+
+    /// Returns the lines of a `MAP` block, or the rows of an `EXPECT MAP`,
+    /// trimmed.
+    pub fn lines(&self) -> &[Spanned<EcoString>] {
+        &self.lines
+    }
+
+    /// Returns the statements of the body of a `MACRO` or a `TEMPLATE`.
+    pub fn body(&self) -> &[Statement] {
+        &self.body
+    }
+
+    /// Returns the span of the statement, up to `END` of a block.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+
+    /// Extends the span to the end.
+    pub(crate) fn extend(&mut self, end: usize) {
+        self.span = Span::new(self.span.source(), self.span.range().start..end);
+    }
+}
+
+/// A property of a [Statement], e.g. `SIZE (3, 3)`.
+#[derive(Clone, Debug)]
+pub struct Property {
+    /// The token of the keyword.
+    pub(crate) kind: SyntaxKind,
+    pub(crate) keyword: Spanned<EcoString>,
+    pub(crate) arguments: Vec<Spanned<Argument>>,
+    pub(crate) span: Span,
+}
+
+impl Property {
+    /// Returns the keyword of the property, in uppercase, e.g. `SIZE`.
+    pub fn keyword(&self) -> &Spanned<EcoString> {
+        &self.keyword
+    }
+
+    /// Returns the arguments of the property.
+    pub fn arguments(&self) -> &[Spanned<Argument>] {
+        &self.arguments
+    }
+
+    /// Returns the span of the property, from the keyword to the last
+    /// argument.
+    pub fn span(&self) -> &Span {
+        &self.span
+    }
+}
+
+/// An argument of a [Statement] or a [Property].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Argument {
+    /// An identifier, without `#`.
+    Ident(EcoString),
+    /// A variable, without `$`.
+    Variable(EcoString),
+    /// A name of a macro, a template or a checkpoint.
+    Name(EcoString),
+    /// An integer.
+    Integer(usize),
+    /// An integer expression as written, e.g. `$x + 1`.
+    Expression(EcoString),
+    /// A character, e.g. of `CHAR`.
+    Char(char),
+    /// A string without the quotes, e.g. of `ELSE`.
+    String(EcoString),
+    /// A tuple, e.g. a size tuple, or the parameters or the arguments of a
+    /// macro.
+    Tuple(Vec<Spanned<Argument>>),
+}
+
+/// Compares the arguments only, ignoring the spans.
+impl PartialEq for Spanned<Argument> {
+    fn eq(&self, other: &Self) -> bool {
+        self.value == other.value
+    }
+}
+
+impl Eq for Spanned<Argument> {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::{ErrorKind, ParseError};
+    use crate::parser::parse_ast;
+    use crate::source::StringSource;
+    use std::sync::Arc;
+
+    fn parse(text: &str) -> (Vec<Statement>, Vec<ParseError>) {
+        parse_ast(Arc::new(StringSource::new(text.to_string())))
+    }
+
+    fn texts(arguments: &[Spanned<Argument>]) -> Vec<&str> {
+        arguments
+            .iter()
+            .map(|argument| argument.span().text())
+            .collect()
+    }
+
+    #[test]
+    fn test_statement() {
+        let (statements, errors) =
+            parse("define box #room size (3, $x + 1) solid; push #room east  // push");
+        assert!(errors.is_empty());
+        assert_eq!(statements.len(), 2);
+
+        let define = &statements[0];
+        assert_eq!(define.kind(), StatementKind::Define);
+        assert_eq!(define.keyword().value(), "DEFINE");
+        assert_eq!(
+            define.span().text(),
+            "define box #room size (3, $x + 1) solid"
+        );
+        let keywords: Vec<_> = define
+            .properties()
+            .iter()
+            .map(|property| property.keyword().value().as_str())
+            .collect();
+        assert_eq!(keywords, vec!["BOX", "SIZE", "SOLID"]);
+        assert_eq!(
+            define.property("box").unwrap().arguments()[0].value(),
+            &Argument::Ident("room".into())
+        );
+
+        let size = define.property("size").unwrap();
+        assert_eq!(size.span().text(), "size (3, $x + 1)");
+        assert_eq!(size.span().locate(), (0, 17));
+        let Argument::Tuple(elements) = size.arguments()[0].value() else {
+            panic!("expected tuple");
+        };
+        assert_eq!(texts(elements), vec!["3", "$x + 1"]);
+        assert_eq!(elements[1].value(), &Argument::Expression("$x + 1".into()));
+
+        let push = &statements[1];
+        assert_eq!(texts(push.arguments()), vec!["#room"]);
+        assert_eq!(push.span().text(), "push #room east");
+    }
+
+    #[test]
+    fn test_else() {
+        let (statements, errors) = parse("expect #box orphan else \"not \\\"orphan\\\"\"");
+        assert!(errors.is_empty());
+
+        let message = statements[0].property("else").unwrap();
+        assert_eq!(message.span().text(), "else \"not \\\"orphan\\\"\"");
+        assert_eq!(
+            message.arguments()[0].value(),
+            &Argument::String("not \"orphan\"".into())
+        );
+    }
+
+    #[test]
+    fn test_blocks() {
+        let (statements, errors) = parse(
+            "macro room($size)\n  define box #room size $size\nend\n\
+             call room((2, 2))\nmap #map\n  w.\n  w = wall\nend\n\
+             expect map #map\n  w.\nundo (1 + 1)",
+        );
+        assert!(errors.is_empty());
+        assert_eq!(statements.len(), 5);
+
+        let macro_ = &statements[0];
+        assert_eq!(
+            macro_.arguments()[0].value(),
+            &Argument::Name("room".into())
+        );
+        assert_eq!(texts(macro_.arguments()), vec!["room", "($size)"]);
+        assert_eq!(macro_.body().len(), 1);
+        assert_eq!(
+            macro_.body()[0].span().text(),
+            "define box #room size $size"
+        );
+        assert_eq!(macro_.span().lines(), 0..3);
+
+        let Argument::Tuple(arguments) = statements[1].arguments()[1].value() else {
+            panic!("expected tuple");
+        };
+        assert_eq!(texts(arguments), vec!["(2, 2)"]);
+
+        let lines: Vec<_> = statements[2]
+            .lines()
+            .iter()
+            .map(|line| line.value().as_str())
+            .collect();
+        assert_eq!(lines, vec!["w.", "w = wall"]);
+        assert_eq!(statements[2].kind(), StatementKind::Map);
+        assert_eq!(statements[3].kind(), StatementKind::Expect);
+        assert_eq!(statements[3].lines()[0].span().text(), "w.");
+        assert_eq!(
+            statements[4].arguments()[0].value(),
+            &Argument::Expression("(1 + 1)".into())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        let (statements, errors) = parse(
+            "define box #room size (3, 3\n#room\nbox #room\npush #room east\ntemplate room()",
+        );
+        assert_eq!(statements.len(), 2);
+        let messages: Vec<_> = errors.iter().map(|error| error.message()).collect();
+        assert_eq!(
+            messages,
+            vec![
+                "expected `)`",
+                "expected statement keyword",
+                "expected statement keyword",
+                "missing `end` of template `room`"
+            ]
+        );
+        assert_eq!(errors[2].span().text(), "box");
+        assert_eq!(errors[3].kind(), ErrorKind::Unterminated);
+    }
+}
@@ -0,0 +1,29 @@
+//! Writes a random valid script to the standard output, e.g. to fuzz the
+//! executor. See [parabox_parser::generate_script].
+
+use parabox_parser::{generate_script, GenerateOptions};
+use std::process::ExitCode;
+
+const USAGE: &str =
+    "usage: parabox-generate [--seed <n>] [--blocks <n>] [--pushes <n>] [--max-size <n>]";
+
+fn main() -> ExitCode {
+    let mut options = GenerateOptions::default();
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let value = args.next().and_then(|value| value.parse::<u64>().ok());
+        match (arg.as_str(), value) {
+            ("--seed", Some(value)) => options.seed = value,
+            ("--blocks", Some(value)) => options.blocks = value as usize,
+            ("--pushes", Some(value)) => options.pushes = value as usize,
+            ("--max-size", Some(value)) => options.max_size = value as usize,
+            _ => {
+                eprintln!("{}", USAGE);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    print!("{}", generate_script(options));
+    ExitCode::SUCCESS
+}
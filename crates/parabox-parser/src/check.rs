@@ -0,0 +1,361 @@
+use crate::command::{Command, MetaPosition, MetaProtoType, Operation};
+use crate::error::{ErrorKind, ParseError};
+use crate::executor::Executor;
+use crate::meta::MetaTable;
+use crate::suggest::did_you_mean;
+use ecow::EcoString;
+use parabox::{Size, World};
+use std::collections::{HashMap, HashSet};
+
+impl Executor {
+    /// Checks the commands to execute without executing them. Returns the
+    /// errors found, in the order of the commands.
+    ///
+    /// The world is not changed and no push is simulated, so that a script is
+    /// checked much faster than it is run. The blocks are checked to be defined
+    /// before they are referred to, and the positions to be inside the sizes of
+    /// their containers. The blocks of the world are known, e.g. those defined
+    /// by the executed commands.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// let source = StringSource::new("DEFINE BOX #room size (2, 2)\nPUSH #box east".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    ///
+    /// let errors = executor.check();
+    /// assert_eq!(errors[0].message(), "unresolved reference to box");
+    /// ```
+    pub fn check(&self) -> Vec<ParseError> {
+        let (sizes, containers) = blocks(self.world(), self.meta());
+        let mut checker = Checker {
+            sizes,
+            containers,
+            checkpoints: self.checkpoint_names().cloned().collect(),
+            world: self.world_name().clone(),
+            worlds: self
+                .aside_worlds()
+                .map(|(name, world, meta)| (name.clone(), blocks(world, meta)))
+                .collect(),
+        };
+
+        self.queued()
+            .filter_map(|command| {
+                let (kind, message) = checker.check(command.command()).err()?;
+                Some(ParseError::new(command.span().clone(), message.into()).with_kind(kind))
+            })
+            .collect()
+    }
+}
+
+/// Returns the sizes of the interiors and the containers of the blocks named
+/// in the meta table.
+fn blocks(world: &World, meta: &MetaTable) -> Blocks {
+    let mut sizes = HashMap::new();
+    let mut containers = HashMap::new();
+    for (name, key) in meta.iter() {
+        let state = &world[key].state;
+        let size = (
+            state.interior.len(),
+            state.interior.first().map_or(0, Vec::len),
+        );
+        if let Some(container) = state.position.container {
+            if let Some(container) = meta.get_name(&container) {
+                containers.insert(name.clone(), container);
+            }
+        }
+        sizes.insert(name, size);
+    }
+
+    (sizes, containers)
+}
+
+/// The sizes of the interiors and the containers of the blocks of a world.
+type Blocks = (HashMap<EcoString, Size>, HashMap<EcoString, EcoString>);
+
+/// The result of checking a command, with the kind and the message of the
+/// error.
+type CheckResult<T> = Result<T, (ErrorKind, String)>;
+
+/// The blocks and the checkpoints known when checking the commands in order.
+struct Checker {
+    /// The sizes of the interiors of the blocks.
+    sizes: HashMap<EcoString, Size>,
+    /// The containers of the blocks placed by the commands, without the pushes.
+    containers: HashMap<EcoString, EcoString>,
+    checkpoints: HashSet<EcoString>,
+    /// The name of the used world.
+    world: EcoString,
+    /// The blocks of the worlds kept aside.
+    worlds: HashMap<EcoString, Blocks>,
+}
+
+impl Checker {
+    fn check(&mut self, command: &Command) -> CheckResult<()> {
+        let (block, operation) = match command.without_message() {
+            Command::Operate { block, operation } => (block, operation),
+            Command::Checkpoint(name) => {
+                self.checkpoints.insert(name.clone());
+                return Ok(());
+            }
+            Command::Restore(name) if !self.checkpoints.contains(name) => {
+                return Err((
+                    ErrorKind::UnknownIdentifier,
+                    format!("no checkpoint named `{}`", name),
+                ));
+            }
+            Command::CreateWorld(name) => {
+                if *name == self.world || self.worlds.contains_key(name) {
+                    return Err((ErrorKind::Other, format!("redefinition of world #{}", name)));
+                }
+                self.worlds.insert(name.clone(), Blocks::default());
+                return Ok(());
+            }
+            Command::UseWorld(name) if *name != self.world => {
+                let Some((sizes, containers)) = self.worlds.remove(name) else {
+                    return Err((
+                        ErrorKind::UnknownIdentifier,
+                        format!("no world named #{}", name),
+                    ));
+                };
+                let sizes = std::mem::replace(&mut self.sizes, sizes);
+                let containers = std::mem::replace(&mut self.containers, containers);
+                let current = std::mem::replace(&mut self.world, name.clone());
+                self.worlds.insert(current, (sizes, containers));
+                return Ok(());
+            }
+            Command::Show(Some(block)) | Command::Where(block) | Command::Info(block) => {
+                return self.size(block).map(drop);
+            }
+            _ => return Ok(()),
+        };
+
+        match operation {
+            Operation::Define(proto) => {
+                let size = match proto {
+                    // References have no interior of their own.
+                    MetaProtoType::Alias { reference } | MetaProtoType::Infinity { reference } => {
+                        self.size(reference)?;
+                        (0, 0)
+                    }
+                    MetaProtoType::Epsilon { reference, size } => {
+                        self.size(reference)?;
+                        *size
+                    }
+                    _ => proto.size(),
+                };
+                self.sizes.insert(block.clone(), size);
+                Ok(())
+            }
+            Operation::Place(position) => {
+                self.size(block)?;
+                self.position(position)?;
+                match &position.container {
+                    Some(container) => self.containers.insert(block.clone(), container.clone()),
+                    None => self.containers.remove(block),
+                };
+                Ok(())
+            }
+            Operation::Copy(target, deep) => {
+                let mut copies = vec![(block.clone(), target.clone())];
+                let mut index = 0;
+                while *deep && index < copies.len() {
+                    let container = copies[index].0.clone();
+                    for (child, _) in self.containers.iter().filter(|(_, c)| **c == container) {
+                        if !copies.iter().any(|(copied, _)| copied == child) {
+                            copies.push((child.clone(), format!("{}::{}", target, child).into()));
+                        }
+                    }
+                    index += 1;
+                }
+                for (copied, copy) in copies {
+                    let size = self.size(&copied)?;
+                    self.sizes.insert(copy, size);
+                }
+                Ok(())
+            }
+            Operation::Expect(position)
+            | Operation::ExpectNot(position)
+            | Operation::ExpectReachable(position)
+            | Operation::Goal(position) => {
+                self.size(block)?;
+                self.position(position)
+            }
+            Operation::PlaceRelative(_, _, anchor) => {
+                self.size(block)?;
+                self.size(anchor)?;
+                if let Some(container) = self.containers.get(anchor).cloned() {
+                    self.containers.insert(block.clone(), container);
+                }
+                Ok(())
+            }
+            Operation::Push(..) | Operation::Display(..) => self.size(block).map(drop),
+            Operation::ExpectNotIn(container) => {
+                self.size(block)?;
+                self.size(container).map(drop)
+            }
+            Operation::PlaceRandom(container, region) => {
+                self.size(block)?;
+                let (width, height) = self.size(container)?;
+                self.containers.insert(block.clone(), container.clone());
+                match region {
+                    Some(((x, y), (w, h))) if x + w > width || y + h > height => Err((
+                        ErrorKind::InvalidValue,
+                        format!(
+                            "region at {:?} of size {:?} outside #{} of size {:?}",
+                            (x, y),
+                            (w, h),
+                            container,
+                            (width, height)
+                        ),
+                    )),
+                    _ => Ok(()),
+                }
+            }
+            Operation::ExpectMap(rows) => {
+                let (width, height) = self.size(block)?;
+                if rows.len() != height || rows.iter().any(|row| row.chars().count() != width) {
+                    return Err((
+                        ErrorKind::InvalidValue,
+                        format!(
+                            "expected a map of {} rows of {} characters for #{}",
+                            height, width, block
+                        ),
+                    ));
+                }
+                Ok(())
+            }
+        }
+    }
+
+    fn size(&self, block: &EcoString) -> CheckResult<Size> {
+        self.sizes.get(block).copied().ok_or_else(|| {
+            (
+                ErrorKind::UnknownIdentifier,
+                format!(
+                    "unresolved reference to {}{}",
+                    block,
+                    did_you_mean(block, self.sizes.keys())
+                ),
+            )
+        })
+    }
+
+    fn position(&self, position: &MetaPosition) -> CheckResult<()> {
+        let Some(container) = &position.container else {
+            return Ok(());
+        };
+
+        let (width, height) = self.size(container)?;
+        if position.pos.0 >= width || position.pos.1 >= height {
+            return Err((
+                ErrorKind::InvalidValue,
+                format!(
+                    "position {:?} outside #{} of size {:?}",
+                    position.pos,
+                    container,
+                    (width, height)
+                ),
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StringSource;
+    use std::sync::Arc;
+
+    fn check(script: &str) -> Vec<(usize, String)> {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        executor
+            .check()
+            .iter()
+            .map(|error| (error.span().locate().0, error.message().to_string()))
+            .collect()
+    }
+
+    #[test]
+    fn test_check() {
+        let errors = check(
+            "DEFINE BOX #room size (3, 1)\nDEFINE ALIAS #alias ref #room\n\
+             DEFINE WALL #wall\nPLACE #wall at (3, 0) in #room\nPLACE #wall at (2, 0) in #alias\n\
+             PUSH #box east\nDEFINE BOX #box size (1, 1)\nPUSH #box east\n\
+             EXPECT MAP #room\n  w\nRESTORE start\nCHECKPOINT start\nRESTORE start\n\
+             EXPECT #box at (0, 0) in #wall\nPLACE #box RANDOM IN #room AT (2, 0) SIZE (2, 1)",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                (3, "position (3, 0) outside #room of size (3, 1)".into()),
+                (4, "position (2, 0) outside #alias of size (0, 0)".into()),
+                (5, "unresolved reference to box".into()),
+                (
+                    8,
+                    "expected a map of 1 rows of 3 characters for #room".into()
+                ),
+                (10, "no checkpoint named `start`".into()),
+                (13, "position (0, 0) outside #wall of size (0, 0)".into()),
+                (
+                    14,
+                    "region at (2, 0) of size (2, 1) outside #room of size (3, 1)".into()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_copy() {
+        let errors = check(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (1, 0) in #room\n\
+             COPY #room AS #deep DEEP\nCOPY #room AS #shallow\n\
+             EXPECT #deep::wall at (1, 0) in #deep\nEXPECT #shallow::wall orphan",
+        );
+        assert_eq!(
+            errors,
+            vec![(6, "unresolved reference to shallow::wall".into())]
+        );
+    }
+
+    #[test]
+    fn test_check_worlds() {
+        let errors = check(
+            "DEFINE BOX #room size (2, 1)\nWORLD CREATE #other\nWORLD USE #other\n\
+             PLACE #room at (0, 0) in #room\nDEFINE BOX #room size (3, 1)\n\
+             WORLD USE #main\nPLACE #room at (2, 0) in #room\nWORLD USE #another\n\
+             WORLD CREATE #other",
+        );
+        assert_eq!(
+            errors,
+            vec![
+                (3, "unresolved reference to room".into()),
+                (6, "position (2, 0) outside #room of size (2, 1)".into()),
+                (7, "no world named #another".into()),
+                (8, "redefinition of world #other".into()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_check_executed() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (2, 1)\nCHECKPOINT start".to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE WALL #wall\nPLACE #wall at (1, 0) in #room\nRESTORE start".to_string(),
+            )))
+            .unwrap();
+        assert!(executor.check().is_empty());
+    }
+}
@@ -1,3 +1,4 @@
+use anstyle::AnsiColor;
 use ecow::EcoString;
 use parabox::{Direction, Size};
 use std::fmt::{Debug, Display};
@@ -7,6 +8,11 @@ use std::fmt::{Debug, Display};
 /// See [parabox::ProtoType] for more information.
 #[allow(missing_docs)]
 #[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(tag = "type", rename_all = "lowercase")
+)]
 pub enum MetaProtoType {
     Wall,
     Box { size: Size },
@@ -14,6 +20,8 @@ pub enum MetaProtoType {
     Infinity { reference: EcoString },
     Epsilon { reference: EcoString, size: Size },
     Void { size: Size },
+    Room { size: Size },
+    Sealed { size: Size },
 }
 
 impl MetaProtoType {
@@ -27,12 +35,28 @@ impl MetaProtoType {
         }
     }
 
+    /// Returns the keyword of the prototype in `DEFINE`, e.g. `BOX`.
+    pub fn keyword(&self) -> &'static str {
+        match self {
+            MetaProtoType::Wall => "WALL",
+            MetaProtoType::Box { .. } => "BOX",
+            MetaProtoType::Alias { .. } => "ALIAS",
+            MetaProtoType::Infinity { .. } => "INFINITY",
+            MetaProtoType::Epsilon { .. } => "EPSILON",
+            MetaProtoType::Void { .. } => "VOID",
+            MetaProtoType::Room { .. } => "ROOM",
+            MetaProtoType::Sealed { .. } => "SEALED",
+        }
+    }
+
     /// Returns the size of the prototype if it has one.
     pub fn size(&self) -> Size {
         match self {
             MetaProtoType::Box { size } => *size,
             MetaProtoType::Epsilon { size, .. } => *size,
             MetaProtoType::Void { size } => *size,
+            MetaProtoType::Room { size } => *size,
+            MetaProtoType::Sealed { size } => *size,
             _ => Size::default(),
         }
     }
@@ -46,6 +70,8 @@ impl MetaProtoType {
             MetaProtoType::Infinity { .. } => false,
             MetaProtoType::Epsilon { .. } => true,
             MetaProtoType::Void { .. } => false,
+            MetaProtoType::Room { .. } => true,
+            MetaProtoType::Sealed { .. } => true,
         }
     }
 
@@ -58,6 +84,8 @@ impl MetaProtoType {
             MetaProtoType::Infinity { .. } => true,
             MetaProtoType::Epsilon { .. } => true,
             MetaProtoType::Void { .. } => false,
+            MetaProtoType::Room { .. } => true,
+            MetaProtoType::Sealed { .. } => true,
         }
     }
 
@@ -70,6 +98,8 @@ impl MetaProtoType {
             MetaProtoType::Infinity { .. } => false,
             MetaProtoType::Epsilon { .. } => true,
             MetaProtoType::Void { .. } => false,
+            MetaProtoType::Room { .. } => true,
+            MetaProtoType::Sealed { .. } => false,
         }
     }
 }
@@ -79,7 +109,15 @@ impl MetaProtoType {
 /// See [parabox::Position] for more information.
 #[allow(missing_docs)]
 #[derive(Clone, Eq, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
 pub struct MetaPosition {
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
     pub container: Option<EcoString>,
     pub pos: Size,
 }
@@ -119,25 +157,93 @@ pub enum Operation {
     Define(MetaProtoType),
     /// Places a block at the position.
     Place(MetaPosition),
+    /// Places a block at the distance in the direction from another block, in
+    /// the same container.
+    PlaceRelative(Direction, usize, EcoString),
+    /// Places a block at a random empty cell of the container, or of the
+    /// region of the container at the position with the size if given.
+    PlaceRandom(EcoString, Option<(Size, Size)>),
     /// Pushes a block in a direction with an assertion.
     Push(Direction, Assertion),
     /// Expects a block at the position.
     Expect(MetaPosition),
+    /// Expects a block not at the position.
+    ExpectNot(MetaPosition),
+    /// Expects a block not directly in the container, at any position.
+    ExpectNotIn(EcoString),
+    /// Expects some sequence of pushes to bring a block to the position.
+    ExpectReachable(MetaPosition),
+    /// Expects the interior of a container to match the rows of display
+    /// characters, from the top row, where `.` is an empty cell.
+    ExpectMap(Vec<EcoString>),
+    /// Adds a target to the goal, requiring the block to be at the position.
+    Goal(MetaPosition),
+    /// Copies a block as a new block of the identifier, orphan. If deep, the
+    /// blocks inside it are copied as well at the same positions, see
+    /// [Executor](crate::Executor) for their identifiers.
+    Copy(EcoString, bool),
+    /// Sets the display character and the color of a block, if given, see
+    /// [MetaTable::set_char](crate::MetaTable::set_char).
+    Display(Option<char>, Option<AnsiColor>),
 }
 
 /// A command to execute.
+///
+/// Commands on a block are [Command::Operate], which used to be the fields of
+/// a struct, see the [crate documentation](crate#migrating-from-the-command-struct).
 #[derive(Clone, Eq, PartialEq, Debug)]
-pub struct Command {
-    /// The execution target.
-    pub block: EcoString,
-    /// The operation to execute.
-    pub operation: Operation,
+pub enum Command {
+    /// Executes an operation on a block.
+    Operate {
+        /// The execution target.
+        block: EcoString,
+        /// The operation to execute.
+        operation: Operation,
+    },
+    /// Reverts the given number of last pushes.
+    Undo(usize),
+    /// Saves the world under the name.
+    Checkpoint(EcoString),
+    /// Restores the world saved under the name.
+    Restore(EcoString),
+    /// Shows the world, or the interior of the block, in the output.
+    Show(Option<EcoString>),
+    /// Writes the names, the prototypes and the positions of the blocks to the
+    /// output, only those of the prototype keyword if given, e.g. `BOX`.
+    List(Option<EcoString>),
+    /// Writes the position of the block to the output.
+    Where(EcoString),
+    /// Writes the position, the prototype, the referrers and the contents of
+    /// the block to the output.
+    Info(EcoString),
+    /// Expects the goal to be solved, or unsolved if `false`.
+    ExpectSolved(bool),
+    /// Expects the number of pushes that moved the world, see
+    /// [Executor::moves](crate::Executor::moves).
+    ExpectMoves(usize),
+    /// Expects the number of pushes, see [Executor::turn](crate::Executor::turn).
+    ExpectTurn(usize),
+    /// Seeds the random number generator of the executor.
+    Seed(u64),
+    /// Creates an empty world under the name, without using it.
+    CreateWorld(EcoString),
+    /// Uses the world of the name for the next commands, keeping the current
+    /// world aside.
+    UseWorld(EcoString),
+    /// Executes an assertion, failing with the message before the error if
+    /// the assertion fails, e.g. `EXPECT ... ELSE "message"`.
+    Else {
+        /// The assertion, an `EXPECT` command or an asserted push.
+        command: Box<Command>,
+        /// The message given by the script.
+        message: EcoString,
+    },
 }
 
 impl Command {
-    /// Creates a new command.
+    /// Creates a new command operating on a block.
     pub fn new(block: EcoString, operation: Operation) -> Self {
-        Self { block, operation }
+        Self::Operate { block, operation }
     }
 
     /// Creates a new define command.
@@ -150,6 +256,25 @@ impl Command {
         Self::new(block, Operation::Place(MetaPosition::new(container, pos)))
     }
 
+    /// Creates a new relative place command.
+    pub fn place_relative(
+        block: EcoString,
+        direction: Direction,
+        distance: usize,
+        anchor: EcoString,
+    ) -> Self {
+        Self::new(block, Operation::PlaceRelative(direction, distance, anchor))
+    }
+
+    /// Creates a new random place command.
+    pub fn place_random(
+        block: EcoString,
+        container: EcoString,
+        region: Option<(Size, Size)>,
+    ) -> Self {
+        Self::new(block, Operation::PlaceRandom(container, region))
+    }
+
     /// Creates a new push command.
     pub fn push(block: EcoString, direction: Direction, assertion: Assertion) -> Self {
         Self::new(block, Operation::Push(direction, assertion))
@@ -159,6 +284,237 @@ impl Command {
     pub fn expect(block: EcoString, container: Option<EcoString>, pos: Size) -> Self {
         Self::new(block, Operation::Expect(MetaPosition::new(container, pos)))
     }
+
+    /// Creates a new negated expect command.
+    pub fn expect_not(block: EcoString, container: Option<EcoString>, pos: Size) -> Self {
+        Self::new(
+            block,
+            Operation::ExpectNot(MetaPosition::new(container, pos)),
+        )
+    }
+
+    /// Creates a new negated expect command on the container only.
+    pub fn expect_not_in(block: EcoString, container: EcoString) -> Self {
+        Self::new(block, Operation::ExpectNotIn(container))
+    }
+
+    /// Creates a new reachability expect command.
+    pub fn expect_reachable(block: EcoString, container: EcoString, pos: Size) -> Self {
+        Self::new(
+            block,
+            Operation::ExpectReachable(MetaPosition::new(Some(container), pos)),
+        )
+    }
+
+    /// Creates a new map expect command.
+    pub fn expect_map(container: EcoString, rows: Vec<EcoString>) -> Self {
+        Self::new(container, Operation::ExpectMap(rows))
+    }
+
+    /// Creates a new goal command.
+    pub fn goal(block: EcoString, container: Option<EcoString>, pos: Size) -> Self {
+        Self::new(block, Operation::Goal(MetaPosition::new(container, pos)))
+    }
+
+    /// Creates a new copy command.
+    pub fn copy(block: EcoString, target: EcoString, deep: bool) -> Self {
+        Self::new(block, Operation::Copy(target, deep))
+    }
+
+    /// Creates a new display command.
+    pub fn display(block: EcoString, char: Option<char>, color: Option<AnsiColor>) -> Self {
+        Self::new(block, Operation::Display(char, color))
+    }
+
+    /// Creates a new command expecting the goal to be solved or unsolved.
+    pub fn expect_solved(solved: bool) -> Self {
+        Self::ExpectSolved(solved)
+    }
+
+    /// Creates a new command expecting the number of pushes that moved the
+    /// world.
+    pub fn expect_moves(moves: usize) -> Self {
+        Self::ExpectMoves(moves)
+    }
+
+    /// Creates a new command expecting the number of pushes.
+    pub fn expect_turn(turn: usize) -> Self {
+        Self::ExpectTurn(turn)
+    }
+
+    /// Creates a new undo command.
+    pub fn undo(pushes: usize) -> Self {
+        Self::Undo(pushes)
+    }
+
+    /// Creates a new show command.
+    pub fn show(block: Option<EcoString>) -> Self {
+        Self::Show(block)
+    }
+
+    /// Creates a new list command.
+    pub fn list(keyword: Option<EcoString>) -> Self {
+        Self::List(keyword)
+    }
+
+    /// Creates a new where command.
+    pub fn where_(block: EcoString) -> Self {
+        Self::Where(block)
+    }
+
+    /// Creates a new info command.
+    pub fn info(block: EcoString) -> Self {
+        Self::Info(block)
+    }
+
+    /// Creates a new checkpoint command.
+    pub fn checkpoint(name: EcoString) -> Self {
+        Self::Checkpoint(name)
+    }
+
+    /// Creates a new restore command.
+    pub fn restore(name: EcoString) -> Self {
+        Self::Restore(name)
+    }
+
+    /// Creates a new seed command.
+    pub fn seed(seed: u64) -> Self {
+        Self::Seed(seed)
+    }
+
+    /// Creates a new command creating a world.
+    pub fn create_world(name: EcoString) -> Self {
+        Self::CreateWorld(name)
+    }
+
+    /// Creates a new command using a world.
+    pub fn use_world(name: EcoString) -> Self {
+        Self::UseWorld(name)
+    }
+
+    /// Wraps the command to fail with the message, see [Command::Else].
+    pub fn with_message(self, message: EcoString) -> Self {
+        Self::Else {
+            command: Box::new(self),
+            message,
+        }
+    }
+}
+
+impl Command {
+    /// Returns the block the command operates on, if it is a
+    /// [Command::Operate].
+    pub fn block(&self) -> Option<&EcoString> {
+        match self {
+            Command::Operate { block, .. } => Some(block),
+            _ => None,
+        }
+    }
+
+    /// Returns the operation of the command, if it is a [Command::Operate].
+    pub fn operation(&self) -> Option<&Operation> {
+        match self {
+            Command::Operate { operation, .. } => Some(operation),
+            _ => None,
+        }
+    }
+
+    /// Returns the command without its `ELSE` message, if any.
+    pub fn without_message(&self) -> &Command {
+        match self {
+            Command::Else { command, .. } => command,
+            command => command,
+        }
+    }
+
+    /// Returns whether the command pushes a block, i.e. `PUSH` or `MOVE`.
+    pub fn is_push(&self) -> bool {
+        matches!(
+            self.without_message(),
+            Command::Operate {
+                operation: Operation::Push(..),
+                ..
+            }
+        )
+    }
+
+    /// Returns whether the command pushes a block with the `MOVED` or the
+    /// `STATIC` assertion.
+    pub fn is_asserted_push(&self) -> bool {
+        matches!(
+            self.without_message(),
+            Command::Operate {
+                operation: Operation::Push(_, Assertion::Moved | Assertion::Static),
+                ..
+            }
+        )
+    }
+
+    /// Returns whether the command is an `EXPECT` command, which fails if the
+    /// world is not as expected.
+    pub fn is_assertion(&self) -> bool {
+        match self.without_message() {
+            Command::Operate { operation, .. } => matches!(
+                operation,
+                Operation::Expect(_)
+                    | Operation::ExpectNot(_)
+                    | Operation::ExpectNotIn(_)
+                    | Operation::ExpectReachable(_)
+                    | Operation::ExpectMap(_)
+            ),
+            Command::ExpectSolved(_) | Command::ExpectMoves(_) | Command::ExpectTurn(_) => true,
+            _ => false,
+        }
+    }
+
+    /// Returns the blocks referred to by the command, except the block defined
+    /// by a `DEFINE` command.
+    pub fn references(&self) -> Vec<EcoString> {
+        let (block, operation) = match self.without_message() {
+            Command::Operate { block, operation } => (block, operation),
+            Command::Show(Some(block)) | Command::Where(block) | Command::Info(block) => {
+                return vec![block.clone()];
+            }
+            _ => return vec![],
+        };
+
+        let mut references = vec![block.clone()];
+        match operation {
+            Operation::Define(proto) => {
+                references = proto.reference().into_iter().collect();
+            }
+            Operation::Place(position)
+            | Operation::Expect(position)
+            | Operation::ExpectNot(position)
+            | Operation::ExpectReachable(position)
+            | Operation::Goal(position) => references.extend(position.container.clone()),
+            Operation::PlaceRelative(_, _, anchor) => references.push(anchor.clone()),
+            Operation::PlaceRandom(container, _) | Operation::ExpectNotIn(container) => {
+                references.push(container.clone())
+            }
+            Operation::Push(..)
+            | Operation::ExpectMap(_)
+            | Operation::Copy(..)
+            | Operation::Display(..) => {}
+        }
+        references
+    }
+}
+
+impl Display for MetaProtoType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.keyword())?;
+        if let Some(reference) = self.reference() {
+            write!(f, " ref #{}", reference)?;
+        }
+        if !matches!(
+            self,
+            MetaProtoType::Wall | MetaProtoType::Alias { .. } | MetaProtoType::Infinity { .. }
+        ) {
+            write!(f, " size {:?}", self.size())?;
+        }
+        Ok(())
+    }
 }
 
 impl Display for MetaPosition {
@@ -171,6 +527,150 @@ impl Display for MetaPosition {
     }
 }
 
+/// Writes the command as a statement of the script, which parses back into
+/// the command, e.g. `PUSH #box east MOVED`.
+///
+/// An [Operation::Display] has no statement of its own, and is written as the
+/// `char` and `color` properties of the `DEFINE` it comes from, e.g.
+/// `#player char '@' color red`. The rows of an `EXPECT MAP` are written on
+/// the following indented lines.
+impl Display for Command {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.without_message().fmt_statement(f)?;
+        if let Command::Else { message, .. } = self {
+            write!(f, " ELSE \"")?;
+            for c in message.chars() {
+                if matches!(c, '"' | '\\') {
+                    write!(f, "\\")?;
+                }
+                write!(f, "{}", c)?;
+            }
+            write!(f, "\"")?;
+        }
+        if let Some(Operation::ExpectMap(rows)) = self.without_message().operation() {
+            for row in rows {
+                write!(f, "\n    {}", row)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Command {
+    /// Writes the statement of the command, without the `ELSE` message and
+    /// the rows of a map.
+    fn fmt_statement(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (block, operation) = match self {
+            Command::Operate { block, operation } => (block, operation),
+            Command::Undo(pushes) => return write!(f, "UNDO {}", pushes),
+            Command::Checkpoint(name) => return write!(f, "CHECKPOINT {}", name),
+            Command::Restore(name) => return write!(f, "RESTORE {}", name),
+            Command::Show(None) => return write!(f, "SHOW"),
+            Command::Show(Some(block)) => return write!(f, "SHOW #{}", block),
+            Command::List(None) => return write!(f, "LIST"),
+            Command::List(Some(proto)) => return write!(f, "LIST {}", proto),
+            Command::Where(block) => return write!(f, "WHERE #{}", block),
+            Command::Info(block) => return write!(f, "INFO #{}", block),
+            Command::ExpectSolved(true) => return write!(f, "EXPECT SOLVED"),
+            Command::ExpectSolved(false) => return write!(f, "EXPECT UNSOLVED"),
+            Command::ExpectMoves(moves) => return write!(f, "EXPECT MOVES {}", moves),
+            Command::ExpectTurn(turn) => return write!(f, "EXPECT TURN {}", turn),
+            Command::Seed(seed) => return write!(f, "SEED {}", seed),
+            Command::CreateWorld(name) => return write!(f, "WORLD CREATE #{}", name),
+            Command::UseWorld(name) => return write!(f, "WORLD USE #{}", name),
+            Command::Else { command, .. } => return command.fmt_statement(f),
+        };
+
+        match operation {
+            Operation::Define(proto) => {
+                write!(f, "DEFINE {} #{}", proto.keyword(), block)?;
+                if let Some(reference) = proto.reference() {
+                    write!(f, " ref #{}", reference)?;
+                }
+                if !matches!(
+                    proto,
+                    MetaProtoType::Wall
+                        | MetaProtoType::Alias { .. }
+                        | MetaProtoType::Infinity { .. }
+                ) {
+                    write!(f, " size {:?}", proto.size())?;
+                }
+                Ok(())
+            }
+            Operation::Place(position) => write!(f, "PLACE #{} {}", block, position),
+            Operation::PlaceRelative(direction, distance, anchor) => write!(
+                f,
+                "PLACE #{} {} {} of #{}",
+                block,
+                distance,
+                direction.name(),
+                anchor
+            ),
+            Operation::PlaceRandom(container, region) => {
+                write!(f, "PLACE #{} RANDOM in #{}", block, container)?;
+                if let Some((at, size)) = region {
+                    write!(f, " at {:?} size {:?}", at, size)?;
+                }
+                Ok(())
+            }
+            Operation::Push(direction, assertion) => {
+                write!(f, "PUSH #{} {}", block, direction.name())?;
+                match assertion {
+                    Assertion::None => Ok(()),
+                    Assertion::Moved => write!(f, " MOVED"),
+                    Assertion::Static => write!(f, " STATIC"),
+                }
+            }
+            Operation::Expect(position) => write!(f, "EXPECT #{} {}", block, position),
+            Operation::ExpectNot(position) => write!(f, "EXPECT #{} NOT {}", block, position),
+            Operation::ExpectNotIn(container) => {
+                write!(f, "EXPECT #{} NOT in #{}", block, container)
+            }
+            Operation::ExpectReachable(position) => write!(
+                f,
+                "EXPECT #{} REACHABLE {:?} in #{}",
+                block,
+                position.pos,
+                position.container.as_deref().unwrap_or_default()
+            ),
+            Operation::ExpectMap(_) => write!(f, "EXPECT MAP #{}", block),
+            Operation::Goal(position) => write!(f, "GOAL #{} {}", block, position),
+            Operation::Copy(target, deep) => {
+                write!(f, "COPY #{} AS #{}", block, target)?;
+                if *deep {
+                    write!(f, " DEEP")?;
+                }
+                Ok(())
+            }
+            Operation::Display(char, color) => {
+                write!(f, "#{}", block)?;
+                if let Some(char) = char {
+                    write!(f, " char '{}'", char)?;
+                }
+                if let Some(color) = color.and_then(color_keyword) {
+                    write!(f, " color {}", color)?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+/// Returns the keyword of a color of `COLOR`, if any.
+pub(crate) fn color_keyword(color: AnsiColor) -> Option<&'static str> {
+    match color {
+        AnsiColor::Black => Some("black"),
+        AnsiColor::Red => Some("red"),
+        AnsiColor::Green => Some("green"),
+        AnsiColor::Yellow => Some("yellow"),
+        AnsiColor::Blue => Some("blue"),
+        AnsiColor::Magenta => Some("magenta"),
+        AnsiColor::Cyan => Some("cyan"),
+        AnsiColor::White => Some("white"),
+        _ => None,
+    }
+}
+
 impl Debug for MetaPosition {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         Display::fmt(self, f)
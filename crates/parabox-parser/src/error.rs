@@ -1,27 +1,30 @@
+use crate::command::MetaPosition;
 use crate::source::Source;
+use crate::suggest::hint;
 use ecow::EcoString;
+use parabox::MoveError;
 use std::fmt::{Debug, Display};
 use std::ops::Range;
-use std::rc::Rc;
+use std::sync::Arc;
 
 /// A span of text in a source.
 ///
 /// Contains the source and the span range.
 #[derive(Clone)]
 pub struct Span {
-    source: Rc<dyn Source>,
+    source: Arc<dyn Source>,
     range: Range<usize>,
 }
 
 impl Span {
     /// Creates a new span from a source and a range.
-    pub fn new(source: Rc<dyn Source>, range: Range<usize>) -> Self {
+    pub fn new(source: Arc<dyn Source>, range: Range<usize>) -> Self {
         Self { source, range }
     }
 
     /// Returns the source of the span.
-    pub fn source(&self) -> Rc<dyn Source> {
-        Rc::clone(&self.source)
+    pub fn source(&self) -> Arc<dyn Source> {
+        Arc::clone(&self.source)
     }
 
     /// Returns the range of the span.
@@ -43,33 +46,119 @@ impl Span {
     pub fn locate(&self) -> (usize, usize) {
         self.source.locate(self.range.start)
     }
+
+    /// Returns the line and column of the end of the span, exclusive.
+    pub fn locate_end(&self) -> (usize, usize) {
+        self.source.locate(self.range.end)
+    }
+
+    /// Returns the range of the lines covered by the span.
+    pub fn lines(&self) -> Range<usize> {
+        self.locate().0..self.locate_end().0 + 1
+    }
+
+    /// Returns whether the span covers several lines.
+    pub fn is_multiline(&self) -> bool {
+        self.lines().len() > 1
+    }
 }
 
 impl Debug for Span {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         let (line, column) = self.locate();
+        let (end_line, end_column) = self.locate_end();
         write!(
             f,
-            "{}:{}:{}: {}",
+            "{}:{}:{}-{}:{}: {}",
             self.name(),
             line + 1,
             column + 1,
+            end_line + 1,
+            end_column + 1,
             self.text()
         )
     }
 }
 
+/// The category of a [ParseError], for tools reacting to errors without
+/// matching their messages.
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// An unexpected token or character, e.g. a missing keyword.
+    UnexpectedToken,
+    /// An unknown keyword.
+    UnknownKeyword,
+    /// A missing property, e.g. `SIZE` of a box.
+    MissingProperty,
+    /// Conflicting properties, e.g. `SIZE` and `SOLID`.
+    ConflictingProperty,
+    /// A property given several times.
+    DuplicateProperty,
+    /// A variable of the wrong kind, e.g. a size tuple instead of an integer.
+    TypeMismatch,
+    /// An undefined variable, macro, template or map character.
+    UnknownIdentifier,
+    /// An invalid value, e.g. an integer expression dividing by zero.
+    InvalidValue,
+    /// A missing `END`, or an unterminated block comment.
+    Unterminated,
+    /// A failure when executing a command, e.g. an unmet `EXPECT`.
+    Execution,
+    /// A command exceeding the budget of the execution, e.g. too many pushes.
+    BudgetExceeded,
+    /// Any other error.
+    Other,
+}
+
+impl ErrorKind {
+    /// Returns the stable code of the kind, e.g. `unknown-keyword`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::UnexpectedToken => "unexpected-token",
+            ErrorKind::UnknownKeyword => "unknown-keyword",
+            ErrorKind::MissingProperty => "missing-property",
+            ErrorKind::ConflictingProperty => "conflicting-property",
+            ErrorKind::DuplicateProperty => "duplicate-property",
+            ErrorKind::TypeMismatch => "type-mismatch",
+            ErrorKind::UnknownIdentifier => "unknown-identifier",
+            ErrorKind::InvalidValue => "invalid-value",
+            ErrorKind::Unterminated => "unterminated",
+            ErrorKind::Execution => "execution",
+            ErrorKind::BudgetExceeded => "budget-exceeded",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+impl Display for ErrorKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.code())
+    }
+}
+
 /// An error that occurs during parsing.
 ///
-/// Contains the error message and the span where the error occurred.
+/// Contains the error message, its kind and the span where the error occurred.
 pub struct ParseError {
     span: Span,
+    kind: ErrorKind,
     message: EcoString,
 }
 
 impl ParseError {
     pub(crate) fn new(span: Span, message: EcoString) -> Self {
-        Self { span, message }
+        Self {
+            span,
+            kind: ErrorKind::Other,
+            message,
+        }
+    }
+
+    /// Sets the kind of the error.
+    pub(crate) fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
     }
 
     /// Returns the span where the error occurred.
@@ -77,6 +166,11 @@ impl ParseError {
         self.span.clone()
     }
 
+    /// Returns the kind of the error.
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
     /// Returns the error message.
     pub fn message(&self) -> EcoString {
         self.message.clone()
@@ -88,58 +182,80 @@ const DISPLAY_CONTEXT_LINES: usize = 3;
 
 impl Display for ParseError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let source = self.span.source();
-        let range = self.span.range();
+        fmt_report(f, "error", &self.span, &self.message)
+    }
+}
 
-        let (line, column) = source.locate(range.start);
+/// Writes a report of the level with the message and the lines around the
+/// span, underlining the span on each of its lines.
+fn fmt_report(
+    f: &mut std::fmt::Formatter<'_>,
+    level: &str,
+    span: &Span,
+    message: &str,
+) -> std::fmt::Result {
+    let source = span.source();
+    let range = span.range();
 
-        let context_start = line.saturating_sub(DISPLAY_CONTEXT_LINES);
-        let context_end = (line + DISPLAY_CONTEXT_LINES + 1).min(source.line_len());
-        let context_range = context_start..context_end;
+    let (line, column) = source.locate(range.start);
+    let (end_line, end_column) = source.locate(range.end);
 
-        let code_indent = context_range.end.to_string().len();
+    let context_start = line.saturating_sub(DISPLAY_CONTEXT_LINES);
+    let context_end = (end_line + DISPLAY_CONTEXT_LINES + 1).min(source.line_len());
+    let context_range = context_start..context_end;
 
-        write!(
-            f,
-            "error: {}\n  --> {}:{}:{}\n",
-            self.message,
-            source.name(),
-            line + 1,
-            column + 1,
-        )?;
+    let code_indent = context_range.end.to_string().len();
 
-        macro_rules! write_line {
-            ($before:expr, $after:expr) => {
-                write!(f, "{:width$} │ {}\n", $before, $after, width = code_indent)?;
-            };
+    write!(
+        f,
+        "{}: {}\n  --> {}:{}:{}\n",
+        level,
+        message,
+        source.name(),
+        line + 1,
+        column + 1,
+    )?;
 
-            ($after:expr) => {
-                write_line!("", $after);
-            };
+    macro_rules! write_line {
+        ($before:expr, $after:expr) => {
+            write!(f, "{:width$} │ {}\n", $before, $after, width = code_indent)?;
+        };
 
-            () => {
-                write_line!("", "");
-            };
-        }
+        ($after:expr) => {
+            write_line!("", $after);
+        };
 
-        for context_line in context_range.clone() {
-            if context_line == line {
-                write_line!();
-            }
+        () => {
+            write_line!("", "");
+        };
+    }
+
+    for context_line in context_range.clone() {
+        if context_line == line {
+            write_line!();
+        }
 
-            write_line!(context_line + 1, source.line(context_line).unwrap());
+        let text = source.line(context_line).unwrap();
+        write_line!(context_line + 1, text);
 
-            if context_line == line {
+        if (line..=end_line).contains(&context_line) {
+            let start = if context_line == line { column } else { 0 };
+            let end = if context_line == end_line {
+                end_column
+            } else {
+                text.len()
+            };
+            if context_line == line || end > start {
                 write_line!(format!(
                     "{}{}",
-                    " ".repeat(column),
-                    "^".repeat(range.end - range.start)
+                    " ".repeat(start),
+                    "^".repeat(end.saturating_sub(start))
                 ));
             }
         }
-
-        Ok(())
     }
+
+    Ok(())
 }
 
 impl Debug for ParseError {
@@ -148,6 +264,232 @@ impl Debug for ParseError {
     }
 }
 
+impl std::error::Error for ParseError {}
+
 /// The result of parsing, just an alias to [Result] with error type
 /// [ParseError].
 pub type ParseResult<T> = Result<T, ParseError>;
+
+/// The cause of an [ExecError].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum ExecCause {
+    /// The engine fails to push a block, and the executor cannot resolve it.
+    Move(MoveError),
+    /// An `EXPECT` or an asserted push does not hold.
+    Assertion {
+        /// The description of the failure, e.g. `expected #box to be at (1, 0)
+        /// in #room`.
+        message: EcoString,
+        /// The expected value.
+        expected: AssertedValue,
+        /// The actual value, `None` only if the block is in a container
+        /// without a name.
+        actual: Option<AssertedValue>,
+    },
+    /// A block is referred to by a name not defined in the world.
+    Unresolved {
+        /// The undefined name.
+        name: EcoString,
+        /// The defined names close to the undefined one, from the closest.
+        suggestions: Vec<EcoString>,
+    },
+    /// The command exceeds the budget of the execution, see
+    /// [Executor::set_budget](crate::Executor::set_budget).
+    Budget(EcoString),
+    /// An assertion fails with the message given by `ELSE`.
+    Else {
+        /// The message given by the script.
+        message: EcoString,
+        /// The failure of the assertion.
+        cause: Box<ExecCause>,
+    },
+    /// Any other failure, e.g. a redefinition.
+    Other(EcoString),
+}
+
+/// A value compared by a failed assertion, see [ExecCause::Assertion].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[non_exhaustive]
+pub enum AssertedValue {
+    /// The position of a block, by `EXPECT` and `EXPECT ... REACHABLE`.
+    Position(MetaPosition),
+    /// Any position but this one, by `EXPECT ... NOT`.
+    NotPosition(MetaPosition),
+    /// Any position outside the container, by `EXPECT ... NOT IN`.
+    NotIn(EcoString),
+    /// Whether the world is moved, by an asserted push.
+    Moved(bool),
+    /// Whether the goal is solved, by `EXPECT SOLVED` and `EXPECT UNSOLVED`.
+    Solved(bool),
+    /// A number of pushes, by `EXPECT MOVES` and `EXPECT TURN`.
+    Count(usize),
+    /// The rows of an interior, by `EXPECT MAP`.
+    Map(Vec<EcoString>),
+}
+
+impl ExecCause {
+    /// Creates a failed assertion.
+    pub(crate) fn assertion(
+        message: impl Into<EcoString>,
+        expected: AssertedValue,
+        actual: AssertedValue,
+    ) -> Self {
+        Self::Assertion {
+            message: message.into(),
+            expected,
+            actual: Some(actual),
+        }
+    }
+
+    /// Returns the engine error failing the push, if any, e.g. below an
+    /// `ELSE` message.
+    pub fn move_error(&self) -> Option<&MoveError> {
+        match self {
+            ExecCause::Move(error) => Some(error),
+            ExecCause::Else { cause, .. } => cause.move_error(),
+            _ => None,
+        }
+    }
+
+    /// Returns the message of the exceeded budget, if any, e.g. below an
+    /// `ELSE` message.
+    pub fn budget(&self) -> Option<&EcoString> {
+        match self {
+            ExecCause::Budget(message) => Some(message),
+            ExecCause::Else { cause, .. } => cause.budget(),
+            _ => None,
+        }
+    }
+}
+
+impl From<String> for ExecCause {
+    fn from(message: String) -> Self {
+        Self::Other(message.into())
+    }
+}
+
+impl Display for ExecCause {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ExecCause::Move(error) => write!(f, "{}", error),
+            ExecCause::Assertion { message, .. } => write!(f, "{}", message),
+            ExecCause::Unresolved { name, suggestions } => {
+                write!(f, "unresolved reference to {}{}", name, hint(suggestions))
+            }
+            ExecCause::Budget(message) | ExecCause::Other(message) => write!(f, "{}", message),
+            ExecCause::Else { message, cause } => write!(f, "{}: {}", message, cause),
+        }
+    }
+}
+
+/// An error that occurs when executing a command.
+///
+/// Contains the cause of the error and the span of the command.
+pub struct ExecError {
+    span: Span,
+    cause: Box<ExecCause>,
+}
+
+impl ExecError {
+    pub(crate) fn new(span: Span, cause: ExecCause) -> Self {
+        Self {
+            span,
+            cause: Box::new(cause),
+        }
+    }
+
+    /// Returns the span of the failed command.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// Returns the kind of the error, [ErrorKind::BudgetExceeded] if the
+    /// command exceeds the budget, or [ErrorKind::Execution] otherwise.
+    pub fn kind(&self) -> ErrorKind {
+        match self.cause.budget() {
+            Some(_) => ErrorKind::BudgetExceeded,
+            None => ErrorKind::Execution,
+        }
+    }
+
+    /// Returns the cause of the error.
+    pub fn cause(&self) -> &ExecCause {
+        &self.cause
+    }
+
+    /// Returns the error message.
+    pub fn message(&self) -> EcoString {
+        self.cause.to_string().into()
+    }
+}
+
+impl Display for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_report(f, "error", &self.span, &self.message())
+    }
+}
+
+impl Debug for ExecError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+/// The source of the error is the [MoveError] of a failed push.
+impl std::error::Error for ExecError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        self.cause
+            .move_error()
+            .map(|error| error as &(dyn std::error::Error + 'static))
+    }
+}
+
+/// Converts the error for the callers handling the parse and the execution
+/// errors alike, keeping its kind and its message.
+impl From<ExecError> for ParseError {
+    fn from(error: ExecError) -> Self {
+        ParseError::new(error.span(), error.message()).with_kind(error.kind())
+    }
+}
+
+/// The result of executing, just an alias to [Result] with error type
+/// [ExecError].
+pub type ExecResult<T> = Result<T, ExecError>;
+
+/// A warning about a suspicious but valid script, e.g. an unused block.
+///
+/// Contains the warning message and the span where the warning occurred.
+#[derive(Clone)]
+pub struct Warning {
+    span: Span,
+    message: EcoString,
+}
+
+impl Warning {
+    pub(crate) fn new(span: Span, message: EcoString) -> Self {
+        Self { span, message }
+    }
+
+    /// Returns the span where the warning occurred.
+    pub fn span(&self) -> Span {
+        self.span.clone()
+    }
+
+    /// Returns the warning message.
+    pub fn message(&self) -> EcoString {
+        self.message.clone()
+    }
+}
+
+impl Display for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        fmt_report(f, "warning", &self.span, &self.message)
+    }
+}
+
+impl Debug for Warning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
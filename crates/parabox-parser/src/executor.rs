@@ -1,15 +1,119 @@
 use crate::command::{Assertion, Command, MetaPosition, MetaProtoType, Operation};
-use crate::error::{ParseError, ParseResult, Span};
+use crate::error::{
+    AssertedValue, ErrorKind, ExecCause, ExecError, ExecResult, ParseError, ParseResult, Span,
+    Warning,
+};
 use crate::meta::{MetaKey, MetaName, MetaTable};
-use crate::parser::{parse, SpannedCommand};
-use crate::source::Source;
+use crate::parser::{parse_all_in, parse_from, parse_in, SpannedCommand};
+use crate::report::{ExecutionReport, Profile, ProfileEntry};
+use crate::rng::Rng;
+use crate::scope::Scope;
+use crate::source::{NamedStringSource, Source};
+use crate::suggest::suggestions;
+use anstyle::AnsiColor;
 use ecow::EcoString;
-use parabox::{BlockKey, Direction, MoveError, World};
+use parabox::{
+    BlockKey, Direction, Goal, MoveError, Position, ProtoType, PushLimit, PushStats, Size, World,
+};
+#[cfg(feature = "format")]
+use parabox_format::Formatter;
 use parabox_macros::trace_func;
-use std::collections::VecDeque;
-use std::rc::Rc;
+#[cfg(feature = "solver")]
+use parabox_solver::{SolveError, Solver};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tracing::{debug, instrument};
 
+/// The world, the meta table and the goal of an executor at some point.
+type Snapshot = (World, MetaTable, Goal);
+
+/// A callback invoked on an executed command, see [Executor::on_before].
+type Hook = Box<dyn FnMut(&Executor, &SpannedCommand)>;
+
+/// The snapshot before a push, with whether the push moved the world.
+///
+/// The world of the snapshot has no committed movements, which are taken from
+/// the current world when undoing the push, see [World::history].
+type Turn = (Snapshot, bool);
+
+/// The snapshot and the undo history saved by a `CHECKPOINT` command.
+type Checkpoint = (Snapshot, Vec<Turn>);
+
+/// The name of the world used before any `WORLD USE` command.
+const MAIN_WORLD: &str = "main";
+
+/// The limits of an execution, see [Executor::set_budget].
+///
+/// A command exceeding a limit, even in the middle of a push, is not executed
+/// and fails with
+/// [ErrorKind::BudgetExceeded], e.g. to stop a generated script pushing blocks
+/// endlessly. There are no limits by default.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Budget {
+    /// The maximum number of executed commands.
+    pub max_commands: Option<usize>,
+    /// The maximum number of executed pushes, including `MOVE`.
+    pub max_pushes: Option<usize>,
+    /// The maximum number of evaluations of the executed pushes, see
+    /// [PushStats::evaluations], checked during each push.
+    pub max_evaluations: Option<usize>,
+    /// The maximum time from the first executed command, checked before each
+    /// command and during each push or search.
+    pub timeout: Option<Duration>,
+    /// The maximum number of states searched by each `EXPECT REACHABLE`.
+    pub max_states: Option<usize>,
+}
+
+/// What the executed commands have spent of the [Budget].
+#[derive(Copy, Clone, Default)]
+struct Spent {
+    commands: usize,
+    pushes: usize,
+    evaluations: usize,
+    /// The time of the first executed command.
+    started: Option<Instant>,
+}
+
+/// What happens when `DEFINE` or `COPY` reuses the name of a block, see
+/// [Executor::set_redefinition].
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub enum Redefinition {
+    /// The command fails, e.g. to catch a typo in a long script.
+    #[default]
+    Error,
+    /// The new block takes the name, and the old block stays in the world
+    /// under the name suffixed by `@` and a number, e.g. `#wall@1`, which
+    /// scripts cannot refer to.
+    Shadow,
+    /// The old block is removed from the world and the new block takes its
+    /// name. The blocks inside the old block become orphans.
+    Replace,
+}
+
+/// An executed command with the state of the executor before it, to rewind it
+/// by [Executor::step_back].
+struct Rewind {
+    command: SpannedCommand,
+    snapshot: Snapshot,
+    /// The state of the random number generator, advanced by a random
+    /// placement or replaced by `SEED`.
+    rng: Rng,
+    /// The budget spent before the command, refunded when rewinding it.
+    spent: Spent,
+    /// The length of the undo history kept by the command, which a push
+    /// appends to and `UNDO` truncates.
+    undo_len: usize,
+    /// The turns of the undo history after the kept ones, removed by `UNDO`
+    /// or replaced by `RESTORE` and `WORLD`.
+    undo_tail: Vec<Turn>,
+    /// The checkpoint replaced by `CHECKPOINT`, with its name.
+    checkpoint: Option<(EcoString, Option<Checkpoint>)>,
+    /// The name of the used world and the worlds kept aside, if changed by
+    /// `WORLD`.
+    worlds: Option<(EcoString, HashMap<EcoString, Checkpoint>)>,
+}
+
 /// An executor that runs the parsed commands.
 ///
 /// The executor contains a world and a meta table. The meta table builds up a
@@ -21,6 +125,58 @@ pub struct Executor {
     world: World,
     meta: MetaTable,
     commands: VecDeque<SpannedCommand>,
+    /// The commands of the pushed sources, to run again after a reset.
+    pushed: Vec<SpannedCommand>,
+    /// The definitions shared by the pushed sources.
+    scope: Scope,
+    /// The text of the lines pushed by [Executor::push_line].
+    session: String,
+    /// The index of the first line of the session not parsed yet, e.g. the
+    /// first line of an unterminated `MACRO`.
+    session_start: usize,
+    /// The goal declared by the `GOAL` commands.
+    goal: Goal,
+    /// The random number generator of the random placements, seeded by the
+    /// `SEED` commands.
+    rng: Rng,
+    /// The world, the meta table and the goal before each executed push, with
+    /// whether the push moved the world.
+    undo: Vec<Turn>,
+    /// The snapshots and the undo history saved by the `CHECKPOINT` commands.
+    checkpoints: HashMap<EcoString, Checkpoint>,
+    /// The name of the world used by the commands.
+    world_name: EcoString,
+    /// The worlds created by `WORLD CREATE` and kept aside by `WORLD USE`,
+    /// with their goals and undo histories.
+    worlds: HashMap<EcoString, Checkpoint>,
+    /// The executed commands, from the first one, if recorded.
+    history: Option<Vec<Rewind>>,
+    /// The sink of the text written by the `SHOW` commands.
+    output: Box<dyn FnMut(&str)>,
+    /// The limits of the execution.
+    budget: Budget,
+    /// What happens when a block is defined again.
+    redefinition: Redefinition,
+    /// The budget spent by the executed commands.
+    spent: Spent,
+    /// The summary of the executed commands.
+    report: ExecutionReport,
+    /// The cost of the executed commands, if profiling.
+    profile: Option<Profile>,
+    /// The number of pushes and their work in the executing command.
+    pushing: (usize, PushStats),
+    /// The callbacks invoked before each executed command.
+    before: Vec<Hook>,
+    /// The callbacks invoked after each successfully executed command.
+    after: Vec<Hook>,
+    /// The blocks defined by the pushed commands, with the worlds they are
+    /// defined in and the spans of their definitions.
+    definitions: Vec<(EcoString, EcoString, Span)>,
+    /// The world used after the pushed commands, which the next pushed
+    /// definitions are in.
+    defining: EcoString,
+    /// The blocks referred to by the pushed commands.
+    references: HashSet<EcoString>,
 }
 
 impl Executor {
@@ -28,11 +184,7 @@ impl Executor {
     ///
     /// If you want to use an existing world, use [`Executor::from_world`].
     pub fn new() -> Self {
-        Self {
-            world: World::new(),
-            meta: MetaTable::new(),
-            commands: VecDeque::new(),
-        }
+        Self::from_world(World::new(), MetaTable::new())
     }
 
     /// Creates a new executor with the given world.
@@ -44,6 +196,29 @@ impl Executor {
             world,
             meta,
             commands: VecDeque::new(),
+            pushed: Vec::new(),
+            scope: Scope::new(),
+            session: String::new(),
+            session_start: 0,
+            goal: Goal::new(),
+            rng: Rng::default(),
+            undo: Vec::new(),
+            checkpoints: HashMap::new(),
+            world_name: MAIN_WORLD.into(),
+            worlds: HashMap::new(),
+            history: None,
+            output: Box::new(|text| print!("{}", text)),
+            budget: Budget::default(),
+            redefinition: Redefinition::default(),
+            spent: Spent::default(),
+            report: ExecutionReport::default(),
+            profile: None,
+            pushing: (0, PushStats::default()),
+            before: Vec::new(),
+            after: Vec::new(),
+            definitions: Vec::new(),
+            defining: MAIN_WORLD.into(),
+            references: HashSet::new(),
         }
     }
 
@@ -52,17 +227,162 @@ impl Executor {
         &self.world
     }
 
+    /// Returns the name of the world used by the commands, `main` unless
+    /// changed by `WORLD USE`.
+    pub fn world_name(&self) -> &EcoString {
+        &self.world_name
+    }
+
     /// Returns the meta table of the executor.
     pub fn meta(&self) -> &MetaTable {
         &self.meta
     }
 
+    /// Returns the world of the executor mutably, e.g. to apply an engine
+    /// call between commands. See [Executor::parts_mut] to also update the
+    /// meta table.
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    /// Returns the meta table of the executor mutably.
+    pub fn meta_mut(&mut self) -> &mut MetaTable {
+        &mut self.meta
+    }
+
+    /// Returns the world and the meta table of the executor mutably at the
+    /// same time, e.g. to insert a block and name it. The executor keeps
+    /// running the remaining commands on the mutated world.
+    ///
+    /// Each block inserted into the world should be inserted into the meta
+    /// table too, see [MetaTable].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox::ProtoType;
+    /// # use parabox_parser::{Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// let (world, meta) = executor.parts_mut();
+    /// let key = world.insert(ProtoType::Wall);
+    /// meta.insert("wall".into(), key);
+    ///
+    /// let source = StringSource::new("EXPECT #wall orphan".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    /// executor.run_all().unwrap();
+    /// ```
+    pub fn parts_mut(&mut self) -> (&mut World, &mut MetaTable) {
+        (&mut self.world, &mut self.meta)
+    }
+
+    /// Returns the block pushed by `MOVE`, if designated by `PLAYER`.
+    pub fn player(&self) -> Option<EcoString> {
+        self.scope.player()
+    }
+
+    /// Designates the block pushed by `MOVE` in the pushed sources.
+    pub(crate) fn set_player(&mut self, player: EcoString) {
+        self.scope.set_player(player);
+    }
+
+    /// Returns the goal declared by the `GOAL` commands.
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    /// Returns the number of pushes in the undo history, including `MOVE`,
+    /// asserted by `EXPECT TURN`.
+    ///
+    /// Unlike [ExecutionReport::pushes_moved], the pushes reverted by `UNDO`
+    /// or `RESTORE` are not counted, and each world counts its own pushes.
+    pub fn turn(&self) -> usize {
+        self.undo.len()
+    }
+
+    /// Returns the number of pushes in the undo history that moved the world,
+    /// asserted by `EXPECT MOVES`, e.g. the length of a solution.
+    pub fn moves(&self) -> usize {
+        self.undo.iter().filter(|(_, moved)| *moved).count()
+    }
+
+    /// Sets the sink of the text written by the `SHOW` commands. The text is
+    /// printed to the standard output by default.
+    pub fn set_output(&mut self, output: impl FnMut(&str) + 'static) {
+        self.output = Box::new(output);
+    }
+
+    /// Sets the limits of the execution. The limits count from the first
+    /// command executed after the executor is created or reset, see
+    /// [Executor::reset].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Budget, ErrorKind, Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// executor.set_budget(Budget {
+    ///     max_commands: Some(1),
+    ///     ..Budget::default()
+    /// });
+    /// let source = StringSource::new("DEFINE WALL #a\nDEFINE WALL #b".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    ///
+    /// let error = executor.run_all().unwrap_err();
+    /// assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+    /// ```
+    pub fn set_budget(&mut self, budget: Budget) {
+        self.budget = budget;
+    }
+
+    /// Sets what happens when `DEFINE` or `COPY` reuses the name of a block
+    /// in the world. The command fails by default.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Executor, Redefinition, StringSource};
+    /// let mut executor = Executor::new();
+    /// executor.set_redefinition(Redefinition::Shadow);
+    /// let source = StringSource::new("DEFINE WALL #wall\nDEFINE WALL #wall".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    /// executor.run_all().unwrap();
+    ///
+    /// assert_eq!(executor.meta().names(), vec!["wall", "wall@1"]);
+    /// ```
+    pub fn set_redefinition(&mut self, redefinition: Redefinition) {
+        self.redefinition = redefinition;
+    }
+
+    /// Registers a callback invoked before each command executed by
+    /// [Executor::step], with the executor and the command.
+    pub fn on_before(&mut self, hook: impl FnMut(&Executor, &SpannedCommand) + 'static) {
+        self.before.push(Box::new(hook));
+    }
+
+    /// Registers a callback invoked after each command successfully executed by
+    /// [Executor::step], with the executor and the command.
+    pub fn on_after(&mut self, hook: impl FnMut(&Executor, &SpannedCommand) + 'static) {
+        self.after.push(Box::new(hook));
+    }
+
+    /// Invokes the hooks with the executor and the command.
+    fn invoke(&mut self, hooks: fn(&mut Self) -> &mut Vec<Hook>, command: &SpannedCommand) {
+        let mut invoked = std::mem::take(hooks(self));
+        for hook in &mut invoked {
+            hook(self, command);
+        }
+        *hooks(self) = invoked;
+    }
+
     /// Takes the world and the meta table from the executor.
     pub fn take(self) -> (World, MetaTable) {
         (self.world, self.meta)
     }
 }
 
+impl Default for Executor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Executor {
     fn contains_meta(&self, block: &EcoString) -> bool {
         self.meta.contains_name(block)
@@ -73,25 +393,160 @@ impl Executor {
         self.meta.insert(block, key);
     }
 
-    fn key_to_name<T: MetaKey>(&self, from: &T) -> Result<T::Target, String> {
+    fn key_to_name<T: MetaKey>(&self, from: &T) -> Result<T::Target, ExecCause> {
         from.convert(&self.meta)
-            .map_err(|key| format!("unresolved reference to {:?}", key))
+            .map_err(|key| format!("unresolved reference to {:?}", key).into())
     }
 
-    fn name_to_key<T: MetaName>(&self, from: &T) -> Result<T::Target, String> {
+    fn name_to_key<T: MetaName>(&self, from: &T) -> Result<T::Target, ExecCause> {
         from.convert(&self.meta)
-            .map_err(|block| format!("unresolved reference to {}", block))
+            .map_err(|name| ExecCause::Unresolved {
+                suggestions: suggestions(&name, self.meta.names().iter())
+                    .into_iter()
+                    .cloned()
+                    .collect(),
+                name,
+            })
     }
 }
 
 impl Executor {
     /// Pushes a source of commands to the executor.
-    pub fn push_source(&mut self, source: Rc<dyn Source>) -> ParseResult<()> {
-        parse(source).map(|commands| {
-            self.commands.extend(commands.into_iter());
+    ///
+    /// Variables defined by the previous sources are visible in the source.
+    pub fn push_source(&mut self, source: Arc<dyn Source>) -> ParseResult<()> {
+        parse_in(source, &mut self.scope).map(|commands| {
+            self.extend_commands(commands);
+        })
+    }
+
+    /// Pushes a line of commands to the executor, e.g. read from a prompt.
+    ///
+    /// The pushed lines make up one source named `<session>`, so that the
+    /// commands and the errors are located in the whole session, with the
+    /// previous lines as their context. The lines of a block, e.g. a `MACRO`
+    /// or a `MAP`, are only parsed once its `END` is pushed.
+    ///
+    /// ```
+    /// # use parabox_parser::Executor;
+    /// let mut executor = Executor::new();
+    /// executor.push_line("DEFINE BOX #room size (3, 3)").unwrap();
+    /// executor.run_all().unwrap();
+    ///
+    /// executor.push_line("PLACE #box at (0, 0) in #room").unwrap();
+    /// let error = executor.run_all().unwrap_err();
+    /// assert_eq!(error.span().locate(), (1, 0));
+    /// ```
+    pub fn push_line(&mut self, line: &str) -> ParseResult<()> {
+        self.session.push_str(line);
+        self.session.push('\n');
+        let source = NamedStringSource::new("<session>".into(), self.session.clone());
+        let source: Arc<dyn Source> = Arc::new(source);
+
+        // The scope is only updated once the block is terminated, so that its
+        // lines are parsed again with the next line.
+        let mut scope = self.scope.clone();
+        match parse_from(source.clone(), self.session_start, &mut scope) {
+            Err(error) if error.kind() == ErrorKind::Unterminated => Ok(()),
+            result => {
+                self.session_start = source.line_len();
+                self.scope = scope;
+                result.map(|commands| {
+                    self.extend_commands(commands);
+                })
+            }
+        }
+    }
+
+    /// Pushes a source of commands to the executor, whose identifiers are in
+    /// the namespace, e.g. `#room` in the source is `#ns::room`.
+    ///
+    /// The blocks of the sources in different namespaces do not conflict. A
+    /// source refers to the blocks of the namespaces pushed before by their
+    /// qualified identifiers, e.g. `#ns::room`, which are kept as is.
+    /// Variables and macros are shared by all the sources.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// for namespace in ["left", "right"] {
+    ///     let source = StringSource::new("DEFINE BOX #room size (3, 3)".to_string());
+    ///     executor.push_source_as(Arc::new(source), namespace.into()).unwrap();
+    /// }
+    /// executor.run_all().unwrap();
+    ///
+    /// assert!(executor.meta().names().contains(&"right::room".into()));
+    /// ```
+    pub fn push_source_as(
+        &mut self,
+        source: Arc<dyn Source>,
+        namespace: EcoString,
+    ) -> ParseResult<()> {
+        self.scope.set_namespace(Some(namespace));
+        let result = parse_in(source, &mut self.scope);
+        self.scope.set_namespace(None);
+        result.map(|commands| {
+            self.extend_commands(commands);
+        })
+    }
+
+    /// Pushes a source of commands to the executor, or returns all the errors
+    /// in the source, see [parse_all](crate::parse_all).
+    ///
+    /// No command of the source is pushed if there is any error.
+    pub fn push_source_all(&mut self, source: Arc<dyn Source>) -> Result<(), Vec<ParseError>> {
+        parse_all_in(source, &mut self.scope).map(|commands| {
+            self.extend_commands(commands);
         })
     }
 
+    /// Appends the commands to execute.
+    pub(crate) fn extend_commands(&mut self, commands: impl IntoIterator<Item = SpannedCommand>) {
+        for command in commands {
+            match command.command() {
+                Command::Operate {
+                    block,
+                    operation: Operation::Define(_),
+                } => self.definitions.push((
+                    self.defining.clone(),
+                    block.clone(),
+                    command.span().clone(),
+                )),
+                Command::UseWorld(name) => self.defining = name.clone(),
+                _ => {}
+            }
+            self.references.extend(command.command().references());
+            self.pushed.push(command.clone());
+            self.commands.push_back(command);
+        }
+    }
+
+    /// Returns the warnings about the pushed commands, in the order of the
+    /// definitions.
+    ///
+    /// A block is unused if no pushed command refers to it, and redefined if
+    /// several `DEFINE` commands define it in the same world, which only
+    /// succeeds if the first definition is reverted by `UNDO` or `RESTORE`.
+    pub fn warnings(&self) -> Vec<Warning> {
+        let mut warnings = Vec::new();
+        let mut defined = HashSet::new();
+        for (world, block, span) in &self.definitions {
+            if !defined.insert((world, block)) {
+                warnings.push(Warning::new(
+                    span.clone(),
+                    format!("redefinition of #{}", block).into(),
+                ));
+            } else if !self.references.contains(block) {
+                warnings.push(Warning::new(
+                    span.clone(),
+                    format!("unused block #{}", block).into(),
+                ));
+            }
+        }
+        warnings
+    }
+
     /// Returns whether there are more commands to execute.
     pub fn has_next(&self) -> bool {
         !self.commands.is_empty()
@@ -102,52 +557,654 @@ impl Executor {
         self.commands.front()
     }
 
+    /// Returns the summary of the commands executed since the executor is
+    /// created or reset, including the failed ones.
+    pub fn report(&self) -> &ExecutionReport {
+        &self.report
+    }
+
+    /// Enables or disables recording the executed commands to rewind them by
+    /// [Executor::step_back]. Disabling discards the recorded commands.
+    ///
+    /// Recording saves the world, the meta table and the goal before each
+    /// command, so it is disabled by default, e.g. for a long script run by
+    /// [Executor::run_all].
+    pub fn set_rewinding(&mut self, enabled: bool) {
+        if !enabled {
+            self.history = None;
+        } else if self.history.is_none() {
+            self.history = Some(Vec::new());
+        }
+    }
+
+    /// Enables or disables profiling the executed commands, see
+    /// [Executor::profile]. Disabling discards the recorded profile.
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// let source = StringSource::new("DEFINE BOX #room size (2, 1)\nDEFINE BOX #box\n\
+    ///                                 PLACE #box at (0, 0) in #room\nPUSH #box east".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    ///
+    /// executor.set_profiling(true);
+    /// executor.run_all().unwrap();
+    /// let profile = executor.profile().unwrap();
+    /// assert_eq!(profile.entries.len(), 4);
+    /// assert_eq!(profile.entries[3].pushes, 1);
+    /// println!("{}", profile);
+    /// ```
+    pub fn set_profiling(&mut self, enabled: bool) {
+        if !enabled {
+            self.profile = None;
+        } else if self.profile.is_none() {
+            self.profile = Some(Profile::default());
+        }
+    }
+
+    /// Returns the cost of each command executed since profiling is enabled
+    /// or the executor is reset, including the failed ones, or `None` if
+    /// not profiling.
+    pub fn profile(&self) -> Option<&Profile> {
+        self.profile.as_ref()
+    }
+
+    /// Returns the commands to execute, in order.
+    pub fn queued(&self) -> impl Iterator<Item = &SpannedCommand> {
+        self.commands.iter()
+    }
+
+    /// Appends a command to execute after the queued commands, e.g. a push
+    /// of an interactive game after the setup of a script.
+    ///
+    /// The command is spanned in a source named `<enqueued>` with the command
+    /// written as a statement, e.g. `PUSH #box east MOVED`. Unlike the commands of the pushed sources, it is
+    /// not executed again after [Executor::reset].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox::Direction;
+    /// # use parabox_parser::{Assertion, Command, Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// let source = StringSource::new("DEFINE BOX #room size (2, 1)\nDEFINE BOX #box solid\n\
+    ///                                 PLACE #box at (0, 0) in #room".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    ///
+    /// executor.enqueue(Command::push("box".into(), Direction::East, Assertion::Moved));
+    /// executor.run_all().unwrap();
+    /// ```
+    pub fn enqueue(&mut self, command: Command) {
+        self.commands.push_back(enqueued(command));
+    }
+
+    /// Inserts a command to execute before the queued commands, see
+    /// [Executor::enqueue].
+    pub fn enqueue_next(&mut self, command: Command) {
+        self.commands.push_front(enqueued(command));
+    }
+
+    /// Removes the next command without executing it. Returns the removed
+    /// command, or `None` if there are no more commands.
+    pub fn skip(&mut self) -> Option<SpannedCommand> {
+        self.commands.pop_front()
+    }
+
+    /// Returns the names of the checkpoints saved by `CHECKPOINT`.
+    pub(crate) fn checkpoint_names(&self) -> impl Iterator<Item = &EcoString> {
+        self.checkpoints.keys()
+    }
+
+    /// Returns the worlds kept aside by `WORLD`, with their meta tables.
+    pub(crate) fn aside_worlds(&self) -> impl Iterator<Item = (&EcoString, &World, &MetaTable)> {
+        self.worlds
+            .iter()
+            .map(|(name, ((world, meta, _), _))| (name, world, meta))
+    }
+
     /// Executes the next command. Returns the span of the executed command.
-    pub fn step(&mut self) -> ParseResult<Span> {
+    ///
+    /// The command can be rewound by [Executor::step_back], even if it fails,
+    /// see [Executor::set_rewinding]. A command exceeding the budget, before
+    /// it or during a push, is not executed and stays the next command, see
+    /// [Executor::set_budget].
+    pub fn step(&mut self) -> ExecResult<Span> {
         if let Some(command) = self.commands.pop_front() {
-            self.execute(command.command().clone())
-                .map(|_| command.span().clone())
-                .map_err(|message| ParseError::new(command.span().clone(), message.into()))
+            let spent = self.spent;
+            if let Err(message) = self.spend(&command) {
+                let span = command.span().clone();
+                self.commands.push_front(command);
+                return Err(ExecError::new(span, ExecCause::Budget(message.into())));
+            }
+            let rewind = self
+                .history
+                .is_some()
+                .then(|| self.rewind(command.clone(), spent));
+
+            let span = command.span().clone();
+            self.invoke(|executor| &mut executor.before, &command);
+            let started = Instant::now();
+            let result = self.execute(command.command().clone());
+            if let Some(message) = result.as_ref().err().and_then(ExecCause::budget).cloned() {
+                // The push exceeding the budget is reverted from the turn it
+                // saved, to run again with a larger one. Other commands, e.g.
+                // `EXPECT REACHABLE`, exceed it without changing the world.
+                if command.command().is_push() {
+                    let (snapshot, _) = self.undo.pop().expect("a push saves its turn");
+                    self.restore_turn(snapshot);
+                }
+                self.spent = Spent {
+                    started: self.spent.started,
+                    ..spent
+                };
+                self.pushing = Default::default();
+                self.commands.push_front(command);
+                return Err(ExecError::new(span, ExecCause::Budget(message)));
+            }
+            let elapsed = started.elapsed();
+            self.report.commands += 1;
+            self.report.timings.push((span.clone(), elapsed));
+            let (pushes, stats) = std::mem::take(&mut self.pushing);
+            if let Some(profile) = &mut self.profile {
+                profile.entries.push(ProfileEntry {
+                    span: span.clone(),
+                    elapsed,
+                    pushes,
+                    stats,
+                });
+            }
+            if command.command().is_assertion() {
+                match result {
+                    Ok(_) => self.report.assertions_passed += 1,
+                    Err(_) => self.report.assertions_failed += 1,
+                }
+            }
+            if result.is_ok() {
+                self.invoke(|executor| &mut executor.after, &command);
+            }
+            if let (Some(history), Some(rewind)) = (&mut self.history, rewind) {
+                history.push(rewind);
+            }
+            result
+                .map(|_| span.clone())
+                .map_err(|cause| ExecError::new(span, cause))
         } else {
             panic!("no more commands");
         }
     }
 
-    /// Runs all the commands until there are no more.
-    pub fn run_all(&mut self) -> ParseResult<()> {
+    /// Returns the state of the executor before the command, keeping only the
+    /// parts of the undo history, the checkpoints and the worlds it changes.
+    fn rewind(&self, command: SpannedCommand, spent: Spent) -> Rewind {
+        let undo_len = match command.command() {
+            Command::Undo(pushes) => self.undo.len().saturating_sub(*pushes),
+            Command::Restore(_) | Command::CreateWorld(_) | Command::UseWorld(_) => 0,
+            _ => self.undo.len(),
+        };
+        let checkpoint = match command.command() {
+            Command::Checkpoint(name) => Some((name.clone(), self.checkpoints.get(name).cloned())),
+            _ => None,
+        };
+        let worlds = match command.command() {
+            Command::CreateWorld(_) | Command::UseWorld(_) => {
+                Some((self.world_name.clone(), self.worlds.clone()))
+            }
+            _ => None,
+        };
+
+        Rewind {
+            command,
+            snapshot: (self.world.fork(), self.meta.clone(), self.goal.clone()),
+            rng: self.rng,
+            spent,
+            undo_len,
+            undo_tail: self.undo[undo_len..].to_vec(),
+            checkpoint,
+            worlds,
+        }
+    }
+
+    /// Runs the commands until the predicate on the world and the meta table
+    /// holds after a command, or until there are no more. Returns the span of
+    /// the command after which the predicate holds, or `None` if it never
+    /// holds.
+    ///
+    /// The remaining commands can be run further, e.g. by [Executor::step].
+    pub fn run_until(
+        &mut self,
+        mut predicate: impl FnMut(&World, &MetaTable) -> bool,
+    ) -> ExecResult<Option<Span>> {
+        while self.has_next() {
+            let span = self.step()?;
+            if predicate(&self.world, &self.meta) {
+                return Ok(Some(span));
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Resets the executor to run the pushed sources again from the first
+    /// command, on an empty world.
+    ///
+    /// The world, the meta table, the goal, the undo history, the checkpoints
+    /// and the remaining commands are cleared, while the pushed sources, the
+    /// definitions of their variables and macros and the output are kept.
+    pub fn reset(&mut self) {
+        self.spent = Spent::default();
+        self.report = ExecutionReport::default();
+        if let Some(profile) = &mut self.profile {
+            profile.entries.clear();
+        }
+        self.world = World::new();
+        self.meta = MetaTable::new();
+        self.goal = Goal::new();
+        self.rng = Rng::default();
+        self.undo.clear();
+        self.checkpoints.clear();
+        self.world_name = MAIN_WORLD.into();
+        self.worlds.clear();
+        if let Some(history) = &mut self.history {
+            history.clear();
+        }
+        self.commands = self.pushed.iter().cloned().collect();
+    }
+
+    /// Rewinds the last executed command, so that it is the next command to
+    /// execute again. Returns the span of the command, or `None` if no command
+    /// is executed since recording them, see [Executor::set_rewinding].
+    ///
+    /// The world, the meta table, the goal, the undo history and the
+    /// checkpoints are reverted, and the budget spent by the command is
+    /// refunded except for the time, but the text written by `SHOW` is not.
+    pub fn step_back(&mut self) -> Option<Span> {
+        let rewind = self.history.as_mut()?.pop()?;
+        (self.world, self.meta, self.goal) = rewind.snapshot;
+        self.spent = Spent {
+            started: self.spent.started,
+            ..rewind.spent
+        };
+        self.rng = rewind.rng;
+        self.undo.truncate(rewind.undo_len);
+        self.undo.extend(rewind.undo_tail);
+        if let Some((name, checkpoint)) = rewind.checkpoint {
+            match checkpoint {
+                Some(checkpoint) => self.checkpoints.insert(name, checkpoint),
+                None => self.checkpoints.remove(&name),
+            };
+        }
+        if let Some((name, worlds)) = rewind.worlds {
+            self.world_name = name;
+            self.worlds = worlds;
+        }
+
+        let span = rewind.command.span().clone();
+        self.commands.push_front(rewind.command);
+        Some(span)
+    }
+
+    /// Runs all the commands until there are no more. Returns the summary of
+    /// the executed commands, see [Executor::report].
+    ///
+    /// ```
+    /// # use std::sync::Arc;
+    /// # use parabox_parser::{Executor, StringSource};
+    /// let mut executor = Executor::new();
+    /// let source = StringSource::new("DEFINE WALL #wall\nEXPECT #wall orphan".to_string());
+    /// executor.push_source(Arc::new(source)).unwrap();
+    ///
+    /// let report = executor.run_all().unwrap();
+    /// assert_eq!(report.commands, 2);
+    /// assert_eq!(report.assertions_passed, 1);
+    /// ```
+    pub fn run_all(&mut self) -> ExecResult<ExecutionReport> {
         while self.has_next() {
             self.step()?;
         }
 
+        Ok(self.report.clone())
+    }
+
+    /// Counts the command against the budget, or returns an error if it would
+    /// exceed the budget.
+    fn spend(&mut self, command: &SpannedCommand) -> Result<(), String> {
+        let pushing = command.command().is_push();
+        let spent = &mut self.spent;
+        let budget = &self.budget;
+
+        if budget.max_commands.is_some_and(|max| spent.commands >= max) {
+            return Err(format!(
+                "exceeded the budget of {} commands",
+                spent.commands
+            ));
+        }
+        if pushing && budget.max_pushes.is_some_and(|max| spent.pushes >= max) {
+            return Err(format!("exceeded the budget of {} pushes", spent.pushes));
+        }
+        if pushing
+            && budget
+                .max_evaluations
+                .is_some_and(|max| spent.evaluations >= max)
+        {
+            return Err(self.exhausted(budget.max_evaluations, "evaluations"));
+        }
+        let started = *spent.started.get_or_insert_with(Instant::now);
+        if let Some(timeout) = budget.timeout {
+            if started.elapsed() > timeout {
+                return Err(format!("exceeded the timeout of {:?}", timeout));
+            }
+        }
+
+        spent.commands += 1;
+        spent.pushes += pushing as usize;
         Ok(())
     }
+
+    /// Returns the limit of the next push, i.e. what is left of the budget.
+    fn push_limit(&self) -> PushLimit {
+        PushLimit {
+            max_evaluations: self
+                .budget
+                .max_evaluations
+                .map(|max| max.saturating_sub(self.spent.evaluations)),
+            deadline: self
+                .spent
+                .started
+                .zip(self.budget.timeout)
+                .map(|(started, timeout)| started + timeout),
+        }
+    }
+
+    /// Returns the message of a push or a search exceeding the budget, by the
+    /// time or by the maximum of the unit, e.g. of the evaluations.
+    fn exhausted(&self, max: Option<usize>, unit: &str) -> String {
+        match self.budget.timeout {
+            Some(timeout)
+                if self
+                    .push_limit()
+                    .deadline
+                    .is_some_and(|deadline| deadline <= Instant::now()) =>
+            {
+                format!("exceeded the timeout of {:?}", timeout)
+            }
+            _ => format!(
+                "exceeded the budget of {} {}",
+                max.unwrap_or_default(),
+                unit
+            ),
+        }
+    }
 }
 
 impl Executor {
-    fn execute(&mut self, command: Command) -> Result<(), String> {
-        let block = command.block;
-        match command.operation {
+    pub(crate) fn execute(&mut self, command: Command) -> Result<(), ExecCause> {
+        let (block, operation) = match command {
+            Command::Operate { block, operation } => (block, operation),
+            Command::Undo(pushes) => return self.undo(pushes),
+            Command::ExpectSolved(solved) => return self.expect_solved(solved),
+            Command::ExpectMoves(moves) => return self.expect_moves(moves),
+            Command::ExpectTurn(turn) => return self.expect_turn(turn),
+            Command::Checkpoint(name) => return self.checkpoint(name),
+            Command::Restore(name) => return self.restore(name),
+            Command::CreateWorld(name) => return self.create_world(name),
+            Command::UseWorld(name) => return self.use_world(name),
+            Command::Show(block) => return self.show(block),
+            Command::List(keyword) => return self.list(keyword),
+            Command::Where(block) => return self.where_(block),
+            Command::Info(block) => return self.info(block),
+            Command::Seed(seed) => {
+                self.rng = Rng::new(seed);
+                return Ok(());
+            }
+            Command::Else { command, message } => {
+                return self.execute(*command).map_err(|cause| ExecCause::Else {
+                    message,
+                    cause: Box::new(cause),
+                });
+            }
+        };
+
+        match operation {
             Operation::Define(proto) => self.define(block, proto),
             Operation::Place(position) => self.place(block, position),
-            Operation::Push(direction, assertion) => self.push(block, direction, assertion),
+            Operation::PlaceRelative(direction, distance, anchor) => {
+                self.place_relative(block, direction, distance, anchor)
+            }
+            Operation::PlaceRandom(container, region) => {
+                self.place_random(block, container, region)
+            }
+            Operation::Push(direction, assertion) => {
+                let snapshot = self.turn_snapshot();
+                self.undo.push((snapshot, false));
+                self.push(block, direction, assertion)
+            }
             Operation::Expect(position) => self.expect(block, position),
+            Operation::ExpectNot(position) => self.expect_not(block, position),
+            Operation::ExpectNotIn(container) => self.expect_not_in(block, container),
+            Operation::ExpectReachable(position) => self.expect_reachable(block, position),
+            Operation::ExpectMap(rows) => self.expect_map(block, rows),
+            Operation::Goal(position) => self.target(block, position),
+            Operation::Display(char, color) => self.display(block, char, color),
+            Operation::Copy(target, deep) => self.copy(block, target, deep),
         }
     }
 
-    fn define(&mut self, block: EcoString, proto: MetaProtoType) -> Result<(), String> {
-        if self.contains_meta(&block) {
-            return Err(format!("redefinition of #{}", block));
-        }
+    fn define(&mut self, block: EcoString, proto: MetaProtoType) -> Result<(), ExecCause> {
+        let proto = self.name_to_key(&proto)?;
+        self.redefine(&block, &proto.reference().into_iter().collect())?;
 
-        let key = self.world.insert(self.name_to_key(&proto)?);
+        let key = self.world.insert(proto);
+        self.world.set_label(key, block.as_str());
         self.insert_meta(block, key);
 
         Ok(())
     }
 
-    fn place(&mut self, block: EcoString, position: MetaPosition) -> Result<(), String> {
-        self.world
-            .place(self.name_to_key(&block)?, self.name_to_key(&position)?);
+    /// Frees the name of a block for a new definition according to the
+    /// [Redefinition] policy. The `kept` blocks cannot be replaced, e.g. the
+    /// blocks being copied.
+    fn redefine(&mut self, name: &EcoString, kept: &HashSet<BlockKey>) -> Result<(), ExecCause> {
+        let Some(key) = self.meta.get_key(name) else {
+            return Ok(());
+        };
+
+        match self.redefinition {
+            Redefinition::Error => Err(format!("redefinition of #{}", name).into()),
+            Redefinition::Shadow => {
+                let shadow = (1..)
+                    .map(|index| EcoString::from(format!("{}@{}", name, index)))
+                    .find(|shadow| !self.contains_meta(shadow))
+                    .unwrap();
+                self.world.set_label(key, shadow.as_str());
+                self.insert_meta(shadow, key);
+                Ok(())
+            }
+            Redefinition::Replace => {
+                let info = &self.world[key].info;
+                if kept.contains(&key)
+                    || !info.references.is_empty()
+                    || info.infinity.is_some()
+                    || info.epsilon.is_some()
+                {
+                    return Err(
+                        format!("cannot replace #{}, which is still referred to", name).into(),
+                    );
+                }
+                self.world.remove(key);
+                self.meta.remove_by_key(&key);
+                Ok(())
+            }
+        }
+    }
+
+    fn copy(&mut self, block: EcoString, target: EcoString, deep: bool) -> Result<(), ExecCause> {
+        // The copied blocks with the identifiers of their copies, from the
+        // block to the blocks inside it, e.g. `#target::wall` for `#wall`.
+        let root = self.name_to_key(&block)?;
+        let mut copies = vec![(root, target.clone())];
+        let mut copied = HashSet::from([root]);
+        let mut index = 0;
+        while deep && index < copies.len() {
+            let interior = self.world[copies[index].0].state.interior.clone();
+            for &child in interior.iter().flatten().flatten() {
+                if copied.insert(child) {
+                    let name = format!("{}::{}", target, self.key_to_name(&child)?);
+                    copies.push((child, name.into()));
+                }
+            }
+            index += 1;
+        }
+        if self.redefinition == Redefinition::Error {
+            if let Some((_, name)) = copies.iter().find(|(_, name)| self.contains_meta(name)) {
+                return Err(format!("redefinition of #{}", name).into());
+            }
+        }
+        for (_, name) in &copies {
+            self.redefine(name, &copied)?;
+        }
+
+        // The referenced blocks are copied first, so that an alias of a copied
+        // block refers to the copy.
+        let mut keys = HashMap::new();
+        let mut pending = copies.clone();
+        while !pending.is_empty() {
+            let index = pending
+                .iter()
+                .position(|(key, _)| {
+                    self.world[*key].proto.reference().is_none_or(|reference| {
+                        keys.contains_key(&reference) || !copied.contains(&reference)
+                    })
+                })
+                .unwrap_or(0);
+            let (key, name) = pending.remove(index);
+            let copy = self.world.insert(remap(self.world[key].proto, &keys));
+            self.world.set_label(copy, name.as_str());
+            if let Some(char) = self.meta.get_char(&key) {
+                self.meta.set_char(copy, char);
+            }
+            if let Some(color) = self.meta.get_color(&key) {
+                self.meta.set_color(copy, color);
+            }
+            self.insert_meta(name, copy);
+            keys.insert(key, copy);
+        }
+
+        // The copies are placed in the copies of their containers only, so
+        // that the copy of the block is orphan unless inside itself.
+        for (key, _) in &copies {
+            let position = self.world[*key].state.position;
+            if let Some(&container) = position.container.and_then(|c| keys.get(&c)) {
+                self.world
+                    .place(keys[key], Position::inside(container, position.pos));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn display(
+        &mut self,
+        block: EcoString,
+        char: Option<char>,
+        color: Option<AnsiColor>,
+    ) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        if let Some(char) = char {
+            self.meta.set_char(key, char);
+        }
+        if let Some(color) = color {
+            self.meta.set_color(key, color);
+        }
+
+        Ok(())
+    }
+
+    fn place(&mut self, block: EcoString, position: MetaPosition) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        let target = self.name_to_key(&position)?;
+        if let (Some(container), Some(name)) = (target.container, &position.container) {
+            let interior = &self.world[container].state.interior;
+            let size = (interior.len(), interior.first().map_or(0, Vec::len));
+            if target.pos.0 >= size.0 || target.pos.1 >= size.1 {
+                return Err(format!(
+                    "cannot place #{} at {:?}, outside #{} of size {:?}",
+                    block, target.pos, name, size
+                )
+                .into());
+            }
+        }
+        self.world.place(key, target);
+
+        Ok(())
+    }
+
+    fn place_relative(
+        &mut self,
+        block: EcoString,
+        direction: Direction,
+        distance: usize,
+        anchor: EcoString,
+    ) -> Result<(), ExecCause> {
+        let position = self.world[self.name_to_key(&anchor)?].state.position;
+        let Some(container) = position.container else {
+            return Err(format!("cannot place #{} relative to orphan #{}", block, anchor).into());
+        };
+
+        let (dx, dy) = direction.delta_for(self.world.orientation());
+        let (width, height) = self.world[container].proto.size();
+        let distance = distance as isize;
+        let x = position.pos.0 as isize + dx * distance;
+        let y = position.pos.1 as isize + dy * distance;
+        if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
+            return Err(format!(
+                "cannot place #{} at {:?}, outside #{}",
+                block,
+                (x, y),
+                self.key_to_name(&container)?
+            )
+            .into());
+        }
+
+        let position = Position::inside(container, (x as usize, y as usize));
+        self.world.place(self.name_to_key(&block)?, position);
+
+        Ok(())
+    }
+
+    fn place_random(
+        &mut self,
+        block: EcoString,
+        container: EcoString,
+        region: Option<(Size, Size)>,
+    ) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        let container_key = self.name_to_key(&container)?;
+        let interior = &self.world[container_key].state.interior;
+        let size = (interior.len(), interior.first().map_or(0, Vec::len));
+        let ((x, y), (width, height)) = region.unwrap_or(((0, 0), size));
+        if x + width > size.0 || y + height > size.1 {
+            return Err(format!(
+                "cannot place #{} in region at {:?} of size {:?}, outside #{}",
+                block,
+                (x, y),
+                (width, height),
+                container
+            )
+            .into());
+        }
+
+        // The empty cells are listed row by row, so that the same seed picks
+        // the same cell. The cell of the block itself is empty to move it.
+        let cells: Vec<Size> = (y..y + height)
+            .flat_map(|y| (x..x + width).map(move |x| (x, y)))
+            .filter(|&(x, y)| interior[x][y].is_none_or(|other| other == key))
+            .collect();
+        if cells.is_empty() {
+            return Err(format!("no empty cell to place #{} in #{}", block, container).into());
+        }
+
+        let pos = cells[self.rng.below(cells.len())];
+        self.world.place(key, Position::inside(container_key, pos));
 
         Ok(())
     }
@@ -159,36 +1216,67 @@ impl Executor {
         block: EcoString,
         direction: Direction,
         assertion: Assertion,
-    ) -> Result<(), String> {
-        let result = match self.world.push(self.name_to_key(&block)?, direction) {
-            Ok(result) => result,
+    ) -> Result<(), ExecCause> {
+        self.pushing.0 += 1;
+        let limit = self.push_limit();
+        let result = match self
+            .world
+            .push_limited(self.name_to_key(&block)?, direction, limit)
+        {
+            Ok((result, stats)) => {
+                self.spent.evaluations += stats.evaluations;
+                self.pushing.1.evaluations += stats.evaluations;
+                self.pushing.1.movements += stats.movements;
+                result
+            }
+            Err(MoveError::Exhausted(..)) => {
+                let message = self.exhausted(self.budget.max_evaluations, "evaluations");
+                return Err(ExecCause::Budget(message.into()));
+            }
             Err(error) => {
+                // The engine cannot resolve a block the executor does not
+                // know, so the error is reported as is.
+                let Ok(name) = self.key_to_name(&error.key()) else {
+                    return Err(ExecCause::Move(error));
+                };
                 match error {
-                    MoveError::Orphan(key) => {
-                        self.solve_orphan(self.key_to_name(&key)?)?;
-                    }
-                    MoveError::NoInfinity(key) => {
-                        self.solve_no_infinity(self.key_to_name(&key)?)?;
-                    }
-                    MoveError::NoEpsilon(key) => {
-                        self.solve_no_epsilon(self.key_to_name(&key)?)?;
-                    }
+                    MoveError::Orphan(..) => self.solve_orphan(name)?,
+                    MoveError::NoInfinity(..) => self.solve_no_infinity(name)?,
+                    MoveError::NoEpsilon(..) => self.solve_no_epsilon(name)?,
+                    MoveError::Exhausted(..) => unreachable!(),
                 }
 
                 return self.push(block, direction, assertion);
             }
         };
 
+        if let Some((_, moved)) = self.undo.last_mut() {
+            *moved = result;
+        }
+        if result {
+            self.report.pushes_moved += 1;
+        } else {
+            self.report.pushes_static += 1;
+        }
+
         match assertion {
             Assertion::None => {}
             Assertion::Moved => {
                 if !result {
-                    return Err("the world is not moved".to_string());
+                    return Err(ExecCause::assertion(
+                        "the world is not moved",
+                        AssertedValue::Moved(true),
+                        AssertedValue::Moved(false),
+                    ));
                 }
             }
             Assertion::Static => {
                 if result {
-                    return Err("the world is moved".to_string());
+                    return Err(ExecCause::assertion(
+                        "the world is moved",
+                        AssertedValue::Moved(false),
+                        AssertedValue::Moved(true),
+                    ));
                 }
             }
         }
@@ -196,26 +1284,296 @@ impl Executor {
         Ok(())
     }
 
-    fn expect(&mut self, block: EcoString, position: MetaPosition) -> Result<(), String> {
-        if self.world[self.name_to_key(&block)?].state.position != self.name_to_key(&position)? {
-            return Err(format!("expected #{} to be {}", block, position));
+    fn expect(&mut self, block: EcoString, position: MetaPosition) -> Result<(), ExecCause> {
+        let actual = self.world[self.name_to_key(&block)?].state.position;
+        if actual != self.name_to_key(&position)? {
+            return Err(ExecCause::Assertion {
+                message: format!("expected #{} to be {}", block, position).into(),
+                actual: self.key_to_name(&actual).ok().map(AssertedValue::Position),
+                expected: AssertedValue::Position(position),
+            });
         }
 
         Ok(())
     }
-}
 
-impl Executor {
-    fn solve_orphan(&mut self, orphan: EcoString) -> Result<(), String> {
-        let container: EcoString = format!("{}::container", orphan).into();
-        self.define(container.clone(), MetaProtoType::Void { size: (5, 5) })?;
-        self.place(orphan.clone(), MetaPosition::inside(container, (2, 2)))?;
+    fn expect_not(&mut self, block: EcoString, position: MetaPosition) -> Result<(), ExecCause> {
+        if self.world[self.name_to_key(&block)?].state.position == self.name_to_key(&position)? {
+            return Err(ExecCause::assertion(
+                format!("expected #{} not to be {}", block, position),
+                AssertedValue::NotPosition(position.clone()),
+                AssertedValue::Position(position),
+            ));
+        }
+
         Ok(())
     }
 
-    fn solve_no_infinity(&mut self, block: EcoString) -> Result<(), String> {
-        let infinity: EcoString = format!("{}::infinity", block).into();
-        self.define(
+    fn expect_not_in(&mut self, block: EcoString, container: EcoString) -> Result<(), ExecCause> {
+        let container_key = self.name_to_key(&container)?;
+        let actual = self.world[self.name_to_key(&block)?].state.position;
+        if actual.container == Some(container_key) {
+            return Err(ExecCause::Assertion {
+                message: format!("expected #{} not to be in #{}", block, container).into(),
+                actual: self.key_to_name(&actual).ok().map(AssertedValue::Position),
+                expected: AssertedValue::NotIn(container),
+            });
+        }
+
+        Ok(())
+    }
+
+    #[cfg(feature = "solver")]
+    fn expect_reachable(
+        &mut self,
+        block: EcoString,
+        position: MetaPosition,
+    ) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        let goal = Goal::new().with_target(key, self.name_to_key(&position)?);
+        // Only the player is pushed if designated, as by `MOVE`.
+        let mut solver = Solver::new();
+        if let Some(player) = self.player() {
+            solver = solver.with_players([self.name_to_key(&player)?]);
+        }
+        if let Some(max_states) = self.budget.max_states {
+            solver = solver.with_max_states(max_states);
+        }
+        if let Some(deadline) = self.push_limit().deadline {
+            solver = solver.with_deadline(deadline);
+        }
+
+        match solver.try_solve(&self.world, &goal) {
+            Ok(_) => Ok(()),
+            Err(SolveError::Exhausted) => {
+                let message = self.exhausted(self.budget.max_states, "states");
+                Err(ExecCause::Budget(message.into()))
+            }
+            Err(SolveError::Unreachable) => Err(ExecCause::Assertion {
+                message: format!("expected #{} to be reachable {}", block, position).into(),
+                expected: AssertedValue::Position(position),
+                actual: self
+                    .key_to_name(&self.world[key].state.position)
+                    .ok()
+                    .map(AssertedValue::Position),
+            }),
+        }
+    }
+
+    #[cfg(not(feature = "solver"))]
+    fn expect_reachable(&mut self, _: EcoString, _: MetaPosition) -> Result<(), ExecCause> {
+        Err("`EXPECT REACHABLE` requires the `solver` feature"
+            .to_string()
+            .into())
+    }
+
+    fn target(&mut self, block: EcoString, position: MetaPosition) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        let position = self.name_to_key(&position)?;
+        self.goal.add_target(key, position);
+
+        Ok(())
+    }
+
+    fn expect_solved(&mut self, solved: bool) -> Result<(), ExecCause> {
+        let reached = self.goal.reached(&self.world);
+        let targets = self.goal.targets().len();
+        if solved && reached != targets {
+            return Err(ExecCause::assertion(
+                format!(
+                    "expected the goal to be solved, {} of {} targets reached",
+                    reached, targets
+                ),
+                AssertedValue::Solved(true),
+                AssertedValue::Solved(false),
+            ));
+        }
+        if !solved && reached == targets {
+            return Err(ExecCause::assertion(
+                "expected the goal to be unsolved",
+                AssertedValue::Solved(false),
+                AssertedValue::Solved(true),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn expect_moves(&mut self, moves: usize) -> Result<(), ExecCause> {
+        if self.moves() != moves {
+            return Err(ExecCause::assertion(
+                format!("expected {} moves, found {}", moves, self.moves()),
+                AssertedValue::Count(moves),
+                AssertedValue::Count(self.moves()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn expect_turn(&mut self, turn: usize) -> Result<(), ExecCause> {
+        if self.turn() != turn {
+            return Err(ExecCause::assertion(
+                format!("expected turn {}, found {}", turn, self.turn()),
+                AssertedValue::Count(turn),
+                AssertedValue::Count(self.turn()),
+            ));
+        }
+
+        Ok(())
+    }
+
+    fn expect_map(&mut self, container: EcoString, rows: Vec<EcoString>) -> Result<(), ExecCause> {
+        let interior = &self.world[self.name_to_key(&container)?].state.interior;
+        let (width, height) = (interior.len(), interior.first().map_or(0, Vec::len));
+        let found = |x: usize, y: usize| match interior[x][y] {
+            Some(key) => self.meta.get_char(&key).unwrap_or('?'),
+            None => '.',
+        };
+        let actual = || {
+            let rows = (0..height).rev();
+            let rows = rows.map(|y| (0..width).map(|x| found(x, y)).collect());
+            AssertedValue::Map(rows.collect())
+        };
+        let expected = AssertedValue::Map(rows.clone());
+        let rows: Vec<Vec<char>> = rows.iter().map(|row| row.chars().collect()).collect();
+        if rows.len() != height || rows.iter().any(|row| row.len() != width) {
+            return Err(ExecCause::assertion(
+                format!(
+                    "expected a map of {} rows of {} characters for #{}",
+                    height, width, container
+                ),
+                expected,
+                actual(),
+            ));
+        }
+
+        for (y, row) in rows.iter().rev().enumerate() {
+            for (x, &char) in row.iter().enumerate() {
+                if found(x, y) != char {
+                    return Err(ExecCause::assertion(
+                        format!(
+                            "expected `{}` at {:?} in #{}, found `{}`",
+                            char,
+                            (x, y),
+                            container,
+                            found(x, y)
+                        ),
+                        expected,
+                        actual(),
+                    ));
+                }
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Executor {
+    /// Restores the world and the meta table as they were before the last
+    /// pushes, reverting every command executed since.
+    fn undo(&mut self, pushes: usize) -> Result<(), ExecCause> {
+        if pushes > self.undo.len() {
+            return Err(format!(
+                "cannot undo {} pushes, only {} executed",
+                pushes,
+                self.undo.len()
+            )
+            .into());
+        }
+
+        if pushes > 0 {
+            let start = self.undo.len() - pushes;
+            let snapshot = self.undo.drain(start..).next().unwrap().0;
+            self.restore_turn(snapshot);
+        }
+
+        Ok(())
+    }
+
+    /// Returns the snapshot of the executor before a push, without the
+    /// committed movements of the world, so that saving it does not copy the
+    /// whole history.
+    fn turn_snapshot(&mut self) -> Snapshot {
+        let history = self.world.take_history();
+        let snapshot = (self.world.fork(), self.meta.clone(), self.goal.clone());
+        self.world.set_history(history);
+        snapshot
+    }
+
+    /// Restores the snapshot saved by [Executor::turn_snapshot], with the
+    /// movements committed up to its turn.
+    fn restore_turn(&mut self, (world, meta, goal): Snapshot) {
+        let mut history = self.world.take_history();
+        history.truncate(history.partition_point(|movement| movement.turn <= world.turn()));
+        (self.world, self.meta, self.goal) = (world, meta, goal);
+        self.world.set_history(history);
+    }
+
+    /// Saves the world, the meta table, the goal and the undo history under
+    /// the name, replacing any previous checkpoint of the name.
+    fn checkpoint(&mut self, name: EcoString) -> Result<(), ExecCause> {
+        let snapshot = (self.world.fork(), self.meta.clone(), self.goal.clone());
+        self.checkpoints.insert(name, (snapshot, self.undo.clone()));
+
+        Ok(())
+    }
+
+    /// Restores the world, the meta table, the goal and the undo history saved
+    /// under the name. The checkpoint can be restored again.
+    fn restore(&mut self, name: EcoString) -> Result<(), ExecCause> {
+        let Some(((world, meta, goal), undo)) = self.checkpoints.get(&name) else {
+            return Err(format!("no checkpoint named `{}`", name).into());
+        };
+        self.world = world.fork();
+        self.meta = meta.clone();
+        self.goal = goal.clone();
+        self.undo = undo.clone();
+
+        Ok(())
+    }
+
+    /// Creates an empty world with an empty goal under the name.
+    fn create_world(&mut self, name: EcoString) -> Result<(), ExecCause> {
+        if name == self.world_name || self.worlds.contains_key(&name) {
+            return Err(format!("redefinition of world #{}", name).into());
+        }
+        let snapshot = (World::new(), MetaTable::new(), Goal::new());
+        self.worlds.insert(name, (snapshot, vec![]));
+
+        Ok(())
+    }
+
+    /// Uses the world of the name, keeping the current world aside with its
+    /// goal and its undo history.
+    fn use_world(&mut self, name: EcoString) -> Result<(), ExecCause> {
+        if name == self.world_name {
+            return Ok(());
+        }
+        let Some(((world, meta, goal), undo)) = self.worlds.remove(&name) else {
+            return Err(format!("no world named #{}", name).into());
+        };
+        let world = std::mem::replace(&mut self.world, world);
+        let meta = std::mem::replace(&mut self.meta, meta);
+        let goal = std::mem::replace(&mut self.goal, goal);
+        let undo = std::mem::replace(&mut self.undo, undo);
+        let current = std::mem::replace(&mut self.world_name, name);
+        self.worlds.insert(current, ((world, meta, goal), undo));
+
+        Ok(())
+    }
+
+    fn solve_orphan(&mut self, orphan: EcoString) -> Result<(), ExecCause> {
+        let container: EcoString = format!("{}::container", orphan).into();
+        self.define(container.clone(), MetaProtoType::Void { size: (5, 5) })?;
+        self.place(orphan.clone(), MetaPosition::inside(container, (2, 2)))?;
+        Ok(())
+    }
+
+    fn solve_no_infinity(&mut self, block: EcoString) -> Result<(), ExecCause> {
+        let infinity: EcoString = format!("{}::infinity", block).into();
+        self.define(
             infinity.clone(),
             MetaProtoType::Infinity { reference: block },
         )?;
@@ -223,7 +1581,7 @@ impl Executor {
         Ok(())
     }
 
-    fn solve_no_epsilon(&mut self, block: EcoString) -> Result<(), String> {
+    fn solve_no_epsilon(&mut self, block: EcoString) -> Result<(), ExecCause> {
         let epsilon: EcoString = format!("{}::epsilon", block).into();
         self.define(
             epsilon.clone(),
@@ -237,9 +1595,115 @@ impl Executor {
 }
 
 impl Executor {
+    /// Writes the hollow blocks of the world, or the interior of the block, to
+    /// the output, with the display characters of the meta table.
+    #[cfg(feature = "format")]
+    fn show(&mut self, block: Option<EcoString>) -> Result<(), ExecCause> {
+        let formatter = Formatter::new(&self.world, &self.meta);
+        let matrix = match block {
+            Some(block) => {
+                let key = self.name_to_key(&block)?;
+                if !self.world[key].proto.is_hollow() {
+                    return Err(format!("#{} has no interior to show", block).into());
+                }
+                formatter.format_block(key)
+            }
+            None => formatter.format(2),
+        };
+        let text = matrix.render();
+        (self.output)(&text);
+
+        Ok(())
+    }
+
+    #[cfg(not(feature = "format"))]
+    fn show(&mut self, _block: Option<EcoString>) -> Result<(), ExecCause> {
+        Err("`SHOW` requires the `format` feature".to_string().into())
+    }
+
+    /// Writes the blocks of the prototype keyword, or all the blocks, sorted by
+    /// name, with their prototypes and positions to the output.
+    fn list(&mut self, keyword: Option<EcoString>) -> Result<(), ExecCause> {
+        let mut lines = Vec::new();
+        for block in self.meta.names() {
+            if block.ends_with("::interior") {
+                continue;
+            }
+
+            let key = self.meta.get_key(&block).unwrap();
+            let proto = self.key_to_name(&self.world[key].proto)?;
+            if keyword
+                .as_ref()
+                .is_some_and(|keyword| keyword != proto.keyword())
+            {
+                continue;
+            }
+            let position = self.key_to_name(&self.world[key].state.position)?;
+            lines.push((format!("#{}", block), proto.to_string(), position));
+        }
+
+        let names = lines.iter().map(|line| line.0.len()).max().unwrap_or(0);
+        let protos = lines.iter().map(|line| line.1.len()).max().unwrap_or(0);
+        let mut text = String::new();
+        for (block, proto, position) in lines {
+            text.push_str(&format!("{block:names$}  {proto:protos$}  {position}\n"));
+        }
+        (self.output)(&text);
+
+        Ok(())
+    }
+
+    /// Writes the position of the block to the output.
+    fn where_(&mut self, block: EcoString) -> Result<(), ExecCause> {
+        let position = self.world[self.name_to_key(&block)?].state.position;
+        let text = format!("#{} {}\n", block, self.key_to_name(&position)?);
+        (self.output)(&text);
+
+        Ok(())
+    }
+
+    /// Writes the prototype, the position, the blocks referring to the block
+    /// and the blocks inside it to the output.
+    fn info(&mut self, block: EcoString) -> Result<(), ExecCause> {
+        let key = self.name_to_key(&block)?;
+        let state = &self.world[key].state;
+        let mut text = format!(
+            "#{}: {}\n  position: {}\n",
+            block,
+            self.key_to_name(&self.world[key].proto)?,
+            self.key_to_name(&state.position)?
+        );
+
+        let referrers: Vec<_> = self
+            .world
+            .blocks_ordered()
+            .filter(|(_, other)| other.proto.reference() == Some(key))
+            .map(|(other, _)| Ok(format!("#{}", self.key_to_name(&other)?)))
+            .collect::<Result<_, ExecCause>>()?;
+        if !referrers.is_empty() {
+            text.push_str(&format!("  referenced by: {}\n", referrers.join(", ")));
+        }
+
+        for (x, column) in state.interior.iter().enumerate() {
+            for (y, &cell) in column.iter().enumerate() {
+                if let Some(cell) = cell {
+                    text.push_str(&format!(
+                        "  contains: #{} at {:?}\n",
+                        self.key_to_name(&cell)?,
+                        (x, y)
+                    ));
+                }
+            }
+        }
+        (self.output)(&text);
+
+        Ok(())
+    }
+
     /// Formats the positions of the blocks in the world.
     ///
-    /// This is useful for debugging purposes.
+    /// This is useful for debugging purposes. See [Executor::format_tree] for
+    /// the nesting of the blocks.
     pub fn format_positions(&self) -> String {
         let blocks = self
             .meta
@@ -250,10 +1714,6 @@ impl Executor {
         let mut result = String::new();
 
         for block in blocks {
-            if block.ends_with("::interior") {
-                continue;
-            }
-
             let key = self.meta.get_key(&block).unwrap();
             let position = self.world[key].state.position;
             result.push_str(&format!(
@@ -266,4 +1726,1222 @@ impl Executor {
 
         result
     }
+
+    /// Formats the blocks in the world as a tree of containers, with the
+    /// blocks inside each container indented under it, e.g.
+    ///
+    /// ```text
+    /// room
+    ///   box at (1, 0)
+    ///     wall at (0, 0)
+    /// ```
+    ///
+    /// The orphan blocks are the roots, by name, and the blocks inside a
+    /// container are ordered by position, from the top row. A cycle of
+    /// containers without orphan is rooted at its first block by name, with
+    /// its position.
+    pub fn format_tree(&self) -> String {
+        let names: Vec<_> = self
+            .meta
+            .names()
+            .into_iter()
+            .filter(|block| !block.ends_with("::interior"))
+            .collect();
+        let mut visited = HashSet::new();
+        let mut result = String::new();
+
+        let (orphans, contained): (Vec<_>, Vec<_>) = names.iter().partition(|block| {
+            let key = self.meta.get_key(block).unwrap();
+            self.world[key].state.position.container.is_none()
+        });
+        for block in orphans.into_iter().chain(contained) {
+            let key = self.meta.get_key(block).unwrap();
+            if visited.contains(&key) {
+                continue;
+            }
+            let position = self.world[key].state.position;
+            if position.container.is_some() {
+                let position = self.key_to_name(&position).unwrap();
+                result.push_str(&format!("{} {}\n", block, position));
+            } else {
+                result.push_str(&format!("{}\n", block));
+            }
+            visited.insert(key);
+            self.format_children(key, 1, &mut visited, &mut result);
+        }
+
+        result
+    }
+
+    fn format_children(
+        &self,
+        key: BlockKey,
+        depth: usize,
+        visited: &mut HashSet<BlockKey>,
+        result: &mut String,
+    ) {
+        let interior = &self.world[key].state.interior;
+        let height = interior.first().map_or(0, Vec::len);
+        for y in (0..height).rev() {
+            for (x, column) in interior.iter().enumerate() {
+                let Some(child) = column[y] else {
+                    continue;
+                };
+                let Some(name) = self.meta.get_name(&child) else {
+                    continue;
+                };
+                if name.ends_with("::interior") || !visited.insert(child) {
+                    continue;
+                }
+
+                result.push_str(&format!(
+                    "{:indent$}{} at {:?}\n",
+                    "",
+                    name,
+                    (x, y),
+                    indent = depth * 2
+                ));
+                self.format_children(child, depth + 1, visited, result);
+            }
+        }
+    }
+}
+
+/// Returns the command spanned in a source of its own, see
+/// [Executor::enqueue].
+fn enqueued(command: Command) -> SpannedCommand {
+    let text = command.to_string();
+    let range = 0..text.len();
+    let source: Arc<dyn Source> = Arc::new(NamedStringSource::new("<enqueued>".into(), text));
+    SpannedCommand::new(command, Span::new(source, range))
+}
+
+/// Returns the prototype referring to the copy of its reference, if copied.
+fn remap(proto: ProtoType, copies: &HashMap<BlockKey, BlockKey>) -> ProtoType {
+    let copy = |reference| copies.get(&reference).copied().unwrap_or(reference);
+    match proto {
+        ProtoType::Alias { reference } => ProtoType::Alias {
+            reference: copy(reference),
+        },
+        ProtoType::Infinity { reference } => ProtoType::Infinity {
+            reference: copy(reference),
+        },
+        ProtoType::Epsilon { size, reference } => ProtoType::Epsilon {
+            size,
+            reference: copy(reference),
+        },
+        proto => proto,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StringSource;
+    #[cfg(feature = "format")]
+    use anstyle::Style;
+    use parabox::MoveContext;
+    #[cfg(feature = "format")]
+    use parabox_format::MetaFmt;
+    use std::cell::RefCell;
+    use std::rc::Rc;
+
+    /// Runs the script, returning the output and the result.
+    fn run(script: &str) -> (String, ExecResult<()>) {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut executor = Executor::new();
+        let sink = output.clone();
+        executor.set_output(move |text| sink.borrow_mut().push_str(text));
+
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        let result = executor.run_all().map(drop);
+        let output = output.borrow().clone();
+        (output, result)
+    }
+
+    #[test]
+    fn test_warnings() {
+        let mut executor = Executor::new();
+        let script = "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box solid\n\
+                      DEFINE WALL #unused\nDEFINE ALIAS #alias ref #room\n\
+                      PLACE #box at (0, 0) in #room\nCHECKPOINT start\n\
+                      DEFINE WALL #wall\nRESTORE start\nDEFINE WALL #wall\nWHERE #wall";
+        executor.set_output(|_| {});
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+
+        let warnings: Vec<_> = executor
+            .warnings()
+            .iter()
+            .map(|warning| (warning.span().locate().0, warning.message()))
+            .collect();
+        assert_eq!(
+            warnings,
+            vec![
+                (2, "unused block #unused".into()),
+                (3, "unused block #alias".into()),
+                (8, "redefinition of #wall".into()),
+            ]
+        );
+        assert!(executor.run_all().is_ok());
+    }
+
+    #[test]
+    fn test_undo_history() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (4, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east\nPUSH #box east\n\
+                 PUSH #box east\nUNDO 2"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+
+        // The snapshots of the undo history do not copy the movements, which
+        // are kept up to the restored turn.
+        assert!(executor
+            .undo
+            .iter()
+            .all(|((world, _, _), _)| world.history().is_empty()));
+        assert_eq!(executor.world().turn(), 1);
+        assert_eq!(executor.world().history().len(), 1);
+        assert_eq!(executor.world().history()[0].turn, 1);
+    }
+
+    #[test]
+    fn test_step_back() {
+        let mut executor = Executor::new();
+        executor.set_output(|_| {});
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nCHECKPOINT start\nPUSH #box east\n\
+                 UNDO 1\nPUSH #box east\nEXPECT #box at (2, 0) in #room"
+                    .to_string(),
+            )))
+            .unwrap();
+        assert!(executor.step_back().is_none());
+
+        // The commands are recorded only once rewinding is enabled.
+        executor.step().unwrap();
+        assert!(executor.step_back().is_none());
+        executor.reset();
+        executor.set_rewinding(true);
+
+        for _ in 0..7 {
+            executor.step().unwrap();
+        }
+        let error = executor.step().unwrap_err();
+        let positions = executor.format_positions();
+
+        // Rewinding the failed `EXPECT` and the push before it.
+        assert_eq!(executor.step_back().unwrap().text(), error.span().text());
+        assert_eq!(executor.step_back().unwrap().text(), "PUSH #box east");
+        assert_ne!(executor.format_positions(), positions);
+        executor.step().unwrap();
+        assert_eq!(executor.format_positions(), positions);
+
+        // Rewinding the `UNDO` restores the undo history.
+        executor.step_back();
+        executor.step_back();
+        assert_eq!(executor.step_back().unwrap().text(), "PUSH #box east");
+        executor.step().unwrap();
+        executor.step().unwrap();
+        executor.step().unwrap();
+        assert_eq!(executor.format_positions(), positions);
+
+        // Rewinding the `CHECKPOINT` and the `DEFINE` commands.
+        while executor.step_back().is_some() {}
+        assert!(executor.meta().names().is_empty());
+        assert!(executor.execute(Command::restore("start".into())).is_err());
+        executor.run_all().unwrap_err();
+        assert_eq!(executor.format_positions(), positions);
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn test_display() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (2, 1) COLOR blue\nDEFINE WALL #wall CHAR '#'\n\
+                 MAP #map\n  xy\n  x = WALL\n  y = BOX #box SIZE (1, 1) CHAR 'b'\nEND"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+
+        let meta = executor.meta();
+        let key = |name: &str| meta.get_key(&name.into()).unwrap();
+        assert_eq!(meta.fmt_repr(key("room")), 'r');
+        assert_eq!(
+            meta.fmt_style(key("room")),
+            Style::new().fg_color(Some(AnsiColor::Blue.into()))
+        );
+        assert_eq!(meta.fmt_repr(key("wall")), '#');
+        assert_eq!(meta.fmt_style(key("wall")), Style::new());
+        assert_eq!(meta.fmt_repr(key("map_0_0")), 'x');
+        assert_eq!(meta.fmt_repr(key("box")), 'b');
+    }
+
+    #[test]
+    fn test_copy() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (2, 1)\n\
+                 DEFINE WALL #wall CHAR '#'\nDEFINE ALIAS #alias ref #box\n\
+                 PLACE #box at (0, 0) in #room\nPLACE #wall at (0, 0) in #box\n\
+                 PLACE #alias at (1, 0) in #box\n\
+                 COPY #box AS #copy DEEP\nCOPY #wall AS #other\nPLACE #copy at (2, 0) in #room\n\
+                 EXPECT #copy::wall at (0, 0) in #copy\nEXPECT #other ORPHAN\n\
+                 EXPECT MAP #copy\n  #a\nEXPECT MAP #room\n  b.b"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+
+        let meta = executor.meta();
+        let key = |name: &str| meta.get_key(&name.into()).unwrap();
+        // The copied alias refers to the copy of the box.
+        assert_eq!(
+            executor.world()[key("copy::alias")].proto,
+            ProtoType::Alias {
+                reference: key("copy")
+            }
+        );
+        assert_eq!(
+            executor.world()[key("alias")].proto,
+            ProtoType::Alias {
+                reference: key("box")
+            }
+        );
+
+        let (_, result) = run(
+            "DEFINE BOX #box size (1, 1)\nDEFINE WALL #wall\nPLACE #wall at (0, 0) in #box\n\
+             DEFINE WALL #copy::wall\nCOPY #box AS #copy DEEP",
+        );
+        assert_eq!(result.unwrap_err().message(), "redefinition of #copy::wall");
+    }
+
+    #[test]
+    fn test_hooks() {
+        let log = Rc::new(RefCell::new(Vec::new()));
+        let mut executor = Executor::new();
+        let before = log.clone();
+        executor.on_before(move |executor, command| {
+            let blocks = executor.meta().names().len();
+            before
+                .borrow_mut()
+                .push(format!("{} {}", command.span().text(), blocks));
+        });
+        let after = log.clone();
+        executor.on_after(move |executor, _| {
+            let blocks = executor.world().blocks_ordered().count();
+            after.borrow_mut().push(format!("after {}", blocks));
+        });
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE WALL #wall\nEXPECT #wall at (0, 0) in #wall".to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap_err();
+
+        assert_eq!(
+            *log.borrow(),
+            vec![
+                "DEFINE WALL #wall 0",
+                "after 1",
+                "EXPECT #wall at (0, 0) in #wall 1"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_run_until() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east\nPUSH #box east\nPUSH #box west"
+                    .to_string(),
+            )))
+            .unwrap();
+        let at = |x| {
+            move |world: &World, meta: &MetaTable| {
+                meta.get_key(&"box".into())
+                    .is_some_and(|key| world[key].state.position.pos == (x, 0))
+            }
+        };
+
+        let span = executor.run_until(at(2)).unwrap().unwrap();
+        assert_eq!(span.locate().0, 4);
+        assert_eq!(executor.peek().unwrap().span().text(), "PUSH #box west");
+        assert!(executor.run_until(at(2)).unwrap().is_none());
+        assert!(!executor.has_next());
+    }
+
+    #[test]
+    fn test_format_tree() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 2)\nDEFINE BOX #box size (2, 1)\n\
+                 DEFINE BOX #solid solid\nDEFINE WALL #wall\nDEFINE WALL #orphan\n\
+                 DEFINE BOX #loop size (1, 1)\n\
+                 PLACE #box at (1, 0) in #room\nPLACE #solid at (0, 1) in #room\n\
+                 PLACE #wall at (1, 0) in #box\nPLACE #loop at (0, 0) in #loop"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+
+        assert_eq!(
+            executor.format_tree(),
+            "orphan\n\
+             room\n\
+             \x20 solid at (0, 1)\n\
+             \x20 box at (1, 0)\n\
+             \x20   wall at (1, 0)\n\
+             loop at (0, 0) in #loop\n"
+        );
+    }
+
+    #[test]
+    fn test_enqueue() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE WALL #box\n\
+                 PLACE #box at (0, 0) in #room\nEXPECT #box at (2, 0) in #room"
+                    .to_string(),
+            )))
+            .unwrap();
+
+        executor.skip();
+        let skipped = executor.skip().unwrap();
+        assert_eq!(skipped.span().text(), "DEFINE WALL #box");
+        executor.enqueue_next(Command::define("box".into(), MetaProtoType::Wall));
+        executor.enqueue_next(Command::define(
+            "room".into(),
+            MetaProtoType::Box { size: (3, 1) },
+        ));
+        executor.enqueue(Command::push(
+            "box".into(),
+            Direction::East,
+            Assertion::None,
+        ));
+        let texts: Vec<_> = executor
+            .queued()
+            .map(|command| command.span().text().to_string())
+            .collect();
+        assert_eq!(texts.len(), 5);
+        assert_eq!(texts[2], "PLACE #box at (0, 0) in #room");
+
+        // The expectation runs before the enqueued push.
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.span().text(), "EXPECT #box at (2, 0) in #room");
+        let command = executor.skip().unwrap();
+        assert_eq!(command.span().name(), "<enqueued>");
+        assert_eq!(command.span().text(), "PUSH #box east");
+        assert!(command.command().is_push());
+        assert!(!executor.has_next());
+
+        executor.reset();
+        assert_eq!(executor.queued().count(), 4);
+    }
+
+    #[test]
+    fn test_push_line() {
+        let mut executor = Executor::new();
+        for line in [
+            "DEFINE BOX #room size (2, 1)",
+            "MACRO wall(#w, $x)",
+            "DEFINE WALL #w",
+        ] {
+            executor.push_line(line).unwrap();
+        }
+        assert_eq!(executor.queued().count(), 1);
+        executor
+            .push_line("PLACE #w at ($x, 0) in #room\nEND")
+            .unwrap();
+        executor.push_line("CALL wall(#a, 0)").unwrap();
+        executor.run_all().unwrap();
+        assert_eq!(
+            executor.format_positions(),
+            "a    -> at (0, 0) in #room\nroom -> orphan\n"
+        );
+
+        let error = executor.push_line("PLACE #a at (1, 0)").unwrap_err();
+        assert_eq!(error.span().locate(), (6, 18));
+        executor.push_line("PLACE #b at (1, 0) in #room").unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.span().locate(), (7, 0));
+        assert_eq!(error.span().source().name(), "<session>");
+        assert!(error.to_string().contains("CALL wall(#a, 0)"));
+    }
+
+    #[test]
+    fn test_namespaces() {
+        let mut executor = Executor::new();
+        let push = |executor: &mut Executor, text: &str, namespace: Option<&str>| {
+            let source = Arc::new(StringSource::new(text.to_string()));
+            match namespace {
+                Some(namespace) => executor.push_source_as(source, namespace.into()),
+                None => executor.push_source(source),
+            }
+            .unwrap()
+        };
+        push(
+            &mut executor,
+            "TEMPLATE cell()\nDEFINE WALL #wall\nEND\n\
+             DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (0, 0) in #room",
+            Some("a"),
+        );
+        push(
+            &mut executor,
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (1, 0) in #a::room\n\
+             SPAWN cell() AS #cell\nPLACE #cell::wall at (0, 0) in #room",
+            Some("b"),
+        );
+        push(
+            &mut executor,
+            "DEFINE BOX #hall size (2, 1)\nPLACE #a::room at (0, 0) in #hall\n\
+             PLACE #b::room at (1, 0) in #hall",
+            None,
+        );
+        executor.run_all().unwrap();
+
+        assert_eq!(
+            executor.format_positions(),
+            "a::room       -> at (0, 0) in #hall\n\
+             a::wall       -> at (0, 0) in #a::room\n\
+             b::cell::wall -> at (0, 0) in #b::room\n\
+             b::room       -> at (1, 0) in #hall\n\
+             b::wall       -> at (1, 0) in #a::room\n\
+             hall          -> orphan\n"
+        );
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let (_, result) = run("DEFINE BOX #room size (3, 1)\nDEFINE WALL #wall_12\n\
+             DEFINE WALL #wall_345\nPLACE #wall_21 at (0, 0) in #room");
+        assert_eq!(
+            result.unwrap_err().message(),
+            "unresolved reference to wall_21, did you mean #wall_12?"
+        );
+
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nPUSH #rooom east".to_string(),
+            )))
+            .unwrap();
+        let errors = executor.check();
+        assert_eq!(
+            errors[0].message(),
+            "unresolved reference to rooom, did you mean #room?"
+        );
+    }
+
+    #[test]
+    fn test_exec_cause() {
+        let (_, result) = run("DEFINE BOX #room size (3, 1)\nDEFINE WALL #wall_12\n\
+             PLACE #wall_21 at (0, 0) in #room");
+        match result.unwrap_err().cause() {
+            ExecCause::Unresolved { name, suggestions } => {
+                assert_eq!(name, "wall_21");
+                assert_eq!(suggestions, &["wall_12"]);
+            }
+            cause => panic!("unexpected cause {:?}", cause),
+        }
+
+        let (_, result) = run("DEFINE BOX #room size (3, 1)\nDEFINE WALL #wall\n\
+             PLACE #wall at (0, 0) in #room\nEXPECT #wall at (2, 0) in #room");
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Execution);
+        assert_eq!(error.span().locate(), (3, 0));
+        match error.cause() {
+            ExecCause::Assertion {
+                expected, actual, ..
+            } => {
+                let position =
+                    |x| AssertedValue::Position(MetaPosition::new(Some("room".into()), (x, 0)));
+                assert_eq!(expected, &position(2));
+                assert_eq!(actual, &Some(position(0)));
+            }
+            cause => panic!("unexpected cause {:?}", cause),
+        }
+
+        // Every assertion has the expected and the actual values.
+        let cases = [
+            (
+                "PUSH #box east STATIC",
+                AssertedValue::Moved(false),
+                AssertedValue::Moved(true),
+            ),
+            (
+                "EXPECT MOVES 2",
+                AssertedValue::Count(2),
+                AssertedValue::Count(0),
+            ),
+            (
+                "EXPECT TURN 1",
+                AssertedValue::Count(1),
+                AssertedValue::Count(0),
+            ),
+            (
+                "EXPECT MAP #room\n  .b",
+                AssertedValue::Map(vec![".b".into()]),
+                AssertedValue::Map(vec!["b.".into()]),
+            ),
+            (
+                "EXPECT #box NOT IN #room ELSE \"message\"",
+                AssertedValue::NotIn("room".into()),
+                AssertedValue::Position(MetaPosition::new(Some("room".into()), (0, 0))),
+            ),
+        ];
+        for (line, expected_value, actual_value) in cases {
+            let (_, result) = run(&format!(
+                "DEFINE BOX #room size (2, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\n{}",
+                line
+            ));
+            let error = result.unwrap_err();
+            let mut cause = error.cause();
+            if let ExecCause::Else { cause: inner, .. } = cause {
+                cause = inner;
+            }
+            match cause {
+                ExecCause::Assertion {
+                    expected, actual, ..
+                } => {
+                    assert_eq!(expected, &expected_value, "{}", line);
+                    assert_eq!(actual, &Some(actual_value), "{}", line);
+                }
+                cause => panic!("unexpected cause {:?}", cause),
+            }
+            assert!(std::error::Error::source(&error).is_none());
+        }
+
+        // The source of the error is the engine error.
+        let mut world = World::new();
+        let key = world.insert(ProtoType::Wall);
+        let context = MoveContext {
+            origin: key,
+            direction: Direction::East,
+            position: world.position(key),
+        };
+        let error = ExecError::new(
+            Span::new(Arc::new(StringSource::new(String::new())), 0..0),
+            ExecCause::Else {
+                message: "message".into(),
+                cause: Box::new(ExecCause::Move(MoveError::Orphan(key, context))),
+            },
+        );
+        let source = std::error::Error::source(&error).unwrap();
+        assert_eq!(
+            source.to_string(),
+            MoveError::Orphan(key, context).to_string()
+        );
+    }
+
+    #[test]
+    fn test_redefinition() {
+        let script =
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (0, 0) in #room\n\
+                      DEFINE WALL #wall\nPLACE #wall at (1, 0) in #room\nCOPY #room AS #wall";
+        let run = |redefinition| {
+            let mut executor = Executor::new();
+            executor.set_redefinition(redefinition);
+            executor
+                .push_source(Arc::new(StringSource::new(script.to_string())))
+                .unwrap();
+            let result = executor
+                .run_all()
+                .map(drop)
+                .map_err(|e| e.message().to_string());
+            (executor.format_positions(), result)
+        };
+
+        let (positions, result) = run(Redefinition::Error);
+        assert_eq!(result, Err("redefinition of #wall".into()));
+        assert_eq!(positions, "room -> orphan\nwall -> at (0, 0) in #room\n");
+
+        let (positions, result) = run(Redefinition::Shadow);
+        assert_eq!(result, Ok(()));
+        assert_eq!(
+            positions,
+            "room   -> orphan\n\
+             wall   -> orphan\n\
+             wall@1 -> at (0, 0) in #room\n\
+             wall@2 -> at (1, 0) in #room\n"
+        );
+
+        let (positions, result) = run(Redefinition::Replace);
+        assert_eq!(result, Ok(()));
+        assert_eq!(positions, "room -> orphan\nwall -> orphan\n");
+
+        // A block cannot be replaced while an alias refers to it.
+        let mut executor = Executor::new();
+        executor.set_redefinition(Redefinition::Replace);
+        let room = MetaProtoType::Box { size: (1, 1) };
+        let alias = MetaProtoType::Alias {
+            reference: "room".into(),
+        };
+        let error = Err("cannot replace #room, which is still referred to".to_string());
+        executor
+            .execute(Command::define("room".into(), room.clone()))
+            .unwrap();
+        assert_eq!(
+            executor
+                .execute(Command::define("room".into(), alias.clone()))
+                .map_err(|cause| cause.to_string()),
+            error
+        );
+        executor
+            .execute(Command::define("alias".into(), alias))
+            .unwrap();
+        assert_eq!(
+            executor
+                .execute(Command::define("room".into(), room))
+                .map_err(|cause| cause.to_string()),
+            error
+        );
+    }
+
+    #[test]
+    fn test_worlds() {
+        let mut executor = Executor::new();
+        executor.set_rewinding(true);
+        let script = "WORLD CREATE #other\n\
+                      DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\n\
+                      PLACE #box at (0, 0) in #room\nPUSH #box east\n\
+                      WORLD USE #other\n\
+                      DEFINE BOX #room size (4, 1)\nDEFINE BOX #box size (1, 1)\n\
+                      PLACE #box at (3, 0) in #room\nPUSH #box west\nPUSH #box west\n\
+                      UNDO 1\nEXPECT #box at (2, 0) in #room\n\
+                      WORLD USE #main\nEXPECT #box at (1, 0) in #room\nUNDO 1\n\
+                      EXPECT #box at (0, 0) in #room\nWORLD CREATE #main";
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        assert!(executor.warnings().is_empty());
+        let errors = executor.check();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(), "redefinition of world #main");
+
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.message(), "redefinition of world #main");
+        assert_eq!(executor.world_name(), "main");
+
+        // Rewinding the `WORLD USE` and the commands after it.
+        while executor.step_back().unwrap().text() != "WORLD USE #main" {}
+        assert_eq!(executor.world_name(), "other");
+        let other = executor.format_positions();
+        assert!(other.contains("at (2, 0) in #room"));
+        executor.step().unwrap();
+        assert_eq!(executor.world_name(), "main");
+        assert_ne!(executor.format_positions(), other);
+
+        assert_eq!(
+            executor
+                .execute(Command::use_world("unknown".into()))
+                .map_err(|cause| cause.to_string()),
+            Err("no world named #unknown".to_string())
+        );
+        executor.reset();
+        assert_eq!(executor.world_name(), "main");
+        assert!(executor
+            .execute(Command::use_world("other".into()))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parts_mut() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east MOVED\nPUSH #box east STATIC"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.step().unwrap();
+        executor.step().unwrap();
+        executor.step().unwrap();
+        executor.step().unwrap();
+
+        let (world, meta) = executor.parts_mut();
+        let wall = world.insert(ProtoType::Wall);
+        let room = meta.get_key(&"room".into()).unwrap();
+        world.place(wall, Position::inside(room, (2, 0)));
+        meta.insert("wall".into(), wall);
+        executor.meta_mut().set_char(wall, '#');
+        executor.run_all().unwrap();
+
+        let box_key = executor.meta().get_key(&"box".into()).unwrap();
+        executor.world_mut().place(wall, Position::orphan());
+        assert!(executor.world_mut().push(box_key, Direction::East).unwrap());
+    }
+
+    #[test]
+    fn test_profile() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\nDEFINE WALL #wall\n\
+                 PLACE #box at (0, 0) in #room\nPLACE #wall at (2, 0) in #room\n\
+                 PUSH #box east\nPUSH #box east"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.step().unwrap();
+        assert!(executor.profile().is_none());
+        executor.set_profiling(true);
+        executor.run_all().unwrap();
+
+        let profile = executor.profile().unwrap();
+        assert_eq!(profile.entries.len(), 6);
+        assert_eq!(profile.entries[4].span.text(), "PUSH #box east");
+        assert_eq!(profile.entries[4].pushes, 1);
+        assert_eq!(profile.entries[4].stats.movements, 1);
+        assert_eq!(profile.entries[5].stats.movements, 0);
+        assert_eq!(profile.entries[0].pushes, 0);
+        assert_eq!(profile.entries[0].stats, PushStats::default());
+
+        let table = profile.to_string();
+        let lines: Vec<_> = table.lines().collect();
+        assert_eq!(lines.len(), 7);
+        assert!(lines[0].starts_with("location"));
+        assert!(lines[0].ends_with("  command"));
+        assert!(lines[5].contains(":6:1 "));
+        assert!(lines[5].ends_with("  1  PUSH #box east"));
+
+        executor.reset();
+        assert_eq!(executor.profile().unwrap().entries.len(), 0);
+        executor.set_profiling(false);
+        assert!(executor.profile().is_none());
+    }
+
+    #[test]
+    fn test_moves() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\nDEFINE WALL #wall\n\
+                 PLACE #box at (0, 0) in #room\nPLACE #wall at (2, 0) in #room\n\
+                 CHECKPOINT start\nPUSH #box east\nPUSH #box east\nEXPECT MOVES 2"
+                    .to_string(),
+            )))
+            .unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.message(), "expected 2 moves, found 1");
+        assert_eq!((executor.moves(), executor.turn()), (1, 2));
+
+        executor.execute(Command::undo(1)).unwrap();
+        assert_eq!((executor.moves(), executor.turn()), (1, 1));
+        executor.execute(Command::restore("start".into())).unwrap();
+        assert_eq!((executor.moves(), executor.turn()), (0, 0));
+
+        executor.execute(Command::expect_turn(0)).unwrap();
+        assert_eq!(
+            executor
+                .execute(Command::expect_turn(1))
+                .map_err(|cause| cause.to_string()),
+            Err("expected turn 1, found 0".to_string())
+        );
+    }
+
+    #[test]
+    fn test_report() {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\nDEFINE WALL #wall\n\
+                 PLACE #box at (0, 0) in #room\nPLACE #wall at (2, 0) in #room\nPUSH #box east MOVED\nPUSH #box east\n\
+                 EXPECT #box at (1, 0) in #room\nEXPECT #box at (0, 0) in #room\nPUSH #box west"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap_err();
+        executor.run_all().unwrap();
+
+        let report = executor.report();
+        assert_eq!(report.commands, 10);
+        assert_eq!(report.assertions_passed, 1);
+        assert_eq!(report.assertions_failed, 1);
+        assert_eq!(report.pushes_moved, 2);
+        assert_eq!(report.pushes_static, 1);
+        assert_eq!(report.timings.len(), 10);
+        assert_eq!(report.timings[9].0.text(), "PUSH #box west");
+        assert_eq!(
+            report.elapsed(),
+            report.timings.iter().map(|(_, t)| *t).sum()
+        );
+
+        executor.reset();
+        assert_eq!(executor.report().commands, 0);
+    }
+
+    #[test]
+    fn test_budget() {
+        let mut executor = Executor::new();
+        executor.set_budget(Budget {
+            max_pushes: Some(2),
+            ..Budget::default()
+        });
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (4, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east\nPUSH #box east\nPUSH #box east"
+                    .to_string(),
+            )))
+            .unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(error.message(), "exceeded the budget of 2 pushes");
+        assert_eq!(error.span().locate().0, 5);
+
+        // The command exceeding the budget is kept to run with a larger one.
+        executor.set_budget(Budget {
+            max_commands: Some(6),
+            ..Budget::default()
+        });
+        executor.run_all().unwrap();
+        assert_eq!(
+            executor.format_positions(),
+            "box  -> at (3, 0) in #room\nroom -> orphan\n"
+        );
+
+        executor.reset();
+        executor.set_budget(Budget {
+            timeout: Some(Duration::from_millis(1)),
+            ..Budget::default()
+        });
+        executor.step().unwrap();
+        std::thread::sleep(Duration::from_millis(2));
+        let error = executor.step().unwrap_err();
+        assert_eq!(error.message(), "exceeded the timeout of 1ms");
+
+        // A push exceeding the evaluations is stopped during the push, and
+        // kept to run again.
+        let mut executor = Executor::new();
+        executor.set_rewinding(true);
+        executor.set_budget(Budget {
+            max_evaluations: Some(1),
+            ..Budget::default()
+        });
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (4, 1)\nDEFINE BOX #a size (1, 1)\nDEFINE BOX #b size (1, 1)\n\
+                 PLACE #a at (0, 0) in #room\nPLACE #b at (1, 0) in #room\nPUSH #a east"
+                    .to_string(),
+            )))
+            .unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(error.message(), "exceeded the budget of 1 evaluations");
+        assert_eq!(executor.queued().count(), 1);
+        assert_eq!(executor.turn(), 0);
+
+        // Rewinding a push refunds its evaluations.
+        executor.set_budget(Budget {
+            max_evaluations: Some(2),
+            ..Budget::default()
+        });
+        executor.step().unwrap();
+        executor.step_back();
+        executor.step().unwrap();
+        assert_eq!(
+            executor.format_positions(),
+            "a    -> at (1, 0) in #room\nb    -> at (2, 0) in #room\nroom -> orphan\n"
+        );
+
+        // The budget exceeded by an asserted push is not a failed assertion.
+        let mut executor = Executor::new();
+        executor.set_budget(Budget {
+            max_evaluations: Some(1),
+            ..Budget::default()
+        });
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (4, 1)\nDEFINE BOX #a size (1, 1)\nDEFINE BOX #b size (1, 1)\n\
+                 PLACE #a at (0, 0) in #room\nPLACE #b at (1, 0) in #room\n\
+                 PUSH #a east MOVED ELSE \"stuck\""
+                    .to_string(),
+            )))
+            .unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(error.message(), "exceeded the budget of 1 evaluations");
+        assert_eq!(executor.queued().count(), 1);
+        assert!(executor.undo.is_empty());
+        assert_eq!(executor.turn(), 0);
+    }
+
+    #[test]
+    fn test_reset() {
+        let output = Rc::new(RefCell::new(String::new()));
+        let mut executor = Executor::new();
+        let text = output.clone();
+        executor.set_output(move |line| text.borrow_mut().push_str(line));
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "LET $width = 3\nDEFINE BOX #room size ($width, 1)\nDEFINE BOX #box size (1, 1)\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east\nWHERE #box"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+        let positions = executor.format_positions();
+
+        executor.reset();
+        assert!(executor.meta().names().is_empty());
+        assert!(executor.execute(Command::undo(1)).is_err());
+        executor.run_all().unwrap();
+        assert_eq!(executor.format_positions(), positions);
+        assert_eq!(*output.borrow(), "#box at (1, 0) in #room\n".repeat(2));
+
+        // The variables are kept for the sources pushed after a reset, whose
+        // commands run after those of the previous sources.
+        executor.reset();
+        executor
+            .push_source(Arc::new(StringSource::new("UNDO $width - 2".to_string())))
+            .unwrap();
+        executor.run_all().unwrap();
+        assert_eq!(
+            executor.format_positions(),
+            "box  -> at (0, 0) in #room\nroom -> orphan\n"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "format")]
+    fn test_show() {
+        let (output, result) = run("DEFINE BOX #room size (3, 2)\nDEFINE WALL #wall\n\
+             PLACE #wall at (2, 1) in #room\nSHOW #room\nSHOW #wall");
+        assert!(result.is_err());
+        assert_eq!(output, "r   w\n     \n");
+    }
+
+    #[test]
+    fn test_place_relative() {
+        let (_, result) = run(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #a\nDEFINE WALL #b\n\
+             PLACE #b EAST OF #room",
+        );
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Execution);
+        assert_eq!(error.message(), "cannot place #b relative to orphan #room");
+
+        let (_, result) = run(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #a\nDEFINE WALL #b\n\
+             PLACE #a at (1, 0) in #room\nPLACE #b WEST OF #a\nPLACE #b 2 EAST OF #a",
+        );
+        assert_eq!(
+            result.unwrap_err().message(),
+            "cannot place #b at (3, 0), outside #room"
+        );
+    }
+
+    #[test]
+    fn test_place_outside() {
+        let (_, result) = run("DEFINE BOX #room size (2, 1)
+DEFINE WALL #a
+PLACE #a at (2, 0) in #room");
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Execution);
+        assert_eq!(
+            error.message(),
+            "cannot place #a at (2, 0), outside #room of size (2, 1)"
+        );
+
+        // Aliases have no interior of their own.
+        let (_, result) = run("DEFINE BOX #room size (2, 1)
+DEFINE ALIAS #alias ref #room
+DEFINE WALL #a
+             PLACE #a at (0, 0) in #alias");
+        assert_eq!(
+            result.unwrap_err().message(),
+            "cannot place #a at (0, 0), outside #alias of size (0, 0)"
+        );
+    }
+
+    #[test]
+    fn test_place_random() {
+        let script = "DEFINE BOX #room size (4, 4)\nDEFINE WALL #a\nDEFINE WALL #b\n\
+                      SEED 7\nPLACE #a RANDOM IN #room\n\
+                      PLACE #b RANDOM IN #room AT (2, 2) SIZE (2, 2)\nWHERE #a\nWHERE #b";
+        let (output, result) = run(script);
+        assert!(result.is_ok());
+        // The same seed gives the same layout.
+        assert_eq!(run(script).0, output);
+        let b = output.lines().nth(1).unwrap();
+        assert!(
+            ["(2, 2)", "(3, 2)", "(2, 3)", "(3, 3)"]
+                .iter()
+                .any(|pos| b.contains(pos)),
+            "{}",
+            b
+        );
+
+        // A random placement is rewound with the generator.
+        let mut executor = Executor::new();
+        executor.set_rewinding(true);
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (8, 8)\nDEFINE WALL #a\nPLACE #a RANDOM IN #room"
+                    .to_string(),
+            )))
+            .unwrap();
+        executor.run_all().unwrap();
+        let position = executor.world()[executor.meta().get_key(&"a".into()).unwrap()]
+            .state
+            .position;
+        executor.step_back();
+        executor.step().unwrap();
+        let key = executor.meta().get_key(&"a".into()).unwrap();
+        assert_eq!(executor.world()[key].state.position, position);
+
+        let (_, result) = run(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #a\nDEFINE WALL #b\nDEFINE WALL #c\n\
+             PLACE #a at (0, 0) in #room\nPLACE #b at (1, 0) in #room\nPLACE #c RANDOM IN #room",
+        );
+        assert_eq!(
+            result.unwrap_err().message(),
+            "no empty cell to place #c in #room"
+        );
+
+        let (_, result) = run("DEFINE BOX #room size (2, 1)\nDEFINE WALL #a\n\
+             PLACE #a RANDOM IN #room AT (1, 0) SIZE (2, 1)");
+        assert_eq!(
+            result.unwrap_err().message(),
+            "cannot place #a in region at (1, 0) of size (2, 1), outside #room"
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "solver")]
+    fn test_expect_reachable() {
+        // The box can never pass the wall.
+        let (_, result) = run(
+            "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box solid\nDEFINE WALL #wall\n\
+             PLACE #box at (0, 0) in #room\nPLACE #wall at (1, 0) in #room\n\
+             EXPECT #box REACHABLE (0, 0) IN #room\nEXPECT #box REACHABLE (2, 0) IN #room",
+        );
+        assert_eq!(
+            result.unwrap_err().message(),
+            "expected #box to be reachable at (2, 0) in #room"
+        );
+
+        // Only the designated player is pushed.
+        let (_, result) = run(
+            "DEFINE BOX #room size (3, 1)\nDEFINE BOX #player solid\nDEFINE BOX #box solid\n\
+             PLACE #player at (0, 0) in #room\nPLACE #box at (2, 0) in #room\nPLAYER #player\n\
+             EXPECT #box REACHABLE (1, 0) IN #room",
+        );
+        assert!(result.is_err());
+
+        // The search gives up by the budget, keeping the previous push.
+        let mut executor = Executor::new();
+        executor.set_budget(Budget {
+            max_states: Some(1),
+            ..Budget::default()
+        });
+        executor
+            .push_source(Arc::new(StringSource::new(
+                "DEFINE BOX #room size (4, 1)\nDEFINE BOX #box solid\n\
+                 PLACE #box at (0, 0) in #room\nPUSH #box east\n\
+                 EXPECT #box REACHABLE (3, 0) IN #room"
+                    .to_string(),
+            )))
+            .unwrap();
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+        assert_eq!(error.message(), "exceeded the budget of 1 states");
+        assert_eq!(executor.commands.len(), 1);
+        assert_eq!(executor.undo.len(), 1);
+
+        executor.set_budget(Budget {
+            timeout: Some(Duration::ZERO),
+            ..Budget::default()
+        });
+        let error = executor.run_all().unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::BudgetExceeded);
+
+        executor.set_budget(Budget::default());
+        executor.run_all().unwrap();
+    }
+
+    #[test]
+    fn test_else() {
+        let (_, result) = run(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (0, 0) in #room\n\
+             EXPECT #wall at (1, 0) in #room ELSE \"the wall is misplaced\"",
+        );
+        assert_eq!(
+            result.unwrap_err().message(),
+            "the wall is misplaced: expected #wall to be at (1, 0) in #room"
+        );
+
+        let (_, result) = run(
+            "DEFINE BOX #room size (2, 1)\nDEFINE WALL #wall\nPLACE #wall at (1, 0) in #room\n\
+             EXPECT #wall at (1, 0) in #room ELSE \"misplaced\"\n\
+             PUSH #wall east MOVED ELSE \"the wall cannot leave the room\"",
+        );
+        let error = result.unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::Execution);
+        assert!(error
+            .message()
+            .starts_with("the wall cannot leave the room: "));
+
+        // Every push of several directions is asserted, not only the last.
+        let (_, result) = run(
+            "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box\nDEFINE WALL #wall\n\
+             PLACE #box at (0, 0) in #room\nPLACE #wall at (2, 0) in #room\n\
+             PUSH #box east east west MOVED ELSE \"blocked\"",
+        );
+        assert!(result.unwrap_err().message().starts_with("blocked: "));
+    }
+
+    #[test]
+    fn test_list() {
+        let (output, result) = run(
+            "DEFINE BOX #room size (3, 1)\nDEFINE ALIAS #alias ref #room\n\
+             DEFINE BOX #box solid\nPLACE #box at (1, 0) in #room\n\
+             LIST\nLIST box\nLIST sealed",
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            "#alias  ALIAS ref #room  orphan\n\
+             #box    BOX size (1, 1)  at (1, 0) in #room\n\
+             #room   BOX size (3, 1)  orphan\n\
+             #box   BOX size (1, 1)  at (1, 0) in #room\n\
+             #room  BOX size (3, 1)  orphan\n"
+        );
+    }
+
+    #[test]
+    fn test_where_info() {
+        let (output, result) = run(
+            "DEFINE BOX #room size (3, 1)\nDEFINE ALIAS #alias ref #room\n\
+             DEFINE BOX #box solid\nPLACE #box at (1, 0) in #room\n\
+             WHERE #box\nWHERE #room\nINFO #room",
+        );
+        assert!(result.is_ok());
+        assert_eq!(
+            output,
+            "#box at (1, 0) in #room\n#room orphan\n\
+             #room: BOX size (3, 1)\n  position: orphan\n\
+             \x20 referenced by: #alias\n  contains: #box at (1, 0)\n"
+        );
+    }
 }
@@ -0,0 +1,283 @@
+use crate::command::color_keyword;
+use crate::executor::Executor;
+use crate::lexer::is_ident_char;
+use crate::meta::MetaTable;
+use ecow::EcoString;
+use parabox::{BlockKey, Position, ProtoType, World};
+use std::collections::{HashMap, HashSet};
+
+/// Formats a world as a script of `DEFINE` and `PLACE` commands reproducing
+/// it.
+///
+/// The blocks are named after the meta table. Blocks without a name, or whose
+/// name is not a valid identifier, are given a new name. Boxes filled by their
+/// `::interior` wall are defined as `SOLID`. Only the prototypes and positions
+/// are exported, not the rules or the history of the world.
+pub fn export_script(world: &World, meta: &MetaTable) -> String {
+    Exporter::new(world, meta).script()
+}
+
+impl Executor {
+    /// Formats the world as a script reproducing it, see [export_script].
+    ///
+    /// The script also designates the player and declares the goal of the
+    /// executor, if any.
+    pub fn export_script(&self) -> String {
+        let exporter = Exporter::new(self.world(), self.meta());
+        let mut script = exporter.script();
+
+        let player = self
+            .player()
+            .and_then(|player| self.meta().get_key(&player));
+        if let Some(player) = player.and_then(|key| exporter.names.get(&key)) {
+            script.push_str(&format!("PLAYER #{}\n", player));
+        }
+        for &(key, position) in self.goal().targets() {
+            if let Some(name) = exporter.names.get(&key) {
+                let position = exporter.position(position);
+                script.push_str(&format!("GOAL #{} {}\n", name, position));
+            }
+        }
+
+        script
+    }
+}
+
+struct Exporter<'a> {
+    world: &'a World,
+    /// The names of the exported blocks.
+    names: HashMap<BlockKey, EcoString>,
+    /// The solid boxes, whose interior walls are not exported.
+    solid: HashSet<BlockKey>,
+    /// The `CHAR` and `COLOR` properties of the blocks displayed otherwise than
+    /// by default.
+    display: HashMap<BlockKey, String>,
+}
+
+impl<'a> Exporter<'a> {
+    fn new(world: &'a World, meta: &MetaTable) -> Self {
+        let mut solid = HashSet::new();
+        let mut interiors = HashSet::new();
+        for (key, block) in world.blocks_ordered() {
+            let Some(name) = meta.get_name(&key) else {
+                continue;
+            };
+            let interior = meta.get_key(&format!("{}::interior", name).into());
+            if let Some(interior) = interior.filter(|&interior| {
+                block.proto == ProtoType::Box { size: (1, 1) }
+                    && world[interior].proto == ProtoType::Wall
+                    && world.position(interior) == Position::inside(key, (0, 0))
+            }) {
+                solid.insert(key);
+                interiors.insert(interior);
+            }
+        }
+
+        let mut names = HashMap::new();
+        let mut used = HashSet::new();
+        for (key, _) in world.blocks_ordered() {
+            if interiors.contains(&key) {
+                continue;
+            }
+
+            let base: EcoString = match meta.get_name(&key) {
+                Some(name) if name.chars().all(is_ident_char) => name,
+                Some(name) => name
+                    .chars()
+                    .map(|c| if is_ident_char(c) { c } else { '_' })
+                    .collect(),
+                None => "block".into(),
+            };
+            let mut name = base.clone();
+            let mut index = 0;
+            while !used.insert(name.clone()) {
+                index += 1;
+                name = format!("{}_{}", base, index).into();
+            }
+            names.insert(key, name);
+        }
+
+        let mut display = HashMap::new();
+        for (&key, name) in &names {
+            let mut properties = String::new();
+            if let Some(char) = meta.get_char(&key).filter(|&char| !name.starts_with(char)) {
+                properties.push_str(&format!(" char '{}'", char));
+            }
+            if let Some(color) = meta.get_color(&key).and_then(color_keyword) {
+                properties.push_str(&format!(" color {}", color));
+            }
+            if !properties.is_empty() {
+                display.insert(key, properties);
+            }
+        }
+
+        Self {
+            world,
+            names,
+            solid,
+            display,
+        }
+    }
+
+    fn script(&self) -> String {
+        let mut script = String::new();
+
+        // The blocks are defined in order, except that the referenced blocks
+        // are defined first.
+        let mut pending: Vec<_> = self
+            .world
+            .blocks_ordered()
+            .filter(|(key, _)| self.names.contains_key(key))
+            .map(|(key, block)| (key, block.proto))
+            .collect();
+        let mut defined = HashSet::new();
+        while !pending.is_empty() {
+            let index = pending
+                .iter()
+                .position(|(_, proto)| {
+                    proto.reference().is_none_or(|reference| {
+                        defined.contains(&reference) || !self.names.contains_key(&reference)
+                    })
+                })
+                .unwrap_or(0);
+            let (key, proto) = pending.remove(index);
+            script.push_str(&self.define(key, proto));
+            defined.insert(key);
+        }
+
+        for (key, block) in self.world.blocks_ordered() {
+            let Some(name) = self.names.get(&key) else {
+                continue;
+            };
+            if block.state.position.container.is_some() {
+                let position = self.position(block.state.position);
+                script.push_str(&format!("PLACE #{} {}\n", name, position));
+            }
+        }
+
+        script
+    }
+
+    fn define(&self, key: BlockKey, proto: ProtoType) -> String {
+        let name = &self.names[&key];
+        let reference = |reference| match self.names.get(&reference) {
+            Some(reference) => format!(" ref #{}", reference),
+            None => String::new(),
+        };
+        let size = |(width, height)| format!(" size ({}, {})", width, height);
+
+        let (keyword, properties) = match proto {
+            _ if self.solid.contains(&key) => ("BOX", " solid".to_string()),
+            ProtoType::Wall => ("WALL", String::new()),
+            ProtoType::Box { size: s } => ("BOX", size(s)),
+            ProtoType::Alias { reference: r } => ("ALIAS", reference(r)),
+            ProtoType::Infinity { reference: r } => ("INFINITY", reference(r)),
+            ProtoType::Epsilon {
+                reference: r,
+                size: s,
+            } => ("EPSILON", reference(r) + &size(s)),
+            ProtoType::Void { size: s } => ("VOID", size(s)),
+            ProtoType::Room { size: s } => ("ROOM", size(s)),
+            ProtoType::Sealed { size: s } => ("SEALED", size(s)),
+        };
+
+        let display = self.display.get(&key).map_or("", String::as_str);
+        format!("DEFINE {} #{}{}{}\n", keyword, name, properties, display)
+    }
+
+    /// Returns the properties of the position, as in `PLACE`.
+    fn position(&self, position: Position) -> String {
+        match position
+            .container
+            .and_then(|container| self.names.get(&container))
+        {
+            Some(container) => format!(
+                "at ({}, {}) in #{}",
+                position.pos.0, position.pos.1, container
+            ),
+            None => "orphan".to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StringSource;
+    use anstyle::AnsiColor;
+    use parabox::Direction;
+    use std::sync::Arc;
+
+    fn execute(script: &str) -> Executor {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        executor.run_all().unwrap();
+        executor
+    }
+
+    #[test]
+    fn test_export_script() {
+        let executor = execute(
+            "DEFINE BOX #room size (4, 3)\nDEFINE BOX #player solid\n\
+             DEFINE ALIAS #alias ref #room\nDEFINE WALL #wall\n\
+             PLACE #player at (0, 0) in #room\nPLACE #alias at (2, 0) in #room\n\
+             PLACE #wall at (3, 2) in #room\nPLAYER #player\n\
+             GOAL #player at (1, 1) in #room\nMOVE east",
+        );
+
+        let script = executor.export_script();
+        assert_eq!(
+            script,
+            "DEFINE BOX #room size (4, 3)\nDEFINE BOX #player solid\n\
+             DEFINE ALIAS #alias ref #room\nDEFINE WALL #wall\n\
+             PLACE #player at (1, 0) in #room\nPLACE #alias at (2, 0) in #room\n\
+             PLACE #wall at (3, 2) in #room\nPLAYER #player\n\
+             GOAL #player at (1, 1) in #room\n"
+        );
+
+        let exported = execute(&script);
+        assert!(exported.world().equivalent(executor.world()));
+        assert_eq!(exported.goal().targets().len(), 1);
+    }
+
+    #[test]
+    fn test_export_display() {
+        let executor = execute(
+            "DEFINE BOX #room size (2, 1) char 'r' color blue\n\
+             DEFINE WALL #wall char '=' color red\nPLACE #wall at (0, 0) in #room",
+        );
+        let script = executor.export_script();
+        assert_eq!(
+            script,
+            "DEFINE BOX #room size (2, 1) color blue\n\
+             DEFINE WALL #wall char '=' color red\nPLACE #wall at (0, 0) in #room\n"
+        );
+
+        let exported = execute(&script);
+        let wall = exported.meta().get_key(&"wall".into()).unwrap();
+        assert_eq!(exported.meta().get_char(&wall), Some('='));
+        assert_eq!(exported.meta().get_color(&wall), Some(AnsiColor::Red));
+    }
+
+    #[test]
+    fn test_export_anonymous() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let infinity = world.insert(ProtoType::Infinity { reference: block });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(infinity, Position::inside(container, (2, 0)));
+        assert!(world.push(block, Direction::East).unwrap());
+
+        let mut meta = MetaTable::new();
+        meta.insert("block".into(), container);
+        meta.insert("room::container".into(), block);
+
+        let exported = execute(&export_script(&world, &meta));
+        assert!(exported.world().equivalent(&world));
+        assert!(exported.meta().contains_name(&"room__container".into()));
+        assert!(exported.meta().contains_name(&"block_1".into()));
+    }
+}
@@ -0,0 +1,215 @@
+use crate::rng::Rng;
+use parabox::{Direction, Size};
+
+/// The options of a script generated by [generate_script].
+#[derive(Copy, Clone, Debug)]
+pub struct GenerateOptions {
+    /// The seed of the random choices. The same options generate the same
+    /// script.
+    pub seed: u64,
+    /// The number of blocks defined besides the root box `#room`.
+    pub blocks: usize,
+    /// The number of pushes, some of which are undone.
+    pub pushes: usize,
+    /// The maximum width and height of the defined boxes.
+    pub max_size: usize,
+}
+
+impl Default for GenerateOptions {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            blocks: 8,
+            pushes: 16,
+            max_size: 4,
+        }
+    }
+}
+
+/// Generates a random valid script, e.g. to fuzz the executor and the engine.
+///
+/// The script defines walls, boxes, solid boxes and aliases, places them at
+/// empty cells of the boxes defined before them, and pushes them in random
+/// directions. The script always parses and refers to defined blocks only, so
+/// that any failure in executing it comes from the pushes.
+///
+/// ```
+/// # use std::sync::Arc;
+/// # use parabox_parser::{generate_script, Executor, GenerateOptions, StringSource};
+/// let script = generate_script(GenerateOptions {
+///     seed: 42,
+///     ..GenerateOptions::default()
+/// });
+///
+/// let mut executor = Executor::new();
+/// executor.push_source(Arc::new(StringSource::new(script))).unwrap();
+/// assert!(executor.check().is_empty());
+/// ```
+pub fn generate_script(options: GenerateOptions) -> String {
+    let mut generator = Generator {
+        rng: Rng::new(options.seed),
+        max_size: options.max_size.max(1),
+        script: String::new(),
+        containers: vec![],
+        placed: vec![],
+    };
+
+    let size = generator.size(3);
+    generator.define("room", "BOX", format!(" size ({}, {})", size.0, size.1));
+    generator
+        .containers
+        .push(("room".into(), vec![vec![false; size.1]; size.0]));
+
+    for index in 0..options.blocks {
+        generator.block(index);
+    }
+
+    let mut undoable = 0;
+    for _ in 0..options.pushes {
+        if undoable > 0 && generator.rng.below(8) == 0 {
+            generator.script.push_str("UNDO\n");
+            undoable -= 1;
+        } else if let Some(block) = generator.pick_placed() {
+            let direction = Direction::ALL[generator.rng.below(4)].name();
+            generator
+                .script
+                .push_str(&format!("PUSH #{} {}\n", block, direction));
+            undoable += 1;
+        }
+    }
+
+    generator.script
+}
+
+struct Generator {
+    rng: Rng,
+    max_size: usize,
+    script: String,
+    /// The boxes with interiors to place blocks in, with their occupied cells.
+    containers: Vec<(String, Vec<Vec<bool>>)>,
+    /// The blocks placed in a container.
+    placed: Vec<String>,
+}
+
+impl Generator {
+    /// Defines a random block and places it in a container, if any cell is
+    /// empty.
+    fn block(&mut self, index: usize) {
+        let (name, interior) = match self.rng.below(4) {
+            0 => {
+                let name = format!("wall{}", index);
+                self.define(&name, "WALL", String::new());
+                (name, None)
+            }
+            1 => {
+                let name = format!("box{}", index);
+                let size = self.size(1);
+                self.define(&name, "BOX", format!(" size ({}, {})", size.0, size.1));
+                (name, Some(size))
+            }
+            2 => {
+                let name = format!("solid{}", index);
+                self.define(&name, "BOX", " solid".to_string());
+                (name, None)
+            }
+            _ => {
+                let name = format!("alias{}", index);
+                let reference = self.rng.below(self.containers.len());
+                let reference = format!(" ref #{}", self.containers[reference].0);
+                self.define(&name, "ALIAS", reference);
+                (name, None)
+            }
+        };
+
+        // The block is placed before becoming a container, so that no box is
+        // placed inside itself.
+        self.place(&name);
+        if let Some((width, height)) = interior {
+            self.containers
+                .push((name, vec![vec![false; height]; width]));
+        }
+    }
+
+    fn define(&mut self, name: &str, keyword: &str, properties: String) {
+        self.script
+            .push_str(&format!("DEFINE {} #{}{}\n", keyword, name, properties));
+    }
+
+    fn place(&mut self, name: &str) {
+        let count = self.containers.len();
+        let start = self.rng.below(count);
+        for offset in 0..count {
+            let (container, interior) = &mut self.containers[(start + offset) % count];
+            let cells: Vec<Size> = (0..interior.len())
+                .flat_map(|x| (0..interior[x].len()).map(move |y| (x, y)))
+                .filter(|&(x, y)| !interior[x][y])
+                .collect();
+            if cells.is_empty() {
+                continue;
+            }
+
+            let (x, y) = cells[self.rng.below(cells.len())];
+            interior[x][y] = true;
+            self.script.push_str(&format!(
+                "PLACE #{} at ({}, {}) in #{}\n",
+                name, x, y, container
+            ));
+            self.placed.push(name.to_string());
+            return;
+        }
+    }
+
+    fn pick_placed(&mut self) -> Option<String> {
+        if self.placed.is_empty() {
+            return None;
+        }
+        Some(self.placed[self.rng.below(self.placed.len())].clone())
+    }
+
+    /// Returns a random size whose width and height are at least the minimum.
+    fn size(&mut self, min: usize) -> Size {
+        let max = self.max_size.max(min);
+        (
+            min + self.rng.below(max - min + 1),
+            min + self.rng.below(max - min + 1),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::{Budget, Executor};
+    use crate::source::StringSource;
+    use std::sync::Arc;
+
+    #[test]
+    fn test_generate_script() {
+        let options = GenerateOptions::default();
+        assert_eq!(generate_script(options), generate_script(options));
+        assert_ne!(
+            generate_script(options),
+            generate_script(GenerateOptions { seed: 1, ..options })
+        );
+
+        for seed in 0..64 {
+            let script = generate_script(GenerateOptions {
+                seed,
+                blocks: 12,
+                pushes: 32,
+                max_size: 3,
+            });
+            let mut executor = Executor::new();
+            executor.set_output(|_| {});
+            executor.set_budget(Budget {
+                max_pushes: Some(64),
+                ..Budget::default()
+            });
+            executor
+                .push_source(Arc::new(StringSource::new(script.clone())))
+                .unwrap();
+            assert!(executor.check().is_empty(), "{}", script);
+            executor.run_all().unwrap();
+        }
+    }
+}
@@ -1,6 +1,7 @@
+use anstyle::AnsiColor;
 use std::fmt::Display;
 
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum SyntaxKind {
     /// `,` character.
     Comma,
@@ -8,6 +9,18 @@ pub enum SyntaxKind {
     LeftParen,
     /// `)` character.
     RightParen,
+    /// `=` character.
+    Equals,
+    /// `;` character.
+    Semicolon,
+    /// `+` character.
+    Plus,
+    /// `-` character.
+    Minus,
+    /// `*` character.
+    Star,
+    /// `/` character.
+    Slash,
     /// Empty characters ignored by parser.
     Empty,
     /// Comment started by `//`.
@@ -21,14 +34,65 @@ pub enum SyntaxKind {
     /// identifier started by a hash `#`.
     Ident,
 
+    /// variable started by a dollar `$`.
+    Variable,
+
+    /// Character between single quotes, e.g. `'p'`.
+    Character,
+
+    /// String between double quotes, e.g. `"message"`.
+    String,
+
     /// `define` statement.
     Define,
     /// `place` statement.
     Place,
     /// `push` statement.
     Push,
+    /// `player` statement.
+    Player,
+    /// `move` statement.
+    Move,
     /// `expect` statement.
     Expect,
+    /// `undo` statement.
+    Undo,
+    /// `checkpoint` statement.
+    Checkpoint,
+    /// `restore` statement.
+    Restore,
+    /// `let` statement.
+    Let,
+    /// `macro` statement.
+    Macro,
+    /// `end` of a `macro` statement.
+    End,
+    /// `call` statement.
+    Call,
+    /// `template` statement.
+    Template,
+    /// `spawn` statement.
+    Spawn,
+    /// `copy` statement.
+    Copy,
+    /// `world` statement.
+    World,
+    /// `version` pragma, before the other statements.
+    Version,
+    /// `goal` statement.
+    Goal,
+    /// `show` statement.
+    Show,
+    /// `list` statement.
+    List,
+    /// `where` statement.
+    Where,
+    /// `info` statement.
+    Info,
+    /// `seed` statement.
+    Seed,
+    /// `else` message of an assertion.
+    Else,
     /// [parabox::ProtoType::Wall]
     Wall,
     /// [parabox::ProtoType::Box]
@@ -41,18 +105,46 @@ pub enum SyntaxKind {
     Epsilon,
     /// [parabox::ProtoType::Void]
     Void,
+    /// [parabox::ProtoType::Room]
+    Room,
+    /// [parabox::ProtoType::Sealed]
+    Sealed,
     /// Property `size`.
     Size,
     /// Property `Ref`.
     Ref,
     /// Property `solid`.
     Solid,
+    /// Property `char`.
+    Char,
+    /// Property `color`.
+    Color,
+    /// Color `black`.
+    Black,
+    /// Color `red`.
+    Red,
+    /// Color `green`.
+    Green,
+    /// Color `yellow`.
+    Yellow,
+    /// Color `blue`.
+    Blue,
+    /// Color `magenta`.
+    Magenta,
+    /// Color `cyan`.
+    Cyan,
+    /// Color `white`.
+    White,
     /// Keyword `at`.
     At,
     /// Keyword `in`.
     In,
     /// Keyword `orphan`.
     Orphan,
+    /// Keyword `of`.
+    Of,
+    /// Keyword `as`.
+    As,
     /// Keyword `north`.
     North,
     /// Keyword `south`.
@@ -63,8 +155,30 @@ pub enum SyntaxKind {
     West,
     /// Keyword `moved`.
     Moved,
+    /// Keyword `not`.
+    Not,
+    /// Keyword `map`.
+    Map,
+    /// Keyword `solved`.
+    Solved,
+    /// Keyword `unsolved`.
+    Unsolved,
     /// Keyword `static`.
     Static,
+    /// Keyword `random`.
+    Random,
+    /// Keyword `reachable`.
+    Reachable,
+    /// Keyword `deep`.
+    Deep,
+    /// Keyword `create`.
+    Create,
+    /// Keyword `use`.
+    Use,
+    /// Keyword `moves`.
+    Moves,
+    /// Keyword `turn`.
+    Turn,
 }
 
 impl SyntaxKind {
@@ -76,6 +190,47 @@ impl SyntaxKind {
         matches!(self, SyntaxKind::Eol)
     }
 
+    /// Returns whether the token is a keyword.
+    pub fn is_keyword(&self) -> bool {
+        !matches!(
+            self,
+            SyntaxKind::Comma
+                | SyntaxKind::LeftParen
+                | SyntaxKind::RightParen
+                | SyntaxKind::Equals
+                | SyntaxKind::Semicolon
+                | SyntaxKind::Plus
+                | SyntaxKind::Minus
+                | SyntaxKind::Star
+                | SyntaxKind::Slash
+                | SyntaxKind::Empty
+                | SyntaxKind::Comment
+                | SyntaxKind::Eol
+                | SyntaxKind::Integer
+                | SyntaxKind::Ident
+                | SyntaxKind::Variable
+                | SyntaxKind::Character
+                | SyntaxKind::String
+        )
+    }
+
+    /// Returns whether the token is an operator of integer expressions.
+    pub fn is_operator(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Plus | SyntaxKind::Minus | SyntaxKind::Star | SyntaxKind::Slash
+        )
+    }
+
+    /// Returns whether the token ends a statement, or its properties before
+    /// the `ELSE` message of an assertion.
+    pub fn is_terminator(&self) -> bool {
+        matches!(
+            self,
+            SyntaxKind::Eol | SyntaxKind::Semicolon | SyntaxKind::Else
+        )
+    }
+
     pub fn is_proto(&self) -> bool {
         matches!(
             self,
@@ -85,13 +240,19 @@ impl SyntaxKind {
                 | SyntaxKind::Infinity
                 | SyntaxKind::Epsilon
                 | SyntaxKind::Void
+                | SyntaxKind::Room
+                | SyntaxKind::Sealed
         )
     }
 
     pub fn proto_needs_size(&self) -> bool {
         matches!(
             self,
-            SyntaxKind::Box | SyntaxKind::Epsilon | SyntaxKind::Void
+            SyntaxKind::Box
+                | SyntaxKind::Epsilon
+                | SyntaxKind::Void
+                | SyntaxKind::Room
+                | SyntaxKind::Sealed
         )
     }
 
@@ -102,6 +263,21 @@ impl SyntaxKind {
         )
     }
 
+    /// Returns the color of a color keyword.
+    pub fn color(&self) -> Option<AnsiColor> {
+        match self {
+            SyntaxKind::Black => Some(AnsiColor::Black),
+            SyntaxKind::Red => Some(AnsiColor::Red),
+            SyntaxKind::Green => Some(AnsiColor::Green),
+            SyntaxKind::Yellow => Some(AnsiColor::Yellow),
+            SyntaxKind::Blue => Some(AnsiColor::Blue),
+            SyntaxKind::Magenta => Some(AnsiColor::Magenta),
+            SyntaxKind::Cyan => Some(AnsiColor::Cyan),
+            SyntaxKind::White => Some(AnsiColor::White),
+            _ => None,
+        }
+    }
+
     pub fn is_direction(&self) -> bool {
         matches!(
             self,
@@ -116,33 +292,88 @@ impl Display for SyntaxKind {
             SyntaxKind::Comma => "`,`",
             SyntaxKind::LeftParen => "`(`",
             SyntaxKind::RightParen => "`)`",
+            SyntaxKind::Equals => "`=`",
+            SyntaxKind::Semicolon => "`;`",
+            SyntaxKind::Plus => "`+`",
+            SyntaxKind::Minus => "`-`",
+            SyntaxKind::Star => "`*`",
+            SyntaxKind::Slash => "`/`",
             SyntaxKind::Comment => "comment",
             SyntaxKind::Empty => "empty character",
             SyntaxKind::Eol => "end of line",
             SyntaxKind::Integer => "integer",
             SyntaxKind::Ident => "identifier",
+            SyntaxKind::Variable => "variable",
+            SyntaxKind::Character => "character",
+            SyntaxKind::String => "string",
             SyntaxKind::Define => "`define`",
             SyntaxKind::Place => "`place`",
             SyntaxKind::Push => "`push`",
+            SyntaxKind::Player => "`player`",
+            SyntaxKind::Move => "`move`",
             SyntaxKind::Expect => "`expect`",
+            SyntaxKind::Undo => "`undo`",
+            SyntaxKind::Checkpoint => "`checkpoint`",
+            SyntaxKind::Restore => "`restore`",
+            SyntaxKind::Let => "`let`",
+            SyntaxKind::Macro => "`macro`",
+            SyntaxKind::End => "`end`",
+            SyntaxKind::Call => "`call`",
+            SyntaxKind::Template => "`template`",
+            SyntaxKind::Spawn => "`spawn`",
+            SyntaxKind::Copy => "`copy`",
+            SyntaxKind::World => "`world`",
+            SyntaxKind::Version => "`version`",
+            SyntaxKind::Goal => "`goal`",
+            SyntaxKind::Show => "`show`",
+            SyntaxKind::List => "`list`",
+            SyntaxKind::Where => "`where`",
+            SyntaxKind::Info => "`info`",
+            SyntaxKind::Seed => "`seed`",
+            SyntaxKind::Else => "`else`",
             SyntaxKind::Wall => "`wall`",
             SyntaxKind::Box => "`box`",
             SyntaxKind::Alias => "`alias`",
             SyntaxKind::Infinity => "`infinity`",
             SyntaxKind::Epsilon => "`epsilon`",
             SyntaxKind::Void => "`void`",
+            SyntaxKind::Room => "`room`",
+            SyntaxKind::Sealed => "`sealed`",
             SyntaxKind::Size => "`size`",
             SyntaxKind::Ref => "`ref`",
             SyntaxKind::Solid => "`solid`",
+            SyntaxKind::Char => "`char`",
+            SyntaxKind::Color => "`color`",
+            SyntaxKind::Black => "`black`",
+            SyntaxKind::Red => "`red`",
+            SyntaxKind::Green => "`green`",
+            SyntaxKind::Yellow => "`yellow`",
+            SyntaxKind::Blue => "`blue`",
+            SyntaxKind::Magenta => "`magenta`",
+            SyntaxKind::Cyan => "`cyan`",
+            SyntaxKind::White => "`white`",
             SyntaxKind::At => "`at`",
             SyntaxKind::In => "`in`",
             SyntaxKind::Orphan => "`orphan`",
+            SyntaxKind::Of => "`of`",
+            SyntaxKind::As => "`as`",
             SyntaxKind::North => "`north`",
             SyntaxKind::South => "`south`",
             SyntaxKind::East => "`east`",
             SyntaxKind::West => "`west`",
             SyntaxKind::Moved => "`moved`",
+            SyntaxKind::Not => "`not`",
+            SyntaxKind::Map => "`map`",
+            SyntaxKind::Solved => "`solved`",
+            SyntaxKind::Unsolved => "`unsolved`",
             SyntaxKind::Static => "`static`",
+            SyntaxKind::Random => "`random`",
+            SyntaxKind::Reachable => "`reachable`",
+            SyntaxKind::Deep => "`deep`",
+            SyntaxKind::Create => "`create`",
+            SyntaxKind::Use => "`use`",
+            SyntaxKind::Moves => "`moves`",
+            SyntaxKind::Turn => "`turn`",
         };
 
         write!(f, "{}", str)
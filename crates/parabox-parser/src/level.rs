@@ -0,0 +1,348 @@
+use crate::command::{Command, MetaPosition, MetaProtoType};
+use crate::error::ExecCause;
+use crate::executor::Executor;
+use crate::meta::{MetaKey, MetaTable};
+use ecow::EcoString;
+use parabox::World;
+use std::collections::HashSet;
+
+/// A level as plain data, i.e. the blocks of a world with their names, the
+/// goal and the player.
+///
+/// Unlike a script, a level is declarative, and can be interchanged as JSON
+/// with the `serde` feature, see [Level::to_json], or as TOML with the `toml`
+/// feature, see [Level::to_toml].
+#[derive(Clone, Default, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct Level {
+    /// The blocks, in the order they are defined.
+    pub blocks: Vec<LevelBlock>,
+    /// The targets of the goal.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Vec::is_empty")
+    )]
+    pub goal: Vec<LevelTarget>,
+    /// The block pushed by `MOVE`.
+    #[cfg_attr(
+        feature = "serde",
+        serde(default, skip_serializing_if = "Option::is_none")
+    )]
+    pub player: Option<EcoString>,
+}
+
+/// A block of a [Level].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct LevelBlock {
+    /// The name of the block.
+    pub name: EcoString,
+    /// The prototype of the block.
+    pub proto: MetaProtoType,
+    /// The position of the block.
+    pub position: MetaPosition,
+}
+
+/// A target of the goal of a [Level].
+#[derive(Clone, Eq, PartialEq, Debug)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize)
+)]
+pub struct LevelTarget {
+    /// The name of the block.
+    pub block: EcoString,
+    /// The position required for the block.
+    pub position: MetaPosition,
+}
+
+impl Level {
+    /// Creates a level from a world, with the names of the meta table.
+    ///
+    /// The blocks without a name are named `block`, followed by a number if
+    /// the name is taken.
+    pub fn from_world(world: &World, meta: &MetaTable) -> Self {
+        let mut meta = meta.clone();
+        let mut index = 0;
+        for (key, _) in world.blocks_ordered() {
+            while !meta.contains_key(&key) {
+                let name: EcoString = match index {
+                    0 => "block".into(),
+                    _ => format!("block_{}", index).into(),
+                };
+                if !meta.contains_name(&name) {
+                    meta.insert(name, key);
+                }
+                index += 1;
+            }
+        }
+
+        let blocks = world
+            .blocks_ordered()
+            .map(|(key, block)| LevelBlock {
+                name: meta.get_name(&key).unwrap(),
+                proto: meta.key_to_name(&block.proto).unwrap(),
+                position: meta.key_to_name(&block.state.position).unwrap(),
+            })
+            .collect();
+
+        Self {
+            blocks,
+            goal: Vec::new(),
+            player: None,
+        }
+    }
+
+    /// Builds an executor with the world, the goal and the player of the level.
+    ///
+    /// The referenced blocks are defined first. Returns the cause of the first
+    /// failing definition, placement or target.
+    pub fn load(&self) -> Result<Executor, ExecCause> {
+        let mut executor = Executor::new();
+
+        let mut pending: Vec<_> = self.blocks.iter().collect();
+        let mut defined = HashSet::new();
+        while !pending.is_empty() {
+            let index = pending
+                .iter()
+                .position(|block| {
+                    block.proto.reference().is_none_or(|reference| {
+                        defined.contains(&reference)
+                            || !self.blocks.iter().any(|other| other.name == reference)
+                    })
+                })
+                .unwrap_or(0);
+            let block = pending.remove(index);
+            executor.execute(Command::define(block.name.clone(), block.proto.clone()))?;
+            defined.insert(block.name.clone());
+        }
+
+        for block in &self.blocks {
+            if block.position.container.is_some() {
+                let position = &block.position;
+                executor.execute(Command::place(
+                    block.name.clone(),
+                    position.container.clone(),
+                    position.pos,
+                ))?;
+            }
+        }
+        for target in &self.goal {
+            let position = &target.position;
+            executor.execute(Command::goal(
+                target.block.clone(),
+                position.container.clone(),
+                position.pos,
+            ))?;
+        }
+        if let Some(player) = &self.player {
+            executor.set_player(player.clone());
+        }
+
+        Ok(executor)
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Level {
+    /// Serializes the level to JSON.
+    pub fn to_json(&self) -> String {
+        serde_json::to_string_pretty(self).unwrap()
+    }
+
+    /// Deserializes a level from JSON.
+    pub fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+}
+
+#[cfg(feature = "toml")]
+impl Level {
+    /// Serializes the level to TOML.
+    ///
+    /// The blocks and the targets are written as arrays of tables, i.e.
+    /// `[[blocks]]` and `[[goal]]`, which can be written by hand with
+    /// comments:
+    ///
+    /// ```toml
+    /// player = "player"
+    ///
+    /// [[blocks]]
+    /// name = "room"
+    /// proto = { type = "box", size = [3, 1] }
+    /// position = { pos = [0, 0] }
+    ///
+    /// # The player starts in the west.
+    /// [[blocks]]
+    /// name = "player"
+    /// proto = { type = "wall" }
+    /// position = { container = "room", pos = [0, 0] }
+    /// ```
+    pub fn to_toml(&self) -> String {
+        toml::to_string_pretty(self).unwrap()
+    }
+
+    /// Deserializes a level from TOML.
+    pub fn from_toml(text: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(text)
+    }
+}
+
+impl Executor {
+    /// Creates a level from the world, the goal and the player of the
+    /// executor, see [Level::from_world].
+    pub fn to_level(&self) -> Level {
+        let mut level = Level::from_world(self.world(), self.meta());
+        let names: Vec<_> = level
+            .blocks
+            .iter()
+            .map(|block| block.name.clone())
+            .collect();
+        let meta = self.meta();
+
+        level.goal = self
+            .goal()
+            .targets()
+            .iter()
+            .filter_map(|(key, position)| {
+                Some(LevelTarget {
+                    block: meta.get_name(key)?,
+                    position: position.convert(meta).ok()?,
+                })
+            })
+            .collect();
+        level.player = self.player().filter(|player| names.contains(player));
+
+        level
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StringSource;
+    use parabox::{Position, ProtoType};
+    use std::sync::Arc;
+
+    fn execute(script: &str) -> Executor {
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        executor.run_all().unwrap();
+        executor
+    }
+
+    #[test]
+    fn test_level() {
+        let executor = execute(
+            "DEFINE BOX #room size (3, 1)\nDEFINE ALIAS #alias ref #room\n\
+             DEFINE BOX #player solid\nPLACE #player at (0, 0) in #room\n\
+             PLACE #alias at (2, 0) in #room\nPLAYER #player\n\
+             GOAL #player at (1, 0) in #room",
+        );
+        let level = executor.to_level();
+        assert_eq!(level.blocks.len(), 4);
+        assert_eq!(level.goal.len(), 1);
+        assert_eq!(level.player, Some("player".into()));
+
+        let loaded = level.load().unwrap();
+        assert!(loaded.world().equivalent(executor.world()));
+        assert_eq!(loaded.to_level(), level);
+
+        // The referenced blocks are defined first.
+        let mut level = level;
+        level.blocks.reverse();
+        assert!(level.load().unwrap().world().equivalent(executor.world()));
+
+        level.blocks[0].proto = MetaProtoType::Alias {
+            reference: "missing".into(),
+        };
+        assert!(level.load().is_err());
+    }
+
+    #[test]
+    fn test_anonymous() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (1, 0)));
+        let mut meta = MetaTable::new();
+        meta.insert("block".into(), container);
+
+        let level = Level::from_world(&world, &meta);
+        assert_eq!(level.blocks[1].name, "block_1");
+        assert!(level.load().unwrap().world().equivalent(&world));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json() {
+        let executor = execute(
+            "DEFINE BOX #room size (3, 1)\nDEFINE BOX #box size (1, 1)\n\
+             PLACE #box at (1, 0) in #room\nGOAL #box at (2, 0) in #room",
+        );
+        let level = executor.to_level();
+        let json = level.to_json();
+        assert!(json.contains(r#""type": "box""#));
+        assert_eq!(Level::from_json(&json).unwrap(), level);
+
+        let json = r#"{
+            "blocks": [
+                { "name": "room", "proto": { "type": "box", "size": [2, 1] }, "position": { "pos": [0, 0] } },
+                { "name": "wall", "proto": { "type": "wall" }, "position": { "container": "room", "pos": [1, 0] } }
+            ],
+            "player": "wall"
+        }"#;
+        let loaded = Level::from_json(json).unwrap().load().unwrap();
+        assert_eq!(loaded.player(), Some("wall".into()));
+        assert!(Level::from_json("{}").is_err());
+    }
+
+    #[cfg(feature = "toml")]
+    #[test]
+    fn test_toml() {
+        let executor = execute(
+            "DEFINE BOX #room size (3, 1)\nDEFINE ALIAS #alias ref #room\n\
+             DEFINE BOX #box size (1, 1)\nPLACE #box at (1, 0) in #room\n\
+             PLACE #alias at (0, 0) in #room\nPLAYER #box\nGOAL #box at (2, 0) in #room",
+        );
+        let level = executor.to_level();
+        let text = level.to_toml();
+        assert!(text.contains("[[blocks]]"));
+        assert_eq!(Level::from_toml(&text).unwrap(), level);
+
+        let text = r#"
+            # A box pushed east in a room.
+            player = "box"
+
+            [[blocks]]
+            name = "room"
+            proto = { type = "box", size = [2, 1] }
+            position = { pos = [0, 0] }
+
+            [[blocks]]
+            name = "box"
+            proto = { type = "box", size = [1, 1] }
+            position = { container = "room", pos = [0, 0] }
+
+            [[goal]]
+            block = "box"
+            position = { container = "room", pos = [1, 0] }
+        "#;
+        let loaded = Level::from_toml(text).unwrap().load().unwrap();
+        assert_eq!(loaded.player(), Some("box".into()));
+        let goal = loaded.goal().clone();
+        let (mut world, meta) = loaded.take();
+        let key = meta.get_key(&"box".into()).unwrap();
+        assert!(world.push(key, parabox::Direction::East).unwrap());
+        assert!(goal.is_satisfied(&world));
+        assert!(Level::from_toml("player = 1").is_err());
+    }
+}
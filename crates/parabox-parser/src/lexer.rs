@@ -1,3 +1,4 @@
+use crate::error::ErrorKind;
 use crate::kind::SyntaxKind;
 use ecow::EcoString;
 use std::ops::Range;
@@ -31,6 +32,11 @@ impl<'s> Lexer<'s> {
     pub fn text(&self) -> &'s str {
         self.s.from(self.start)
     }
+
+    /// Returns the whole text being lexed.
+    pub fn line(&self) -> &'s str {
+        self.s.string()
+    }
 }
 
 impl Lexer<'_> {
@@ -45,22 +51,67 @@ impl Lexer<'_> {
                 self.s.eat_while(|_: char| true);
                 Ok(SyntaxKind::Comment)
             }
+            Some('+') => Ok(SyntaxKind::Plus),
+            Some('-') => Ok(SyntaxKind::Minus),
+            Some('*') => Ok(SyntaxKind::Star),
+            Some('/') => Ok(SyntaxKind::Slash),
             Some(c) if c.is_whitespace() => {
                 self.s.eat_whitespace();
                 Ok(SyntaxKind::Empty)
             }
             None => Ok(SyntaxKind::Eol),
 
-            Some(c) if c.is_digit(10) => {
-                self.s.eat_while(|c: char| c.is_digit(10));
+            Some(c) if c.is_ascii_digit() => {
+                self.s.eat_while(|c: char| c.is_ascii_digit());
                 Ok(SyntaxKind::Integer)
             }
 
             Some('#') => {
                 self.s.eat_while(is_ident_char);
+                // The namespaces of spawned templates, e.g. `#prefix::ident`.
+                while self.s.eat_if("::") {
+                    self.s.eat_while(is_ident_char);
+                }
                 Ok(SyntaxKind::Ident)
             }
 
+            Some('$') => {
+                if self.s.eat_while(is_ident_char).is_empty() {
+                    return Err(self
+                        .error("expected a variable name after `$`".into())
+                        .with_kind(ErrorKind::InvalidValue));
+                }
+                Ok(SyntaxKind::Variable)
+            }
+
+            Some('\'') => {
+                if self.s.eat().is_some() && self.s.eat_if('\'') {
+                    Ok(SyntaxKind::Character)
+                } else {
+                    Err(self
+                        .error("expected a character between `'`".into())
+                        .with_kind(ErrorKind::UnexpectedToken))
+                }
+            }
+
+            Some('"') => loop {
+                match self.s.eat() {
+                    Some('"') => break Ok(SyntaxKind::String),
+                    Some('\\') => {
+                        self.s.eat();
+                    }
+                    Some(_) => {}
+                    None => {
+                        break Err(self
+                            .error("expected `\"` at the end of the string".into())
+                            .with_kind(ErrorKind::UnexpectedToken))
+                    }
+                }
+            },
+
+            Some('=') => Ok(SyntaxKind::Equals),
+            Some(';') => Ok(SyntaxKind::Semicolon),
+
             Some(c) if c.is_alphabetic() => {
                 self.s.eat_while(char::is_alphabetic);
                 keyword(self.text()).ok_or_else(|| self.unexpected_keyword())
@@ -71,6 +122,22 @@ impl Lexer<'_> {
     }
 }
 
+impl Lexer<'_> {
+    /// Lexes a name after the optional whitespaces, which may be a keyword.
+    pub fn name(&mut self) -> LexResult<()> {
+        self.s.eat_whitespace();
+        self.start = self.s.cursor();
+        if self.s.eat_if(char::is_alphabetic) {
+            self.s.eat_while(is_ident_char);
+            Ok(())
+        } else {
+            Err(self
+                .error("expected name".into())
+                .with_kind(ErrorKind::UnexpectedToken))
+        }
+    }
+}
+
 impl Lexer<'_> {
     pub fn error(&self, message: String) -> LexError {
         LexError::new(self.range(), message.into())
@@ -78,10 +145,12 @@ impl Lexer<'_> {
 
     pub fn unexpected_char(&self) -> LexError {
         self.error(format!("unexpected character `{}`", self.text()))
+            .with_kind(ErrorKind::UnexpectedToken)
     }
 
     pub fn unexpected_keyword(&self) -> LexError {
         self.error(format!("unexpected keyword `{}`", self.text()))
+            .with_kind(ErrorKind::UnknownKeyword)
     }
 }
 
@@ -101,48 +170,195 @@ pub fn is_ident_char(character: char) -> bool {
     character.is_alphanumeric() || character == '_'
 }
 
+/// Returns the text of a string token without the quotes, where a backslash
+/// escapes the next character, e.g. `\"`.
+pub fn unescape(token: &str) -> EcoString {
+    let mut string = EcoString::new();
+    let mut chars = token[1..token.len() - 1].chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => string.extend(chars.next()),
+            c => string.push(c),
+        }
+    }
+    string
+}
+
+/// The keywords of the script, in lowercase.
+pub const KEYWORDS: &[&str] = &[
+    "define",
+    "place",
+    "push",
+    "player",
+    "move",
+    "expect",
+    "undo",
+    "checkpoint",
+    "restore",
+    "let",
+    "goal",
+    "show",
+    "list",
+    "where",
+    "info",
+    "seed",
+    "else",
+    "macro",
+    "end",
+    "call",
+    "template",
+    "spawn",
+    "copy",
+    "world",
+    "version",
+    "wall",
+    "box",
+    "alias",
+    "infinity",
+    "epsilon",
+    "void",
+    "room",
+    "sealed",
+    "size",
+    "ref",
+    "solid",
+    "char",
+    "color",
+    "black",
+    "red",
+    "green",
+    "yellow",
+    "blue",
+    "magenta",
+    "cyan",
+    "white",
+    "at",
+    "in",
+    "orphan",
+    "of",
+    "as",
+    "north",
+    "south",
+    "east",
+    "west",
+    "moved",
+    "not",
+    "map",
+    "solved",
+    "unsolved",
+    "static",
+    "random",
+    "reachable",
+    "deep",
+    "create",
+    "use",
+    "moves",
+    "turn",
+];
+
 fn keyword(text: &str) -> Option<SyntaxKind> {
     match text.to_lowercase().as_str() {
         "define" => Some(SyntaxKind::Define),
         "place" => Some(SyntaxKind::Place),
         "push" => Some(SyntaxKind::Push),
+        "player" => Some(SyntaxKind::Player),
+        "move" => Some(SyntaxKind::Move),
         "expect" => Some(SyntaxKind::Expect),
+        "undo" => Some(SyntaxKind::Undo),
+        "checkpoint" => Some(SyntaxKind::Checkpoint),
+        "restore" => Some(SyntaxKind::Restore),
+        "let" => Some(SyntaxKind::Let),
+        "goal" => Some(SyntaxKind::Goal),
+        "show" => Some(SyntaxKind::Show),
+        "list" => Some(SyntaxKind::List),
+        "where" => Some(SyntaxKind::Where),
+        "info" => Some(SyntaxKind::Info),
+        "seed" => Some(SyntaxKind::Seed),
+        "else" => Some(SyntaxKind::Else),
+        "macro" => Some(SyntaxKind::Macro),
+        "end" => Some(SyntaxKind::End),
+        "call" => Some(SyntaxKind::Call),
+        "template" => Some(SyntaxKind::Template),
+        "spawn" => Some(SyntaxKind::Spawn),
+        "copy" => Some(SyntaxKind::Copy),
+        "world" => Some(SyntaxKind::World),
+        "version" => Some(SyntaxKind::Version),
         "wall" => Some(SyntaxKind::Wall),
         "box" => Some(SyntaxKind::Box),
         "alias" => Some(SyntaxKind::Alias),
         "infinity" => Some(SyntaxKind::Infinity),
         "epsilon" => Some(SyntaxKind::Epsilon),
         "void" => Some(SyntaxKind::Void),
+        "room" => Some(SyntaxKind::Room),
+        "sealed" => Some(SyntaxKind::Sealed),
         "size" => Some(SyntaxKind::Size),
         "ref" => Some(SyntaxKind::Ref),
         "solid" => Some(SyntaxKind::Solid),
+        "char" => Some(SyntaxKind::Char),
+        "color" => Some(SyntaxKind::Color),
+        "black" => Some(SyntaxKind::Black),
+        "red" => Some(SyntaxKind::Red),
+        "green" => Some(SyntaxKind::Green),
+        "yellow" => Some(SyntaxKind::Yellow),
+        "blue" => Some(SyntaxKind::Blue),
+        "magenta" => Some(SyntaxKind::Magenta),
+        "cyan" => Some(SyntaxKind::Cyan),
+        "white" => Some(SyntaxKind::White),
         "at" => Some(SyntaxKind::At),
         "in" => Some(SyntaxKind::In),
         "orphan" => Some(SyntaxKind::Orphan),
+        "of" => Some(SyntaxKind::Of),
+        "as" => Some(SyntaxKind::As),
         "north" => Some(SyntaxKind::North),
         "south" => Some(SyntaxKind::South),
         "east" => Some(SyntaxKind::East),
         "west" => Some(SyntaxKind::West),
         "moved" => Some(SyntaxKind::Moved),
+        "not" => Some(SyntaxKind::Not),
+        "map" => Some(SyntaxKind::Map),
+        "solved" => Some(SyntaxKind::Solved),
+        "unsolved" => Some(SyntaxKind::Unsolved),
         "static" => Some(SyntaxKind::Static),
+        "random" => Some(SyntaxKind::Random),
+        "reachable" => Some(SyntaxKind::Reachable),
+        "deep" => Some(SyntaxKind::Deep),
+        "create" => Some(SyntaxKind::Create),
+        "use" => Some(SyntaxKind::Use),
+        "moves" => Some(SyntaxKind::Moves),
+        "turn" => Some(SyntaxKind::Turn),
         _ => None,
     }
 }
 
 pub struct LexError {
     range: Range<usize>,
+    kind: ErrorKind,
     message: EcoString,
 }
 
 impl LexError {
     pub fn new(range: Range<usize>, message: EcoString) -> Self {
-        Self { range, message }
+        Self {
+            range,
+            kind: ErrorKind::Other,
+            message,
+        }
+    }
+
+    /// Sets the kind of the error.
+    pub fn with_kind(mut self, kind: ErrorKind) -> Self {
+        self.kind = kind;
+        self
     }
 
     pub fn range(&self) -> Range<usize> {
         self.range.clone()
     }
 
+    pub fn kind(&self) -> ErrorKind {
+        self.kind
+    }
+
     pub fn message(&self) -> EcoString {
         self.message.clone()
     }
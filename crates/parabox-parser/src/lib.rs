@@ -17,19 +17,39 @@
 //!
 //! A command is made up of _keywords_, _identifiers_ and _size tuples_:
 //! - _keywords_ are case-insensitive and are made up of alphabetic characters.
-//! -_identifiers_ are case-sensitive and are started by a `#` character
-//! followed by a series of alphabetic, numeric, or underscore characters.
+//! - _identifiers_ are case-sensitive and are started by a `#` character
+//!   followed by a series of alphabetic, numeric, or underscore characters,
+//!   which may be namespaced by `::`, e.g. `#left::room`.
 //! - _size tuples_ are of the form `(<x>, <y>)` where `<x>` and `<y>` are
-//! unsigned integers.
+//!   unsigned integers.
 //!
 //! These different parts are separated by spaces.
 //!
-//! You can also write inline comments by starting with `//`.
+//! You can also write inline comments by starting with `//`, and block
+//! comments spanning multiple lines between `/*` and `*/`.
+//!
+//! Several commands can be written on one line, separated by `;` characters,
+//! e.g. `PUSH #player east; PUSH #player east`. Each command is then spanned
+//! by its own statement, rather than the whole line.
+//!
+//! ## Version
+//!
+//! ```text
+//! VERSION <version>
+//! ```
+//!
+//! A script may start with the version of the script language it is written
+//! in, before any other statement. A script of a version later than
+//! [`SCRIPT_VERSION`] is rejected when parsed, instead of failing on the first
+//! statement it does not understand.
+//!
+//! [`SCRIPT_VERSION`]: crate::SCRIPT_VERSION
 //!
 //! ## Define
 //!
 //! ```text
 //! DEFINE <prototype> <identifier> [SIZE (<width>, <height>)] [REF <reference>] [SOLID]
+//!     [CHAR '<character>'] [COLOR <color>]
 //! ```
 //!
 //! The different properties are interchangeable, and each prototype has its own
@@ -42,7 +62,18 @@
 //! wall inside it. It can only be used on box prototype, and will conflict with
 //! `SIZE` property.
 //!
+//! The `CHAR` and `COLOR` properties are display hints, setting how the block
+//! is rendered by the formatter, see [`MetaTable`] which implements
+//! `MetaFmt` with the `format` feature. The character is also the one expected by `EXPECT MAP`, and
+//! defaults to the first character of the name. The colors are `BLACK`, `RED`,
+//! `GREEN`, `YELLOW`, `BLUE`, `MAGENTA`, `CYAN` and `WHITE`. For example:
+//!
+//! ```text
+//! DEFINE BOX #player SOLID CHAR '@' COLOR RED
+//! ```
+//!
 //! [`MetaProtoType`]: crate::MetaProtoType
+//! [`MetaTable`]: crate::MetaTable
 //!
 //! ## Place
 //!
@@ -57,37 +88,484 @@
 //! Usually you don't need to specify `ORPHAN` property, since the default
 //! position of a block is orphan.
 //!
+//! ```text
+//! PLACE <identifier> [<distance>] <direction> OF <anchor>
+//! ```
+//!
+//! Places the block next to the anchor, or at the distance from it, in the
+//! container of the anchor, e.g. `PLACE #box 2 EAST OF #player`. The position
+//! is computed from the current position of the anchor when executed.
+//!
+//! ```text
+//! PLACE <identifier> RANDOM IN <container> [AT (<x>, <y>) SIZE (<width>, <height>)]
+//! ```
+//!
+//! Places the block at a random empty cell of the container, or of the region
+//! at the position with the size if given. The cells are picked by the random
+//! number generator of the executor, see [Seed](#seed), and the command fails
+//! if no cell is empty.
+//!
+//! ## Copy
+//!
+//! ```text
+//! COPY <identifier> AS <identifier> [DEEP]
+//! ```
+//!
+//! Defines a new orphan block with the prototype, the display character and
+//! the color of the block. With `DEEP`, the blocks inside the block are copied
+//! as well, recursively, at the same positions inside the copy. Their copies
+//! are namespaced by the new identifier, e.g. `#wall` inside `#room` is copied
+//! as `#copy::wall` by `COPY #room AS #copy DEEP`, and an alias of a copied
+//! block refers to its copy.
+//!
 //! ## Push
 //!
 //! ```text
-//! PUSH <identifier> [NORTH | SOUTH | EAST | WEST] [[MOVED | STATIC]]
+//! PUSH <identifier> [NORTH | SOUTH | EAST | WEST]... [[MOVED | STATIC]]
 //! ```
 //!
 //! The `MOVED` and `STATIC` properties are optional. If not specified, no
 //! assertion is made. See the enum [`Assertion`] for more information.
 //!
+//! Several directions push the block in order, e.g. `PUSH #player EAST EAST
+//! NORTH`, and are the same as one push per direction. The assertion is made
+//! on every push, so `PUSH #player EAST EAST MOVED` fails if either push does
+//! not move the block.
+//!
 //! [`Assertion`]: crate::Assertion
 //!
+//! ## Map
+//!
+//! ```text
+//! MAP <container>
+//! <row>
+//! ...
+//! <character> = <prototype> [<identifier>] [<properties>]
+//! <character> = PLAYER <identifier>
+//! ...
+//! END
+//! ```
+//!
+//! Defines a box container from a grid of characters, and the blocks in it
+//! from a legend of the characters. The rows are from the top row to the
+//! bottom row, where `.` is an empty cell, and the lines containing `=` are
+//! the legend. For example:
+//!
+//! ```text
+//! MAP #room
+//!     wwwww
+//!     wp.bw
+//!     wwwww
+//!     w = WALL
+//!     b = BOX #box SOLID
+//!     p = PLAYER #player
+//! END
+//! ```
+//!
+//! A character with an identifier defines one block, which must appear once in
+//! the grid. A character without identifier defines a block in each cell,
+//! named after the container and the position, e.g. `#room_0_2`. The
+//! properties are the same as those in the `DEFINE` command, and `PLAYER`
+//! defines a solid box designated as the player, see below. The blocks are
+//! displayed by their character in the grid, unless given another by `CHAR`.
+//!
+//! The commands of the map are spanned by the whole block. Maps cannot be
+//! defined in the body of a macro.
+//!
+//! ## Player and Move
+//!
+//! ```text
+//! PLAYER <identifier>
+//!
+//! MOVE [NORTH | SOUTH | EAST | WEST]... [[MOVED | STATIC]]
+//! ```
+//!
+//! `PLAYER` designates the block pushed by the following `MOVE` commands,
+//! which are the same as `PUSH` commands on the player. Like variables, the
+//! player is designated when parsing, and remains designated in the sources
+//! pushed later to the same [`Executor`].
+//!
 //! ## Expect
 //!
 //! ```text
-//! EXPECT <identifier> [AT (<x>, <y>)] [IN <container>] [ORPHAN]
+//! EXPECT <identifier> [NOT] [AT (<x>, <y>)] [IN <container>] [ORPHAN]
 //! ```
 //!
 //! The properties are the same as those in the `PLACE` command.
 //!
+//! With `NOT`, the block is expected not to be at the position. The `AT`
+//! property is then optional, e.g. `EXPECT #box NOT IN #container` expects the
+//! block not to be directly in the container, at any position.
+//!
+//! ```text
+//! EXPECT <identifier> REACHABLE (<x>, <y>) IN <container>
+//! ```
+//!
+//! Expects some sequence of pushes to bring the block to the position, without
+//! changing the world. The pushes are searched by `parabox_solver::Solver`,
+//! pushing only the player if designated by `PLAYER`, and any box otherwise.
+//! It requires the default `solver` feature, and fails otherwise.
+//!
+//! The search is limited by the `max_states` and the `timeout` of the
+//! [`Budget`], and fails with [`ErrorKind::BudgetExceeded`] when giving up.
+//!
+//! [`Budget`]: crate::Budget
+//! [`ErrorKind::BudgetExceeded`]: crate::ErrorKind::BudgetExceeded
+//!
+//! ## Expect Map
+//!
+//! ```text
+//! EXPECT MAP <container>
+//!     <row>
+//!     ...
+//! ```
+//!
+//! Expects the interior of the container to match the rows on the following
+//! indented lines, from the top row to the bottom row. Each character is the
+//! display character of the block in the cell, see [`MetaTable::get_char`],
+//! or `.` for an empty cell. For example, with `#player` and `#wall` in a
+//! `(3, 2)` room:
+//!
+//! ```text
+//! EXPECT MAP #room
+//!     ..w
+//!     p..
+//! ```
+//!
+//! [`MetaTable::get_char`]: crate::MetaTable::get_char
+//!
+//! ## Else
+//!
+//! ```text
+//! <assertion> ELSE "<message>"
+//! ```
+//!
+//! Any `EXPECT` command, and any push with `MOVED` or `STATIC`, can end with a
+//! message explaining the assertion, which is written before the error if the
+//! assertion fails, e.g. `EXPECT #box AT (2, 0) IN #room ELSE "the box must
+//! reach the door"`. A backslash escapes the next character of the message,
+//! e.g. `\"`.
+//!
+//! ## Goal
+//!
+//! ```text
+//! GOAL <identifier> [AT (<x>, <y>)] [IN <container>] [ORPHAN]
+//!
+//! EXPECT SOLVED
+//! EXPECT UNSOLVED
+//! EXPECT MOVES <count>
+//! EXPECT TURN <count>
+//! ```
+//!
+//! Adds a target to the goal of the level, requiring the block to be at the
+//! position. The properties are the same as those in the `PLACE` command.
+//!
+//! `EXPECT SOLVED` expects all the targets to be reached, and `EXPECT
+//! UNSOLVED` expects some target not to be reached. See [`Executor::goal`] and
+//! [`parabox::Goal`].
+//!
+//! `EXPECT MOVES` expects the number of pushes that moved the world, e.g. to
+//! assert the length of a solution, and `EXPECT TURN` the number of pushes,
+//! including those leaving the world static. The pushes reverted by `UNDO` are
+//! not counted. See [`Executor::moves`] and [`Executor::turn`].
+//!
+//! [`Executor::goal`]: crate::Executor::goal
+//! [`Executor::moves`]: crate::Executor::moves
+//! [`Executor::turn`]: crate::Executor::turn
+//!
+//! ## Undo
+//!
+//! ```text
+//! UNDO [<count>]
+//! ```
+//!
+//! Reverts the last `<count>` pushes, or the last push if not specified. Any
+//! command executed since these pushes is reverted as well.
+//!
+//! ## Checkpoint
+//!
+//! ```text
+//! CHECKPOINT <name>
+//! RESTORE <name>
+//! ```
+//!
+//! `CHECKPOINT` saves the world, the definitions and the goal under the name,
+//! and `RESTORE` brings them back, so that several sequences of pushes can be
+//! tested from the same setup. A checkpoint can be restored any number of
+//! times, and `UNDO` after `RESTORE` reverts the pushes executed before the
+//! checkpoint.
+//!
+//! ## World
+//!
+//! ```text
+//! WORLD CREATE <identifier>
+//! WORLD USE <identifier>
+//! ```
+//!
+//! An executor manages several named worlds, so that e.g. the same pushes can
+//! be compared on different boards in one script. The commands run in the
+//! world named `#main` until `WORLD USE` switches to another world created by
+//! `WORLD CREATE`, which starts empty. Each world keeps its own blocks, goal
+//! and undo history, while the checkpoints are shared, restoring into the
+//! world in use.
+//!
+//! ## Seed
+//!
+//! ```text
+//! SEED <n>
+//! ```
+//!
+//! Seeds the random number generator of the `RANDOM` placements, so that the
+//! following placements are the same on each run. The generator is seeded with
+//! `0` before the first `SEED`, and a random layout is thus reproducible even
+//! without it.
+//!
+//! ## Show
+//!
+//! ```text
+//! SHOW [#<block>]
+//! ```
+//!
+//! Writes the hollow blocks of the world, or the interior of the block, to the
+//! output of the executor, see [`Executor::set_output`]. Each block is drawn
+//! by its display hints, see [Define](#define): its `CHAR`, or the first
+//! character of its name by default, or `?` if unnamed, in its `COLOR` if
+//! any, written as ANSI escape codes. It requires the default `format`
+//! feature, and fails otherwise.
+//!
+//! [`Executor::set_output`]: crate::Executor::set_output
+//!
+//! ## List
+//!
+//! ```text
+//! LIST [<proto>]
+//! ```
+//!
+//! Writes the names of the blocks, sorted by name, with their prototypes and
+//! positions to the output of the executor. If a prototype keyword is given,
+//! e.g. `LIST BOX`, only the blocks of the prototype are listed.
+//!
+//! ## Where and Info
+//!
+//! ```text
+//! WHERE #<block>
+//! INFO #<block>
+//! ```
+//!
+//! `WHERE` writes the position of the block to the output of the executor.
+//! `INFO` writes the prototype and the position of the block, followed by the
+//! blocks referring to it and the blocks inside it, e.g.
+//!
+//! ```text
+//! #room: BOX size (3, 1)
+//!   position: orphan
+//!   referenced by: #alias
+//!   contains: #box at (1, 0)
+//! ```
+//!
+//! ## Let
+//!
+//! ```text
+//! LET $<name> = <value>
+//! ```
+//!
+//! Defines a _variable_, whose value is an unsigned integer, a size tuple or
+//! another variable. Variables can be used wherever an integer or a size tuple
+//! of the same kind is accepted, e.g. `DEFINE BOX #room SIZE $size` or
+//! `PLACE #box AT ($x, 0) IN #room`.
+//!
+//! Variables are substituted when parsing, and remain defined in the sources
+//! pushed later to the same [`Executor`]. Redefining a variable only affects
+//! the commands after it.
+//!
+//! ## Arithmetic
+//!
+//! Wherever an integer is accepted, an expression of integers and integer
+//! variables can be used, with `+`, `-`, `*`, `/` and parentheses, e.g.
+//! `PLACE #wall AT ($width - 1, 0) IN #room`. Expressions are evaluated when
+//! parsing. The division rounds down, and a negative result is an error.
+//!
+//! ## Macro
+//!
+//! ```text
+//! MACRO <name>(<param>, ...)
+//! <line>
+//! ...
+//! END
+//!
+//! CALL <name>(<arg>, ...)
+//! ```
+//!
+//! Defines a _macro_, whose body is the lines until `END`. The parameters are
+//! identifiers or variables, e.g. `MACRO room(#room, $size)`.
+//!
+//! Calling a macro parses its body with the parameters bound to the arguments.
+//! A variable parameter takes a value as in `LET`, and an identifier
+//! parameter takes an identifier, which also replaces the prefix of the
+//! identifiers starting with the parameter followed by `_`. For example,
+//! `#room_wall` in the body is `#hall_wall` after `CALL room(#hall, (5, 5))`.
+//! The commands of a call are spanned by the `CALL` statement.
+//!
+//! Like variables, macros remain defined in the sources pushed later to the
+//! same [`Executor`]. Macros cannot be defined in the body of a macro.
+//!
+//! ## Template
+//!
+//! ```text
+//! TEMPLATE <name>(<param>, ...)
+//! <line>
+//! ...
+//! END
+//!
+//! SPAWN <name>(<arg>, ...) AS <prefix>
+//! ```
+//!
+//! Defines a _template_, which is a macro whose identifiers are namespaced by
+//! the prefix of each spawn. Any identifier in the body other than the
+//! identifier parameters is prefixed, e.g. `#room` in the body is
+//! `#left::room` after `SPAWN room() AS #left`, so that a template can be
+//! spawned several times without conflicting definitions. The spawned blocks
+//! are referred to by their namespaced identifiers, e.g. `PLACE #left::room AT
+//! (0, 0) IN #hall`.
+//!
+//! Templates share the names of macros, but are only spawned by `SPAWN` and
+//! macros are only called by `CALL`.
+//!
 //! # Execution
 //!
 //! Use [`Executor`] to execute commands. If you want to parse a script only,
 //! use [`parse`] function. See [`Executor`] and [`parse`] for more information.
 //!
+//! Parsing stops at the first error. To report all the errors of a script at
+//! once, use [`parse_all`] or [`Executor::push_source_all`] instead, which
+//! continue on the next line after an error.
+//!
+//! An interactive prompt can push each line read by [`Executor::push_line`]
+//! instead of a source, so that the errors are reported with the previous
+//! lines of the session.
+//!
+//! [`Executor::push_line`]: crate::Executor::push_line
+//!
+//! To build a level from several files without conflicting identifiers, push
+//! each file in its own namespace by [`Executor::push_source_as`], e.g. `#wall`
+//! in the namespace `left` is `#left::wall`, which the other files refer to.
+//!
+//! [`Executor::push_source_as`]: crate::Executor::push_source_as
+//!
+//! The executor also reports the blocks that are never used or are defined
+//! again as warnings, see [`Executor::warnings`].
+//!
+//! To check a script without running it, e.g. a corpus of levels, use
+//! [`Executor::check`], which finds the undefined blocks and the positions
+//! outside their containers without simulating the pushes.
+//!
+//! [`Executor::check`]: crate::Executor::check
+//!
+//! An error about an undefined block suggests the defined blocks with similar
+//! names, e.g. `did you mean #wall_12?` for `#wall_21`.
+//!
+//! When debugging a script, the executed commands can be rewound one by one by
+//! [`Executor::step_back`], e.g. after a failing `EXPECT`, once recording them
+//! is enabled by [`Executor::set_rewinding`].
+//!
+//! [`Executor::step_back`]: crate::Executor::step_back
+//! [`Executor::set_rewinding`]: crate::Executor::set_rewinding
+//!
+//! A `DEFINE` reusing the name of a block fails by default, which catches
+//! typos in long scripts. [`Executor::set_redefinition`] lets the new block
+//! shadow or replace the old one instead, see [`Redefinition`].
+//!
+//! [`Executor::set_redefinition`]: crate::Executor::set_redefinition
+//! [`Redefinition`]: crate::Redefinition
+//!
+//! The pending commands are listed by [`Executor::queued`]. Commands can be
+//! inserted programmatically by [`Executor::enqueue`] and
+//! [`Executor::enqueue_next`], or removed without execution by
+//! [`Executor::skip`].
+//!
+//! [`Executor::queued`]: crate::Executor::queued
+//! [`Executor::enqueue`]: crate::Executor::enqueue
+//! [`Executor::enqueue_next`]: crate::Executor::enqueue_next
+//! [`Executor::skip`]: crate::Executor::skip
+//!
+//! To trace the execution, e.g. to print the world after each push, register
+//! hooks called around each command by [`Executor::on_before`] and
+//! [`Executor::on_after`].
+//!
+//! [`Executor::on_before`]: crate::Executor::on_before
+//! [`Executor::on_after`]: crate::Executor::on_after
+//!
+//! Generated scripts may push blocks for a long time. Limit the executed
+//! commands, the pushes or the time by [`Executor::set_budget`].
+//!
+//! [`Executor::set_budget`]: crate::Executor::set_budget
+//!
+//! To fuzz the executor and the engine, [`generate_script`] writes random
+//! valid scripts of definitions, placements and pushes from a seed. The
+//! `parabox-generate` binary writes one to the standard output.
+//!
+//! [`generate_script`]: crate::generate_script
+//!
+//! [`Executor::run_all`] returns an [`ExecutionReport`], counting the executed
+//! commands, the assertions and the pushes, with the time of each command.
+//!
+//! [`Executor::run_all`]: crate::Executor::run_all
+//! [`ExecutionReport`]: crate::ExecutionReport
+//!
+//! To find the commands dominating the load time of a large script, enable
+//! [`Executor::set_profiling`] before running it. [`Executor::profile`] then
+//! returns a [`Profile`] with the time and the work of the pushes of each
+//! command, displayed as a table.
+//!
+//! [`Executor::set_profiling`]: crate::Executor::set_profiling
+//! [`Executor::profile`]: crate::Executor::profile
+//! [`Profile`]: crate::Profile
+//!
+//! Each error has a kind, see [`ParseError::kind`], with a stable code such as
+//! `unknown-keyword`, to tell the errors apart without matching the messages.
+//!
+//! [`ParseError::kind`]: crate::ParseError::kind
+//!
+//! The errors of the execution are [`ExecError`]s instead, whose
+//! [`ExecCause`] tells a failed push, a failed assertion with the expected and
+//! the actual values, and an unresolved name with its suggestions apart.
+//! They convert into a [`ParseError`] for the callers reporting both alike.
+//!
+//! [`ExecError`]: crate::ExecError
+//! [`ExecCause`]: crate::ExecCause
+//! [`ParseError`]: crate::ParseError
+//!
+//! Tools such as formatters and editors can use [`parse_ast`] instead, which
+//! keeps the statements as written, with their [`StatementKind`] and the span
+//! of each keyword, property and argument.
+//!
+//! [`parse_ast`]: crate::parse_ast
+//! [`StatementKind`]: crate::StatementKind
+//! [`Executor::warnings`]: crate::Executor::warnings
 //! [`Executor`]: crate::Executor
 //! [`parse`]: crate::parse
+//! [`parse_all`]: crate::parse_all
+//! [`Executor::push_source_all`]: crate::Executor::push_source_all
+//!
+//! # Migrating from the command struct
+//!
+//! [`Command`] used to be a struct with the `block` and `operation` fields.
+//! It is now an enum, since commands such as `UNDO` do not operate on a
+//! block, and the former commands are [`Command::Operate`]. This is a
+//! breaking change: replace the struct expressions and patterns by
+//! [`Command::new`] and `Command::Operate { .. }`, and the field accesses by
+//! [`Command::block`] and [`Command::operation`].
+//!
+//! [`Command`]: crate::Command
+//! [`Command::Operate`]: crate::Command::Operate
+//! [`Command::new`]: crate::Command::new
+//! [`Command::block`]: crate::Command::block
+//! [`Command::operation`]: crate::Command::operation
 //!
 //! # Examples
 //!
 //! ```
-//! # use std::rc::Rc;
+//! # use std::sync::Arc;
 //! # use parabox_parser::{Executor, StringSource};
 //!
 //! let script = r#"
@@ -106,7 +584,7 @@
 //! EXPECT #box2 at (1, 1) in #container
 //! "#;
 //!
-//! let source = Rc::new(StringSource::new(script.to_string()));
+//! let source = Arc::new(StringSource::new(script.to_string()));
 //! let mut executor = Executor::new();
 //!
 //! executor.push_source(source).unwrap();
@@ -124,32 +602,192 @@
 
 extern crate self as parabox_parser;
 
+mod ast;
+mod check;
 mod command;
 mod error;
 mod executor;
+mod export;
+mod generate;
 mod kind;
+mod level;
 mod lexer;
 mod meta;
 mod parser;
+mod report;
+mod rng;
+mod scope;
+mod solution;
 mod source;
+mod suggest;
 
+pub use ast::{Argument, Property, Spanned, Statement, StatementKind};
 pub use command::{Assertion, Command, MetaPosition, MetaProtoType, Operation};
-pub use error::{ParseError, ParseResult, Span};
-pub use executor::Executor;
+pub use error::{
+    AssertedValue, ErrorKind, ExecCause, ExecError, ExecResult, ParseError, ParseResult, Span,
+    Warning,
+};
+pub use executor::{Budget, Executor, Redefinition};
+pub use export::export_script;
+pub use generate::{generate_script, GenerateOptions};
+pub use level::{Level, LevelBlock, LevelTarget};
+pub use lexer::KEYWORDS;
 pub use meta::{MetaKey, MetaName, MetaTable};
-pub use parser::{parse, SpannedCommand};
+pub use parser::{parse, parse_all, parse_ast, SpannedCommand, SCRIPT_VERSION};
+pub use report::{ExecutionReport, Profile, ProfileEntry};
+pub use solution::format_solution;
 pub use source::{FileSource, NamedStringSource, Source, StringSource};
 
 #[cfg(test)]
 mod tests {
     use crate::command::{Assertion, Command, MetaProtoType};
-    use crate::parser::{parse, SpannedCommand};
-    use crate::source::StringSource;
+    use crate::error::{ErrorKind, ParseError};
+    use crate::parser::{parse, parse_all, SpannedCommand};
+    use crate::source::{Source, StringSource};
+    use anstyle::AnsiColor;
     use parabox::Direction;
-    use std::rc::Rc;
+    use std::sync::Arc;
 
     fn parse_command(text: &str) -> Result<Vec<SpannedCommand>, String> {
-        parse(Rc::new(StringSource::new(text.to_string()))).map_err(|e| e.to_string())
+        parse(Arc::new(StringSource::new(text.to_string()))).map_err(|e| e.to_string())
+    }
+
+    #[test]
+    fn test_error_kind() {
+        let cases = [
+            ("define box", ErrorKind::UnexpectedToken),
+            ("define box #box size (1, 1) ~", ErrorKind::UnexpectedToken),
+            ("teleport #box", ErrorKind::UnknownKeyword),
+            ("define alias #alias", ErrorKind::MissingProperty),
+            (
+                "define box #box solid size (1, 1)",
+                ErrorKind::ConflictingProperty,
+            ),
+            (
+                "define box #box size (1, 1) size (2, 2)",
+                ErrorKind::DuplicateProperty,
+            ),
+            ("let $x = (1, 1)\nundo $x", ErrorKind::TypeMismatch),
+            ("undo $x", ErrorKind::UnknownIdentifier),
+            ("call room()", ErrorKind::UnknownIdentifier),
+            ("undo 1 / 0", ErrorKind::InvalidValue),
+            ("let $ = 1", ErrorKind::InvalidValue),
+            ("undo 99999999999999999999999", ErrorKind::InvalidValue),
+            ("/* comment", ErrorKind::Unterminated),
+            (
+                "macro room()\nundo $x\nend\ncall room()",
+                ErrorKind::UnknownIdentifier,
+            ),
+        ];
+
+        for (text, kind) in cases {
+            let error = parse(Arc::new(StringSource::new(text.to_string())))
+                .err()
+                .unwrap();
+            assert_eq!(error.kind(), kind, "{}", text);
+        }
+
+        let text = "undo 1 + 99999999999999999999999";
+        let error = parse(Arc::new(StringSource::new(text.to_string())))
+            .err()
+            .unwrap();
+        assert_eq!(error.span().text(), "99999999999999999999999");
+
+        let error: Box<dyn std::error::Error> = Box::new(
+            parse(Arc::new(StringSource::new("define".to_string())))
+                .err()
+                .unwrap(),
+        );
+        assert!(error
+            .to_string()
+            .starts_with("error: expected proto type keyword"));
+    }
+
+    #[test]
+    fn test_keywords() {
+        for keyword in crate::KEYWORDS {
+            let kind = crate::lexer::Lexer::new(keyword).next().ok().unwrap();
+            assert!(kind.is_keyword(), "{}", keyword);
+        }
+    }
+
+    #[test]
+    fn test_threads() {
+        let source: Arc<dyn Source> = Arc::new(StringSource::new(
+            "define box #room size (3, 1)\nfoo".to_string(),
+        ));
+        let shared = source.clone();
+        let error = std::thread::spawn(move || parse(shared).err().unwrap())
+            .join()
+            .unwrap();
+        assert!(Arc::ptr_eq(&error.span().source(), &source));
+        assert_eq!(error.span().locate(), (1, 0));
+    }
+
+    #[test]
+    fn test_span() {
+        let text = "define box #room size (3, 1 + 1)  // room\n\
+                    place #room at (0, 0) in #room; push #room east\nmap #map\nw\nw = wall\nend";
+        let source: Arc<dyn Source> = Arc::new(StringSource::new(text.to_string()));
+        let commands = parse(source).unwrap();
+
+        let tokens: Vec<_> = commands[0]
+            .tokens()
+            .iter()
+            .map(|t| t.text().to_string())
+            .collect();
+        assert_eq!(
+            tokens,
+            vec!["define", "box", "#room", "size", "(", "3", ",", "1", "+", "1", ")"]
+        );
+        let size = commands[0].property("SIZE").unwrap();
+        assert_eq!(size.text(), "size (3, 1 + 1)");
+        assert_eq!((size.locate(), size.locate_end()), ((0, 17), (0, 32)));
+        assert_eq!(commands[1].property("at").unwrap().text(), "at (0, 0)");
+        assert_eq!(commands[1].property("in").unwrap().text(), "in #room");
+        assert_eq!(commands[2].span().text(), "push #room east");
+        assert_eq!(commands[2].property("east").unwrap().text(), "east");
+        assert!(commands[2].property("size").is_none());
+
+        let map = commands.last().unwrap().span();
+        assert!(map.is_multiline());
+        assert_eq!(map.lines(), 2..6);
+
+        let report = ParseError::new(map.clone(), "message".into()).to_string();
+        assert!(report.starts_with("error: message\n  --> "));
+        assert!(report.ends_with(
+            ":3:1\n\
+             1 │ define box #room size (3, 1 + 1)  // room\n\
+             2 │ place #room at (0, 0) in #room; push #room east\n\
+             \x20 │ \n\
+             3 │ map #map\n  │ ^^^^^^^^\n\
+             4 │ w\n  │ ^\n\
+             5 │ w = wall\n  │ ^^^^^^^^\n\
+             6 │ end\n  │ ^^^\n"
+        ));
+    }
+
+    #[test]
+    fn test_parse_all() {
+        let text = "define box #room size (3, 3)\ndefine wall\nplace #room at\n\
+                    push #room east\nmacro room()\nundo 1 - 2\nend\nundo 1 - 2";
+        let errors = parse_all(Arc::new(StringSource::new(text.to_string())))
+            .err()
+            .unwrap();
+
+        let lines: Vec<_> = errors.iter().map(|e| e.span().locate().0).collect();
+        assert_eq!(lines, vec![1, 2, 7]);
+        assert_eq!(errors[2].message(), "negative result of subtraction");
+
+        let text = "define wall #wall\nmap #room\n#\ndefine wall #wall";
+        let errors = parse_all(Arc::new(StringSource::new(text.to_string())))
+            .err()
+            .unwrap();
+        assert_eq!(errors.len(), 1);
+        assert_eq!(errors[0].message(), "missing `end` of map #room");
+
+        let result = parse_all(Arc::new(StringSource::new("push #box east".to_string())));
+        assert_eq!(result.unwrap().len(), 1);
     }
 
     #[test]
@@ -241,6 +879,28 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_define_room() {
+        let result = parse_command("define room #room size (2, 2)").unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].command(),
+            &Command::define("room".into(), MetaProtoType::Room { size: (2, 2) })
+        );
+    }
+
+    #[test]
+    fn test_define_sealed() {
+        let result = parse_command("define sealed #sealed size (2, 2)").unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].command(),
+            &Command::define("sealed".into(), MetaProtoType::Sealed { size: (2, 2) })
+        );
+    }
+
     #[test]
     fn test_define_solid() {
         let result = parse_command("define box #solid solid").unwrap();
@@ -260,6 +920,35 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_define_display() {
+        let result = parse_command("define box #box solid char '@' color red").unwrap();
+
+        assert_eq!(result.len(), 4);
+        assert_eq!(
+            result[3].command(),
+            &Command::display("box".into(), Some('@'), Some(AnsiColor::Red))
+        );
+
+        let result = parse_command("define wall #wall COLOR cyan").unwrap();
+        assert_eq!(
+            result[1].command(),
+            &Command::display("wall".into(), None, Some(AnsiColor::Cyan))
+        );
+
+        let commands = vec![
+            "define wall #wall char 'ab'",
+            "define wall #wall char '",
+            "define wall #wall char 'a' char 'b'",
+            "define wall #wall color",
+            "define wall #wall color east",
+            "define wall #wall color red color red",
+        ];
+        for command in commands {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
     #[test]
     fn test_place_in_container() {
         let result = parse_command("place #box at (1, 1) in #container").unwrap();
@@ -326,6 +1015,700 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_expect_reachable() {
+        let result = parse_command("expect #box reachable (1, 2) in #room").unwrap();
+
+        assert_eq!(
+            result[0].command(),
+            &Command::expect_reachable("box".into(), "room".into(), (1, 2))
+        );
+
+        for command in [
+            "expect #box reachable in #room",
+            "expect #box reachable (1, 2)",
+            "expect #box reachable at (1, 2) in #room",
+        ] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_copy() {
+        let result = parse_command("copy #a as #b; copy #a as #c deep").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].command(),
+            &Command::copy("a".into(), "b".into(), false)
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::copy("a".into(), "c".into(), true)
+        );
+
+        for command in ["copy #a", "copy #a #b", "copy #a as #b deep deep"] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_version() {
+        let result = parse_command("// The version.\n\nversion 1\ndefine wall #wall").unwrap();
+        assert_eq!(result.len(), 1);
+
+        let cases = [
+            ("version 2", "unsupported script version 2, expected 1 to 1"),
+            ("version 0", "unsupported script version 0, expected 1 to 1"),
+            ("version", "expected integer"),
+            ("version 1 2", "expected end of line"),
+            (
+                "define wall #wall\nversion 1",
+                "unexpected `version` after the first statement",
+            ),
+            ("version 1; define wall #wall", "expected end of line"),
+        ];
+        for (command, message) in cases {
+            let error = parse(Arc::new(StringSource::new(command.to_string())))
+                .err()
+                .unwrap();
+            assert_eq!(error.message(), message, "{}", command);
+        }
+
+        let error = parse(Arc::new(StringSource::new("version 1 + 1".to_string())))
+            .err()
+            .unwrap();
+        assert_eq!(error.span().text(), "1 + 1");
+        assert_eq!(error.span().locate(), (0, 8));
+    }
+
+    #[test]
+    fn test_world() {
+        let result = parse_command("world create #other; world use #other").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].command(), &Command::create_world("other".into()));
+        assert_eq!(result[1].command(), &Command::use_world("other".into()));
+
+        for command in ["world #other", "world create", "world use other"] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_else() {
+        let result = parse_command(
+            "expect #box at (1, 1) in #container else \"box \\\"a\\\" misplaced\"\n\
+             push #box east north moved else \"blocked\"\nexpect solved else \"unsolved\"\n\
+             expect map #room else \"wrong map\"\n  ..",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 5);
+        assert_eq!(
+            result[0].command(),
+            &Command::expect("box".into(), Some("container".into()), (1, 1))
+                .with_message("box \"a\" misplaced".into())
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::push("box".into(), Direction::East, Assertion::Moved)
+                .with_message("blocked".into())
+        );
+        assert_eq!(
+            result[2].command(),
+            &Command::push("box".into(), Direction::North, Assertion::Moved)
+                .with_message("blocked".into())
+        );
+        assert_eq!(
+            result[3].command(),
+            &Command::expect_solved(true).with_message("unsolved".into())
+        );
+        assert_eq!(
+            result[4].command(),
+            &Command::expect_map("room".into(), vec!["..".into()]).with_message("wrong map".into())
+        );
+
+        for command in [
+            "push #box east else \"message\"",
+            "expect #box orphan else",
+            "expect #box orphan else message",
+            "expect #box orphan else \"message",
+        ] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_undo() {
+        let result = parse_command("undo").unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].command(), &Command::undo(1));
+
+        let result = parse_command("undo 3").unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(result[0].command(), &Command::undo(3));
+    }
+
+    #[test]
+    fn test_checkpoint() {
+        let result = parse_command("checkpoint start; restore start").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].command(), &Command::checkpoint("start".into()));
+        assert_eq!(result[1].command(), &Command::restore("start".into()));
+
+        assert!(parse_command("restore #start").is_err());
+    }
+
+    #[test]
+    fn test_show() {
+        let result = parse_command("show; show #room").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].command(), &Command::show(None));
+        assert_eq!(result[1].command(), &Command::show(Some("room".into())));
+    }
+
+    #[test]
+    fn test_list() {
+        let result = parse_command("list; list box").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].command(), &Command::list(None));
+        assert_eq!(result[1].command(), &Command::list(Some("BOX".into())));
+
+        assert!(parse_command("list #box").is_err());
+    }
+
+    #[test]
+    fn test_where_info() {
+        let result = parse_command("where #box; info #box").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(result[0].command(), &Command::where_("box".into()));
+        assert_eq!(result[1].command(), &Command::info("box".into()));
+
+        assert!(parse_command("where").is_err());
+    }
+
+    #[test]
+    fn test_let() {
+        let result = parse_command(
+            "let $size = (3, 2)\nlet $x = 1\nlet $y = $x\ndefine box #box size $size",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 1);
+        assert_eq!(
+            result[0].command(),
+            &Command::define("box".into(), MetaProtoType::Box { size: (3, 2) })
+        );
+
+        let result = parse_command("let $x = 1\nplace #box at ($x, 2) in #container").unwrap();
+
+        assert_eq!(
+            result[0].command(),
+            &Command::place("box".into(), Some("container".into()), (1, 2))
+        );
+    }
+
+    #[test]
+    fn test_place_relative() {
+        let result = parse_command("place #a east of #b; place #a 2 + 1 north of #b").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].command(),
+            &Command::place_relative("a".into(), Direction::East, 1, "b".into())
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::place_relative("a".into(), Direction::North, 3, "b".into())
+        );
+
+        for command in ["place #a east #b", "place #a 2 of #b", "place #a west of"] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_place_random() {
+        let result = parse_command(
+            "seed 42; place #a random in #b; place #a random at (1, 0) size (2, 2) in #b",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(result[0].command(), &Command::seed(42));
+        assert_eq!(
+            result[1].command(),
+            &Command::place_random("a".into(), "b".into(), None)
+        );
+        assert_eq!(
+            result[2].command(),
+            &Command::place_random("a".into(), "b".into(), Some(((1, 0), (2, 2))))
+        );
+
+        for command in [
+            "place #a random",
+            "place #a random in #b at (0, 0)",
+            "place #a random in #b in #c",
+            "place #a random in #b orphan",
+            "seed #a",
+        ] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_arithmetic() {
+        let result = parse_command(
+            "let $w = 5\nlet $h = $w - 2\n\
+             place #box at ($w - 1, ($h + 1) / 2) in #room\n\
+             define box #room size (2 * $w + 1, $h * 2 - 1)\nundo (1 + 1) * 2",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            result[0].command(),
+            &Command::place("box".into(), Some("room".into()), (4, 2))
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::define("room".into(), MetaProtoType::Box { size: (11, 5) })
+        );
+        assert_eq!(result[2].command(), &Command::undo(4));
+
+        let result = parse_command("undo 1 // 2 + 3").unwrap();
+        assert_eq!(result[0].command(), &Command::undo(1));
+
+        for command in ["undo 1 - 2", "undo 1 / 0", "undo 1 +", "undo (1 + 2"] {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_fail_on_invalid_variable() {
+        let commands = vec![
+            "define box #box size $size",
+            "let $x = 1\ndefine box #box size $x",
+            "let $size = (1, 1)\nundo $size",
+            "let $x",
+            "let x = 1",
+        ];
+
+        for command in commands {
+            assert!(parse_command(command).is_err());
+        }
+    }
+
+    #[test]
+    fn test_macro() {
+        let result = parse_command(
+            "macro cell(#cell, $pos)\n  define box #cell solid\n  \
+             place #cell at $pos in #cell_container\nend\n\
+             call cell(#box, (1, 2))",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 4);
+        assert_eq!(
+            result[0].command(),
+            &Command::define("box".into(), MetaProtoType::Box { size: (1, 1) })
+        );
+        assert_eq!(
+            result[3].command(),
+            &Command::place("box".into(), Some("box_container".into()), (1, 2))
+        );
+        assert_eq!(result[3].span().text(), "call cell(#box, (1, 2))");
+
+        let result = parse_command(
+            "MACRO box(#box)\nDEFINE BOX #box SIZE $size\nEND\n\
+             MACRO room(#room)\nLET $size = (3, 3)\nCALL box(#room)\nEND\n\
+             CALL room(#hall) CALL room(#kitchen)",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result[1].command(),
+            &Command::define("kitchen".into(), MetaProtoType::Box { size: (3, 3) })
+        );
+    }
+
+    #[test]
+    fn test_fail_on_invalid_macro() {
+        let commands = vec![
+            "call room(#room)",
+            "macro room(#room)\ndefine box #room",
+            "macro room(#room)\nend\ncall room()",
+            "macro room(#room)\nend\ncall room(#a, #b)",
+            "macro room(#room, #room)\nend",
+            "macro room($size)\ndefine box #room size $size\nend\ncall room(1)",
+            "macro room()\nmacro inner()\nend\nend\ncall room()",
+            "macro room()\ncall room()\nend\ncall room()",
+        ];
+
+        for command in commands {
+            assert!(parse_command(command).is_err());
+        }
+
+        // The recursive calls are reported once.
+        let error = parse_command("macro room()\ncall room()\nend\ncall room()")
+            .err()
+            .unwrap();
+        assert!(error
+            .contains("in macro `room` (nested 64 times): too deeply nested call of macro `room`"));
+    }
+
+    #[test]
+    fn test_template() {
+        let result = parse_command(
+            "template room(#hall, $pos)\n  define box #room size (3, 3)\n  \
+             place #room at $pos in #hall\n  define wall #wall\n  \
+             place #wall at (1, 1) in #room\nend\n\
+             spawn room(#hall, (0, 0)) as #left; spawn room(#hall, (2, 0)) as #right\n\
+             place #box east of #left::wall",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 9);
+        assert_eq!(
+            result[0].command(),
+            &Command::define("left::room".into(), MetaProtoType::Box { size: (3, 3) })
+        );
+        assert_eq!(
+            result[5].command(),
+            &Command::place("right::room".into(), Some("hall".into()), (2, 0))
+        );
+        assert_eq!(
+            result[7].command(),
+            &Command::place("right::wall".into(), Some("right::room".into()), (1, 1))
+        );
+        assert_eq!(
+            result[8].command(),
+            &Command::place_relative("box".into(), Direction::East, 1, "left::wall".into())
+        );
+
+        // Templates spawned in templates are nested in the namespace.
+        let result = parse_command(
+            "template cell()\ndefine wall #wall\nend\n\
+             template row()\nspawn cell() as #a\nend\nspawn row() as #r",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result[0].command(),
+            &Command::define("r::a::wall".into(), MetaProtoType::Wall)
+        );
+
+        let commands = vec![
+            "template room()\nend\ncall room()",
+            "macro room()\nend\nspawn room() as #a",
+            "template room()\nend\nspawn room()",
+            "template room()\nend\nspawn room() as",
+            "template room()\ndefine wall #wall",
+        ];
+
+        for command in commands {
+            assert!(parse_command(command).is_err(), "{}", command);
+        }
+    }
+
+    #[test]
+    fn test_semicolon() {
+        let result =
+            parse_command("push #p east; push #p east ;; expect #p at (3, 1) in #room // done")
+                .unwrap();
+
+        assert_eq!(result.len(), 3);
+        assert_eq!(
+            result[1].command(),
+            &Command::push("p".into(), Direction::East, Assertion::None)
+        );
+        assert_eq!(
+            result[2].command(),
+            &Command::expect("p".into(), Some("room".into()), (3, 1))
+        );
+
+        let spans: Vec<_> = result.iter().map(|c| c.span().text()).collect();
+        assert_eq!(
+            spans,
+            [
+                "push #p east",
+                "push #p east",
+                "expect #p at (3, 1) in #room"
+            ]
+        );
+
+        let result = parse_command("define box #box solid; place #box orphan").unwrap();
+        assert_eq!(result.len(), 4);
+        assert_eq!(result[3].span().text(), "place #box orphan");
+    }
+
+    #[test]
+    fn test_push_directions() {
+        let result = parse_command("push #p east east north moved").unwrap();
+
+        assert_eq!(
+            result
+                .iter()
+                .map(|c| c.command().clone())
+                .collect::<Vec<_>>(),
+            [
+                Command::push("p".into(), Direction::East, Assertion::Moved),
+                Command::push("p".into(), Direction::East, Assertion::Moved),
+                Command::push("p".into(), Direction::North, Assertion::Moved),
+            ]
+        );
+        assert!(parse_command("push #p east moved north").is_err());
+
+        let result = parse_command("push #p east north static else \"stuck\"").unwrap();
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].command(),
+            &Command::push("p".into(), Direction::East, Assertion::Static)
+                .with_message("stuck".into())
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::push("p".into(), Direction::North, Assertion::Static)
+                .with_message("stuck".into())
+        );
+    }
+
+    #[test]
+    fn test_block_comment() {
+        let result = parse_command(
+            "push #a east /* push #b east\npush #c east\n*/ push #d /* é */ east // /*\n/**/",
+        )
+        .unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[1].command(),
+            &Command::push("d".into(), Direction::East, Assertion::None)
+        );
+        assert_eq!(result[1].span().text(), "push #d /* é */ east");
+
+        let Err(error) = parse_command("push #a east\n/* push #b east\n/* */ /*") else {
+            panic!("expected an error");
+        };
+        assert!(error.contains("unterminated block comment"));
+        assert!(parse_command("push #a east */").is_err());
+
+        // Comment markers inside strings and characters are kept.
+        let result = parse_command(
+            "expect #w orphan else \"files /* are\" /* note */\n\
+             expect #w orphan else \"a \\\" // b\"\ndefine wall #w char '\"' /* \" */",
+        )
+        .unwrap();
+        assert_eq!(result.len(), 4);
+        assert_eq!(
+            result[0].span().text(),
+            "expect #w orphan else \"files /* are\""
+        );
+        assert_eq!(
+            result[1].span().text(),
+            "expect #w orphan else \"a \\\" // b\""
+        );
+        assert_eq!(result[3].span().text(), "define wall #w char '\"'");
+    }
+
+    #[test]
+    fn test_expect_not() {
+        let result = parse_command(
+            "expect #box not at (1, 2) in #container\nexpect #box not in #container\n\
+             expect #box not orphan",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result[0].command(),
+            &Command::expect_not("box".into(), Some("container".into()), (1, 2))
+        );
+        assert_eq!(
+            result[1].command(),
+            &Command::expect_not_in("box".into(), "container".into())
+        );
+        assert_eq!(
+            result[2].command(),
+            &Command::expect_not("box".into(), None, (0, 0))
+        );
+
+        assert!(parse_command("expect #box in #container").is_err());
+        assert!(parse_command("expect #box not").is_err());
+        assert!(parse_command("expect #box not at (1, 2)").is_err());
+    }
+
+    #[test]
+    fn test_expect_map() {
+        let result = parse_command("expect map #room\n  ..w // comment\n\npush #p east").unwrap();
+
+        assert_eq!(result.len(), 2);
+        assert_eq!(
+            result[0].command(),
+            &Command::expect_map("room".into(), vec!["..w".into()])
+        );
+        assert_eq!(
+            result[0].span().text(),
+            "expect map #room\n  ..w // comment"
+        );
+
+        let result = parse_command("expect map #room\n  p.\n  ..").unwrap();
+        assert_eq!(
+            result[0].command(),
+            &Command::expect_map("room".into(), vec!["p.".into(), "..".into()])
+        );
+
+        // An empty line ends the rows.
+        assert!(parse_command("expect map #room\n  p.\n\n  ..").is_err());
+        assert!(parse_command("expect map #room; push #p east").is_err());
+        assert!(parse_command("expect map\n  ..").is_err());
+    }
+
+    #[test]
+    fn test_display_command() {
+        let script = "define box #room size (3, 2) char 'r' color red\n\
+                      define box #p solid\ndefine epsilon #e ref #room size (1, 1)\n\
+                      place #p at (0, 0) in #room\nplace #e orphan\n\
+                      place #a 2 east of #p\nplace #b random in #room at (1, 0) size (2, 2)\n\
+                      push #p east moved\npush #p west static\n\
+                      expect #p not at (1, 0) in #room else \"a \\\"quote\\\"\"\n\
+                      expect #p not in #e\nexpect #p reachable (2, 1) in #room\n\
+                      expect map #room else \"map\"\n  p..\n  ...\n\
+                      goal #p orphan\ncopy #p as #q deep\nexpect solved\nexpect moves 1\n\
+                      undo 2\ncheckpoint start\nlist box\nshow\nseed 7\nworld use #w";
+        let commands: Vec<_> = parse_command(script)
+            .unwrap()
+            .into_iter()
+            .map(|command| command.command().clone())
+            .collect();
+
+        let display = Command::display("room".into(), Some('r'), Some(AnsiColor::Red));
+        assert_eq!(display.to_string(), "#room char 'r' color red");
+        assert_eq!(commands[3].to_string(), "DEFINE WALL #p::interior",);
+        assert_eq!(commands[10].to_string(), "PUSH #p east MOVED",);
+
+        // Every command but the display parses back into itself.
+        let text: Vec<_> = commands
+            .iter()
+            .filter(|&command| command != &display)
+            .map(|command| command.to_string())
+            .collect();
+        let parsed: Vec<_> = parse_command(&text.join("\n"))
+            .unwrap()
+            .into_iter()
+            .map(|command| command.command().clone())
+            .collect();
+        assert_eq!(parsed.len(), commands.len() - 1);
+        assert!(parsed.iter().all(|command| commands.contains(command)));
+    }
+
+    #[test]
+    fn test_goal() {
+        let result = parse_command(
+            "goal #box at (1, 2) in #room\nexpect solved\nexpect unsolved\n\
+             expect moves 3\nexpect turn 2 + 2",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result
+                .iter()
+                .map(|c| c.command().clone())
+                .collect::<Vec<_>>(),
+            [
+                Command::goal("box".into(), Some("room".into()), (1, 2)),
+                Command::expect_solved(true),
+                Command::expect_solved(false),
+                Command::expect_moves(3),
+                Command::expect_turn(4),
+            ]
+        );
+        assert!(parse_command("goal #box in #room").is_err());
+        assert!(parse_command("expect solved #box").is_err());
+        assert!(parse_command("expect moves").is_err());
+    }
+
+    #[test]
+    fn test_player() {
+        let result =
+            parse_command("player #p\nmove east north moved\nplayer #q; move west").unwrap();
+
+        assert_eq!(
+            result
+                .iter()
+                .map(|c| c.command().clone())
+                .collect::<Vec<_>>(),
+            [
+                Command::push("p".into(), Direction::East, Assertion::Moved),
+                Command::push("p".into(), Direction::North, Assertion::Moved),
+                Command::push("q".into(), Direction::West, Assertion::None),
+            ]
+        );
+        assert!(parse_command("move east").is_err());
+        assert!(parse_command("player #p\nmove").is_err());
+    }
+
+    #[test]
+    fn test_map() {
+        let result = parse_command(
+            "map #room\n  w.p // comment\n  wb.\n  w = wall\n  p = player #p\n\
+             b = box #box size (2, 2)\nend\nmove east",
+        )
+        .unwrap();
+
+        assert_eq!(
+            result
+                .iter()
+                .map(|c| c.command().clone())
+                .collect::<Vec<_>>(),
+            [
+                Command::define("room".into(), MetaProtoType::Box { size: (3, 2) }),
+                Command::define("room_0_1".into(), MetaProtoType::Wall),
+                Command::display("room_0_1".into(), Some('w'), None),
+                Command::place("room_0_1".into(), Some("room".into()), (0, 1)),
+                Command::define("p".into(), MetaProtoType::Box { size: (1, 1) }),
+                Command::define("p::interior".into(), MetaProtoType::Wall),
+                Command::place("p::interior".into(), Some("p".into()), (0, 0)),
+                Command::display("p".into(), Some('p'), None),
+                Command::place("p".into(), Some("room".into()), (2, 1)),
+                Command::define("room_0_0".into(), MetaProtoType::Wall),
+                Command::display("room_0_0".into(), Some('w'), None),
+                Command::place("room_0_0".into(), Some("room".into()), (0, 0)),
+                Command::define("box".into(), MetaProtoType::Box { size: (2, 2) }),
+                Command::display("box".into(), Some('b'), None),
+                Command::place("box".into(), Some("room".into()), (1, 0)),
+                Command::push("p".into(), Direction::East, Assertion::None),
+            ]
+        );
+        assert!(result[0].span().text().starts_with("map #room\n"));
+        assert!(result[0].span().text().ends_with("end"));
+    }
+
+    #[test]
+    fn test_fail_on_invalid_map() {
+        let commands = vec![
+            "map #room\n  ..",
+            "map #room\nend",
+            "map #room\n  ..\n  ...\nend",
+            "map #room\n  .x\nend",
+            "map #room\n  pp\n  p = player #p\nend",
+            "map #room\n  w.\n  w = wall\n  w = box\nend",
+            "map #room\n  w.\n  ww = wall\nend",
+            "map #room\n  w.\n  w = wall size (1, 1)\nend",
+            "map #room push #p east",
+        ];
+
+        for command in commands {
+            assert!(parse_command(command).is_err());
+        }
+    }
+
     #[test]
     fn test_fail_on_invalid_syntax() {
         let commands = vec![
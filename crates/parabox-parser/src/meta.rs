@@ -1,14 +1,22 @@
 use crate::MetaProtoType;
+use anstyle::AnsiColor;
+#[cfg(feature = "format")]
+use anstyle::Style;
 use ecow::EcoString;
 use parabox::{BlockKey, Position, ProtoType};
+#[cfg(feature = "format")]
+use parabox_format::MetaFmt;
 use parabox_parser::MetaPosition;
 use std::collections::HashMap;
 use std::fmt::Debug;
 
 /// A table that maps block names to block keys and vice versa.
+#[derive(Clone)]
 pub struct MetaTable {
     name_to_key: HashMap<EcoString, BlockKey>,
     key_to_name: HashMap<BlockKey, EcoString>,
+    chars: HashMap<BlockKey, char>,
+    colors: HashMap<BlockKey, AnsiColor>,
 }
 
 impl MetaTable {
@@ -17,6 +25,8 @@ impl MetaTable {
         Self {
             name_to_key: HashMap::new(),
             key_to_name: HashMap::new(),
+            chars: HashMap::new(),
+            colors: HashMap::new(),
         }
     }
 
@@ -24,6 +34,8 @@ impl MetaTable {
     pub fn remove_by_name(&mut self, name: &EcoString) -> Option<BlockKey> {
         if let Some(key) = self.name_to_key.remove(name) {
             self.key_to_name.remove(&key);
+            self.chars.remove(&key);
+            self.colors.remove(&key);
             Some(key)
         } else {
             None
@@ -34,6 +46,8 @@ impl MetaTable {
     pub fn remove_by_key(&mut self, key: &BlockKey) -> Option<EcoString> {
         if let Some(name) = self.key_to_name.remove(key) {
             self.name_to_key.remove(&name);
+            self.chars.remove(key);
+            self.colors.remove(key);
             Some(name)
         } else {
             None
@@ -47,14 +61,15 @@ impl MetaTable {
         name: EcoString,
         key: BlockKey,
     ) -> (Option<BlockKey>, Option<EcoString>) {
-        let old_key = self.name_to_key.insert(name.clone(), key).map(|old_key| {
-            self.key_to_name.remove(&old_key);
-            old_key
-        });
-
-        let old_name = self.key_to_name.insert(key, name).map(|old_name| {
-            self.name_to_key.remove(&old_name);
-            old_name
+        let old_key = self
+            .name_to_key
+            .insert(name.clone(), key)
+            .inspect(|old_key| {
+                self.key_to_name.remove(old_key);
+            });
+
+        let old_name = self.key_to_name.insert(key, name).inspect(|old_name| {
+            self.name_to_key.remove(old_name);
         });
 
         (old_key, old_name)
@@ -80,6 +95,30 @@ impl MetaTable {
         self.key_to_name.get(key).cloned()
     }
 
+    /// Sets the display character of a block.
+    pub fn set_char(&mut self, key: BlockKey, char: char) {
+        self.chars.insert(key, char);
+    }
+
+    /// Gets the display character of a block by its key. Defaults to the first
+    /// character of its name.
+    pub fn get_char(&self, key: &BlockKey) -> Option<char> {
+        self.chars
+            .get(key)
+            .copied()
+            .or_else(|| self.key_to_name.get(key)?.chars().next())
+    }
+
+    /// Sets the display color of a block.
+    pub fn set_color(&mut self, key: BlockKey, color: AnsiColor) {
+        self.colors.insert(key, color);
+    }
+
+    /// Gets the display color of a block by its key, if set.
+    pub fn get_color(&self, key: &BlockKey) -> Option<AnsiColor> {
+        self.colors.get(key).copied()
+    }
+
     /// Gets the list of block names in the table. Sorted by name.
     pub fn names(&self) -> Vec<EcoString> {
         let mut names = self.name_to_key.keys().cloned().collect::<Vec<_>>();
@@ -106,16 +145,35 @@ impl MetaTable {
     }
 }
 
+impl Default for MetaTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Debug for MetaTable {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        write!(f, "MetaTable {{\n")?;
+        writeln!(f, "MetaTable {{")?;
         for (key, name) in self.key_to_name.iter() {
-            write!(f, "  {key:?} => #{name}\n")?;
+            writeln!(f, "  {key:?} => #{name}")?;
         }
         write!(f, "}}")
     }
 }
 
+/// Formats the blocks by their display characters, or `?` if unnamed, in
+/// their display colors, e.g. as set by `CHAR` and `COLOR` in `DEFINE`.
+#[cfg(feature = "format")]
+impl MetaFmt for MetaTable {
+    fn fmt_repr(&self, key: BlockKey) -> char {
+        self.get_char(&key).unwrap_or('?')
+    }
+
+    fn fmt_style(&self, key: BlockKey) -> Style {
+        Style::new().fg_color(self.get_color(&key).map(Into::into))
+    }
+}
+
 /// A key meta that can be converted to the corresponding name meta.
 ///
 /// There are structures that need to refer to other blocks. The data generated
@@ -246,6 +304,8 @@ impl MetaKey for ProtoType {
                 size: *size,
             }),
             ProtoType::Void { size } => Ok(MetaProtoType::Void { size: *size }),
+            ProtoType::Room { size } => Ok(MetaProtoType::Room { size: *size }),
+            ProtoType::Sealed { size } => Ok(MetaProtoType::Sealed { size: *size }),
         }
     }
 }
@@ -268,6 +328,8 @@ impl MetaName for MetaProtoType {
                 size: *size,
             }),
             MetaProtoType::Void { size } => Ok(ProtoType::Void { size: *size }),
+            MetaProtoType::Room { size } => Ok(ProtoType::Room { size: *size }),
+            MetaProtoType::Sealed { size } => Ok(ProtoType::Sealed { size: *size }),
         }
     }
 }
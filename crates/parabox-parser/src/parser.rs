@@ -1,61 +1,736 @@
+use crate::ast::{Argument, Property, Spanned, Statement, StatementKind};
 use crate::command::{Assertion, Command, MetaProtoType, Operation};
-use crate::error::{ParseError, ParseResult, Span};
+use crate::error::{ErrorKind, ParseError, ParseResult, Span};
 use crate::kind::SyntaxKind;
-use crate::lexer::{LexError, LexResult, Lexer};
+use crate::lexer::{unescape, LexError, LexResult, Lexer};
+use crate::scope::{Macro, Param, Scope, Value};
 use crate::source::Source;
+use anstyle::AnsiColor;
 use ecow::EcoString;
 use parabox::{Direction, Size};
-use std::rc::Rc;
+use std::collections::{HashMap, HashSet};
+use std::ops::Range;
+use std::sync::Arc;
+use unscanny::Scanner;
+
+/// The latest version of the script language, accepted by the `VERSION`
+/// pragma along with the earlier versions.
+pub const SCRIPT_VERSION: usize = 1;
 
 /// Parses a source into a list of commands.
-pub fn parse(source: Rc<dyn Source>) -> ParseResult<Vec<SpannedCommand>> {
-    let mut spanned_commands = vec![];
+pub fn parse(source: Arc<dyn Source>) -> ParseResult<Vec<SpannedCommand>> {
+    parse_in(source, &mut Scope::new())
+}
 
-    for line in 0..source.line_len() {
-        let text = source.line(line).unwrap();
+/// Parses a source into a list of commands, or returns all the errors in the
+/// source.
+///
+/// Unlike [parse], parsing continues on the next line after an error, so that
+/// the errors of all the lines are reported at once.
+pub fn parse_all(source: Arc<dyn Source>) -> Result<Vec<SpannedCommand>, Vec<ParseError>> {
+    parse_all_in(source, &mut Scope::new())
+}
+
+/// Parses a source into a list of commands, with the definitions of the
+/// scope and updating them.
+pub(crate) fn parse_in(
+    source: Arc<dyn Source>,
+    scope: &mut Scope,
+) -> ParseResult<Vec<SpannedCommand>> {
+    parse_from(source, 0, scope)
+}
+
+/// Parses the lines of a source from the line at the given index, as
+/// [parse_in].
+pub(crate) fn parse_from(
+    source: Arc<dyn Source>,
+    first: usize,
+    scope: &mut Scope,
+) -> ParseResult<Vec<SpannedCommand>> {
+    let (commands, mut errors) = parse_lines(source, first, scope, false);
+    match errors.pop() {
+        Some(error) => Err(error),
+        None => Ok(commands),
+    }
+}
+
+/// Parses a source as [parse_in], but continues after the errors, see
+/// [parse_all].
+pub(crate) fn parse_all_in(
+    source: Arc<dyn Source>,
+    scope: &mut Scope,
+) -> Result<Vec<SpannedCommand>, Vec<ParseError>> {
+    let (commands, errors) = parse_lines(source, 0, scope, true);
+    if errors.is_empty() {
+        Ok(commands)
+    } else {
+        Err(errors)
+    }
+}
+
+/// Parses the lines of a source from the line at the given index. Returns the
+/// commands and the errors, either stopping at the first error, or continuing
+/// on the next line if `recover` is set.
+fn parse_lines(
+    source: Arc<dyn Source>,
+    first: usize,
+    scope: &mut Scope,
+    recover: bool,
+) -> (Vec<SpannedCommand>, Vec<ParseError>) {
+    let mut spanned_commands: Vec<SpannedCommand> = vec![];
+    let mut errors = vec![];
+    let mut comment = None;
+    // Whether the last command is an `EXPECT MAP` reading the rows.
+    let mut map = false;
+    // The `MAP` block being read.
+    let mut grid: Option<Grid> = None;
+    // Whether no statement is read yet, so that `VERSION` may come.
+    let mut leading = first == 0;
+
+    for line in first..source.line_len() {
         let range = source.line_range(line).unwrap();
-        let commands = match parse_line(text) {
+        let text = &strip_block_comments(source.line(line).unwrap(), range.start, &mut comment);
+        if scope.is_recording() {
+            if is_end(text) {
+                scope.end_macro();
+            } else {
+                scope.record(text);
+            }
+            continue;
+        }
+
+        if let Some(block) = &mut grid {
+            if !is_end(text) {
+                block.lines.push((text.to_string(), range.start));
+                continue;
+            }
+
+            let block = grid.take().unwrap();
+            let commands = match block.desugar(scope) {
+                Ok(commands) => commands,
+                Err(e) => {
+                    errors.push(
+                        ParseError::new(Span::new(source.clone(), e.range()), e.message())
+                            .with_kind(e.kind()),
+                    );
+                    if !recover {
+                        return (spanned_commands, errors);
+                    }
+                    continue;
+                }
+            };
+            for command in commands {
+                spanned_commands.push(SpannedCommand::new(
+                    command,
+                    Span::new(source.clone(), block.start..range.end),
+                ));
+            }
+            continue;
+        }
+
+        if map {
+            if let Some(row) = map_row(text) {
+                spanned_commands
+                    .last_mut()
+                    .unwrap()
+                    .push_row(row, range.end);
+                continue;
+            }
+        }
+
+        map = false;
+        if leading && !is_blank(text) {
+            leading = false;
+            match version(text, scope) {
+                Ok(true) => continue,
+                Ok(false) => {}
+                Err(e) => {
+                    errors.push(line_error(&source, range.start, e));
+                    if !recover {
+                        return (spanned_commands, errors);
+                    }
+                    continue;
+                }
+            }
+        }
+
+        let header = match grid_header(text, scope) {
+            Ok(header) => header,
+            Err(e) => {
+                errors.push(line_error(&source, range.start, e));
+                if !recover {
+                    return (spanned_commands, errors);
+                }
+                continue;
+            }
+        };
+        if let Some(container) = header {
+            grid = Some(Grid {
+                container,
+                start: range.start + (text.len() - text.trim_start().len()),
+                lines: vec![],
+            });
+            continue;
+        }
+
+        let commands = match parse_line(text, scope) {
             Ok(commands) => commands,
             Err(e) => {
-                let error_range = e.range();
-                let range = (range.start + error_range.start)..(range.start + error_range.end);
-                return Err(ParseError::new(Span::new(source, range), e.message()));
+                errors.push(line_error(&source, range.start, e));
+                if !recover {
+                    return (spanned_commands, errors);
+                }
+                continue;
             }
         };
 
-        for command in commands {
-            spanned_commands.push(SpannedCommand::new(
-                command,
-                Span::new(source.clone(), range.clone()),
+        for (command, statement, tokens) in commands {
+            let offset =
+                |token: Range<usize>| (range.start + token.start)..(range.start + token.end);
+            spanned_commands.push(
+                SpannedCommand::new(command, Span::new(source.clone(), offset(statement)))
+                    .with_tokens(tokens.into_iter().map(offset).collect()),
+            );
+        }
+        map = spanned_commands.last().is_some_and(|command| {
+            command.span.range().end > range.start
+                && matches!(
+                    command.command.without_message(),
+                    Command::Operate {
+                        operation: Operation::ExpectMap(_),
+                        ..
+                    }
+                )
+        });
+    }
+
+    if let Some(block) = grid {
+        scope.discard_macro();
+        errors.push(
+            ParseError::new(
+                Span::new(source, block.start..block.start + 3),
+                format!("missing `end` of map #{}", block.container).into(),
+            )
+            .with_kind(ErrorKind::Unterminated),
+        );
+    } else if let Some(start) = comment {
+        scope.discard_macro();
+        errors.push(
+            ParseError::new(
+                Span::new(source, start..(start + 2)),
+                "unterminated block comment".into(),
+            )
+            .with_kind(ErrorKind::Unterminated),
+        );
+    } else if let Some((name, macro_)) = scope.discard_macro() {
+        let end = source.text().len();
+        errors.push(
+            ParseError::new(
+                Span::new(source, end..end),
+                format!("missing `end` of {} `{}`", macro_.kind(), name).into(),
+            )
+            .with_kind(ErrorKind::Unterminated),
+        );
+    }
+
+    (spanned_commands, errors)
+}
+
+/// Parses a source into a syntax tree, returning the statements and the errors
+/// of the lines that cannot be lexed.
+///
+/// Unlike [parse], the syntax tree is built from the tokens only, i.e. the
+/// blocks, the variables and the macros are not resolved, and the statements
+/// are kept as written, e.g. the body of a macro. It suits tools like
+/// formatters and editors, which need the span of each keyword and argument.
+pub fn parse_ast(source: Arc<dyn Source>) -> (Vec<Statement>, Vec<ParseError>) {
+    let mut statements: Vec<Statement> = vec![];
+    let mut errors = vec![];
+    let mut comment = None;
+    // The `MAP`, `MACRO` or `TEMPLATE` statement reading the lines until `END`.
+    let mut block: Option<Statement> = None;
+    // Whether the last statement is an `EXPECT MAP` reading the rows.
+    let mut rows = false;
+
+    for line in 0..source.line_len() {
+        let range = source.line_range(line).unwrap();
+        let text = &strip_block_comments(source.line(line).unwrap(), range.start, &mut comment);
+        let indent = text.len() - text.trim_start().len();
+
+        if let Some(statement) = &mut block {
+            if is_end(text) {
+                statement.extend(range.end);
+                statements.extend(block.take());
+            } else if statement.kind == StatementKind::Map {
+                let trimmed = text.trim();
+                if !trimmed.is_empty() {
+                    let start = range.start + indent;
+                    let span = Span::new(source.clone(), start..start + trimmed.len());
+                    statement.lines.push(Spanned::new(trimmed.into(), span));
+                }
+            } else {
+                match syntax_line(&source, text, range.start) {
+                    Ok(body) => statement.body.extend(body),
+                    Err(e) => errors.push(line_error(&source, range.start, e)),
+                }
+            }
+            continue;
+        }
+
+        if rows {
+            if let Some(row) = map_row(text) {
+                let start = range.start + indent;
+                let statement = statements.last_mut().unwrap();
+                let span = Span::new(source.clone(), start..start + row.len());
+                statement.lines.push(Spanned::new(row, span));
+                statement.extend(range.end);
+                continue;
+            }
+        }
+
+        match syntax_line(&source, text, range.start) {
+            Ok(mut line_statements) => {
+                rows = line_statements.last().is_some_and(|statement| {
+                    statement.kind == StatementKind::Expect
+                        && statement
+                            .properties
+                            .iter()
+                            .any(|property| property.kind == SyntaxKind::Map)
+                });
+                if line_statements
+                    .last()
+                    .is_some_and(|statement| statement.kind.is_block())
+                {
+                    block = line_statements.pop();
+                }
+                statements.extend(line_statements);
+            }
+            Err(e) => {
+                rows = false;
+                errors.push(line_error(&source, range.start, e));
+            }
+        }
+    }
+
+    if let Some(statement) = block {
+        let name = statement
+            .arguments
+            .first()
+            .map_or(EcoString::new(), |argument| match argument.value() {
+                Argument::Name(name) => format!(" `{}`", name).into(),
+                _ => format!(" {}", argument.span().text()).into(),
+            });
+        errors.push(
+            ParseError::new(
+                statement.keyword.span().clone(),
+                format!(
+                    "missing `end` of {}{}",
+                    statement.kind.keyword().to_lowercase(),
+                    name
+                )
+                .into(),
+            )
+            .with_kind(ErrorKind::Unterminated),
+        );
+        statements.push(statement);
+    } else if let Some(start) = comment {
+        errors.push(
+            ParseError::new(
+                Span::new(source, start..(start + 2)),
+                "unterminated block comment".into(),
+            )
+            .with_kind(ErrorKind::Unterminated),
+        );
+    }
+
+    (statements, errors)
+}
+
+/// Returns the error of the line at the offset in the source.
+fn line_error(source: &Arc<dyn Source>, offset: usize, e: LexError) -> ParseError {
+    let range = e.range();
+    ParseError::new(
+        Span::new(source.clone(), (offset + range.start)..(offset + range.end)),
+        e.message(),
+    )
+    .with_kind(e.kind())
+}
+
+/// Returns the line with the block comments replaced by spaces, so that the
+/// offsets are kept. Comment markers inside strings and characters are kept.
+///
+/// `comment` is the offset of the block comment not yet terminated before the
+/// line, if any, and is updated after the line. `start` is the offset of the
+/// line.
+fn strip_block_comments(text: &str, start: usize, comment: &mut Option<usize>) -> String {
+    let mut stripped = String::with_capacity(text.len());
+    let mut s = Scanner::new(text);
+
+    while let Some(c) = s.peek() {
+        if comment.is_some() {
+            if s.eat_if("*/") {
+                *comment = None;
+                stripped.push_str("  ");
+            } else {
+                s.eat();
+                stripped.extend(std::iter::repeat_n(' ', c.len_utf8()));
+            }
+        } else if s.at("//") {
+            stripped.push_str(s.after());
+            break;
+        } else if s.at("/*") {
+            *comment = Some(start + s.cursor());
+            s.eat_if("/*");
+            stripped.push_str("  ");
+        } else if c == '"' {
+            // Keep the string up to its unescaped closing quote, if any.
+            let from = s.cursor();
+            s.eat();
+            while let Some(c) = s.eat() {
+                match c {
+                    '"' => break,
+                    '\\' => {
+                        s.eat();
+                    }
+                    _ => {}
+                }
+            }
+            stripped.push_str(s.from(from));
+        } else if c == '\'' {
+            // Keep the character, e.g. `'"'`.
+            let from = s.cursor();
+            s.eat();
+            s.eat();
+            s.eat_if('\'');
+            stripped.push_str(s.from(from));
+        } else {
+            s.eat();
+            stripped.push(c);
+        }
+    }
+
+    stripped
+}
+
+/// Returns the row of a map on the line, i.e. the indented characters before
+/// any comment, or `None` if the line is not indented or empty.
+fn map_row(text: &str) -> Option<EcoString> {
+    if !text.starts_with(char::is_whitespace) {
+        return None;
+    }
+    let row = text.split("//").next().unwrap().trim();
+    (!row.is_empty()).then(|| row.into())
+}
+
+/// Returns whether the line has no statement, e.g. only a comment.
+fn is_blank(text: &str) -> bool {
+    let mut scope = Scope::new();
+    let mut parser = Parser::new(text, &mut scope);
+    matches!(parser.peek(), Ok(SyntaxKind::Eol))
+}
+
+/// Returns whether the line is the `VERSION` pragma, checking that the script
+/// version is supported.
+fn version(text: &str, scope: &mut Scope) -> LexResult<bool> {
+    let mut parser = Parser::new(text, scope);
+    if !matches!(parser.peek(), Ok(SyntaxKind::Version)) {
+        return Ok(false);
+    }
+
+    parser.next()?;
+    parser.peek()?;
+    let start = parser.start();
+    let version = parser.expect_integer()?;
+    // The parser has peeked the token after the version, so the error is
+    // located at the version itself.
+    let range = start..parser.end();
+    if !(1..=SCRIPT_VERSION).contains(&version) {
+        let message = format!(
+            "unsupported script version {}, expected 1 to {}",
+            version, SCRIPT_VERSION
+        );
+        return Err(LexError::new(range, message.into()).with_kind(ErrorKind::InvalidValue));
+    }
+    if !parser.peek()?.is_eof() {
+        return Err(parser.expected(SyntaxKind::Eol));
+    }
+    Ok(true)
+}
+
+/// Returns the container of the `MAP` block started by the line, if any.
+fn grid_header(text: &str, scope: &mut Scope) -> LexResult<Option<EcoString>> {
+    let mut parser = Parser::new(text, scope);
+    if !matches!(parser.peek(), Ok(SyntaxKind::Map)) {
+        return Ok(None);
+    }
+
+    parser.next()?;
+    let container = parser.expect_ident()?;
+    // The rows and the legend are on the following lines.
+    if !parser.peek()?.is_eof() {
+        return Err(parser.expected(SyntaxKind::Eol));
+    }
+    Ok(Some(container))
+}
+
+/// A `MAP` block, defining a container and its interior from a grid of
+/// characters and a legend.
+struct Grid {
+    container: EcoString,
+    /// The offset of the header.
+    start: usize,
+    /// The lines between the header and `END`, with their offsets.
+    lines: Vec<(String, usize)>,
+}
+
+/// The blocks defined by a character of a `MAP` block.
+struct Legend {
+    /// The name of the only block, or `None` for a block in each cell.
+    block: Option<EcoString>,
+    properties: Properties,
+    /// Whether the block is designated as the player.
+    player: bool,
+}
+
+impl Grid {
+    /// Returns the commands defining the container and the blocks in it. The
+    /// ranges of the errors are offsets in the source.
+    fn desugar(&self, scope: &mut Scope) -> LexResult<Vec<Command>> {
+        let mut rows = vec![];
+        let mut legends = HashMap::new();
+
+        for (line, offset) in &self.lines {
+            let text = line.split("//").next().unwrap();
+            let trimmed = text.trim();
+            let start = offset + (text.len() - text.trim_start().len());
+            if trimmed.is_empty() {
+                continue;
+            }
+
+            let Some(equals) = trimmed.find('=') else {
+                rows.push((trimmed, start));
+                continue;
+            };
+
+            let char = trimmed.chars().next().unwrap();
+            if !trimmed[char.len_utf8()..equals].trim().is_empty() {
+                return Err(LexError::new(
+                    start..start + equals,
+                    "expected a character before `=`".into(),
+                ));
+            }
+            if legends.contains_key(&char) {
+                return Err(LexError::new(
+                    start..start + char.len_utf8(),
+                    format!("multiple legends of `{}`", char).into(),
+                ));
+            }
+
+            let rest = start + equals + 1;
+            let mut parser = Parser::new(&trimmed[equals + 1..], scope);
+            let legend = legend(&mut parser).map_err(|e| {
+                let range = e.range();
+                LexError::new(rest + range.start..rest + range.end, e.message()).with_kind(e.kind())
+            })?;
+            legends.insert(char, legend);
+        }
+
+        let Some(&(first, _)) = rows.first() else {
+            return Err(LexError::new(
+                self.start..self.start + 3,
+                format!("missing rows of map #{}", self.container).into(),
             ));
+        };
+        let size = (first.chars().count(), rows.len());
+
+        let container = self.container.clone();
+        let mut commands = vec![Command::define(
+            container.clone(),
+            MetaProtoType::Box { size },
+        )];
+        let mut named = HashSet::new();
+
+        for (index, (row, offset)) in rows.into_iter().enumerate() {
+            if row.chars().count() != size.0 {
+                return Err(LexError::new(
+                    offset..offset + row.len(),
+                    format!("expected a row of {} characters", size.0).into(),
+                ));
+            }
+
+            let y = size.1 - 1 - index;
+            for (x, (column, char)) in row.char_indices().enumerate() {
+                if char == '.' {
+                    continue;
+                }
+
+                let range = offset + column..offset + column + char.len_utf8();
+                let legend = legends.get(&char).ok_or_else(|| {
+                    LexError::new(
+                        range.clone(),
+                        format!("undefined map character `{}`", char).into(),
+                    )
+                    .with_kind(ErrorKind::UnknownIdentifier)
+                })?;
+                let block = match &legend.block {
+                    Some(block) if !named.insert(char) => {
+                        return Err(LexError::new(
+                            range,
+                            format!("multiple `{}` of the block #{}", char, block).into(),
+                        ));
+                    }
+                    Some(block) => block.clone(),
+                    None => format!("{}_{}_{}", container, x, y).into(),
+                };
+
+                if legend.player {
+                    scope.set_player(block.clone());
+                }
+                // The blocks are displayed by their character in the map,
+                // unless given another by `CHAR`.
+                let properties = &legend.properties;
+                commands.append(&mut define_block(
+                    block.clone(),
+                    properties.proto.clone(),
+                    properties.solid,
+                    properties.char.or(Some(char)),
+                    properties.color,
+                ));
+                commands.push(Command::place(block, Some(container.clone()), (x, y)));
+            }
         }
+
+        Ok(commands)
     }
+}
 
-    Ok(spanned_commands)
+/// Parses the blocks of a legend, after the `=` of the line.
+fn legend(parser: &mut Parser) -> LexResult<Legend> {
+    let legend = if parser.peek()? == SyntaxKind::Player {
+        parser.next()?;
+        Legend {
+            block: Some(parser.expect_ident()?),
+            properties: Properties {
+                proto: MetaProtoType::Box { size: (1, 1) },
+                solid: true,
+                char: None,
+                color: None,
+            },
+            player: true,
+        }
+    } else {
+        let proto = parser.expect_proto()?;
+        let block = match parser.peek()? {
+            SyntaxKind::Ident => Some(parser.expect_ident()?),
+            _ => None,
+        };
+        Legend {
+            block,
+            properties: properties(parser, proto)?,
+            player: false,
+        }
+    };
+
+    if !parser.peek()?.is_eof() {
+        return Err(parser.expected(SyntaxKind::Eol));
+    }
+    Ok(legend)
 }
 
-fn parse_line(text: &str) -> LexResult<Vec<Command>> {
-    let mut parser = Parser::new(text);
+/// Returns whether the line ends the body of a macro or a `MAP` block.
+fn is_end(text: &str) -> bool {
+    let mut scope = Scope::new();
+    let mut parser = Parser::new(text, &mut scope);
+    matches!(parser.next(), Ok(SyntaxKind::End)) && matches!(parser.next(), Ok(SyntaxKind::Eol))
+}
+
+/// A command parsed from a line, with the range of its statement and the
+/// ranges of the tokens of the statement.
+type LineCommand = (Command, Range<usize>, Vec<Range<usize>>);
+
+/// Parses a line into a list of commands, with the ranges of their
+/// statements in the line.
+fn parse_line(text: &str, scope: &mut Scope) -> LexResult<Vec<LineCommand>> {
+    let mut parser = Parser::new(text, scope);
     let mut commands = vec![];
 
     while !parser.peek()?.is_eof() {
-        match parser.next()? {
-            SyntaxKind::Define => {
-                commands.append(&mut define(&mut parser)?);
+        let kind = parser.next()?;
+        let start = parser.start();
+        let first = parser.tokens.len() - 1;
+        let mut statement = match kind {
+            SyntaxKind::Semicolon => continue,
+            SyntaxKind::Define => define(&mut parser)?,
+            SyntaxKind::Place => vec![place(&mut parser)?],
+            SyntaxKind::Push => push(&mut parser)?,
+            SyntaxKind::Player => {
+                player(&mut parser)?;
+                vec![]
             }
-            SyntaxKind::Place => {
-                commands.push(place(&mut parser)?);
+            SyntaxKind::Move => move_(&mut parser)?,
+            SyntaxKind::Expect => vec![expect(&mut parser)?],
+            SyntaxKind::Undo => vec![undo(&mut parser)?],
+            SyntaxKind::Checkpoint => vec![Command::checkpoint(parser.expect_name()?)],
+            SyntaxKind::Restore => vec![Command::restore(parser.expect_name()?)],
+            SyntaxKind::Goal => vec![goal(&mut parser)?],
+            SyntaxKind::Show => vec![show(&mut parser)?],
+            SyntaxKind::List => vec![list(&mut parser)?],
+            SyntaxKind::Where => vec![Command::where_(parser.expect_ident()?)],
+            SyntaxKind::Info => vec![Command::info(parser.expect_ident()?)],
+            SyntaxKind::Seed => vec![Command::seed(parser.expect_integer()? as u64)],
+            SyntaxKind::Let => {
+                let_(&mut parser)?;
+                vec![]
             }
-            SyntaxKind::Push => {
-                commands.push(push(&mut parser)?);
+            SyntaxKind::Macro => {
+                macro_(&mut parser, false)?;
+                vec![]
             }
-            SyntaxKind::Expect => {
-                commands.push(expect(&mut parser)?);
+            SyntaxKind::Template => {
+                macro_(&mut parser, true)?;
+                vec![]
+            }
+            SyntaxKind::Call => call(&mut parser, false)?,
+            SyntaxKind::Spawn => call(&mut parser, true)?,
+            SyntaxKind::Copy => vec![copy(&mut parser)?],
+            SyntaxKind::World => vec![world(&mut parser)?],
+            SyntaxKind::Version => {
+                return Err(parser.unexpected("`version` after the first statement"));
             }
             _ => {
                 return Err(parser.expected("statement keyword"));
             }
         };
+
+        // The message of the assertions of the statement, e.g. both pushes
+        // of `PUSH #box EAST EAST MOVED ELSE "message"`.
+        if parser.peek()? == SyntaxKind::Else {
+            parser.next()?;
+            if !statement
+                .iter()
+                .any(|command| command.is_assertion() || command.is_asserted_push())
+            {
+                return Err(parser.unexpected("`else` without an assertion"));
+            }
+            let message = parser.expect_string()?;
+            for command in &mut statement {
+                if command.is_assertion() || command.is_asserted_push() {
+                    *command = command.clone().with_message(message.clone());
+                }
+            }
+        }
+
+        let range = start..parser.end();
+        let tokens: Vec<_> = parser.tokens[first..]
+            .iter()
+            .filter(|token| token.end <= range.end)
+            .cloned()
+            .collect();
+        commands.extend(
+            statement
+                .into_iter()
+                .map(|command| (command, range.clone(), tokens.clone())),
+        );
     }
 
     Ok(commands)
@@ -64,12 +739,35 @@ fn parse_line(text: &str) -> LexResult<Vec<Command>> {
 fn define(parser: &mut Parser) -> LexResult<Vec<Command>> {
     let proto = parser.expect_proto()?;
     let block = parser.expect_ident()?;
+    let properties = properties(parser, proto)?;
+    Ok(define_block(
+        block,
+        properties.proto,
+        properties.solid,
+        properties.char,
+        properties.color,
+    ))
+}
+
+/// The properties of a block defined by `DEFINE` or by a legend of a `MAP`.
+struct Properties {
+    proto: MetaProtoType,
+    solid: bool,
+    /// The display character given by `CHAR`.
+    char: Option<char>,
+    /// The display color given by `COLOR`.
+    color: Option<AnsiColor>,
+}
 
+/// Parses the properties of a prototype.
+fn properties(parser: &mut Parser, proto: SyntaxKind) -> LexResult<Properties> {
     let mut reference = None;
     let mut size = None;
     let mut solid = false;
+    let mut char = None;
+    let mut color = None;
 
-    while !parser.peek()?.is_eof() {
+    while !parser.peek()?.is_terminator() {
         match parser.next()? {
             SyntaxKind::Size => {
                 if !proto.proto_needs_size() {
@@ -112,8 +810,22 @@ fn define(parser: &mut Parser) -> LexResult<Vec<Command>> {
 
                 solid = true;
             }
+            SyntaxKind::Char => {
+                if char.is_some() {
+                    return Err(parser.multiple("`char` keywords"));
+                }
+
+                char = Some(parser.expect_char()?);
+            }
+            SyntaxKind::Color => {
+                if color.is_some() {
+                    return Err(parser.multiple("`color` keywords"));
+                }
+
+                color = Some(parser.expect_color()?);
+            }
             _ => {
-                return Err(parser.expected("`size`, `ref` or `solid`"));
+                return Err(parser.expected("`size`, `ref`, `solid`, `char` or `color`"));
             }
         }
     }
@@ -138,35 +850,117 @@ fn define(parser: &mut Parser) -> LexResult<Vec<Command>> {
             size,
         },
         SyntaxKind::Void => MetaProtoType::Void { size },
+        SyntaxKind::Room => MetaProtoType::Room { size },
+        SyntaxKind::Sealed => MetaProtoType::Sealed { size },
         _ => unreachable!(),
     };
 
-    let statement = Command::define(block.clone(), proto);
+    Ok(Properties {
+        proto,
+        solid,
+        char,
+        color,
+    })
+}
 
-    let result = if solid {
+/// Returns the commands defining a block, filling a solid block with a wall,
+/// and setting its display character and color if given.
+fn define_block(
+    block: EcoString,
+    proto: MetaProtoType,
+    solid: bool,
+    char: Option<char>,
+    color: Option<AnsiColor>,
+) -> Vec<Command> {
+    let mut commands = vec![Command::define(block.clone(), proto)];
+
+    if solid {
         let interior: EcoString = format!("{}::interior", block).into();
         let interior_proto = MetaProtoType::Wall;
 
-        vec![
-            statement,
-            Command::define(interior.clone(), interior_proto),
-            Command::place(interior.clone(), Some(block), (0, 0)),
-        ]
-    } else {
-        vec![statement]
-    };
+        commands.push(Command::define(interior.clone(), interior_proto));
+        commands.push(Command::place(
+            interior.clone(),
+            Some(block.clone()),
+            (0, 0),
+        ));
+    }
+    if char.is_some() || color.is_some() {
+        commands.push(Command::display(block, char, color));
+    }
 
-    Ok(result)
+    commands
 }
 
 fn place(parser: &mut Parser) -> LexResult<Command> {
     let block = parser.expect_ident()?;
+    if parser.peek()? == SyntaxKind::Random {
+        parser.next()?;
+        return place_random(parser, block);
+    }
+    let distance = match parser.peek()? {
+        SyntaxKind::Integer | SyntaxKind::Variable | SyntaxKind::LeftParen => {
+            Some(parser.expect_integer()?)
+        }
+        _ => None,
+    };
+    if distance.is_some() || parser.peek()?.is_direction() {
+        let direction = parser.expect_direction()?;
+        parser.expect(SyntaxKind::Of)?;
+        let anchor = parser.expect_ident()?;
+        return Ok(Command::place_relative(
+            block,
+            direction,
+            distance.unwrap_or(1),
+            anchor,
+        ));
+    }
+
+    let (container, pos) = position(parser, false)?;
+    Ok(Command::place(block, container, pos.unwrap()))
+}
 
+/// Parses the `IN` property of a random placement, and the `AT` and `SIZE`
+/// properties of the region of the container to place the block in.
+fn place_random(parser: &mut Parser, block: EcoString) -> LexResult<Command> {
+    let mut container = None;
+    let mut at = None;
+    let mut size = None;
+
+    while !parser.peek()?.is_terminator() {
+        match parser.next()? {
+            SyntaxKind::In if container.is_none() => container = Some(parser.expect_ident()?),
+            SyntaxKind::At if at.is_none() => at = Some(parser.expect_size()?),
+            SyntaxKind::Size if size.is_none() => size = Some(parser.expect_size()?),
+            SyntaxKind::In => return Err(parser.multiple("`in` keywords")),
+            SyntaxKind::At => return Err(parser.multiple("`at` keywords")),
+            SyntaxKind::Size => return Err(parser.multiple("`size` keywords")),
+            _ => return Err(parser.expected("`in`, `at` or `size`")),
+        }
+    }
+
+    let container = container.ok_or_else(|| parser.missing("`in` keyword"))?;
+    let region = match (at, size) {
+        (Some(at), Some(size)) => Some((at, size)),
+        (None, None) => None,
+        (Some(_), None) => return Err(parser.missing("`size` keyword")),
+        (None, Some(_)) => return Err(parser.missing("`at` keyword")),
+    };
+    Ok(Command::place_random(block, container, region))
+}
+
+/// Parses the `AT`, `IN` and `ORPHAN` properties of a position. Returns the
+/// container and the position, which is only optional with the container if
+/// `optional_at` is set.
+fn position(
+    parser: &mut Parser,
+    optional_at: bool,
+) -> LexResult<(Option<EcoString>, Option<Size>)> {
     let mut container = None;
     let mut pos = None;
     let mut orphan = false;
 
-    while !parser.peek()?.is_eof() {
+    while !parser.peek()?.is_terminator() {
         match parser.next()? {
             SyntaxKind::At => {
                 if orphan {
@@ -207,20 +1001,43 @@ fn place(parser: &mut Parser) -> LexResult<Command> {
         }
     }
 
-    let (container, pos) = if orphan {
-        (None, (0, 0))
+    if orphan {
+        Ok((None, Some((0, 0))))
     } else {
         let container = container.ok_or_else(|| parser.missing("`in` keyword"))?;
-        let pos = pos.ok_or_else(|| parser.missing("`at` keyword"))?;
-        (Some(container), pos)
-    };
-
-    Ok(Command::place(block, container, pos))
+        if pos.is_none() && !optional_at {
+            return Err(parser.missing("`at` keyword"));
+        }
+        Ok((Some(container), pos))
+    }
 }
 
-fn push(parser: &mut Parser) -> LexResult<Command> {
+fn push(parser: &mut Parser) -> LexResult<Vec<Command>> {
     let block = parser.expect_ident()?;
-    let direction = parser.expect_direction()?;
+    directions(parser, block)
+}
+
+fn player(parser: &mut Parser) -> LexResult<()> {
+    let player = parser.expect_ident()?;
+    parser.scope.set_player(player);
+    Ok(())
+}
+
+fn move_(parser: &mut Parser) -> LexResult<Vec<Command>> {
+    let player = parser
+        .scope
+        .player()
+        .ok_or_else(|| parser.undefined("no player designated by `player`".to_string()))?;
+    directions(parser, player)
+}
+
+/// Parses the directions and the assertion of pushes of the block.
+fn directions(parser: &mut Parser, block: EcoString) -> LexResult<Vec<Command>> {
+    let mut directions = vec![parser.expect_direction()?];
+    while parser.peek()?.is_direction() {
+        directions.push(parser.expect_direction()?);
+    }
+
     let assertion = match parser.peek()? {
         SyntaxKind::Moved => {
             parser.next()?;
@@ -233,37 +1050,420 @@ fn push(parser: &mut Parser) -> LexResult<Command> {
         _ => Assertion::None,
     };
 
-    Ok(Command::push(block, direction, assertion))
+    // Every push is asserted, so that a failing push is reported even if a
+    // later one moves the block.
+    Ok(directions
+        .into_iter()
+        .map(|direction| Command::push(block.clone(), direction, assertion))
+        .collect())
 }
 
 fn expect(parser: &mut Parser) -> LexResult<Command> {
-    let statement = place(parser)?;
-    let position = match statement.operation {
-        Operation::Place(pos) => pos,
-        _ => unreachable!(),
+    match parser.peek()? {
+        SyntaxKind::Solved => {
+            parser.next()?;
+            return Ok(Command::expect_solved(true));
+        }
+        SyntaxKind::Unsolved => {
+            parser.next()?;
+            return Ok(Command::expect_solved(false));
+        }
+        SyntaxKind::Moves => {
+            parser.next()?;
+            return Ok(Command::expect_moves(parser.expect_integer()?));
+        }
+        SyntaxKind::Turn => {
+            parser.next()?;
+            return Ok(Command::expect_turn(parser.expect_integer()?));
+        }
+        _ => {}
+    }
+
+    if parser.peek()? == SyntaxKind::Map {
+        parser.next()?;
+        let container = parser.expect_ident()?;
+        // The rows are on the following lines.
+        if !matches!(parser.peek()?, SyntaxKind::Eol | SyntaxKind::Else) {
+            return Err(parser.expected(SyntaxKind::Eol));
+        }
+        return Ok(Command::expect_map(container, vec![]));
+    }
+
+    let block = parser.expect_ident()?;
+    if parser.peek()? == SyntaxKind::Reachable {
+        parser.next()?;
+        let pos = parser.expect_size()?;
+        parser.expect(SyntaxKind::In)?;
+        let container = parser.expect_ident()?;
+        return Ok(Command::expect_reachable(block, container, pos));
+    }
+
+    let negated = parser.peek()? == SyntaxKind::Not;
+    if negated {
+        parser.next()?;
+    }
+
+    let command = match position(parser, negated)? {
+        (container, Some(pos)) if !negated => Command::expect(block, container, pos),
+        (container, Some(pos)) => Command::expect_not(block, container, pos),
+        (Some(container), None) => Command::expect_not_in(block, container),
+        (None, None) => unreachable!(),
     };
-    Ok(Command::expect(
-        statement.block,
-        position.container,
-        position.pos,
-    ))
+    Ok(command)
+}
+
+fn goal(parser: &mut Parser) -> LexResult<Command> {
+    let block = parser.expect_ident()?;
+    let (container, pos) = position(parser, false)?;
+    Ok(Command::goal(block, container, pos.unwrap()))
+}
+
+fn copy(parser: &mut Parser) -> LexResult<Command> {
+    let block = parser.expect_ident()?;
+    parser.expect(SyntaxKind::As)?;
+    let target = parser.expect_ident()?;
+    let deep = parser.peek()? == SyntaxKind::Deep;
+    if deep {
+        parser.next()?;
+    }
+
+    Ok(Command::copy(block, target, deep))
+}
+
+fn world(parser: &mut Parser) -> LexResult<Command> {
+    match parser.next()? {
+        SyntaxKind::Create => Ok(Command::create_world(parser.expect_ident()?)),
+        SyntaxKind::Use => Ok(Command::use_world(parser.expect_ident()?)),
+        _ => Err(parser.expected("`create` or `use`")),
+    }
+}
+
+fn show(parser: &mut Parser) -> LexResult<Command> {
+    let block = match parser.peek()? {
+        SyntaxKind::Ident => Some(parser.expect_ident()?),
+        _ => None,
+    };
+
+    Ok(Command::show(block))
+}
+
+fn list(parser: &mut Parser) -> LexResult<Command> {
+    if parser.peek()?.is_terminator() {
+        return Ok(Command::list(None));
+    }
+
+    parser.expect_proto()?;
+    Ok(Command::list(Some(parser.text().to_uppercase().into())))
+}
+
+fn undo(parser: &mut Parser) -> LexResult<Command> {
+    let pushes = match parser.peek()? {
+        SyntaxKind::Integer | SyntaxKind::Variable | SyntaxKind::LeftParen => {
+            parser.expect_integer()?
+        }
+        _ => 1,
+    };
+
+    Ok(Command::undo(pushes))
+}
+
+fn let_(parser: &mut Parser) -> LexResult<()> {
+    parser.expect(SyntaxKind::Variable)?;
+    let name: EcoString = parser.text()[1..].into();
+    parser.expect(SyntaxKind::Equals)?;
+
+    let value = parser.expect_value()?;
+    parser.scope.set(name, value);
+
+    Ok(())
+}
+
+/// Parses the header of a macro, or of a template if `template` is set.
+fn macro_(parser: &mut Parser, template: bool) -> LexResult<()> {
+    let kind = if template { "template" } else { "macro" };
+    if parser.scope.in_macro() {
+        return Err(parser.unexpected(&format!("`{}` in the body of a macro", kind)));
+    }
+
+    let name = parser.expect_name()?;
+    parser.expect(SyntaxKind::LeftParen)?;
+    let mut params: Vec<Param> = Vec::new();
+    while parser.peek()? != SyntaxKind::RightParen {
+        if !params.is_empty() {
+            parser.expect(SyntaxKind::Comma)?;
+        }
+        let param = match parser.next()? {
+            SyntaxKind::Ident => Param::Ident(parser.text()[1..].into()),
+            SyntaxKind::Variable => Param::Variable(parser.text()[1..].into()),
+            _ => return Err(parser.expected("identifier or variable")),
+        };
+        if params.contains(&param) {
+            return Err(parser.multiple(&format!("parameters `{}`", parser.text())));
+        }
+        params.push(param);
+    }
+    parser.next()?;
+
+    if !parser.peek()?.is_eof() {
+        return Err(parser.expected(SyntaxKind::Eol));
+    }
+    parser.scope.begin_macro(name, Macro::new(params, template));
+
+    Ok(())
+}
+
+/// Parses a call of a macro, or a spawn of a template if `spawn` is set.
+fn call(parser: &mut Parser, spawn: bool) -> LexResult<Vec<Command>> {
+    let name = parser.expect_name()?;
+    let kind = if spawn { "template" } else { "macro" };
+    let macro_ = parser
+        .scope
+        .get_macro(&name)
+        .ok_or_else(|| parser.undefined(format!("undefined {} `{}`", kind, name)))?;
+    if macro_.is_template() != spawn {
+        return Err(parser.error(format!(
+            "expected {} `{}`, found {}",
+            kind,
+            name,
+            macro_.kind()
+        )));
+    }
+    let mut scope = parser
+        .scope
+        .enter_macro()
+        .ok_or_else(|| parser.error(format!("too deeply nested call of {} `{}`", kind, name)))?;
+
+    parser.expect(SyntaxKind::LeftParen)?;
+    for (index, param) in macro_.params().iter().enumerate() {
+        if index > 0 {
+            parser.expect(SyntaxKind::Comma)?;
+        }
+        match param {
+            Param::Ident(param) => scope.rename(param.clone(), parser.expect_ident()?),
+            Param::Variable(param) => scope.set(param.clone(), parser.expect_value()?),
+        }
+    }
+    if parser.peek()? == SyntaxKind::Comma {
+        return Err(parser.error(format!(
+            "too many arguments of {} `{}`, expected {}",
+            kind,
+            name,
+            macro_.params().len()
+        )));
+    }
+    parser.expect(SyntaxKind::RightParen)?;
+    if spawn {
+        parser.expect(SyntaxKind::As)?;
+        scope.set_prefix(parser.expect_ident()?);
+    }
+
+    let mut commands = vec![];
+    for line in macro_.body() {
+        let body = parse_line(line, &mut scope).map_err(|e| {
+            parser
+                .error(nested_message(kind, &name, &e.message()))
+                .with_kind(e.kind())
+        })?;
+        commands.extend(body.into_iter().map(|(command, ..)| command));
+    }
+
+    Ok(commands)
+}
+
+/// Prefixes the message of an error in the body of a macro with its name,
+/// counting the nested calls of the same macro instead of repeating the
+/// prefix, e.g. "in macro `m` (nested 3 times): ...".
+fn nested_message(kind: &str, name: &str, message: &str) -> String {
+    let prefix = format!("in {} `{}`", kind, name);
+    let Some(rest) = message.strip_prefix(&prefix) else {
+        return format!("{}: {}", prefix, message);
+    };
+
+    let nested = rest
+        .strip_prefix(" (nested ")
+        .and_then(|rest| rest.split_once(" times)"))
+        .and_then(|(times, rest)| Some((times.parse::<usize>().ok()?, rest)));
+    match nested {
+        Some((times, rest)) => format!("{} (nested {} times){}", prefix, times + 1, rest),
+        None => format!("{} (nested 2 times){}", prefix, rest),
+    }
+}
+
+/// Parses the statements of a line into syntax trees, spanned from the offset
+/// of the line in the source, see [parse_ast].
+fn syntax_line(source: &Arc<dyn Source>, text: &str, offset: usize) -> LexResult<Vec<Statement>> {
+    let span = |range: Range<usize>| {
+        Span::new(source.clone(), (offset + range.start)..(offset + range.end))
+    };
+    let mut scope = Scope::new();
+    let mut parser = Parser::new(text, &mut scope);
+
+    let mut statements = vec![];
+    loop {
+        match parser.peek()? {
+            SyntaxKind::Eol => return Ok(statements),
+            SyntaxKind::Semicolon => {
+                parser.next()?;
+            }
+            _ => statements.push(syntax_statement(&mut parser, &span)?),
+        }
+    }
+}
+
+/// Reads a statement as written, with its keyword, its arguments and its
+/// properties, each started by a keyword.
+fn syntax_statement(
+    parser: &mut Parser,
+    span: &impl Fn(Range<usize>) -> Span,
+) -> LexResult<Statement> {
+    let token = parser.next()?;
+    let kind =
+        StatementKind::from_syntax(token).ok_or_else(|| parser.expected("statement keyword"))?;
+    let start = parser.start();
+    let keyword = syntax_keyword(parser, span);
+
+    let mut arguments = vec![];
+    let mut named = false;
+    if kind.is_named() {
+        let name = parser.expect_name()?;
+        arguments.push(Spanned::new(Argument::Name(name), span(parser.range())));
+        named = true;
+    }
+
+    let mut properties: Vec<Property> = vec![];
+    loop {
+        match parser.peek()? {
+            // The message of an assertion is a property, e.g. `ELSE "message"`.
+            kind if kind.is_terminator() && kind != SyntaxKind::Else => break,
+            SyntaxKind::Equals => {
+                parser.next()?;
+            }
+            kind if kind.is_keyword() => {
+                parser.next()?;
+                properties.push(Property {
+                    kind,
+                    keyword: syntax_keyword(parser, span),
+                    arguments: vec![],
+                    span: span(parser.range()),
+                });
+            }
+            _ => {
+                let argument = syntax_argument(parser, span, named)?;
+                named = false;
+                match properties.last_mut() {
+                    Some(property) => {
+                        let start = property.span.range().start;
+                        property.span =
+                            Span::new(property.span.source(), start..argument.span().range().end);
+                        property.arguments.push(argument);
+                    }
+                    None => arguments.push(argument),
+                }
+            }
+        }
+    }
+
+    Ok(Statement {
+        kind,
+        keyword,
+        arguments,
+        properties,
+        lines: vec![],
+        body: vec![],
+        span: span(start..parser.end()),
+    })
+}
+
+/// Returns the keyword just read, in uppercase.
+fn syntax_keyword(parser: &Parser, span: &impl Fn(Range<usize>) -> Span) -> Spanned<EcoString> {
+    Spanned::new(parser.text().to_uppercase().into(), span(parser.range()))
+}
+
+/// Reads an argument as written, which is a tuple if it is parenthesized and
+/// `tuple` is set, e.g. after the name of a macro.
+fn syntax_argument(
+    parser: &mut Parser,
+    span: &impl Fn(Range<usize>) -> Span,
+    tuple: bool,
+) -> LexResult<Spanned<Argument>> {
+    let kind = parser.next()?;
+    let start = parser.start();
+    let text = parser.text();
+    let mut argument = match kind {
+        SyntaxKind::Ident => Argument::Ident(text[1..].into()),
+        SyntaxKind::Variable => Argument::Variable(text[1..].into()),
+        SyntaxKind::Character => Argument::Char(text[1..].chars().next().unwrap()),
+        SyntaxKind::String => Argument::String(unescape(text)),
+        SyntaxKind::Integer => match text.parse() {
+            Ok(value) => Argument::Integer(value),
+            Err(_) => Argument::Expression(text.into()),
+        },
+        SyntaxKind::LeftParen => {
+            let elements = syntax_group(parser, span)?;
+            if tuple || elements.len() != 1 {
+                Argument::Tuple(elements)
+            } else {
+                Argument::Expression(parser.line()[start..parser.end()].into())
+            }
+        }
+        _ => return Err(parser.unexpected(&kind.to_string())),
+    };
+
+    while parser.peek()?.is_operator() {
+        parser.next()?;
+        syntax_argument(parser, span, false)?;
+        argument = Argument::Expression(parser.line()[start..parser.end()].into());
+    }
+
+    Ok(Spanned::new(argument, span(start..parser.end())))
+}
+
+/// Reads the elements of a tuple after `(`, until `)`.
+fn syntax_group(
+    parser: &mut Parser,
+    span: &impl Fn(Range<usize>) -> Span,
+) -> LexResult<Vec<Spanned<Argument>>> {
+    let mut elements = vec![];
+    if parser.peek()? == SyntaxKind::RightParen {
+        parser.next()?;
+        return Ok(elements);
+    }
+
+    loop {
+        elements.push(syntax_argument(parser, span, false)?);
+        match parser.next()? {
+            SyntaxKind::Comma => continue,
+            SyntaxKind::RightParen => return Ok(elements),
+            _ => return Err(parser.expected(SyntaxKind::RightParen)),
+        }
+    }
 }
 
 struct Parser<'s> {
     lexer: Lexer<'s>,
-    peeked: Option<SyntaxKind>,
+    /// The ranges of the tokens lexed, including the peeked token.
+    tokens: Vec<Range<usize>>,
+    /// The peeked token, with the end of the token before it.
+    peeked: Option<(SyntaxKind, usize)>,
+    /// The end of the last token.
+    end: usize,
+    scope: &'s mut Scope,
 }
 
 impl<'s> Parser<'s> {
-    pub fn new(text: &'s str) -> Self {
+    pub fn new(text: &'s str, scope: &'s mut Scope) -> Self {
         Self {
             lexer: Lexer::new(text),
+            tokens: vec![],
             peeked: None,
+            end: 0,
+            scope,
         }
     }
 
     pub fn next(&mut self) -> LexResult<SyntaxKind> {
-        if let Some(kind) = self.peeked.take() {
+        if let Some((kind, _)) = self.peeked.take() {
+            self.end = self.lexer.cursor();
             return Ok(kind);
         }
 
@@ -272,13 +1472,22 @@ impl<'s> Parser<'s> {
             if next.is_skipped() {
                 continue;
             }
+            if !next.is_eof() {
+                self.tokens.push(self.lexer.range());
+            }
+            self.end = self.lexer.cursor();
             return Ok(next);
         }
     }
 
     pub fn peek(&mut self) -> LexResult<SyntaxKind> {
+        if let Some((kind, _)) = self.peeked {
+            return Ok(kind);
+        }
+
+        let end = self.end;
         let kind = self.next()?;
-        self.peeked = Some(kind);
+        self.peeked = Some((kind, end));
         Ok(kind)
     }
 
@@ -286,9 +1495,32 @@ impl<'s> Parser<'s> {
         self.lexer.text()
     }
 
+    /// Returns the whole line being parsed.
+    pub fn line(&self) -> &'s str {
+        self.lexer.line()
+    }
+
+    /// Returns the range of the last token, or of the peeked token.
+    pub fn range(&self) -> Range<usize> {
+        self.lexer.range()
+    }
+
     pub fn cursor(&self) -> usize {
         self.lexer.cursor()
     }
+
+    /// Returns the start of the last token, or of the peeked token.
+    pub fn start(&self) -> usize {
+        self.lexer.start()
+    }
+
+    /// Returns the end of the last token, excluding the peeked token.
+    pub fn end(&self) -> usize {
+        match self.peeked {
+            Some((_, end)) => end,
+            None => self.end,
+        }
+    }
 }
 
 impl Parser<'_> {
@@ -301,12 +1533,85 @@ impl Parser<'_> {
         }
     }
 
+    /// Expects an integer expression of integers, integer variables, `+`,
+    /// `-`, `*`, `/` and parentheses.
     pub fn expect_integer(&mut self) -> LexResult<usize> {
-        self.expect(SyntaxKind::Integer)?;
-        Ok(self.text().parse().unwrap())
+        let first = self.factor()?;
+        self.expression(first)
+    }
+
+    /// Continues an integer expression after its first factor.
+    fn expression(&mut self, first: usize) -> LexResult<usize> {
+        let mut sum = self.term(first)?;
+        loop {
+            let operator = self.peek()?;
+            if !matches!(operator, SyntaxKind::Plus | SyntaxKind::Minus) {
+                return Ok(sum);
+            }
+            self.next()?;
+            let first = self.factor()?;
+            let term = self.term(first)?;
+            sum = match operator {
+                SyntaxKind::Plus => sum
+                    .checked_add(term)
+                    .ok_or_else(|| self.invalid(format!("integer overflow by {}", operator)))?,
+                _ => sum
+                    .checked_sub(term)
+                    .ok_or_else(|| self.invalid("negative result of subtraction".into()))?,
+            };
+        }
+    }
+
+    /// Continues a term of an integer expression after its first factor.
+    fn term(&mut self, first: usize) -> LexResult<usize> {
+        let mut product = first;
+        loop {
+            let operator = self.peek()?;
+            if !matches!(operator, SyntaxKind::Star | SyntaxKind::Slash) {
+                return Ok(product);
+            }
+            self.next()?;
+            let factor = self.factor()?;
+            product = match operator {
+                SyntaxKind::Star => product
+                    .checked_mul(factor)
+                    .ok_or_else(|| self.invalid(format!("integer overflow by {}", operator)))?,
+                _ => product
+                    .checked_div(factor)
+                    .ok_or_else(|| self.invalid("division by zero".into()))?,
+            };
+        }
+    }
+
+    /// Expects an integer, an integer variable or a parenthesized expression.
+    fn factor(&mut self) -> LexResult<usize> {
+        match self.next()? {
+            SyntaxKind::Integer => self
+                .text()
+                .parse()
+                .map_err(|_| self.invalid(format!("integer {} is too large", self.text()))),
+            SyntaxKind::Variable => match self.variable()? {
+                Value::Integer(value) => Ok(value),
+                value => Err(self.mismatch("integer", value)),
+            },
+            SyntaxKind::LeftParen => {
+                let value = self.expect_integer()?;
+                self.expect(SyntaxKind::RightParen)?;
+                Ok(value)
+            }
+            _ => Err(self.expected(SyntaxKind::Integer)),
+        }
     }
 
     pub fn expect_size(&mut self) -> LexResult<Size> {
+        if self.peek()? == SyntaxKind::Variable {
+            self.next()?;
+            return match self.variable()? {
+                Value::Size(size) => Ok(size),
+                value => Err(self.mismatch("size tuple", value)),
+            };
+        }
+
         self.expect(SyntaxKind::LeftParen)?;
         let x = self.expect_integer()?;
         self.expect(SyntaxKind::Comma)?;
@@ -315,9 +1620,42 @@ impl Parser<'_> {
         Ok((x, y))
     }
 
+    /// Expects a size tuple, an integer or a variable.
+    pub fn expect_value(&mut self) -> LexResult<Value> {
+        match self.peek()? {
+            SyntaxKind::LeftParen => Ok(Value::Size(self.expect_size()?)),
+            SyntaxKind::Variable => {
+                self.next()?;
+                match self.variable()? {
+                    Value::Integer(value) => Ok(Value::Integer(self.expression(value)?)),
+                    value => Ok(value),
+                }
+            }
+            _ => Ok(Value::Integer(self.expect_integer()?)),
+        }
+    }
+
+    /// Returns the value of the variable just lexed.
+    pub fn variable(&self) -> LexResult<Value> {
+        self.scope
+            .get(&self.text()[1..])
+            .ok_or_else(|| self.undefined(format!("undefined variable `{}`", self.text())))
+    }
+
     pub fn expect_ident(&mut self) -> LexResult<EcoString> {
         self.expect(SyntaxKind::Ident)?;
-        Ok(self.text()[1..].into())
+        Ok(self.scope.resolve(&self.text()[1..]))
+    }
+
+    /// Expects a name, e.g. of a macro, which may be a keyword.
+    pub fn expect_name(&mut self) -> LexResult<EcoString> {
+        if let Some((kind, _)) = self.peeked {
+            return Err(self.expected(format!("name, found {}", kind)));
+        }
+        self.lexer.name()?;
+        self.tokens.push(self.lexer.range());
+        self.end = self.lexer.cursor();
+        Ok(self.text().into())
     }
 
     pub fn expect_proto(&mut self) -> LexResult<SyntaxKind> {
@@ -329,6 +1667,24 @@ impl Parser<'_> {
         }
     }
 
+    /// Expects a character between single quotes, e.g. `'p'`.
+    pub fn expect_char(&mut self) -> LexResult<char> {
+        self.expect(SyntaxKind::Character)?;
+        let text = self.text();
+        Ok(text[1..text.len() - 1].chars().next().unwrap())
+    }
+
+    pub fn expect_string(&mut self) -> LexResult<EcoString> {
+        self.expect(SyntaxKind::String)?;
+        Ok(unescape(self.text()))
+    }
+
+    pub fn expect_color(&mut self) -> LexResult<AnsiColor> {
+        self.next()?
+            .color()
+            .ok_or_else(|| self.expected("color, e.g. `red`"))
+    }
+
     pub fn expect_direction(&mut self) -> LexResult<Direction> {
         let next = self.next()?;
         match next {
@@ -348,35 +1704,76 @@ impl Parser<'_> {
 
     pub fn expected(&self, expect: impl ToString) -> LexError {
         self.error(format!("expected {}", expect.to_string()))
+            .with_kind(ErrorKind::UnexpectedToken)
     }
 
     pub fn multiple(&self, multiple: &str) -> LexError {
         self.error(format!("multiple {}", multiple))
+            .with_kind(ErrorKind::DuplicateProperty)
     }
 
     pub fn conflict(&self, source: &str, conflict: &str) -> LexError {
         self.error(format!("conflicting {} and {}", source, conflict))
+            .with_kind(ErrorKind::ConflictingProperty)
     }
 
     pub fn missing(&self, missing: &str) -> LexError {
         self.error(format!("missing {}", missing))
+            .with_kind(ErrorKind::MissingProperty)
+    }
+
+    pub fn mismatch(&self, expect: &str, value: Value) -> LexError {
+        self.error(format!(
+            "expected {}, found {} `{}`",
+            expect,
+            value,
+            self.text()
+        ))
+        .with_kind(ErrorKind::TypeMismatch)
     }
 
     pub fn unexpected(&self, unexpected: &str) -> LexError {
         self.error(format!("unexpected {}", unexpected))
+            .with_kind(ErrorKind::UnexpectedToken)
+    }
+
+    /// Returns an error of an invalid value, e.g. of an integer expression.
+    pub fn invalid(&self, message: String) -> LexError {
+        self.error(message).with_kind(ErrorKind::InvalidValue)
+    }
+
+    /// Returns an error of an undefined variable, macro or player.
+    pub fn undefined(&self, message: String) -> LexError {
+        self.error(message).with_kind(ErrorKind::UnknownIdentifier)
     }
 }
 
 /// A command with a span.
+///
+/// The span covers the whole statement of the command, which may span several
+/// lines, e.g. of a `MAP` block. The spans of the tokens of the statement are
+/// also recorded, see [SpannedCommand::tokens] and [SpannedCommand::property].
+#[derive(Clone)]
 pub struct SpannedCommand {
     command: Command,
     span: Span,
+    tokens: Vec<Range<usize>>,
 }
 
 impl SpannedCommand {
     /// Creates a new spanned command.
     pub fn new(command: Command, span: Span) -> Self {
-        Self { command, span }
+        Self {
+            command,
+            span,
+            tokens: vec![],
+        }
+    }
+
+    /// Sets the ranges of the tokens of the statement.
+    pub(crate) fn with_tokens(mut self, tokens: Vec<Range<usize>>) -> Self {
+        self.tokens = tokens;
+        self
     }
 
     /// Returns the command.
@@ -388,4 +1785,51 @@ impl SpannedCommand {
     pub fn span(&self) -> &Span {
         &self.span
     }
+
+    /// Returns the spans of the tokens of the statement, in order.
+    ///
+    /// Empty for the commands desugared from a `MAP` block.
+    pub fn tokens(&self) -> Vec<Span> {
+        self.tokens
+            .iter()
+            .map(|range| Span::new(self.span.source(), range.clone()))
+            .collect()
+    }
+
+    /// Returns the span of the property started by the keyword, up to the next
+    /// keyword, e.g. `SIZE (3, 3)` for `size`. The keyword is
+    /// case-insensitive.
+    pub fn property(&self, keyword: &str) -> Option<Span> {
+        let tokens = self.tokens();
+        let is_keyword = |span: &Span| span.text().starts_with(|c: char| c.is_alphabetic());
+        let start = tokens
+            .iter()
+            .position(|span| is_keyword(span) && span.text().eq_ignore_ascii_case(keyword))?;
+        let end = tokens[start + 1..]
+            .iter()
+            .take_while(|span| !is_keyword(span))
+            .last()
+            .unwrap_or(&tokens[start]);
+        Some(Span::new(
+            self.span.source(),
+            tokens[start].range().start..end.range().end,
+        ))
+    }
+
+    /// Appends a row to an `EXPECT MAP` command, extending the span to the
+    /// end of the row.
+    fn push_row(&mut self, row: EcoString, end: usize) {
+        let command = match &mut self.command {
+            Command::Else { command, .. } => command,
+            command => command,
+        };
+        if let Command::Operate {
+            operation: Operation::ExpectMap(rows),
+            ..
+        } = command
+        {
+            rows.push(row);
+            self.span = Span::new(self.span.source(), self.span.range().start..end);
+        }
+    }
 }
@@ -0,0 +1,123 @@
+use crate::error::Span;
+use parabox::PushStats;
+use std::fmt::{Display, Formatter};
+use std::time::Duration;
+
+/// A summary of the commands executed by an executor, see
+/// [Executor::report](crate::Executor::report).
+#[derive(Clone, Default, Debug)]
+pub struct ExecutionReport {
+    /// The number of executed commands, including the failed ones.
+    pub commands: usize,
+    /// The number of `EXPECT` commands that held.
+    pub assertions_passed: usize,
+    /// The number of `EXPECT` commands that failed.
+    pub assertions_failed: usize,
+    /// The number of pushes, including `MOVE`, that moved the world.
+    pub pushes_moved: usize,
+    /// The number of pushes, including `MOVE`, that left the world static.
+    pub pushes_static: usize,
+    /// The time taken by each executed command, in order.
+    pub timings: Vec<(Span, Duration)>,
+}
+
+impl ExecutionReport {
+    /// Returns the total time taken by the executed commands.
+    pub fn elapsed(&self) -> Duration {
+        self.timings.iter().map(|(_, elapsed)| *elapsed).sum()
+    }
+
+    /// Returns the command that took the longest time, with its time.
+    pub fn slowest(&self) -> Option<&(Span, Duration)> {
+        self.timings.iter().max_by_key(|(_, elapsed)| *elapsed)
+    }
+}
+
+/// The cost of an executed command, see [Profile].
+#[derive(Clone, Debug)]
+pub struct ProfileEntry {
+    /// The span of the command.
+    pub span: Span,
+    /// The time taken by the command.
+    pub elapsed: Duration,
+    /// The number of pushes done by the command, including the failed ones.
+    pub pushes: usize,
+    /// The work done by the pushes of the command, summed.
+    pub stats: PushStats,
+}
+
+/// The cost of each command executed while profiling, see
+/// [Executor::set_profiling](crate::Executor::set_profiling).
+///
+/// The profile is displayed as a table with a row per command, e.g. to find
+/// the lines dominating the load time of a script.
+#[derive(Clone, Default, Debug)]
+pub struct Profile {
+    /// The executed commands, in order.
+    pub entries: Vec<ProfileEntry>,
+}
+
+impl Profile {
+    /// Returns the total time taken by the executed commands.
+    pub fn elapsed(&self) -> Duration {
+        self.entries.iter().map(|entry| entry.elapsed).sum()
+    }
+
+    /// Sorts the entries from the slowest command to the fastest one.
+    pub fn sort_by_elapsed(&mut self) {
+        self.entries
+            .sort_by_key(|entry| std::cmp::Reverse(entry.elapsed));
+    }
+}
+
+impl Display for Profile {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let header = [
+            "location",
+            "time (ms)",
+            "pushes",
+            "evaluations",
+            "movements",
+            "command",
+        ];
+        let rows: Vec<[String; 6]> = self
+            .entries
+            .iter()
+            .map(|entry| {
+                let (line, column) = entry.span.locate();
+                [
+                    format!("{}:{}:{}", entry.span.name(), line + 1, column + 1),
+                    format!("{:.3}", entry.elapsed.as_secs_f64() * 1000.0),
+                    entry.pushes.to_string(),
+                    entry.stats.evaluations.to_string(),
+                    entry.stats.movements.to_string(),
+                    entry
+                        .span
+                        .text()
+                        .lines()
+                        .next()
+                        .unwrap_or_default()
+                        .to_string(),
+                ]
+            })
+            .collect();
+
+        let mut widths = header.map(str::len);
+        for row in &rows {
+            for (width, cell) in widths.iter_mut().zip(row) {
+                *width = (*width).max(cell.len());
+            }
+        }
+
+        let header = header.map(str::to_string);
+        for row in std::iter::once(&header).chain(&rows) {
+            write!(f, "{:<1$}", row[0], widths[0])?;
+            for (cell, width) in row[1..5].iter().zip(&widths[1..5]) {
+                write!(f, "  {:>1$}", cell, width)?;
+            }
+            writeln!(f, "  {}", row[5])?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,229 @@
+use ecow::EcoString;
+use parabox::Size;
+use std::collections::{HashMap, HashSet};
+use std::fmt::Display;
+use std::rc::Rc;
+
+/// The maximum depth of nested macro calls.
+const MAX_CALL_DEPTH: usize = 64;
+
+/// The value of a variable defined by `LET`.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Value {
+    /// An unsigned integer.
+    Integer(usize),
+    /// A size tuple.
+    Size(Size),
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Value::Integer(_) => write!(f, "integer"),
+            Value::Size(_) => write!(f, "size tuple"),
+        }
+    }
+}
+
+/// A parameter of a macro defined by `MACRO`.
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Param {
+    /// An identifier parameter, e.g. `#name`.
+    Ident(EcoString),
+    /// A variable parameter, e.g. `$size`.
+    Variable(EcoString),
+}
+
+/// A macro defined by `MACRO`, whose body is parsed on each `CALL`, or a
+/// template defined by `TEMPLATE`, whose body is parsed on each `SPAWN`.
+#[derive(Clone, Default, Debug)]
+pub struct Macro {
+    params: Vec<Param>,
+    body: Vec<String>,
+    template: bool,
+}
+
+impl Macro {
+    pub fn new(params: Vec<Param>, template: bool) -> Self {
+        Self {
+            params,
+            body: Vec::new(),
+            template,
+        }
+    }
+
+    /// Returns whether the macro is a template.
+    pub fn is_template(&self) -> bool {
+        self.template
+    }
+
+    /// Returns the keyword defining the macro, i.e. `macro` or `template`.
+    pub fn kind(&self) -> &'static str {
+        if self.template {
+            "template"
+        } else {
+            "macro"
+        }
+    }
+
+    /// Returns the parameters.
+    pub fn params(&self) -> &[Param] {
+        &self.params
+    }
+
+    /// Returns the lines of the body.
+    pub fn body(&self) -> &[String] {
+        &self.body
+    }
+}
+
+/// The definitions visible to the parser, which persist across the sources
+/// parsed by the same executor.
+#[derive(Clone, Default, Debug)]
+pub struct Scope {
+    variables: HashMap<EcoString, Value>,
+    macros: HashMap<EcoString, Rc<Macro>>,
+    /// The block pushed by `MOVE`.
+    player: Option<EcoString>,
+    /// The identifier parameters of the macro being called, with their
+    /// arguments.
+    renames: Vec<(EcoString, EcoString)>,
+    /// The prefix of the identifiers in the body of the spawned template.
+    prefix: Option<EcoString>,
+    /// The namespace of the identifiers in the source being parsed.
+    namespace: Option<EcoString>,
+    /// The namespaces of the sources parsed so far.
+    namespaces: HashSet<EcoString>,
+    /// The macro whose body is being recorded.
+    recording: Option<(EcoString, Macro)>,
+    /// The depth of nested macro calls.
+    depth: usize,
+}
+
+impl Scope {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Defines or redefines a variable.
+    pub fn set(&mut self, name: EcoString, value: Value) {
+        self.variables.insert(name, value);
+    }
+
+    /// Returns the value of a variable, if defined.
+    pub fn get(&self, name: &str) -> Option<Value> {
+        self.variables.get(name).copied()
+    }
+
+    /// Designates the block pushed by `MOVE`.
+    pub fn set_player(&mut self, player: EcoString) {
+        self.player = Some(player);
+    }
+
+    /// Returns the block pushed by `MOVE`, if designated.
+    pub fn player(&self) -> Option<EcoString> {
+        self.player.clone()
+    }
+
+    /// Returns a macro, if defined.
+    pub fn get_macro(&self, name: &str) -> Option<Rc<Macro>> {
+        self.macros.get(name).cloned()
+    }
+
+    /// Starts recording the body of a macro.
+    pub fn begin_macro(&mut self, name: EcoString, macro_: Macro) {
+        self.recording = Some((name, macro_));
+    }
+
+    /// Returns whether the body of a macro is being recorded.
+    pub fn is_recording(&self) -> bool {
+        self.recording.is_some()
+    }
+
+    /// Appends a line to the body of the macro being recorded.
+    pub fn record(&mut self, line: &str) {
+        if let Some((_, macro_)) = &mut self.recording {
+            macro_.body.push(line.to_string());
+        }
+    }
+
+    /// Finishes recording, defining or redefining the macro.
+    pub fn end_macro(&mut self) {
+        if let Some((name, macro_)) = self.recording.take() {
+            self.macros.insert(name, Rc::new(macro_));
+        }
+    }
+
+    /// Stops recording without defining the macro. Returns the name and the
+    /// macro, if any is recorded.
+    pub fn discard_macro(&mut self) -> Option<(EcoString, Macro)> {
+        self.recording.take()
+    }
+
+    /// Returns whether the scope is in the body of a called macro.
+    pub fn in_macro(&self) -> bool {
+        self.depth > 0
+    }
+
+    /// Returns the scope of a call of a macro, with the variables and macros of
+    /// this scope, or `None` if the calls are nested too deeply.
+    pub fn enter_macro(&self) -> Option<Scope> {
+        (self.depth < MAX_CALL_DEPTH).then(|| Scope {
+            variables: self.variables.clone(),
+            macros: self.macros.clone(),
+            player: self.player.clone(),
+            renames: Vec::new(),
+            prefix: None,
+            namespace: self.namespace.clone(),
+            namespaces: self.namespaces.clone(),
+            recording: None,
+            depth: self.depth + 1,
+        })
+    }
+
+    /// Binds an identifier parameter of the called macro to the argument.
+    pub fn rename(&mut self, param: EcoString, arg: EcoString) {
+        self.renames.push((param, arg));
+    }
+
+    /// Prefixes the identifiers in the body of the spawned template.
+    pub fn set_prefix(&mut self, prefix: EcoString) {
+        self.prefix = Some(prefix);
+    }
+
+    /// Namespaces the identifiers of the source being parsed, or stops
+    /// namespacing them if `None`.
+    pub fn set_namespace(&mut self, namespace: Option<EcoString>) {
+        self.namespaces.extend(namespace.clone());
+        self.namespace = namespace;
+    }
+
+    /// Returns the identifier after substituting the identifier parameters of
+    /// the called macro.
+    ///
+    /// A parameter substitutes the same identifier, and the prefix of the
+    /// identifiers starting with the parameter followed by `_`. An identifier
+    /// qualified by the namespace of a source is kept, e.g. `#ns::ident`. Any
+    /// other identifier in the body of a spawned template is namespaced by the
+    /// prefix, e.g. `#prefix::ident`, or else by the namespace of the source.
+    pub fn resolve(&self, ident: &str) -> EcoString {
+        for (param, arg) in &self.renames {
+            match ident.strip_prefix(param.as_str()) {
+                Some("") => return arg.clone(),
+                Some(suffix) if suffix.starts_with('_') => {
+                    return format!("{}{}", arg, suffix).into();
+                }
+                _ => {}
+            }
+        }
+        if let Some((namespace, _)) = ident.split_once("::") {
+            if self.namespaces.contains(namespace) {
+                return ident.into();
+            }
+        }
+        match self.prefix.as_ref().or(self.namespace.as_ref()) {
+            Some(prefix) => format!("{}::{}", prefix, ident).into(),
+            None => ident.into(),
+        }
+    }
+}
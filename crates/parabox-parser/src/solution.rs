@@ -0,0 +1,114 @@
+use crate::command::{Assertion, Command};
+use crate::executor::Executor;
+use crate::lexer::is_ident_char;
+use crate::meta::MetaTable;
+use crate::source::{NamedStringSource, Source};
+use parabox::{BlockKey, Direction};
+use std::sync::Arc;
+
+/// Formats a solution as a script of `PUSH` commands, one per line.
+///
+/// Each push asserts that the world is moved. The blocks are named after the
+/// meta table, and the first block without a name, or whose name is not a
+/// valid identifier, is returned as the error.
+pub fn format_solution(
+    meta: &MetaTable,
+    solution: &[(BlockKey, Direction)],
+) -> Result<String, BlockKey> {
+    let mut script = String::new();
+    for (key, direction) in solution {
+        let name = meta
+            .get_name(key)
+            .filter(|name| is_identifier(name))
+            .ok_or(*key)?;
+        let push = Command::push(name, *direction, Assertion::Moved);
+        script.push_str(&format!("{}\n", push));
+    }
+    Ok(script)
+}
+
+/// Returns whether the name is lexed as one identifier, possibly qualified
+/// by namespaces, e.g. `ns::room`.
+fn is_identifier(name: &str) -> bool {
+    name.split("::")
+        .all(|part| !part.is_empty() && part.chars().all(is_ident_char))
+}
+
+impl Executor {
+    /// Appends the pushes of a solution to the commands, e.g. one found by a
+    /// solver.
+    ///
+    /// The commands are parsed from a source named `<solution>` with the
+    /// text of [format_solution], so that the errors point to the failing
+    /// push.
+    pub fn push_solution(&mut self, solution: &[(BlockKey, Direction)]) -> Result<(), BlockKey> {
+        let text = format_solution(self.meta(), solution)?;
+        let source: Arc<dyn Source> = Arc::new(NamedStringSource::new("<solution>".into(), text));
+        self.push_source(source)
+            .expect("the formatted solution is a valid script");
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::StringSource;
+
+    fn setup() -> Executor {
+        let script = "DEFINE BOX #container size (3, 1)\nDEFINE BOX #box solid\n\
+                      DEFINE WALL #wall\nPLACE #box at (0, 0) in #container\n\
+                      PLACE #wall at (2, 0) in #container\n";
+        let mut executor = Executor::new();
+        executor
+            .push_source(Arc::new(StringSource::new(script.to_string())))
+            .unwrap();
+        executor.run_all().unwrap();
+        executor
+    }
+
+    #[test]
+    fn test_format_solution() {
+        let executor = setup();
+        let key = executor.meta().get_key(&"box".into()).unwrap();
+        let solution = [(key, Direction::East), (key, Direction::West)];
+        assert_eq!(
+            format_solution(executor.meta(), &solution).unwrap(),
+            "PUSH #box east MOVED\nPUSH #box west MOVED\n"
+        );
+
+        let container = executor.meta().get_key(&"container".into()).unwrap();
+        let meta = MetaTable::new();
+        assert_eq!(
+            format_solution(&meta, &[(container, Direction::East)]),
+            Err(container)
+        );
+
+        let mut meta = MetaTable::new();
+        meta.insert("not a name".into(), container);
+        assert_eq!(
+            format_solution(&meta, &[(container, Direction::East)]),
+            Err(container)
+        );
+    }
+
+    #[test]
+    fn test_push_solution() {
+        let mut executor = setup();
+        let key = executor.meta().get_key(&"box".into()).unwrap();
+        executor
+            .push_solution(&[(key, Direction::East), (key, Direction::East)])
+            .unwrap();
+        executor.step().unwrap();
+        assert_eq!(executor.world().position(key).pos, (1, 0));
+
+        // The second push is blocked by the wall.
+        let Err(error) = executor.step() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.span().name(), "<solution>");
+        assert_eq!(error.span().locate(), (1, 0));
+        assert_eq!(error.span().text(), "PUSH #box east MOVED");
+    }
+}
@@ -9,7 +9,10 @@ use unscanny::Scanner;
 ///
 /// Parser deals with a single line of command. It is the responsibility of the
 /// source to split a text into lines and provide the location information.
-pub trait Source {
+///
+/// Sources are shared by [Arc](std::sync::Arc), so that the parsed commands
+/// and the errors spanning them can be sent to other threads.
+pub trait Source: Send + Sync {
     /// Returns the name of the source.
     fn name(&self) -> EcoString;
 
@@ -97,14 +100,14 @@ impl Source for StringSource {
     }
 
     fn line_range(&self, line: usize) -> Option<Range<usize>> {
-        self.ranges.get(line).map(|range| range.clone())
+        self.ranges.get(line).cloned()
     }
 
     fn locate(&self, cursor: usize) -> (usize, usize) {
         let mut line = 0;
 
         for range in &self.ranges {
-            if cursor < range.end {
+            if cursor <= range.end {
                 return (line, cursor - range.start);
             }
 
@@ -0,0 +1,106 @@
+use ecow::EcoString;
+use std::fmt::Display;
+
+/// The maximum number of names suggested for an unknown identifier.
+const MAX_SUGGESTIONS: usize = 3;
+
+/// Returns the hint suggesting the names close to the unknown name, e.g.
+/// `, did you mean #wall_21?`, or an empty string if no name is close.
+pub(crate) fn did_you_mean<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a EcoString>,
+) -> String {
+    hint(&suggestions(name, candidates))
+}
+
+/// Returns the hint suggesting the names, e.g. `, did you mean #wall_21?`, or
+/// an empty string if there is no name.
+pub(crate) fn hint(suggestions: &[impl Display]) -> String {
+    match suggestions.split_last() {
+        None => String::new(),
+        Some((last, [])) => format!(", did you mean #{}?", last),
+        Some((last, rest)) => {
+            let rest: Vec<_> = rest.iter().map(|name| format!("#{}", name)).collect();
+            format!(", did you mean {} or #{}?", rest.join(", "), last)
+        }
+    }
+}
+
+/// Returns the names close to the unknown name, from the closest one. A name
+/// is close if it is at most a third of the length of the unknown name of
+/// edits away, or one edit for a short name.
+pub(crate) fn suggestions<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a EcoString>,
+) -> Vec<&'a EcoString> {
+    let threshold = (name.chars().count() / 3).max(1);
+    let mut close: Vec<_> = candidates
+        .into_iter()
+        .map(|candidate| (distance(name, candidate), candidate))
+        .filter(|(distance, _)| *distance <= threshold)
+        .collect();
+    close.sort();
+    close
+        .into_iter()
+        .take(MAX_SUGGESTIONS)
+        .map(|(_, candidate)| candidate)
+        .collect()
+}
+
+/// Returns the number of insertions, deletions, substitutions and swaps of
+/// adjacent characters turning a string into the other, so that `wall_12` is
+/// one edit away from `wall_21`.
+fn distance(from: &str, to: &str) -> usize {
+    let from: Vec<char> = from.chars().collect();
+    let to: Vec<char> = to.chars().collect();
+
+    // The distances from the prefixes of `from` to the prefixes of `to`, for
+    // the last two rows and the current one.
+    let mut before: Vec<usize> = vec![0; to.len() + 1];
+    let mut last: Vec<usize> = (0..=to.len()).collect();
+    for i in 1..=from.len() {
+        let mut row = vec![i; to.len() + 1];
+        for j in 1..=to.len() {
+            let cost = usize::from(from[i - 1] != to[j - 1]);
+            row[j] = (last[j] + 1).min(row[j - 1] + 1).min(last[j - 1] + cost);
+            if i > 1 && j > 1 && from[i - 1] == to[j - 2] && from[i - 2] == to[j - 1] {
+                row[j] = row[j].min(before[j - 2] + 1);
+            }
+        }
+        before = std::mem::replace(&mut last, row);
+    }
+
+    last[to.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_distance() {
+        assert_eq!(distance("wall", "wall"), 0);
+        assert_eq!(distance("wall_12", "wall_21"), 1);
+        assert_eq!(distance("box", "boxes"), 2);
+        assert_eq!(distance("room", "rom"), 1);
+        assert_eq!(distance("", "abc"), 3);
+    }
+
+    #[test]
+    fn test_did_you_mean() {
+        let names: Vec<EcoString> = ["wall_12", "wall_21", "wall_2", "room", "player"]
+            .into_iter()
+            .map(Into::into)
+            .collect();
+        assert_eq!(
+            did_you_mean("wall_1", &names),
+            ", did you mean #wall_12, #wall_2 or #wall_21?"
+        );
+        assert_eq!(
+            did_you_mean("wal_21", &names),
+            ", did you mean #wall_21, #wall_12 or #wall_2?"
+        );
+        assert_eq!(did_you_mean("rom", &names), ", did you mean #room?");
+        assert_eq!(did_you_mean("box", &names), "");
+    }
+}
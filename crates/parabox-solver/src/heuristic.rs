@@ -0,0 +1,91 @@
+use parabox::{BlockKey, Goal, Position, World};
+
+/// An estimate of the number of pushes needed to satisfy a goal, guiding
+/// the search of a [Solver](crate::Solver).
+///
+/// The estimate must be `0` when the goal is satisfied. Solutions are the
+/// shortest ones only if the estimate never exceeds the actual number of
+/// pushes. Closures taking the world and the goal are heuristics as well.
+pub trait Heuristic {
+    /// Returns the estimated number of pushes from the world to the goal.
+    fn estimate(&self, world: &World, goal: &Goal) -> usize;
+}
+
+impl<F: Fn(&World, &Goal) -> usize> Heuristic for F {
+    fn estimate(&self, world: &World, goal: &Goal) -> usize {
+        self(world, goal)
+    }
+}
+
+/// The default heuristic, summing the distances of the targets.
+///
+/// The distance of a block inside the container of its target is the
+/// Manhattan distance. Otherwise, it is the number of containers the block
+/// needs to exit and enter to reach the container of its target, plus one.
+#[derive(Copy, Clone, Default, Debug)]
+pub struct Manhattan;
+
+impl Heuristic for Manhattan {
+    fn estimate(&self, world: &World, goal: &Goal) -> usize {
+        goal.targets()
+            .iter()
+            .map(|&(key, target)| distance(world, world.position(key), target))
+            .sum()
+    }
+}
+
+fn distance(world: &World, from: Position, to: Position) -> usize {
+    if from.container == to.container {
+        let ((x1, y1), (x2, y2)) = (from.pos, to.pos);
+        return x1.abs_diff(x2) + y1.abs_diff(y2);
+    }
+
+    let (exits, enters) = (ancestors(world, from), ancestors(world, to));
+    let common = exits
+        .iter()
+        .enumerate()
+        .find_map(|(i, key)| Some((i, enters.iter().position(|other| other == key)?)));
+    match common {
+        Some((exit, enter)) => exit + enter + 1,
+        None => exits.len() + enters.len() + 1,
+    }
+}
+
+/// Returns the containers around the position, from the innermost one and
+/// stopping before the first repeated container.
+fn ancestors(world: &World, position: Position) -> Vec<BlockKey> {
+    let mut ancestors = Vec::new();
+    let mut container = position.container;
+    while let Some(key) = container.filter(|key| !ancestors.contains(key)) {
+        ancestors.push(key);
+        container = world.position(key).container;
+    }
+    ancestors
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parabox::ProtoType;
+
+    #[test]
+    fn test_manhattan() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (5, 5) });
+        let inner = world.insert(ProtoType::Box { size: (3, 3) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(root, Position::inside(root, (0, 0)));
+        world.place(inner, Position::inside(root, (4, 4)));
+        world.place(block, Position::inside(root, (1, 1)));
+
+        let goal = Goal::new().with_target(block, Position::inside(root, (3, 0)));
+        assert_eq!(Manhattan.estimate(&world, &goal), 3);
+
+        // Entering `inner` from `root`.
+        let goal = Goal::new().with_target(block, Position::inside(inner, (0, 0)));
+        assert_eq!(Manhattan.estimate(&world, &goal), 2);
+
+        world.place(block, Position::inside(inner, (0, 0)));
+        assert_eq!(Manhattan.estimate(&world, &goal), 0);
+    }
+}
@@ -0,0 +1,47 @@
+//! The level solver for the Parabox game.
+//!
+//! # Overview
+//!
+//! A [Solver] searches the sequences of pushes from a [World] until the
+//! [Goal] is satisfied, which verifies that a level is solvable. States are
+//! deduplicated by [World::encode_full_state], so the search terminates for
+//! every level with finitely many reachable states. States where a target
+//! block can never reach its target are pruned, see [World::detect_deadlocks].
+//!
+//! The search can be guided by a [Heuristic], estimating the number of pushes
+//! left in a state, such as the default [Manhattan] heuristic.
+//!
+//! With the `parallel` feature, `Solver::solve_parallel` searches on the
+//! rayon thread pool, sharing the visited states between the threads.
+//!
+//! See crate [`parabox`] for more information about the Parabox world.
+//!
+//! [`parabox`]: https://crates.io/crates/parabox
+//! [World]: parabox::World
+//! [Goal]: parabox::Goal
+//! [World::encode_full_state]: parabox::World::encode_full_state
+//! [World::detect_deadlocks]: parabox::World::detect_deadlocks
+//!
+//! # Example
+//!
+//! ```
+//! # use parabox::{Direction, Goal, Position, ProtoType, World};
+//! let mut world = World::new();
+//! let container = world.insert(ProtoType::Box { size: (4, 1) });
+//! let player = world.insert(ProtoType::Box { size: (1, 1) });
+//! world.place(player, Position::inside(container, (0, 0)));
+//!
+//! let goal = Goal::new().with_target(player, Position::inside(container, (2, 0)));
+//! let solution = parabox_solver::solve(&world, &goal).unwrap();
+//! assert_eq!(solution, [(player, Direction::East), (player, Direction::East)]);
+//! ```
+
+#![warn(missing_docs)]
+
+mod heuristic;
+#[cfg(feature = "parallel")]
+mod parallel;
+mod solver;
+
+pub use heuristic::{Heuristic, Manhattan};
+pub use solver::{solve, SolveError, Solver};
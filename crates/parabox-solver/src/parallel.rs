@@ -0,0 +1,151 @@
+use crate::solver::{Push, Solver};
+use parabox::{BlockKey, Direction, Goal, World};
+use rayon::prelude::*;
+use std::collections::HashSet;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// The number of independently locked shards of a [Table].
+const SHARDS: usize = 64;
+
+/// A transposition table shared by the threads, keyed on the hashes of the
+/// encoded states.
+struct Table {
+    shards: Vec<Mutex<HashSet<u64>>>,
+    len: AtomicUsize,
+}
+
+impl Table {
+    fn new() -> Self {
+        Self {
+            shards: (0..SHARDS).map(|_| Mutex::default()).collect(),
+            len: AtomicUsize::new(0),
+        }
+    }
+
+    /// Inserts the state. Returns whether the state is newly inserted.
+    fn insert(&self, world: &World) -> bool {
+        let mut hasher = DefaultHasher::new();
+        world.encode_full_state().hash(&mut hasher);
+        let hash = hasher.finish();
+
+        let inserted = self.shards[hash as usize % SHARDS]
+            .lock()
+            .unwrap()
+            .insert(hash);
+        if inserted {
+            self.len.fetch_add(1, Ordering::Relaxed);
+        }
+        inserted
+    }
+
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Relaxed)
+    }
+}
+
+/// The pushes leading to a state, shared with the states reached from it.
+struct Path {
+    parent: Option<Arc<Path>>,
+    push: Push,
+}
+
+impl Path {
+    fn pushes(path: &Option<Arc<Path>>) -> Vec<Push> {
+        let mut pushes = Vec::new();
+        let mut path = path;
+        while let Some(node) = path {
+            pushes.push(node.push);
+            path = &node.parent;
+        }
+        pushes.reverse();
+        pushes
+    }
+}
+
+impl Solver {
+    /// Returns the shortest sequence of pushes satisfying the goal, searching
+    /// on the rayon thread pool, or `None` if the goal is unreachable or the
+    /// budget is exhausted.
+    ///
+    /// The search is breadth-first, expanding the states of each depth in
+    /// parallel, and ignores the [Heuristic](crate::Heuristic). States are
+    /// deduplicated by the hashes of their encodings, so a hash collision may
+    /// rarely hide a solution. The budget is only checked between depths.
+    pub fn solve_parallel(&self, world: &World, goal: &Goal) -> Option<Vec<(BlockKey, Direction)>> {
+        if goal.is_satisfied(world) {
+            return Some(Vec::new());
+        }
+
+        let table = Table::new();
+        table.insert(world);
+        let mut frontier = vec![(None, world.fork())];
+
+        while !frontier.is_empty() {
+            let next: Vec<(Option<Arc<Path>>, World)> = frontier
+                .par_iter()
+                .flat_map_iter(|(path, state)| {
+                    self.successors(state)
+                        .filter(|(_, next)| table.insert(next) && !self.is_deadlocked(next, goal))
+                        .map(|(push, next)| {
+                            let parent = path.clone();
+                            (Some(Arc::new(Path { parent, push })), next)
+                        })
+                })
+                .collect();
+
+            if let Some((path, _)) = next
+                .par_iter()
+                .find_first(|(_, state)| goal.is_satisfied(state))
+            {
+                return Some(Path::pushes(path));
+            }
+            if self.is_exhausted(table.len()) {
+                return None;
+            }
+            frontier = next;
+        }
+
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use parabox::{Position, ProtoType};
+
+    #[test]
+    fn test_solve_parallel() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (5, 5) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (2, 1)));
+        for key in [player, block] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, (0, 0)));
+        }
+
+        let goal = Goal::new().with_target(block, Position::inside(container, (4, 1)));
+        let solver = Solver::new().with_players([player]);
+        let solution = solver.solve_parallel(&world, &goal).unwrap();
+        assert_eq!(
+            Some(solution.len()),
+            solver.solve(&world, &goal).map(|s| s.len())
+        );
+
+        for (key, direction) in solution {
+            assert!(world.push(key, direction).unwrap());
+        }
+        assert!(goal.is_satisfied(&world));
+
+        let goal = Goal::new().with_target(player, Position::inside(container, (4, 4)));
+        assert_eq!(
+            solver.with_max_states(2).solve_parallel(&world, &goal),
+            None
+        );
+    }
+}
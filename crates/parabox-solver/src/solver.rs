@@ -0,0 +1,323 @@
+use crate::Heuristic;
+use parabox::{BlockKey, Direction, Goal, World};
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::sync::Arc;
+use std::time::Instant;
+
+/// A push in a solution, i.e. the pushed block and the direction.
+pub(crate) type Push = (BlockKey, Direction);
+
+/// A searched state, linked to the state it is reached from.
+struct Node {
+    /// The index of the previous node and the push from there, or `None` for
+    /// the initial state.
+    parent: Option<(usize, Push)>,
+    /// The number of pushes from the initial state.
+    cost: usize,
+}
+
+/// Why a [Solver] found no solution, see [Solver::try_solve].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum SolveError {
+    /// Every reachable state is searched without satisfying the goal.
+    Unreachable,
+    /// The search gave up, by the number of states or by the deadline.
+    Exhausted,
+}
+
+/// A solver over the pushes of the controlled blocks.
+///
+/// Without a [Heuristic], the search is breadth-first and solutions are the
+/// shortest sequences of successful pushes. With a heuristic, the search is
+/// A*, expanding the states with the least estimated total cost first.
+/// Pushes that are blocked or result in a [MoveError](parabox::MoveError) are
+/// skipped, and so are states with deadlocks, see
+/// [World::detect_deadlocks_with_players].
+#[derive(Clone, Default)]
+pub struct Solver {
+    players: Option<Vec<BlockKey>>,
+    max_states: Option<usize>,
+    deadline: Option<Instant>,
+    heuristic: Option<Arc<dyn Heuristic + Send + Sync>>,
+}
+
+impl Solver {
+    /// Creates a solver controlling every non-static block, without a budget.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Only controls the given blocks, in the given order.
+    pub fn with_players(mut self, players: impl IntoIterator<Item = BlockKey>) -> Self {
+        self.players = Some(players.into_iter().collect());
+        self
+    }
+
+    /// Gives up after visiting the number of distinct states.
+    pub fn with_max_states(mut self, max_states: usize) -> Self {
+        self.max_states = Some(max_states);
+        self
+    }
+
+    /// Gives up once the deadline is passed, checked after each new state.
+    pub fn with_deadline(mut self, deadline: Instant) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Guides the search with the heuristic, e.g. [Manhattan](crate::Manhattan).
+    pub fn with_heuristic(mut self, heuristic: impl Heuristic + Send + Sync + 'static) -> Self {
+        self.heuristic = Some(Arc::new(heuristic));
+        self
+    }
+
+    /// Returns a sequence of pushes satisfying the goal, or `None` if the goal
+    /// is unreachable or the budget is exhausted.
+    pub fn solve(&self, world: &World, goal: &Goal) -> Option<Vec<(BlockKey, Direction)>> {
+        self.try_solve(world, goal).ok()
+    }
+
+    /// Returns a sequence of pushes satisfying the goal, or why there is none,
+    /// e.g. to tell an unreachable goal from an exhausted budget.
+    pub fn try_solve(
+        &self,
+        world: &World,
+        goal: &Goal,
+    ) -> Result<Vec<(BlockKey, Direction)>, SolveError> {
+        if goal.is_satisfied(world) {
+            return Ok(Vec::new());
+        }
+
+        let mut visited = HashSet::from([world.encode_full_state()]);
+        let mut nodes = vec![Node {
+            parent: None,
+            cost: 0,
+        }];
+        let mut states = vec![Some(world.fork())];
+        // Ties are broken by the node index, so that the search without a
+        // heuristic is breadth-first.
+        let mut queue = BinaryHeap::from([Reverse((0, 0))]);
+
+        while let Some(Reverse((_, index))) = queue.pop() {
+            let state = states[index].take().unwrap();
+            for (push, next) in self.successors(&state) {
+                // Deadlocked states are never expanded.
+                if !visited.insert(next.encode_full_state()) || self.is_deadlocked(&next, goal) {
+                    continue;
+                }
+
+                let cost = nodes[index].cost + 1;
+                nodes.push(Node {
+                    parent: Some((index, push)),
+                    cost,
+                });
+                if goal.is_satisfied(&next) {
+                    return Ok(path(&nodes, nodes.len() - 1));
+                }
+                if self.is_exhausted(visited.len()) {
+                    return Err(SolveError::Exhausted);
+                }
+
+                let estimate = match &self.heuristic {
+                    Some(heuristic) => heuristic.estimate(&next, goal),
+                    None => 0,
+                };
+                queue.push(Reverse((cost + estimate, nodes.len() - 1)));
+                states.push(Some(next));
+            }
+        }
+
+        Err(SolveError::Unreachable)
+    }
+
+    /// Returns the states after each successful push of the players.
+    pub(crate) fn successors<'a>(
+        &'a self,
+        state: &'a World,
+    ) -> impl Iterator<Item = (Push, World)> + 'a {
+        self.players(state).into_iter().flat_map(move |key| {
+            Direction::ALL.into_iter().filter_map(move |direction| {
+                let mut next = state.fork();
+                let moved = next.push(key, direction).unwrap_or(false);
+                moved.then_some(((key, direction), next))
+            })
+        })
+    }
+
+    /// Returns whether a target block can never reach its target.
+    pub(crate) fn is_deadlocked(&self, state: &World, goal: &Goal) -> bool {
+        !state
+            .detect_deadlocks_with_players(goal, &self.players(state))
+            .is_empty()
+    }
+
+    /// Returns whether the number of visited states or the time exhausts the
+    /// budget.
+    pub(crate) fn is_exhausted(&self, visited: usize) -> bool {
+        self.max_states.is_some_and(|max| visited >= max)
+            || self
+                .deadline
+                .is_some_and(|deadline| deadline <= Instant::now())
+    }
+
+    /// Returns the controlled blocks that still exist in the world.
+    fn players(&self, world: &World) -> Vec<BlockKey> {
+        match &self.players {
+            Some(players) => players
+                .iter()
+                .copied()
+                .filter(|&key| world.blocks().contains_key(key))
+                .collect(),
+            None => world
+                .blocks_ordered()
+                .filter(|(_, block)| !block.proto.is_static())
+                .map(|(key, _)| key)
+                .collect(),
+        }
+    }
+}
+
+/// Returns the pushes leading from the initial state to the node.
+fn path(nodes: &[Node], mut index: usize) -> Vec<Push> {
+    let mut pushes = Vec::new();
+    while let Some((parent, push)) = nodes[index].parent {
+        pushes.push(push);
+        index = parent;
+    }
+    pushes.reverse();
+    pushes
+}
+
+/// Returns the shortest sequence of pushes of non-static blocks satisfying
+/// the goal, see [Solver::solve].
+pub fn solve(world: &World, goal: &Goal) -> Option<Vec<(BlockKey, Direction)>> {
+    Solver::new().solve(world, goal)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Manhattan;
+    use parabox::{Position, ProtoType, RuleConfig};
+
+    /// Returns a world with a player and a box in a row of the container.
+    fn setup(width: usize) -> (World, BlockKey, BlockKey, BlockKey) {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (width, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+        for key in [player, block] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, (0, 0)));
+        }
+        (world, container, player, block)
+    }
+
+    #[test]
+    fn test_solve() {
+        let (world, container, player, block) = setup(4);
+        let goal = Goal::new().with_target(block, Position::inside(container, (3, 0)));
+
+        let solution = Solver::new().with_players([player]).solve(&world, &goal);
+        assert_eq!(
+            solution.unwrap(),
+            [(player, Direction::East), (player, Direction::East)]
+        );
+
+        let solution = solve(&world, &goal).unwrap();
+        assert_eq!(solution.len(), 2);
+
+        let mut world = world;
+        for (key, direction) in solution {
+            assert!(world.push(key, direction).unwrap());
+        }
+        assert!(goal.is_satisfied(&world));
+    }
+
+    #[test]
+    fn test_unsolvable() {
+        let (world, container, player, block) = setup(4);
+        let goal = Goal::new().with_target(block, Position::inside(container, (0, 0)));
+        let solver = Solver::new().with_players([player]);
+        assert_eq!(solver.solve(&world, &goal), None);
+
+        let goal = Goal::new().with_target(player, Position::inside(container, (0, 0)));
+        assert_eq!(solver.solve(&world, &goal), Some(Vec::new()));
+    }
+
+    #[test]
+    fn test_max_states() {
+        let (world, container, player, block) = setup(6);
+        let goal = Goal::new().with_target(block, Position::inside(container, (5, 0)));
+        let solver = Solver::new().with_players([player]);
+        assert_eq!(solver.solve(&world, &goal).unwrap().len(), 4);
+        assert_eq!(
+            solver.clone().with_max_states(3).try_solve(&world, &goal),
+            Err(SolveError::Exhausted)
+        );
+        assert_eq!(
+            solver
+                .with_deadline(Instant::now())
+                .try_solve(&world, &goal),
+            Err(SolveError::Exhausted)
+        );
+
+        let goal = Goal::new().with_target(block, Position::inside(container, (0, 0)));
+        assert_eq!(
+            Solver::new()
+                .with_players([player])
+                .try_solve(&world, &goal),
+            Err(SolveError::Unreachable)
+        );
+    }
+
+    #[test]
+    fn test_heuristic() {
+        let (world, container, player, block) = setup(6);
+        let goal = Goal::new().with_target(block, Position::inside(container, (5, 0)));
+        let solver = Solver::new().with_heuristic(Manhattan);
+        let solution = solver.solve(&world, &goal).unwrap();
+        assert_eq!(solution.len(), 4);
+
+        // A custom heuristic preferring states where the player is away from
+        // the left border.
+        let custom = move |world: &World, goal: &Goal| {
+            let (x, _) = world.position(player).pos;
+            Manhattan.estimate(world, goal) + usize::from(x == 0)
+        };
+        let solver = Solver::new().with_players([player]).with_heuristic(custom);
+        assert_eq!(
+            solver.solve(&world, &goal),
+            Some(vec![(player, Direction::East); 4])
+        );
+    }
+
+    #[test]
+    fn test_deadlock_pruning() {
+        let mut world = World::new();
+        world.set_rule_config(RuleConfig::sokoban());
+        let container = world.insert(ProtoType::Box { size: (5, 5) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(player, Position::inside(container, (0, 2)));
+        world.place(block, Position::inside(container, (1, 2)));
+        world.place(wall, Position::inside(container, (2, 0)));
+
+        // A block frozen at its target is not deadlocked.
+        let goal = Goal::new().with_target(block, Position::inside(container, (1, 0)));
+        let solver = Solver::new().with_players([player]);
+        assert_eq!(solver.solve(&world, &goal).unwrap().len(), 4);
+
+        world.place(block, Position::inside(container, (1, 0)));
+        world.place(player, Position::inside(container, (1, 1)));
+        let goal = Goal::new().with_target(block, Position::inside(container, (4, 4)));
+        assert!(!world
+            .detect_deadlocks_with_players(&goal, &[player])
+            .is_empty());
+        assert_eq!(solver.solve(&world, &goal), None);
+    }
+}
@@ -1,7 +1,9 @@
 use crate::logger::init_logger;
 use parabox_macros::scan_tests;
 use parabox_parser::{Executor, NamedStringSource, ParseResult};
+use std::cell::RefCell;
 use std::rc::Rc;
+use std::sync::Arc;
 
 #[scan_tests("crates/parabox-tests/worlds/")]
 #[test]
@@ -17,20 +19,18 @@ fn run_test(name: &str, text: &str) {
 fn execute_with_debug_info(name: &str, text: &str) -> ParseResult<()> {
     let mut executor = Executor::new();
     let source = NamedStringSource::new(name.into(), text.to_string());
-    executor.push_source(Rc::new(source))?;
+    executor.push_source(Arc::new(source))?;
+    for warning in executor.warnings() {
+        println!("{}", warning);
+    }
 
-    let mut traces = vec![];
+    let traces = Rc::new(RefCell::new(vec![]));
     let mut last_format = None;
 
-    while executor.has_next() {
-        let span = executor.step().map_err(|e| {
-            for output in &traces {
-                println!("{}", output);
-            }
-            e
-        })?;
-
-        let pushing = span.text()[..4].to_lowercase() == "push";
+    let pushes = traces.clone();
+    executor.on_after(move |executor, command| {
+        let span = command.span();
+        let pushing = command.command().is_push();
 
         let current = if !pushing {
             center("Initial".to_string(), 24)
@@ -39,9 +39,10 @@ fn execute_with_debug_info(name: &str, text: &str) -> ParseResult<()> {
                 + "\n"
                 + format!(">>> {}", span.text()).as_str()
         } + "\n"
-            + executor.format_positions().as_str();
+            + executor.format_tree().as_str();
 
         if pushing {
+            let mut traces = pushes.borrow_mut();
             if let Some(last) = last_format.take() {
                 traces.push(last)
             }
@@ -49,13 +50,22 @@ fn execute_with_debug_info(name: &str, text: &str) -> ParseResult<()> {
         } else {
             last_format = Some(current);
         }
-    }
+    });
 
-    for output in traces {
+    let result = executor.run_all();
+    for output in traces.borrow().iter() {
         println!("{}", output);
     }
 
-    Ok(())
+    let report = executor.report();
+    println!(
+        "{} commands in {:?}, {} assertions passed",
+        report.commands,
+        report.elapsed(),
+        report.assertions_passed
+    );
+
+    result.map(drop).map_err(Into::into)
 }
 
 fn center(s: String, width: usize) -> String {
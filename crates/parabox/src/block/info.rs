@@ -1,10 +1,12 @@
 use super::BlockKey;
+use crate::{Direction, EntryFilter, GroupId};
 use std::collections::HashSet;
+use std::sync::Arc;
 
 /// Information about a block.
 ///
 /// The values won't change during the movements.
-#[derive(Default)]
+#[derive(Default, Clone)]
 pub struct Info {
     /// The alias blocks referring to this block.
     pub references: HashSet<BlockKey>,
@@ -12,4 +14,26 @@ pub struct Info {
     pub infinity: Option<BlockKey>,
     /// The epsilon block referring to this block.
     pub epsilon: Option<BlockKey>,
+    /// Whether this alias block also forwards the exits of its reference, see
+    /// [World::set_two_way](crate::World::set_two_way).
+    pub two_way: bool,
+    /// The sides of the boundary that cannot be crossed, see
+    /// [World::set_side_wall](crate::World::set_side_wall).
+    pub side_walls: HashSet<Direction>,
+    /// Whether this block is removed instead of being eaten, see
+    /// [World::set_fragile](crate::World::set_fragile).
+    pub fragile: bool,
+    /// The tags of this block, see [World::add_tag](crate::World::add_tag).
+    pub tags: HashSet<Arc<str>>,
+    /// The predicate deciding which blocks may enter this block, see
+    /// [World::set_entry_filter](crate::World::set_entry_filter).
+    pub entry_filter: Option<EntryFilter>,
+    /// The group of linked blocks this block belongs to, see
+    /// [World::link](crate::World::link).
+    pub group: Option<GroupId>,
+    /// The base block of the stack this block is a layer of, see
+    /// [World::stack](crate::World::stack).
+    pub stack: Option<BlockKey>,
+    /// The optional label of this block, used for debugging.
+    pub label: Option<Arc<str>>,
 }
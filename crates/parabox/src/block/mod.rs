@@ -5,13 +5,16 @@ mod types;
 
 pub use info::Info;
 pub use proto::ProtoType;
-pub use state::State;
-pub use types::{BlockKey, Position, Size};
+pub use state::{Interior, State};
+pub use types::{BlockId, BlockKey, Position, Size};
 
 /// A block in the world.
+#[derive(Clone)]
 pub struct Block {
     /// The key of the block.
     pub key: BlockKey,
+    /// The stable id of the block.
+    pub id: BlockId,
     /// The prototype of the block.
     pub proto: ProtoType,
     /// The state of the block.
@@ -21,9 +24,10 @@ pub struct Block {
 }
 
 impl Block {
-    pub(crate) fn new(key: BlockKey, proto: ProtoType) -> Self {
+    pub(crate) fn new(key: BlockKey, id: BlockId, proto: ProtoType) -> Self {
         Self {
             key,
+            id,
             proto,
             state: State::new(proto.size()),
             info: Info::default(),
@@ -24,6 +24,10 @@ use super::BlockKey;
 ///   transfering them to another block
 /// - [ProtoType::Void]: a static, hollow block that only allow pushings for the
 ///   child blocks
+/// - [ProtoType::Room]: a static, hollow block that can be entered and exited,
+///   but never pushed or eaten
+/// - [ProtoType::Sealed]: a hollow block that can be exited but never entered,
+///   so it cannot eat either
 #[allow(missing_docs)]
 #[derive(Copy, Clone, Eq, PartialEq, Debug)]
 pub enum ProtoType {
@@ -33,6 +37,8 @@ pub enum ProtoType {
     Infinity { reference: BlockKey },
     Epsilon { size: Size, reference: BlockKey },
     Void { size: Size },
+    Room { size: Size },
+    Sealed { size: Size },
 }
 
 impl ProtoType {
@@ -65,6 +71,16 @@ impl ProtoType {
     pub fn void(size: Size) -> Self {
         ProtoType::Void { size }
     }
+
+    /// Returns a [ProtoType::Room].
+    pub fn room(size: Size) -> Self {
+        ProtoType::Room { size }
+    }
+
+    /// Returns a [ProtoType::Sealed].
+    pub fn sealed(size: Size) -> Self {
+        ProtoType::Sealed { size }
+    }
 }
 
 impl ProtoType {
@@ -74,6 +90,8 @@ impl ProtoType {
             ProtoType::Box { size } => size,
             ProtoType::Epsilon { size, .. } => size,
             ProtoType::Void { size } => size,
+            ProtoType::Room { size } => size,
+            ProtoType::Sealed { size } => size,
             _ => Size::default(),
         }
     }
@@ -92,29 +110,37 @@ impl ProtoType {
     ///
     /// See [ProtoType] for more information.
     pub fn is_solid(&self) -> bool {
-        match self {
-            ProtoType::Wall => true,
-            ProtoType::Alias { .. } => true,
-            ProtoType::Infinity { .. } => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            ProtoType::Wall | ProtoType::Alias { .. } | ProtoType::Infinity { .. }
+        )
     }
 
     /// Returns whether the block is hollow.
     ///
     /// See [ProtoType] for more information.
     pub fn is_hollow(&self) -> bool {
-        match self {
-            ProtoType::Box { .. } => true,
-            ProtoType::Epsilon { .. } => true,
-            ProtoType::Void { .. } => true,
-            _ => false,
-        }
+        matches!(
+            self,
+            ProtoType::Box { .. }
+                | ProtoType::Epsilon { .. }
+                | ProtoType::Void { .. }
+                | ProtoType::Room { .. }
+                | ProtoType::Sealed { .. }
+        )
     }
 
     /// Returns whether the block is static.
     pub fn is_static(&self) -> bool {
-        matches!(self, ProtoType::Wall | ProtoType::Void { .. })
+        matches!(
+            self,
+            ProtoType::Wall | ProtoType::Void { .. } | ProtoType::Room { .. }
+        )
+    }
+
+    /// Returns whether the block is sealed, i.e. cannot be entered.
+    pub fn is_sealed(&self) -> bool {
+        matches!(self, ProtoType::Sealed { .. })
     }
 
     /// Returns whether the block is void.
@@ -143,6 +169,8 @@ impl ProtoType {
             ProtoType::Infinity { .. } => false,
             ProtoType::Epsilon { .. } => true,
             ProtoType::Void { .. } => false,
+            ProtoType::Room { .. } => true,
+            ProtoType::Sealed { .. } => true,
         }
     }
 
@@ -155,6 +183,8 @@ impl ProtoType {
             ProtoType::Infinity { .. } => true,
             ProtoType::Epsilon { .. } => true,
             ProtoType::Void { .. } => false,
+            ProtoType::Room { .. } => true,
+            ProtoType::Sealed { .. } => true,
         }
     }
 
@@ -167,6 +197,8 @@ impl ProtoType {
             ProtoType::Infinity { .. } => false,
             ProtoType::Epsilon { .. } => true,
             ProtoType::Void { .. } => false,
+            ProtoType::Room { .. } => true,
+            ProtoType::Sealed { .. } => false,
         }
     }
 }
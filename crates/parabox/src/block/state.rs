@@ -1,26 +1,78 @@
 use super::types::Size;
 use super::{BlockKey, Position};
+use std::fmt::Debug;
+use std::ops::{Deref, DerefMut};
+use std::sync::Arc;
 
 /// The state of a block.
+#[derive(Clone)]
 pub struct State {
     /// The position of the block.
     pub position: Position,
     /// The interior matrix of the block.
-    ///
-    /// The matrix is represented as a 2D vector, where the first dimension
-    /// represents the x-axis and the second dimension represents the y-axis.
-    ///
-    /// The matrix is filled with `Option<BlockKey>`, where `None` represents
-    /// an empty space and `Some(BlockKey)` represents a block.
-    pub interior: Vec<Vec<Option<BlockKey>>>,
+    pub interior: Interior,
 }
 
 impl State {
     pub(crate) fn new(size: Size) -> Self {
-        let (width, height) = size;
         let position = Position::default();
-        let interior = vec![vec![None; height]; width];
+        let interior = Interior::new(size);
 
         Self { position, interior }
     }
 }
+
+/// The interior matrix of a block.
+///
+/// The matrix is represented as a 2D vector, where the first dimension
+/// represents the x-axis and the second dimension represents the y-axis.
+///
+/// The matrix is filled with `Option<BlockKey>`, where `None` represents
+/// an empty space and `Some(BlockKey)` represents a block.
+///
+/// Cloning an interior is cheap, since the matrix is shared until one of the
+/// clones is modified, see [World::fork](crate::World::fork).
+#[derive(Clone, Eq, PartialEq)]
+pub struct Interior(Arc<Vec<Vec<Option<BlockKey>>>>);
+
+impl Interior {
+    pub(crate) fn new(size: Size) -> Self {
+        let (width, height) = size;
+        Self(Arc::new(vec![vec![None; height]; width]))
+    }
+
+    /// Returns whether the matrix is shared with the other interior.
+    pub fn is_shared_with(&self, other: &Interior) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Deref for Interior {
+    type Target = Vec<Vec<Option<BlockKey>>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+/// Copies the matrix first if it is shared.
+impl DerefMut for Interior {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        Arc::make_mut(&mut self.0)
+    }
+}
+
+impl<'a> IntoIterator for &'a Interior {
+    type Item = &'a Vec<Option<BlockKey>>;
+    type IntoIter = std::slice::Iter<'a, Vec<Option<BlockKey>>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.0.iter()
+    }
+}
+
+impl Debug for Interior {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        self.0.fmt(f)
+    }
+}
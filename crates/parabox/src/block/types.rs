@@ -13,6 +13,31 @@ new_key_type! {
     pub struct BlockKey;
 }
 
+/// A stable identifier of a block.
+///
+/// Ids are assigned by [World] when inserting blocks. They increase
+/// monotonically and are never reused within a world, so unlike [BlockKey]
+/// they can be exported for serialization, network sync and replays.
+///
+/// Use [World::key_of] to look up the key of an id.
+///
+/// [World]: crate::World
+/// [World::key_of]: crate::World::key_of
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct BlockId(u64);
+
+impl BlockId {
+    /// Creates an id from its raw value.
+    pub fn new(id: u64) -> Self {
+        Self(id)
+    }
+
+    /// Returns the raw value of the id.
+    pub fn get(self) -> u64 {
+        self.0
+    }
+}
+
 /// A position of a block.
 ///
 /// The position includes the container and the pos in the interior of the
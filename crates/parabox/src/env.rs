@@ -0,0 +1,221 @@
+//! A gym-style environment for reinforcement learning.
+//!
+//! An [Environment] wraps a [World] together with a [Goal] and a controlled
+//! player block. Each [Environment::step] pushes the player and returns an
+//! [Observation], a reward and whether the episode is done.
+//!
+//! The episode is also done once the player is removed from the world, e.g.
+//! despawned by [OrphanPolicy::Despawn](crate::OrphanPolicy::Despawn) or
+//! crushed as a fragile block.
+
+use crate::{BlockKey, Direction, Goal, Position, World};
+
+/// An action of the agent, i.e. the direction to push the player.
+pub type Action = Direction;
+
+/// The rewards given by an [Environment].
+#[derive(Copy, Clone, PartialEq, Debug)]
+pub struct Rewards {
+    /// The reward of every step.
+    pub step: f64,
+    /// The reward when a push is blocked or fails.
+    pub blocked: f64,
+    /// The reward for each newly reached target, negated for each lost one.
+    pub target: f64,
+    /// The reward when the goal is satisfied.
+    pub goal: f64,
+}
+
+impl Default for Rewards {
+    fn default() -> Self {
+        Self {
+            step: -0.01,
+            blocked: -0.05,
+            target: 0.1,
+            goal: 1.0,
+        }
+    }
+}
+
+/// An observation of the environment after a step.
+#[derive(Clone, PartialEq, Debug)]
+pub struct Observation {
+    /// The position of the player, `None` once it is removed.
+    pub player: Option<Position>,
+    /// The positions of the target blocks, in the order of the goal targets,
+    /// `None` for the removed ones.
+    pub targets: Vec<Option<Position>>,
+    /// The number of reached targets.
+    pub reached: usize,
+    /// The number of steps taken in the episode.
+    pub steps: usize,
+}
+
+/// A gym-style environment, see the [module documentation](self).
+#[derive(Clone)]
+pub struct Environment {
+    initial: World,
+    world: World,
+    player: BlockKey,
+    goal: Goal,
+    rewards: Rewards,
+    max_steps: Option<usize>,
+    steps: usize,
+}
+
+impl Environment {
+    /// Creates a new environment, where the agent controls `player` in order
+    /// to satisfy the `goal`.
+    ///
+    /// The world is cloned to be restored on [Environment::reset].
+    pub fn new(world: World, player: BlockKey, goal: Goal) -> Self {
+        Self {
+            initial: world.clone(),
+            world,
+            player,
+            goal,
+            rewards: Rewards::default(),
+            max_steps: None,
+            steps: 0,
+        }
+    }
+
+    /// Sets the rewards of the environment.
+    pub fn with_rewards(mut self, rewards: Rewards) -> Self {
+        self.rewards = rewards;
+        self
+    }
+
+    /// Sets the maximum number of steps of an episode, after which the episode
+    /// is done.
+    pub fn with_max_steps(mut self, max_steps: usize) -> Self {
+        self.max_steps = Some(max_steps);
+        self
+    }
+
+    /// Returns the current world.
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    /// Returns the goal of the environment.
+    pub fn goal(&self) -> &Goal {
+        &self.goal
+    }
+
+    /// Restores the initial world, returning the initial observation.
+    pub fn reset(&mut self) -> Observation {
+        self.world = self.initial.clone();
+        self.steps = 0;
+        self.observe()
+    }
+
+    /// Pushes the player in the direction of the action, returning the
+    /// observation, the reward and whether the episode is done.
+    ///
+    /// Once the player is removed, the step does nothing and the episode is
+    /// done, with a reward of zero.
+    pub fn step(&mut self, action: Action) -> (Observation, f64, bool) {
+        if !self.world.blocks().contains_key(self.player) {
+            return (self.observe(), 0.0, true);
+        }
+
+        let before = self.goal.reached(&self.world);
+        let moved = self.world.push(self.player, action).unwrap_or(false);
+        let after = self.goal.reached(&self.world);
+        self.steps += 1;
+
+        let mut reward = self.rewards.step;
+        if !moved {
+            reward += self.rewards.blocked;
+        }
+        reward += self.rewards.target * (after as f64 - before as f64);
+
+        let satisfied = self.goal.is_satisfied(&self.world);
+        if satisfied {
+            reward += self.rewards.goal;
+        }
+
+        let truncated = self.max_steps.is_some_and(|max| self.steps >= max);
+        let removed = !self.world.blocks().contains_key(self.player);
+        (self.observe(), reward, satisfied || truncated || removed)
+    }
+
+    fn observe(&self) -> Observation {
+        let position = |key| {
+            self.world
+                .blocks()
+                .get(key)
+                .map(|block| block.state.position)
+        };
+        Observation {
+            player: position(self.player),
+            targets: self
+                .goal
+                .targets()
+                .iter()
+                .map(|&(key, _)| position(key))
+                .collect(),
+            reached: self.goal.reached(&self.world),
+            steps: self.steps,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrphanPolicy, ProtoType};
+
+    #[test]
+    fn test_environment() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        for key in [player, block] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, (0, 0)));
+        }
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+
+        let goal = Goal::new().with_target(block, Position::inside(container, (3, 0)));
+        let mut env = Environment::new(world, player, goal).with_max_steps(10);
+
+        let (_, _, done) = env.step(Direction::East);
+        assert!(!done);
+        let (observation, reward, done) = env.step(Direction::East);
+        assert!(done);
+        assert!(reward > 1.0);
+        assert_eq!(observation.reached, 1);
+        assert_eq!(observation.steps, 2);
+
+        let observation = env.reset();
+        assert_eq!(observation.reached, 0);
+        assert_eq!(
+            observation.player,
+            Some(Position::inside(container, (0, 0)))
+        );
+    }
+
+    #[test]
+    fn test_environment_removed() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(container, (1, 0)));
+        world.set_orphan_policy(OrphanPolicy::Despawn);
+
+        let goal = Goal::new().with_target(player, Position::inside(container, (0, 0)));
+        let mut env = Environment::new(world, player, goal);
+
+        // The despawned player ends the episode.
+        let (observation, _, done) = env.step(Direction::East);
+        assert!(done);
+        assert_eq!(observation.player, None);
+        assert_eq!(observation.targets, [None]);
+        assert_eq!(observation.reached, 0);
+        assert_eq!(env.step(Direction::West), (observation, 0.0, true));
+    }
+}
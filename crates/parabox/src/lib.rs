@@ -41,15 +41,21 @@
 //!   manage the blocks and perform game operations.
 //! - [`ProtoType`]: The prototype of a block. Use this enum to create a block.
 //! - [`BlockKey`]: The key of a block. Generated by the world when creating a
-//!  block. Used to refer to the generated block.
+//!   block. Used to refer to the generated block.
 //! - [`Position`]: The position of a block.
-//! - [`Direction`]: The direction of a movement.
+//! - [`Direction`]: The direction of a movement, including the movements
+//!   across the layers of a stacked block, see [`World::stack`].
+//! - [`Goal`]: The goal of a level, i.e. target positions of blocks.
+//! - [`env`]: A gym-style environment for reinforcement learning.
 //!
 //! [`World`]: crate::World
 //! [`ProtoType`]: crate::ProtoType
 //! [`BlockKey`]: crate::BlockKey
 //! [`Position`]: crate::Position
 //! [`Direction`]: crate::Direction
+//! [`World::stack`]: crate::World::stack
+//! [`Goal`]: crate::Goal
+//! [`env`]: crate::env
 //!
 //! # Example
 //!
@@ -96,7 +102,15 @@
 extern crate self as parabox;
 
 mod block;
+pub mod env;
 mod world;
 
-pub use block::{Block, BlockKey, Info, Position, ProtoType, Size, State};
-pub use world::{Direction, MoveError, MoveResult, World};
+pub use block::{Block, BlockId, BlockKey, Info, Interior, Position, ProtoType, Size, State};
+pub use world::{
+    Attempt, BlockedPolicy, Channel, Conveyor, Direction, EncodingOptions, EntryFilter,
+    EntryPolicy, Goal, GroupId, Move, MoveContext, MoveError, MoveResult, Orientation,
+    OrphanPolicy, Outcome, ParseDirectionError, ParseReplayError, PushLimit, PushStats, PushTrace,
+    RayHit, ReachMode, Replay, Rule, RuleConfig, SequenceReport, Spawner, TieBreak, TimedDoor,
+    TowardReport, TraceStep, TriggerCallback, TriggerEvent, TriggerId, TriggerKind, Violation,
+    World, WorldStats,
+};
@@ -1,27 +1,139 @@
 use super::cycle::Cycle;
+use super::explain::{Attempt, Outcome, PushLimit, TraceStep};
 use super::movement::{
-    Direction, EatInfo, EnterInfo, ExitInfo, IntoMoveResult, MoveProcessor, MoveResult, Movement,
-    SourceArrow, TargetArrow,
+    Direction, EatInfo, EnterInfo, ExitInfo, IntoMoveResult, MoveContext, MoveError, MoveProcessor,
+    MoveResult, Movement, OrphanPolicy, SourceArrow, TargetArrow,
 };
 use super::rational::Rational;
 use crate::world::query::PositionState;
+use crate::Move;
 use crate::{BlockKey, Position, World};
 use parabox_macros::trace_func;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use tracing::{debug, instrument};
 
+/// The arguments of an evaluation of [Algorithm::push_into].
+type Evaluation = (BlockKey, TargetArrow, bool);
+
 pub(crate) struct Algorithm {
+    origin: BlockKey,
     trace: Cycle<BlockKey>,
+    /// The failed evaluations, each with the traces it failed under.
+    failures: HashMap<Evaluation, Vec<Vec<BlockKey>>>,
+    evaluations: usize,
+    limit: PushLimit,
     movements: Vec<Movement>,
     positioned: HashSet<Position>,
+    /// The blocks to be removed when committing, i.e. the crushed fragile
+    /// blocks and the despawned ones.
+    removed: Vec<BlockKey>,
+    explain: Option<Vec<TraceStep>>,
+    depth: usize,
 }
 
 impl Algorithm {
-    pub fn new() -> Self {
+    /// Creates a new algorithm for pushing the `origin` block.
+    pub fn new(origin: BlockKey) -> Self {
         Self {
+            origin,
             trace: Cycle::new(),
+            failures: HashMap::new(),
+            evaluations: 0,
+            limit: PushLimit::default(),
             movements: Vec::new(),
             positioned: HashSet::new(),
+            removed: Vec::new(),
+            explain: None,
+            depth: 0,
+        }
+    }
+
+    /// Creates a new algorithm that also records the decision tree of the
+    /// push, see [World::explain].
+    pub fn explained(origin: BlockKey) -> Self {
+        Self {
+            explain: Some(Vec::new()),
+            ..Self::new(origin)
+        }
+    }
+
+    /// Creates a new algorithm that fails with [MoveError::Exhausted] once the
+    /// push exceeds the limit.
+    pub fn limited(origin: BlockKey, limit: PushLimit) -> Self {
+        Self {
+            limit,
+            ..Self::new(origin)
+        }
+    }
+
+    /// Discards the evaluation of the push to start it again in a modified
+    /// world, keeping the number of evaluations and the limit.
+    pub fn restart(&mut self) {
+        let evaluations = self.evaluations;
+        *self = Self {
+            evaluations,
+            limit: self.limit,
+            explain: self.explain.take().map(|_| Vec::new()),
+            ..Self::new(self.origin)
+        };
+    }
+
+    /// Takes the recorded steps of the decision tree.
+    pub fn take_steps(&mut self) -> Vec<TraceStep> {
+        self.explain.take().unwrap_or_default()
+    }
+
+    /// Records an attempt whose outcome depends on nested attempts. Returns
+    /// the index of the step for [Algorithm::end].
+    fn begin(&mut self, attempt: Attempt) -> usize {
+        let Some(steps) = &mut self.explain else {
+            return 0;
+        };
+
+        steps.push(TraceStep {
+            depth: self.depth,
+            attempt,
+            outcome: Outcome::Blocked,
+        });
+        self.depth += 1;
+        steps.len() - 1
+    }
+
+    /// Finishes the attempt started by [Algorithm::begin].
+    fn end(&mut self, index: usize, outcome: Outcome) {
+        if let Some(steps) = &mut self.explain {
+            steps[index].outcome = outcome;
+            self.depth -= 1;
+        }
+    }
+
+    /// Finishes the attempt started by [Algorithm::begin] if the result is an
+    /// error, so that the error propagates with the step closed.
+    fn abort<T>(&mut self, index: usize, result: MoveResult<T>) -> MoveResult<T> {
+        if result.is_err() {
+            self.end(index, Outcome::Blocked);
+        }
+        result
+    }
+
+    /// Records an attempt without nested attempts.
+    fn record(&mut self, attempt: Attempt, outcome: Outcome) {
+        let index = self.begin(attempt);
+        self.end(index, outcome);
+    }
+
+    /// Returns the number of evaluations of [Algorithm::push_into] that are
+    /// not memoized.
+    pub fn evaluations(&self) -> usize {
+        self.evaluations
+    }
+
+    /// Returns the context for errors occurring at the position.
+    fn context(&self, direction: Direction, position: Position) -> MoveContext {
+        MoveContext {
+            origin: self.origin,
+            direction,
+            position,
         }
     }
 }
@@ -36,21 +148,35 @@ impl Algorithm {
         world: &World,
         source: SourceArrow,
     ) -> Result<TargetArrow, ExitInfo> {
+        let container = source.position.container.unwrap();
+
+        // Move across the layers of a stack, or exit it beyond its top or
+        // bottom layer.
+        if source.direction.is_depth() {
+            return match world.adjacent_layer(source.position, source.direction) {
+                Some(position) => Ok(TargetArrow::new(position, source.direction, source.precise)),
+                None => Err(ExitInfo {
+                    from: container,
+                    direction: source.direction,
+                    precise: source.precise,
+                }),
+            };
+        }
+
         // Compute the target pos according to the source pos and direction.
         let (x, y) = source.position.pos;
         let (x, y) = (x as isize, y as isize);
-        let (dx, dy) = source.direction.delta();
+        let (dx, dy) = source.direction.delta_for(world.orientation());
         let (x, y) = (x + dx, y + dy);
 
-        let container = source.position.container.unwrap();
         let (width, height) = container.get(world).proto.size();
 
         // Check if the target pos is out of bounds.
         if x < 0 || y < 0 || x >= width as isize || y >= height as isize {
             // Compute and return the exit info.
-            let (offset, total) = match source.direction {
-                Direction::North | Direction::South => (x as usize, width),
-                Direction::East | Direction::West => (y as usize, height),
+            let (offset, total) = match source.direction.is_vertical() {
+                true => (x as usize, width),
+                false => (y as usize, height),
             };
 
             let precise = (source.precise + offset) / total;
@@ -104,8 +230,13 @@ impl Algorithm {
     /// saved. This is because if the movement occurs in a cycle, a block
     /// that triggers the cycle does not necessarily move.
     #[trace_func]
-    #[instrument(skip(self))]
-    fn confirm(&mut self, movement: Movement, cycling: bool) -> MoveResult<bool> {
+    #[instrument(skip(self, world))]
+    pub(super) fn confirm(
+        &mut self,
+        world: &World,
+        movement: Movement,
+        cycling: bool,
+    ) -> MoveResult<bool> {
         // Check if the movement is blocked.
         //
         // When `cycling` is `true`, the target block is always moving, leaving an empty
@@ -123,6 +254,9 @@ impl Algorithm {
             // Save the movement.
             self.movements.push(movement);
             self.positioned.insert(movement.target);
+        } else if world.is_fragile(movement.key) {
+            // Crush the fragile block blocked in the cycle.
+            self.removed.push(movement.key);
         }
 
         Ok(true)
@@ -152,26 +286,71 @@ impl Algorithm {
                 }
                 Err(mut info) => {
                     // The target is out of bounds.
+                    // Forbid exits when disabled.
+                    if !world.rule_config().exit {
+                        self.record(
+                            Attempt::Exit {
+                                key,
+                                from: info.from,
+                            },
+                            Outcome::Disabled,
+                        );
+                        break Ok(false);
+                    }
+
                     // Try to resolve the exit info.
+                    let context = self.context(info.direction, current.position);
+
                     while let Some(original) = cycle.push(info.from, info.precise) {
                         // The exit info is in a cycle.
                         // Use infinity to resolve the cycle.
-                        info = MoveProcessor.infinity(
-                            world,
-                            ExitInfo {
-                                from: info.from,
-                                direction: info.direction,
-                                precise: *original,
-                            },
-                        )?;
+                        info = MoveProcessor
+                            .infinity(
+                                world,
+                                ExitInfo {
+                                    from: info.from,
+                                    direction: info.direction,
+                                    precise: *original,
+                                },
+                            )
+                            .no_infinity(info.from, context)?;
                     }
 
                     // Forbid exits from a void block.
+                    let attempt = Attempt::Exit {
+                        key,
+                        from: info.from,
+                    };
                     if info.from.is_void(world) {
+                        self.record(attempt, Outcome::Void);
+                        break Ok(false);
+                    }
+
+                    // Forbid exits through a side wall.
+                    if MoveProcessor.exit_blocked(world, info) {
+                        self.record(attempt, Outcome::SideWall);
                         break Ok(false);
                     }
 
-                    current = MoveProcessor.exit(world, info)?;
+                    current = match MoveProcessor.exit(world, info) {
+                        Some(source) => {
+                            self.record(attempt, Outcome::Success);
+                            source
+                        }
+                        None => {
+                            self.record(attempt, Outcome::Orphan);
+                            match world.orphan_policy_for(info.direction) {
+                                OrphanPolicy::Stay => break Ok(false),
+                                OrphanPolicy::Despawn => {
+                                    self.removed.push(key);
+                                    break Ok(true);
+                                }
+                                OrphanPolicy::Error | OrphanPolicy::Promote => {
+                                    break Err(MoveError::Orphan(info.from, context))
+                                }
+                            }
+                        }
+                    };
                 }
             }
         }
@@ -192,6 +371,10 @@ impl Algorithm {
     ///
     /// Movements directly inside a [crate::ProtoType::Void] block will be
     /// forbidden.
+    ///
+    /// Failed evaluations are memoized. A failed evaluation never reaches a
+    /// block in the trace, since that would resolve a cycle and succeed, so
+    /// it fails again under any trace that is a subset of the original one.
     #[trace_func]
     #[instrument(skip(self, world))]
     fn push_into(
@@ -201,6 +384,17 @@ impl Algorithm {
         target: TargetArrow,
         eating: bool,
     ) -> MoveResult<bool> {
+        let evaluation = (key, target, eating);
+        if self.failed(world, evaluation) {
+            debug!("memoized failure: {:?}", evaluation);
+            return Ok(false);
+        }
+        self.evaluations += 1;
+        if self.limit.exceeded(self.evaluations) {
+            let context = self.context(target.direction, world.position(key));
+            return Err(MoveError::Exhausted(key, context));
+        }
+
         if self.trace.push(key, ()).is_some() {
             // Found a pushing cycle.
             debug!("cycle: {:?}", self.trace);
@@ -218,26 +412,35 @@ impl Algorithm {
         // Stop the first pushing try when eating.
         let mut can_push = !eating;
 
+        // Always allow the first entering try when eating, since it is the
+        // eating itself.
+        let mut can_enter = eating || world.rule_config().enter;
+
         loop {
             // Try to push the block into the target position.
             match self.target_to_movement(world, key, current) {
                 Ok(movement) => {
                     // The target is empty.
                     // Confirm the movement.
-                    return self.confirm(movement, false);
+                    return self.confirm(world, movement, false);
                 }
                 Err((movement, mut info)) => {
                     // The target is taken by a block.
                     // Try to push the target block.
                     // But do not push when eating.
                     if can_push && self.push(world, info.into, info.direction)? {
-                        return self.confirm(movement, true);
+                        return self.confirm(world, movement, true);
                     }
 
                     can_push = true;
 
                     // Forbid entering movements directly inside a void block.
                     if movement.target.is_in_void(world) {
+                        let attempt = Attempt::Enter {
+                            key,
+                            into: info.into,
+                        };
+                        self.record(attempt, Outcome::Void);
                         break;
                     }
 
@@ -248,20 +451,58 @@ impl Algorithm {
                     while cycle.push(info, ()).is_some() {
                         // The enter info is in a cycle.
                         // Use epsilon to resolve the cycle.
-                        info = MoveProcessor.epsilon(world, info)?;
+                        let context = self.context(info.direction, movement.target);
+                        info = MoveProcessor
+                            .epsilon(world, info)
+                            .no_epsilon(info.into, context)?;
                     }
 
-                    match MoveProcessor.enter(world, info) {
+                    // Forbid entering movements when disabled, or into a sealed block.
+                    // Skip straight to the eating tries.
+                    let attempt = Attempt::Enter {
+                        key,
+                        into: info.into,
+                    };
+                    if !can_enter {
+                        self.record(attempt, Outcome::Disabled);
+                        break;
+                    }
+                    if info.into.get(world).proto.is_sealed() {
+                        self.record(attempt, Outcome::Sealed);
+                        break;
+                    }
+                    can_enter = world.rule_config().enter;
+
+                    match MoveProcessor.enter(world, key, info) {
                         // Successfully entered the target block.
                         // Go to the next push-enter loop.
-                        Some(next) => current = next,
+                        Some(next) => {
+                            self.record(attempt, Outcome::Success);
+                            current = next
+                        }
                         // Failed to enter the target block.
-                        None => break,
+                        None => {
+                            let into = info.into.get(world);
+                            let outcome = if into.proto.is_solid() {
+                                Outcome::Solid
+                            } else if into.info.side_walls.contains(&info.direction.opposite()) {
+                                Outcome::SideWall
+                            } else {
+                                Outcome::Filtered
+                            };
+                            self.record(attempt, outcome);
+                            break;
+                        }
                     }
                 }
             }
         }
 
+        // Forbid eating movements when disabled.
+        if !world.rule_config().eat {
+            return self.fail(evaluation);
+        }
+
         // Try to eat the target block, in the reverse order of entering movements.
         while let Some((info, _)) = cycle.pop() {
             // Stop the last eating try when eating.
@@ -277,7 +518,12 @@ impl Algorithm {
             };
 
             // Cannot eat a static block.
+            let attempt = Attempt::Eat {
+                key,
+                ate: info.into,
+            };
             if eat_info.ate.get(world).proto.is_static() {
+                self.record(attempt, Outcome::Static);
                 continue;
             }
 
@@ -290,15 +536,52 @@ impl Algorithm {
                 enter_info.precise,
             );
 
-            if self.push_into(world, eat_info.ate, enter_target, true)? {
+            let step = self.begin(attempt);
+            let ate = self.push_into(world, eat_info.ate, enter_target, true);
+            let ate = self.abort(step, ate)?;
+            if ate && world.is_fragile(eat_info.ate) {
+                // Crush the fragile block instead of moving it into the eater.
+                self.removed.push(eat_info.ate);
+                self.end(step, Outcome::Crushed);
+            } else {
+                self.end(step, Outcome::of(ate));
+            }
+            if ate {
                 return self.confirm(
+                    world,
                     Movement::new(key, info.into.get(world).state.position),
                     true,
                 );
             }
         }
 
+        self.fail(evaluation)
+    }
+
+    /// Returns whether the evaluation is known to fail under the current
+    /// trace.
+    ///
+    /// Memoization is skipped when explaining, so that every step is recorded.
+    ///
+    /// Memoization is also skipped in worlds with linked groups, where a
+    /// blocked member may fail a push after it resolves a cycle.
+    fn failed(&self, world: &World, evaluation: Evaluation) -> bool {
+        if self.explain.is_some() || !world.groups.is_empty() {
+            return false;
+        }
+
+        self.failures.get(&evaluation).is_some_and(|traces| {
+            traces
+                .iter()
+                .any(|trace| self.trace.keys().all(|key| trace.contains(key)))
+        })
+    }
+
+    /// Pops the block from the trace and memoizes the failed evaluation.
+    fn fail(&mut self, evaluation: Evaluation) -> MoveResult<bool> {
         self.trace.pop();
+        let trace = self.trace.keys().copied().collect();
+        self.failures.entry(evaluation).or_default().push(trace);
 
         Ok(false)
     }
@@ -309,23 +592,155 @@ impl Algorithm {
     #[trace_func]
     #[instrument(skip(self, world))]
     pub fn push(&mut self, world: &World, key: BlockKey, direction: Direction) -> MoveResult<bool> {
+        let attempt = Attempt::Push { key, direction };
         let block = key.get(world);
         if block.proto.is_static() {
+            self.record(attempt, Outcome::Static);
             return Ok(false);
         }
         let position = block.state.position;
-        position.container.orphan(key)?;
+        if position.is_orphan() {
+            self.record(attempt, Outcome::Orphan);
+            return match world.orphan_policy_for(direction) {
+                OrphanPolicy::Stay | OrphanPolicy::Despawn => Ok(false),
+                OrphanPolicy::Error | OrphanPolicy::Promote => {
+                    Err(MoveError::Orphan(key, self.context(direction, position)))
+                }
+            };
+        }
 
+        let step = self.begin(attempt);
         let source = SourceArrow::new(position, direction, Rational::HALF);
-        self.push_from(world, key, source)
+        let (movements, trace, removed) =
+            (self.movements.len(), self.trace.len(), self.removed.len());
+        let moved = match self.push_from(world, key, source) {
+            Ok(true) => self.push_group(world, key, direction),
+            result => result,
+        };
+        let moved = self.abort(step, moved)?;
+        if !moved {
+            self.rollback(movements, trace, removed);
+        }
+        self.end(step, Outcome::of(moved));
+        Ok(moved)
+    }
+
+    /// Pushes the other members of the group of the block, returning whether
+    /// all of them are successfully pushed.
+    ///
+    /// Members already moved or removed are skipped, and members being pushed,
+    /// i.e. in the trace, are resolved as a pushing cycle. The group is
+    /// blocked if two of its members move into the same cell.
+    fn push_group(
+        &mut self,
+        world: &World,
+        key: BlockKey,
+        direction: Direction,
+    ) -> MoveResult<bool> {
+        let Some(group) = world.group(key) else {
+            return Ok(true);
+        };
+
+        for &member in world.members(group) {
+            let moved = self.movements.iter().any(|movement| movement.key == member)
+                || self.removed.contains(&member)
+                || self.trace.keys().any(|&key| key == member);
+            if member != key && !moved && !self.push(world, member, direction)? {
+                return Ok(false);
+            }
+
+            // Every saved movement has its own target, unless two members
+            // collide.
+            if self.positioned.len() < self.movements.len() {
+                return Ok(false);
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Discards the movements and the trace of a push that succeeded but is
+    /// then blocked by its group.
+    ///
+    /// The positioned cells are collected again, as a discarded movement may
+    /// share its target with a kept one.
+    fn rollback(&mut self, movements: usize, trace: usize, removed: usize) {
+        self.movements.truncate(movements);
+        self.positioned = self
+            .movements
+            .iter()
+            .map(|movement| movement.target)
+            .collect();
+        self.trace.truncate(trace);
+        self.removed.truncate(removed);
     }
 }
 
 impl Algorithm {
+    /// Returns the movements to be committed.
+    pub fn movements(&self) -> &[Movement] {
+        &self.movements
+    }
+
+    /// Commits the movements to the world.
+    ///
+    /// Removed blocks are removed before the movements are placed, recorded
+    /// as moving to [Position::orphan].
     pub fn commit(&self, world: &mut World) {
+        world.turn += 1;
+
+        let removed = self
+            .removed
+            .iter()
+            .map(|&key| Movement::new(key, Position::orphan()));
+        let moves: Vec<_> = self
+            .movements
+            .iter()
+            .filter(|movement| !self.removed.contains(&movement.key))
+            .copied()
+            .chain(removed)
+            .map(|movement| Move {
+                turn: world.turn,
+                key: movement.key,
+                from: world.position(movement.key),
+                to: movement.target,
+            })
+            .collect();
+
+        world.update_checksum(&moves);
+
+        let mut successors = HashMap::new();
+        for &key in &self.removed {
+            if !world.blocks.contains_key(key) {
+                continue;
+            }
+            if let Some(successor) = world.crush(key) {
+                successors.insert(key, successor);
+            }
+        }
+
+        // Movements into a removed block go into the alias taking it over.
         for movement in &self.movements {
-            world.place(movement.key, movement.target);
+            if self.removed.contains(&movement.key) {
+                continue;
+            }
+            let mut target = movement.target;
+            if let Some(container) = target.container {
+                match successors.get(&container) {
+                    Some(&successor) => target.container = Some(successor),
+                    None if !world.blocks.contains_key(container) => target = Position::orphan(),
+                    None => {}
+                }
+            }
+            world.place(movement.key, target);
         }
+
+        world.fire_triggers(&moves);
+        world.history.extend(
+            moves
+                .into_iter()
+                .filter(|movement| !self.removed.contains(&movement.key)),
+        );
     }
 }
 
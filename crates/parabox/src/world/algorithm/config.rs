@@ -0,0 +1,80 @@
+/// Where a block enters another block, see [RuleConfig::entry].
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum EntryPolicy {
+    /// Enter at the cell proportional to the position where the block hits
+    /// the face, as in the original game.
+    #[default]
+    Proportional,
+    /// Always enter at the center cell of the face, i.e. the cell at index
+    /// `size / 2` along the face.
+    Center,
+    /// Always enter at the first cell of the face, i.e. the one with the
+    /// smallest coordinate along the face.
+    Edge,
+}
+
+/// Which cell is entered when a block hits exactly the boundary between two
+/// cells of a face, see [RuleConfig::tie_break].
+///
+/// This only matters for [EntryPolicy::Proportional]. For example, a block
+/// entering the middle of a face of width `2` hits the boundary between
+/// the cells `0` and `1`.
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum TieBreak {
+    /// Enter the cell after the boundary, i.e. with the larger coordinate,
+    /// at its lower edge.
+    #[default]
+    Upper,
+    /// Enter the cell before the boundary, i.e. with the smaller coordinate,
+    /// at its center.
+    Lower,
+}
+
+/// The mechanics enabled in a world, see [World::set_rule_config].
+///
+/// All the mechanics are enabled by default. Disabling all of them emulates
+/// plain Sokoban, where blocks can only push each other.
+///
+/// [World::set_rule_config]: crate::World::set_rule_config
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct RuleConfig {
+    /// Whether blocks can enter other blocks.
+    pub enter: bool,
+    /// Whether blocks can eat other blocks.
+    ///
+    /// Eating is always allowed to bring the eaten block into the eating
+    /// block, even if entering is disabled.
+    pub eat: bool,
+    /// Whether blocks can exit their containers.
+    pub exit: bool,
+    /// Where blocks enter other blocks.
+    pub entry: EntryPolicy,
+    /// Which cell is entered on a tie, see [TieBreak].
+    pub tie_break: TieBreak,
+}
+
+impl RuleConfig {
+    /// Returns the configuration of plain Sokoban, where all the mechanics
+    /// are disabled.
+    pub fn sokoban() -> Self {
+        Self {
+            enter: false,
+            eat: false,
+            exit: false,
+            entry: EntryPolicy::default(),
+            tie_break: TieBreak::default(),
+        }
+    }
+}
+
+impl Default for RuleConfig {
+    fn default() -> Self {
+        Self {
+            enter: true,
+            eat: true,
+            exit: true,
+            entry: EntryPolicy::default(),
+            tie_break: TieBreak::default(),
+        }
+    }
+}
@@ -37,12 +37,24 @@ impl<T, V> Cycle<T, V> {
     pub fn is_empty(&self) -> bool {
         self.trace.is_empty()
     }
+
+    pub fn len(&self) -> usize {
+        self.trace.len()
+    }
+
+    pub fn truncate(&mut self, len: usize) {
+        self.trace.truncate(len);
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &T> {
+        self.trace.iter().map(|(key, _)| key)
+    }
 }
 
 impl<T: Eq, V> Cycle<T, V> {
     /// Panics if the cycle burns.
     pub fn push(&mut self, key: T, value: V) -> Option<&V> {
-        if let Some(index) = self.trace.iter().position(|x| &x.0 == &key) {
+        if let Some(index) = self.trace.iter().position(|x| x.0 == key) {
             return Some(&self.trace[index].1);
         }
 
@@ -0,0 +1,237 @@
+use super::algorithm::Algorithm;
+use crate::{BlockKey, Direction, MoveResult, World};
+use std::time::Instant;
+
+/// An attempted movement in a [PushTrace].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Attempt {
+    /// The block is pushed in the direction.
+    Push {
+        /// The pushed block.
+        key: BlockKey,
+        /// The direction of the push.
+        direction: Direction,
+    },
+    /// The block exits its container `from`.
+    Exit {
+        /// The exiting block.
+        key: BlockKey,
+        /// The exited block.
+        from: BlockKey,
+    },
+    /// The block enters the block `into`.
+    Enter {
+        /// The entering block.
+        key: BlockKey,
+        /// The entered block, after resolving aliases and epsilons.
+        into: BlockKey,
+    },
+    /// The block eats the block `ate`.
+    Eat {
+        /// The eating block.
+        key: BlockKey,
+        /// The eaten block.
+        ate: BlockKey,
+    },
+}
+
+/// The outcome of an [Attempt].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum Outcome {
+    /// The attempt succeeds.
+    Success,
+    /// The attempt fails because of the nested attempts.
+    Blocked,
+    /// The block is static and cannot be pushed or eaten.
+    Static,
+    /// The entered block has no interior.
+    Solid,
+    /// The movement crosses a side wall, see
+    /// [World::set_side_wall](crate::World::set_side_wall).
+    SideWall,
+    /// The eaten block is fragile and removed, see
+    /// [World::set_fragile](crate::World::set_fragile).
+    Crushed,
+    /// The entry filter of the entered block rejects the block, see
+    /// [World::set_entry_filter](crate::World::set_entry_filter).
+    Filtered,
+    /// The entered block is [ProtoType::Sealed](crate::ProtoType::Sealed).
+    Sealed,
+    /// The movement is directly inside a [ProtoType::Void](crate::ProtoType::Void)
+    /// block.
+    Void,
+    /// The movement is disabled by the [RuleConfig](crate::RuleConfig).
+    Disabled,
+    /// The pushed or exited block is an orphan.
+    Orphan,
+}
+
+impl Outcome {
+    /// Returns [Outcome::Success] or [Outcome::Blocked].
+    pub(crate) fn of(success: bool) -> Self {
+        if success {
+            Outcome::Success
+        } else {
+            Outcome::Blocked
+        }
+    }
+}
+
+/// A node in the decision tree of a [PushTrace].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TraceStep {
+    /// The depth in the tree, where the root push has depth `0`.
+    pub depth: usize,
+    /// The attempted movement.
+    pub attempt: Attempt,
+    /// The outcome of the attempt.
+    pub outcome: Outcome,
+}
+
+/// The work done by a push, see [World::push_profiled].
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub struct PushStats {
+    /// The number of evaluated movements into a position, excluding the
+    /// memoized ones.
+    pub evaluations: usize,
+    /// The number of committed movements, i.e. the number of moved blocks.
+    pub movements: usize,
+}
+
+/// The limits of a push, see [World::push_limited]. There are no limits by
+/// default.
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub struct PushLimit {
+    /// The maximum number of evaluations, see [PushStats::evaluations].
+    pub max_evaluations: Option<usize>,
+    /// The time after which the push stops evaluating.
+    pub deadline: Option<Instant>,
+}
+
+impl PushLimit {
+    /// Returns whether the push exceeds the limit after the evaluations.
+    pub(crate) fn exceeded(&self, evaluations: usize) -> bool {
+        self.max_evaluations.is_some_and(|max| evaluations > max)
+            || self
+                .deadline
+                .is_some_and(|deadline| Instant::now() >= deadline)
+    }
+}
+
+/// The decision tree of a push, see [World::explain].
+#[derive(Debug)]
+pub struct PushTrace {
+    /// The result the push would have.
+    pub result: MoveResult<bool>,
+    /// The steps in pre-order, i.e. each step is followed by its nested steps,
+    /// which have a greater depth.
+    pub steps: Vec<TraceStep>,
+}
+
+impl World {
+    /// Explains what would happen when pushing the block in the direction,
+    /// without modifying the world.
+    ///
+    /// Exits from orphans are reported as [Outcome::Orphan] and handled like
+    /// [World::push] according to the [OrphanPolicy](crate::OrphanPolicy). If
+    /// an orphan is promoted, the steps are those of the last attempt, after
+    /// the promotions.
+    pub fn explain(&self, key: BlockKey, direction: Direction) -> PushTrace {
+        let mut algorithm = Algorithm::explained(key);
+        let result = self
+            .evaluate(&mut algorithm, key, direction)
+            .map(|(result, _)| result);
+
+        PushTrace {
+            result,
+            steps: algorithm.take_steps(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Position, ProtoType};
+
+    #[test]
+    fn test_explain() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(player, Position::inside(container, (1, 0)));
+        world.place(block, Position::inside(container, (2, 0)));
+
+        let trace = world.explain(player, Direction::East);
+        assert!(matches!(trace.result, Ok(false)));
+        assert_eq!(
+            trace.steps,
+            vec![
+                TraceStep {
+                    depth: 0,
+                    attempt: Attempt::Push {
+                        key: player,
+                        direction: Direction::East,
+                    },
+                    outcome: Outcome::Blocked,
+                },
+                TraceStep {
+                    depth: 1,
+                    attempt: Attempt::Push {
+                        key: block,
+                        direction: Direction::East,
+                    },
+                    outcome: Outcome::Static,
+                },
+                TraceStep {
+                    depth: 1,
+                    attempt: Attempt::Enter {
+                        key: player,
+                        into: block,
+                    },
+                    outcome: Outcome::Solid,
+                },
+                TraceStep {
+                    depth: 1,
+                    attempt: Attempt::Eat {
+                        key: player,
+                        ate: block,
+                    },
+                    outcome: Outcome::Static,
+                },
+            ]
+        );
+
+        let trace = world.explain(player, Direction::West);
+        assert!(matches!(trace.result, Ok(true)));
+        assert_eq!(trace.steps.len(), 1);
+        assert_eq!(trace.steps[0].outcome, Outcome::Success);
+        assert_eq!(world.position(player), Position::inside(container, (1, 0)));
+    }
+
+    #[test]
+    fn test_explain_orphan() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(container, (0, 0)));
+
+        let trace = world.explain(player, Direction::North);
+        assert!(trace.result.is_err());
+        assert_eq!(
+            trace.steps.last().unwrap().attempt,
+            Attempt::Exit {
+                key: player,
+                from: container,
+            }
+        );
+        assert_eq!(trace.steps.last().unwrap().outcome, Outcome::Orphan);
+
+        world.set_orphan_policy(crate::OrphanPolicy::Promote);
+        let trace = world.explain(player, Direction::North);
+        assert!(matches!(trace.result, Ok(true)));
+        assert_eq!(trace.steps.last().unwrap().outcome, Outcome::Success);
+        assert!(world.root().is_none());
+    }
+}
@@ -1,12 +1,21 @@
+#[allow(clippy::module_inception)]
 mod algorithm;
+mod config;
 mod cycle;
+mod explain;
 mod movement;
 mod rational;
+mod reach;
 
 use crate::{BlockKey, World};
 use algorithm::Algorithm;
 
-pub use movement::{Direction, MoveError, MoveResult};
+pub use config::{EntryPolicy, RuleConfig, TieBreak};
+pub use explain::{Attempt, Outcome, PushLimit, PushStats, PushTrace, TraceStep};
+pub use movement::{
+    Direction, MoveContext, MoveError, MoveResult, Orientation, OrphanPolicy, ParseDirectionError,
+};
+pub use reach::{ReachMode, TowardReport};
 
 impl World {
     /// Push a block in a direction.
@@ -15,14 +24,599 @@ impl World {
     /// - `Ok(true)` if some movement occurs in the world.
     /// - `Ok(false)` if no movement occurs in the world.
     /// - `Err(MoveError)` if there is an error. See [MoveError].
+    ///
+    /// Exits from orphans are handled according to the [OrphanPolicy] of the
+    /// world. Pushing [Direction::Up] or [Direction::Down] moves the block
+    /// across the layers of a stack, see [World::stack].
     pub fn push(&mut self, key: BlockKey, direction: Direction) -> MoveResult<bool> {
-        let mut algorithm = Algorithm::new();
-        let result = algorithm.push(self, key, direction)?;
+        self.push_profiled(key, direction).map(|(result, _)| result)
+    }
+
+    /// Push a block in a direction like [World::push], also returning the
+    /// work done by the push.
+    ///
+    /// If an orphan is promoted, the statistics include the attempts before
+    /// the promotion.
+    pub fn push_profiled(
+        &mut self,
+        key: BlockKey,
+        direction: Direction,
+    ) -> MoveResult<(bool, PushStats)> {
+        self.push_limited(key, direction, PushLimit::default())
+    }
+
+    /// Push a block in a direction like [World::push_profiled], failing with
+    /// [MoveError::Exhausted] once the push exceeds the limit.
+    ///
+    /// The limit is checked at each evaluation, so that a push exploring many
+    /// movements, e.g. through nested references, stops in time. The world
+    /// is not modified by an exhausted push.
+    pub fn push_limited(
+        &mut self,
+        key: BlockKey,
+        direction: Direction,
+        limit: PushLimit,
+    ) -> MoveResult<(bool, PushStats)> {
+        let mut algorithm = Algorithm::limited(key, limit);
+        let (result, promoted) = self.evaluate(&mut algorithm, key, direction)?;
 
+        let mut stats = PushStats {
+            evaluations: algorithm.evaluations(),
+            movements: 0,
+        };
         if result {
+            if let Some(promoted) = promoted {
+                *self = promoted;
+            }
+            stats.movements = algorithm.movements().len();
             algorithm.commit(self);
         }
 
-        Ok(result)
+        Ok((result, stats))
+    }
+
+    /// Evaluates the push with the algorithm without modifying the world.
+    ///
+    /// Under [OrphanPolicy::Promote], the orphans are promoted in a fork of
+    /// the world, which is returned if the push is to be committed to it
+    /// instead, so that a failed push leaves no promotion behind.
+    fn evaluate(
+        &self,
+        algorithm: &mut Algorithm,
+        key: BlockKey,
+        direction: Direction,
+    ) -> MoveResult<(bool, Option<World>)> {
+        let mut promoted: Option<World> = None;
+        loop {
+            let world = promoted.as_ref().unwrap_or(self);
+            match algorithm.push(world, key, direction) {
+                Err(MoveError::Orphan(orphan, context))
+                    if self.orphan_policy() == OrphanPolicy::Promote =>
+                {
+                    let world = promoted.get_or_insert_with(|| self.fork());
+                    world.promote(orphan, context.direction);
+                    algorithm.restart();
+                }
+                result => return result.map(|result| (result, promoted)),
+            }
+        }
+    }
+
+    /// Returns the directions in which pushing the block would change the
+    /// world, in the order north, south, east, west.
+    ///
+    /// The pushes are evaluated without modifying the world, handling the
+    /// exits from orphans like [World::push]. Directions whose push results
+    /// in a [MoveError] are not considered legal.
+    pub fn legal_pushes(&self, key: BlockKey) -> Vec<Direction> {
+        Direction::ALL
+            .into_iter()
+            .filter(|&direction| {
+                self.evaluate(&mut Algorithm::new(key), key, direction)
+                    .is_ok_and(|(result, _)| result)
+            })
+            .collect()
+    }
+
+    /// Returns the legal pushes of each of the blocks, see
+    /// [World::legal_pushes].
+    pub fn legal_pushes_of(
+        &self,
+        keys: impl IntoIterator<Item = BlockKey>,
+    ) -> Vec<(BlockKey, Direction)> {
+        keys.into_iter()
+            .flat_map(|key| {
+                self.legal_pushes(key)
+                    .into_iter()
+                    .map(move |direction| (key, direction))
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Position, ProtoType};
+
+    #[test]
+    fn test_legal_pushes() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(wall, Position::inside(block, (0, 0)));
+        world.place(other, Position::inside(container, (1, 0)));
+        let wall = world.insert(ProtoType::Wall);
+        world.place(wall, Position::inside(other, (0, 0)));
+
+        // Every push exits the orphan container or is blocked.
+        assert_eq!(world.legal_pushes(block), vec![]);
+
+        let root = world.insert(ProtoType::Box { size: (1, 3) });
+        world.place(container, Position::inside(root, (0, 1)));
+
+        assert_eq!(
+            world.legal_pushes(block),
+            vec![Direction::North, Direction::South]
+        );
+        assert_eq!(
+            world.legal_pushes_of([block, other]),
+            vec![
+                (block, Direction::North),
+                (block, Direction::South),
+                (other, Direction::North),
+                (other, Direction::South),
+            ]
+        );
+        assert_eq!(world.position(block), Position::inside(container, (0, 0)));
+    }
+
+    #[test]
+    fn test_two_way_alias() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (5, 1) });
+        let reference = world.insert(ProtoType::Box { size: (3, 1) });
+        let unplaced = world.insert(ProtoType::Alias { reference });
+        let alias = world.insert(ProtoType::Alias { reference });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(alias, Position::inside(root, (1, 0)));
+        world.place(block, Position::inside(reference, (2, 0)));
+        world.place(wall, Position::inside(block, (0, 0)));
+
+        // A one-way alias does not forward exits.
+        assert!(matches!(
+            world.push(block, Direction::East),
+            Err(MoveError::Orphan(key, _)) if key == reference
+        ));
+
+        // Unplaced aliases are skipped.
+        world.set_two_way(unplaced, true);
+        world.set_two_way(alias, true);
+        assert!(world.push(block, Direction::East).unwrap());
+        assert_eq!(world.position(block), Position::inside(root, (2, 0)));
+
+        // The two-way alias is preferred over the container of a placed
+        // reference.
+        world.place(reference, Position::inside(root, (4, 0)));
+        world.place(block, Position::inside(reference, (0, 0)));
+        assert!(world.push(block, Direction::West).unwrap());
+        assert_eq!(world.position(block), Position::inside(root, (0, 0)));
+
+        // Without a placed two-way alias, the block exits into the container.
+        world.place(block, Position::inside(reference, (0, 0)));
+        world.set_two_way(alias, false);
+        assert!(world.push(block, Direction::West).unwrap());
+        assert_eq!(world.position(block), Position::inside(root, (3, 0)));
+    }
+
+    #[test]
+    fn test_memoize() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (5, 1) });
+        let other = world.insert(ProtoType::Box { size: (2, 1) });
+        let reference = world.insert(ProtoType::Box { size: (1, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let first = world.insert(ProtoType::Alias { reference });
+        let second = world.insert(ProtoType::Alias { reference });
+        let solid = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(root, (0, 0)));
+        world.place(first, Position::inside(root, (1, 0)));
+        world.place(second, Position::inside(root, (2, 0)));
+        world.place(other, Position::inside(root, (4, 0)));
+        world.place(reference, Position::inside(other, (0, 0)));
+        world.place(solid, Position::inside(reference, (0, 0)));
+        for (container, pos) in [(root, (3, 0)), (other, (1, 0)), (solid, (0, 0))] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(container, pos));
+        }
+
+        world.set_rule_config(RuleConfig {
+            eat: false,
+            ..RuleConfig::default()
+        });
+
+        // Both aliases lead to pushing `solid` out of `reference`.
+        let mut memoized = Algorithm::new(player);
+        let mut explained = Algorithm::explained(player);
+        assert!(!memoized.push(&world, player, Direction::East).unwrap());
+        assert!(!explained.push(&world, player, Direction::East).unwrap());
+        assert!(memoized.evaluations() < explained.evaluations());
+    }
+
+    #[test]
+    fn test_explain_error_depth() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        // The steps left open by the error do not nest the next push.
+        let mut algorithm = Algorithm::explained(block);
+        assert!(algorithm.push(&world, block, Direction::East).is_err());
+        algorithm
+            .push(&world, container, Direction::East)
+            .unwrap_err();
+        let steps = algorithm.take_steps();
+        let last_push = steps
+            .iter()
+            .rfind(|step| matches!(step.attempt, Attempt::Push { key, .. } if key == container))
+            .unwrap();
+        assert_eq!(last_push.depth, 0);
+    }
+
+    #[test]
+    fn test_push_profiled() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+        world.place(wall, Position::inside(container, (3, 0)));
+
+        let (moved, stats) = world.push_profiled(player, Direction::East).unwrap();
+        assert!(moved);
+        assert_eq!(stats.movements, 2);
+        assert!(stats.evaluations >= 2);
+
+        let (moved, stats) = world.push_profiled(wall, Direction::West).unwrap();
+        assert!(!moved);
+        assert_eq!(stats.movements, 0);
+    }
+
+    #[test]
+    fn test_push_limited() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+
+        // The push stops at the first evaluation beyond the limit, leaving the
+        // world unchanged.
+        let limit = PushLimit {
+            max_evaluations: Some(1),
+            ..PushLimit::default()
+        };
+        let error = world
+            .push_limited(player, Direction::East, limit)
+            .unwrap_err();
+        assert!(matches!(error, MoveError::Exhausted(key, _) if key == block));
+        assert_eq!(world.position(player), Position::inside(container, (0, 0)));
+
+        let limit = PushLimit {
+            deadline: Some(std::time::Instant::now()),
+            ..PushLimit::default()
+        };
+        assert!(world.push_limited(player, Direction::East, limit).is_err());
+
+        let limit = PushLimit {
+            max_evaluations: Some(2),
+            ..PushLimit::default()
+        };
+        let (moved, stats) = world.push_limited(player, Direction::East, limit).unwrap();
+        assert!(moved);
+        assert_eq!(stats.evaluations, 2);
+    }
+
+    #[test]
+    fn test_side_wall() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let room = world.insert(ProtoType::Box { size: (3, 3) });
+        for (key, pos) in [(player, (0, 0)), (container, (3, 0))] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, pos));
+        }
+        world.place(player, Position::inside(container, (1, 0)));
+        world.place(room, Position::inside(container, (2, 0)));
+
+        world.set_side_wall(room, Direction::West, true);
+        assert!(world.has_side_wall(room, Direction::West));
+        assert!(!world.push(player, Direction::East).unwrap());
+
+        world.set_side_wall(room, Direction::West, false);
+        assert!(world.push(player, Direction::East).unwrap());
+        assert_eq!(world.position(player), Position::inside(room, (0, 1)));
+
+        world.set_side_wall(room, Direction::West, true);
+        assert!(!world.push(player, Direction::West).unwrap());
+        assert!(world.push(player, Direction::East).unwrap());
+    }
+
+    #[test]
+    fn test_fragile() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let eat = world.insert(ProtoType::Box { size: (3, 3) });
+        let ate = world.insert(ProtoType::Box { size: (1, 1) });
+        let inner = world.insert(ProtoType::Wall);
+        let wall = world.insert(ProtoType::Wall);
+        world.place(eat, Position::inside(container, (1, 0)));
+        world.place(ate, Position::inside(container, (2, 0)));
+        world.place(inner, Position::inside(ate, (0, 0)));
+        world.place(wall, Position::inside(container, (3, 0)));
+        world.set_fragile(ate, true);
+
+        let trace = world.explain(eat, Direction::East);
+        assert!(trace
+            .steps
+            .iter()
+            .any(|step| step.outcome == crate::Outcome::Crushed));
+
+        assert!(world.push(eat, Direction::East).unwrap());
+        assert_eq!(world.position(eat), Position::inside(container, (2, 0)));
+        assert!(!world.blocks().contains_key(ate));
+        assert!(world.position(inner).is_orphan());
+        assert!(world[eat]
+            .state
+            .interior
+            .iter()
+            .flatten()
+            .all(Option::is_none));
+        assert!(world.history().iter().all(|movement| movement.key != ate));
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_fragile_references() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let eat = world.insert(ProtoType::Box { size: (3, 3) });
+        let ate = world.insert(ProtoType::Box { size: (1, 1) });
+        let inner = world.insert(ProtoType::Wall);
+        let wall = world.insert(ProtoType::Wall);
+        let first = world.insert(ProtoType::Alias { reference: ate });
+        let second = world.insert(ProtoType::Alias { reference: ate });
+        let infinity = world.insert(ProtoType::Infinity { reference: ate });
+        world.place(eat, Position::inside(container, (1, 0)));
+        world.place(ate, Position::inside(container, (2, 0)));
+        world.place(inner, Position::inside(ate, (0, 0)));
+        world.place(wall, Position::inside(container, (3, 0)));
+        world.set_fragile(ate, true);
+
+        // The first alias takes over the crushed block.
+        assert!(world.push(eat, Direction::East).unwrap());
+        assert!(!world.blocks().contains_key(ate));
+        assert_eq!(world[first].proto, ProtoType::Box { size: (1, 1) });
+        assert_eq!(world.position(inner), Position::inside(first, (0, 0)));
+        assert_eq!(world[second].proto, ProtoType::Alias { reference: first });
+        assert_eq!(
+            world[infinity].proto,
+            ProtoType::Infinity { reference: first }
+        );
+        assert!(world.validate().is_empty());
+
+        // Without aliases, the infinity block is detached.
+        world.place(eat, Position::inside(container, (1, 0)));
+        world.place(first, Position::inside(container, (2, 0)));
+        world.remove(second);
+        world.set_fragile(first, true);
+        assert!(world.push(eat, Direction::East).unwrap());
+        assert!(!world.blocks().contains_key(first));
+        assert_eq!(world[infinity].proto, ProtoType::Wall);
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_fragile_self() {
+        let mut world = World::new();
+        let block = world.insert(ProtoType::Box { size: (2, 2) });
+        let alias = world.insert(ProtoType::Alias { reference: block });
+        world.place(block, Position::inside(block, (1, 1)));
+        world.place(alias, Position::inside(block, (1, 0)));
+        world.set_fragile(block, true);
+
+        // The block inside itself is crushed, and the alias taking over is
+        // left inside itself.
+        assert!(world.push(block, Direction::South).unwrap());
+        assert!(!world.blocks().contains_key(block));
+        assert_eq!(world.position(alias), Position::inside(alias, (1, 0)));
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(wall, Position::inside(container, (2, 0)));
+
+        assert!(world.push(block, Direction::East).unwrap());
+        assert!(!world.push(block, Direction::East).unwrap());
+        assert!(world.push(block, Direction::West).unwrap());
+
+        assert_eq!(world.turn(), 2);
+        assert_eq!(world.history().len(), 2);
+        assert_eq!(
+            world.moves_since(1),
+            [crate::Move {
+                turn: 2,
+                key: block,
+                from: Position::inside(container, (1, 0)),
+                to: Position::inside(container, (0, 0)),
+            }]
+        );
+        assert!(world.moves_since(2).is_empty());
+    }
+
+    #[test]
+    fn test_orientation() {
+        let mut world = World::new();
+        world.set_orientation(Orientation::YDown);
+        let root = world.insert(ProtoType::Box { size: (1, 1) });
+        let container = world.insert(ProtoType::Box { size: (1, 3) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let target = world.insert(ProtoType::Box { size: (1, 2) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(container, Position::inside(root, (0, 0)));
+        world.place(block, Position::inside(container, (0, 2)));
+        world.place(target, Position::inside(container, (0, 1)));
+        world.place(wall, Position::inside(container, (0, 0)));
+
+        // North is `-y`, so the block enters the target from its bottom, i.e.
+        // the largest y.
+        assert!(world.push(block, Direction::North).unwrap());
+        assert_eq!(world.position(block), Position::inside(target, (0, 1)));
+
+        let hit = world.raycast(Position::inside(container, (0, 2)), Direction::North);
+        assert_eq!(hit.block, Some(target));
+    }
+
+    #[test]
+    fn test_orphan_policy() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        world.set_orphan_policy(OrphanPolicy::Stay);
+        assert!(!world.push(block, Direction::East).unwrap());
+        assert_eq!(world.position(block), Position::inside(container, (0, 0)));
+
+        let mut despawned = world.clone();
+        despawned.set_orphan_policy(OrphanPolicy::Despawn);
+        assert!(despawned.push(block, Direction::East).unwrap());
+        assert!(!despawned.blocks().contains_key(block));
+        assert!(despawned[container].state.interior[0][0].is_none());
+        assert!(despawned
+            .history()
+            .iter()
+            .all(|movement| movement.key != block));
+        assert!(despawned.validate().is_empty());
+
+        world.set_orphan_policy(OrphanPolicy::Promote);
+        assert!(world.push(block, Direction::East).unwrap());
+        let root = world.root().unwrap();
+        assert_eq!(world[root].proto, ProtoType::Room { size: (2, 1) });
+        assert_eq!(world.position(container), Position::inside(root, (0, 0)));
+        assert_eq!(world.position(block), Position::inside(root, (1, 0)));
+    }
+
+    #[test]
+    fn test_rule_config() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (1, 1) });
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(container, Position::inside(root, (0, 0)));
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+        world.place(wall, Position::inside(container, (2, 0)));
+
+        let mut sokoban = world.clone();
+        sokoban.set_rule_config(RuleConfig::sokoban());
+        assert!(!sokoban.push(player, Direction::East).unwrap());
+        assert!(!sokoban.push(player, Direction::North).unwrap());
+
+        let mut eating = world.clone();
+        eating.set_rule_config(RuleConfig {
+            enter: false,
+            ..RuleConfig::default()
+        });
+        assert!(eating.push(player, Direction::East).unwrap());
+        assert_eq!(eating.position(block), Position::inside(player, (0, 0)));
+
+        assert!(world.push(player, Direction::East).unwrap());
+        assert_eq!(world.position(player), Position::inside(block, (0, 0)));
+    }
+
+    #[test]
+    fn test_entry_policy() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (3, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let target = world.insert(ProtoType::Box { size: (3, 3) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(player, Position::inside(root, (0, 0)));
+        world.place(target, Position::inside(root, (1, 0)));
+        world.place(wall, Position::inside(root, (2, 0)));
+
+        for (entry, y) in [
+            (EntryPolicy::Proportional, 1),
+            (EntryPolicy::Center, 1),
+            (EntryPolicy::Edge, 0),
+        ] {
+            let mut world = world.clone();
+            world.set_rule_config(RuleConfig {
+                entry,
+                ..RuleConfig::default()
+            });
+
+            assert!(world.push(player, Direction::East).unwrap());
+            assert_eq!(world.position(player), Position::inside(target, (0, y)));
+        }
+    }
+
+    #[test]
+    fn test_tie_break() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (3, 1) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let target = world.insert(ProtoType::Box { size: (2, 2) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(player, Position::inside(root, (0, 0)));
+        world.place(target, Position::inside(root, (1, 0)));
+        world.place(wall, Position::inside(root, (2, 0)));
+
+        for (tie_break, y) in [(TieBreak::Upper, 1), (TieBreak::Lower, 0)] {
+            let mut world = world.clone();
+            world.set_rule_config(RuleConfig {
+                tie_break,
+                ..RuleConfig::default()
+            });
+
+            assert!(world.push(player, Direction::East).unwrap());
+            assert_eq!(world.position(player), Position::inside(target, (0, y)));
+        }
+    }
+
+    #[test]
+    fn test_orphan_error_context() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let error = world.push(block, Direction::East).unwrap_err();
+
+        assert!(matches!(error, MoveError::Orphan(key, _) if key == container));
+        assert_eq!(error.context().origin, block);
+        assert_eq!(error.context().direction, Direction::East);
+        assert_eq!(
+            error.context().position,
+            Position::inside(container, (0, 0))
+        );
     }
 }
@@ -1,27 +1,71 @@
+use super::config::{EntryPolicy, TieBreak};
 use super::rational::Rational;
 use crate::{BlockKey, Position, ProtoType, World};
 use parabox_macros::trace_func;
-use std::fmt::Debug;
+use std::fmt::{Debug, Display};
+use std::ops::Mul;
+use std::str::FromStr;
 use tracing::instrument;
 
 /// The direction of a movement.
+///
+/// Besides the four planar directions, a block moves [Direction::Up] or
+/// [Direction::Down] across the layers of a stacked block, see
+/// [World::stack].
 #[allow(missing_docs)]
-#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
 pub enum Direction {
     North,
     South,
     East,
     West,
+    Up,
+    Down,
+}
+
+/// The orientation of the y-axis, i.e. the meaning of [Direction::North].
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum Orientation {
+    /// The y-axis grows upward, so north is `+y`.
+    #[default]
+    YUp,
+    /// The y-axis grows downward, as in most screen-space coordinates, so
+    /// north is `-y`.
+    YDown,
 }
 
 impl Direction {
-    /// The corresponding vector of the direction.
+    /// All the planar directions, in the order north, south, east, west.
+    pub const ALL: [Direction; 4] = [
+        Direction::North,
+        Direction::South,
+        Direction::East,
+        Direction::West,
+    ];
+
+    /// The depth directions across the layers of a stack, in the order up,
+    /// down.
+    pub const DEPTH: [Direction; 2] = [Direction::Up, Direction::Down];
+
+    /// The corresponding vector of the direction, with [Orientation::YUp].
     pub fn delta(self) -> (isize, isize) {
-        match self {
+        self.delta_for(Orientation::YUp)
+    }
+
+    /// The corresponding vector of the direction with the orientation, which
+    /// is zero for the depth directions.
+    pub fn delta_for(self, orientation: Orientation) -> (isize, isize) {
+        let (dx, dy) = match self {
             Direction::North => (0, 1),
             Direction::South => (0, -1),
             Direction::East => (1, 0),
             Direction::West => (-1, 0),
+            Direction::Up | Direction::Down => (0, 0),
+        };
+
+        match orientation {
+            Orientation::YUp => (dx, dy),
+            Orientation::YDown => (dx, -dy),
         }
     }
 
@@ -32,6 +76,97 @@ impl Direction {
             Direction::South => Direction::North,
             Direction::East => Direction::West,
             Direction::West => Direction::East,
+            Direction::Up => Direction::Down,
+            Direction::Down => Direction::Up,
+        }
+    }
+
+    /// The direction rotated clockwise by a quarter turn, which keeps the
+    /// depth directions.
+    pub fn rotate_cw(self) -> Direction {
+        match self {
+            Direction::North => Direction::East,
+            Direction::East => Direction::South,
+            Direction::South => Direction::West,
+            Direction::West => Direction::North,
+            Direction::Up | Direction::Down => self,
+        }
+    }
+
+    /// The direction rotated counterclockwise by a quarter turn, which keeps
+    /// the depth directions.
+    pub fn rotate_ccw(self) -> Direction {
+        match self {
+            Direction::Up | Direction::Down => self,
+            _ => self.rotate_cw().opposite(),
+        }
+    }
+
+    /// Returns whether the direction is east or west.
+    pub fn is_horizontal(self) -> bool {
+        matches!(self, Direction::East | Direction::West)
+    }
+
+    /// Returns whether the direction is north or south.
+    pub fn is_vertical(self) -> bool {
+        matches!(self, Direction::North | Direction::South)
+    }
+
+    /// Returns whether the direction is up or down.
+    pub fn is_depth(self) -> bool {
+        matches!(self, Direction::Up | Direction::Down)
+    }
+
+    /// The lowercase name of the direction, e.g. `"north"`, which parses
+    /// back into the direction.
+    pub fn name(self) -> &'static str {
+        match self {
+            Direction::North => "north",
+            Direction::South => "south",
+            Direction::East => "east",
+            Direction::West => "west",
+            Direction::Up => "up",
+            Direction::Down => "down",
+        }
+    }
+}
+
+/// Scales the [Direction::delta] of the direction.
+impl Mul<usize> for Direction {
+    type Output = (isize, isize);
+
+    fn mul(self, rhs: usize) -> Self::Output {
+        let (dx, dy) = self.delta();
+        (dx * rhs as isize, dy * rhs as isize)
+    }
+}
+
+/// An error when parsing a [Direction].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParseDirectionError(String);
+
+impl Display for ParseDirectionError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "invalid direction {:?}", self.0)
+    }
+}
+
+impl std::error::Error for ParseDirectionError {}
+
+/// Parses a direction from its name or initial, case-insensitively, e.g.
+/// `"north"`, `"North"` or `"N"`, and `"up"` or `"U"`.
+impl FromStr for Direction {
+    type Err = ParseDirectionError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "north" | "n" => Ok(Direction::North),
+            "south" | "s" => Ok(Direction::South),
+            "east" | "e" => Ok(Direction::East),
+            "west" | "w" => Ok(Direction::West),
+            "up" | "u" => Ok(Direction::Up),
+            "down" | "d" => Ok(Direction::Down),
+            _ => Err(ParseDirectionError(s.to_string())),
         }
     }
 }
@@ -64,7 +199,7 @@ impl Debug for SourceArrow {
 }
 
 /// An arrow into the target.
-#[derive(Copy, Clone, Eq, PartialEq)]
+#[derive(Copy, Clone, Eq, PartialEq, Hash)]
 pub(crate) struct TargetArrow {
     pub position: Position,
     pub direction: Direction,
@@ -130,6 +265,21 @@ impl Debug for Movement {
     }
 }
 
+/// The context in which a [MoveError] occurs.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct MoveContext {
+    /// The block originally pushed, i.e. the block passed to
+    /// [World::push](crate::World::push).
+    pub origin: BlockKey,
+    /// The direction of the push.
+    pub direction: Direction,
+    /// The position where the resolution failed.
+    ///
+    /// For exits, this is the position inside the block being exited. For
+    /// enterings, this is the position of the block being entered.
+    pub position: Position,
+}
+
 /// The errors that may occur during a movement.
 ///
 /// This is due to the lazy creation of [ProtoType::Infinity],
@@ -139,41 +289,146 @@ impl Debug for Movement {
 ///
 /// When an exit occurs in an orphan, infinite exit or entering occurs on a
 /// block without an infinity or epsilon reference, this error will be returned.
+///
+/// Each variant carries the offending block and the [MoveContext] of the push.
 #[derive(Debug)]
 pub enum MoveError {
     /// Trying to exit the orphan.
-    Orphan(BlockKey),
+    Orphan(BlockKey, MoveContext),
     /// Trying to resolve the infinite exit from the block.
-    NoInfinity(BlockKey),
+    NoInfinity(BlockKey, MoveContext),
     /// Trying to resolve the infinite entering to the block.
-    NoEpsilon(BlockKey),
+    NoEpsilon(BlockKey, MoveContext),
+    /// Exceeding the limit of the push while evaluating the movement of the
+    /// block, see [World::push_limited].
+    Exhausted(BlockKey, MoveContext),
+}
+
+impl MoveError {
+    /// Returns the offending block.
+    pub fn key(&self) -> BlockKey {
+        match self {
+            MoveError::Orphan(key, _) => *key,
+            MoveError::NoInfinity(key, _) => *key,
+            MoveError::NoEpsilon(key, _) => *key,
+            MoveError::Exhausted(key, _) => *key,
+        }
+    }
+
+    /// Returns the context of the error.
+    pub fn context(&self) -> MoveContext {
+        match self {
+            MoveError::Orphan(_, context) => *context,
+            MoveError::NoInfinity(_, context) => *context,
+            MoveError::NoEpsilon(_, context) => *context,
+            MoveError::Exhausted(_, context) => *context,
+        }
+    }
+
+    /// Returns a [Display] of the error naming the blocks by their labels in
+    /// the world, see [World::set_label]. Blocks without a label are named by
+    /// their keys, as in the [Display] of the error itself.
+    pub fn display<'a>(&'a self, world: &'a World) -> impl Display + 'a {
+        DisplayMoveError {
+            error: self,
+            world: Some(world),
+        }
+    }
+}
+
+struct DisplayMoveError<'a> {
+    error: &'a MoveError,
+    world: Option<&'a World>,
+}
+
+impl DisplayMoveError<'_> {
+    fn name(&self, key: BlockKey) -> String {
+        let label = self
+            .world
+            .filter(|world| world.blocks.contains_key(key))
+            .and_then(|world| world.label(key));
+        match label {
+            Some(label) => format!("{:?}", label),
+            None => format!("{:?}", key),
+        }
+    }
+}
+
+impl Display for DisplayMoveError<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = self.name(self.error.key());
+        match self.error {
+            MoveError::Orphan(..) => write!(f, "cannot exit the orphan {}", name)?,
+            MoveError::NoInfinity(..) => write!(f, "no infinity refers to {}", name)?,
+            MoveError::NoEpsilon(..) => write!(f, "no epsilon refers to {}", name)?,
+            MoveError::Exhausted(..) => write!(f, "exceeded the push limit at {}", name)?,
+        }
+
+        let context = self.error.context();
+        write!(
+            f,
+            " (pushing {} {:?}, failed at {:?})",
+            self.name(context.origin),
+            context.direction,
+            context.position
+        )
+    }
+}
+
+impl Display for MoveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let display = DisplayMoveError {
+            error: self,
+            world: None,
+        };
+        Display::fmt(&display, f)
+    }
+}
+
+impl std::error::Error for MoveError {}
+
+/// What happens when a block exits an orphan, see [World::set_orphan_policy].
+#[derive(Copy, Clone, Eq, PartialEq, Default, Debug)]
+pub enum OrphanPolicy {
+    /// Return [MoveError::Orphan].
+    #[default]
+    Error,
+    /// The exit is blocked, leaving the block in place.
+    Stay,
+    /// The exiting block is removed from the world.
+    Despawn,
+    /// The orphan is promoted into the root of the world, an unbounded
+    /// container growing on demand, and the push is retried, see
+    /// [World::root]. The promotion is discarded if the push fails. Orphans
+    /// exited [Direction::Up] or [Direction::Down] stay in place instead.
+    Promote,
 }
 
 /// The result of a movement, just an alias of [Result] with [MoveError].
 pub type MoveResult<T> = Result<T, MoveError>;
 
 pub trait IntoMoveResult<T> {
-    fn orphan(self, key: BlockKey) -> MoveResult<T>;
+    fn orphan(self, key: BlockKey, context: MoveContext) -> MoveResult<T>;
 
-    fn no_infinity(self, key: BlockKey) -> MoveResult<T>;
+    fn no_infinity(self, key: BlockKey, context: MoveContext) -> MoveResult<T>;
 
-    fn no_epsilon(self, key: BlockKey) -> MoveResult<T>;
+    fn no_epsilon(self, key: BlockKey, context: MoveContext) -> MoveResult<T>;
 }
 
 impl<T> IntoMoveResult<T> for Option<T> {
     #[inline]
-    fn orphan(self, key: BlockKey) -> MoveResult<T> {
-        self.ok_or(MoveError::Orphan(key))
+    fn orphan(self, key: BlockKey, context: MoveContext) -> MoveResult<T> {
+        self.ok_or(MoveError::Orphan(key, context))
     }
 
     #[inline]
-    fn no_infinity(self, key: BlockKey) -> MoveResult<T> {
-        self.ok_or(MoveError::NoInfinity(key))
+    fn no_infinity(self, key: BlockKey, context: MoveContext) -> MoveResult<T> {
+        self.ok_or(MoveError::NoInfinity(key, context))
     }
 
     #[inline]
-    fn no_epsilon(self, key: BlockKey) -> MoveResult<T> {
-        self.ok_or(MoveError::NoEpsilon(key))
+    fn no_epsilon(self, key: BlockKey, context: MoveContext) -> MoveResult<T> {
+        self.ok_or(MoveError::NoEpsilon(key, context))
     }
 }
 
@@ -182,17 +437,44 @@ pub struct MoveProcessor;
 impl MoveProcessor {
     #[trace_func]
     #[instrument(skip(self, world))]
-    pub fn exit(&self, world: &World, info: ExitInfo) -> MoveResult<SourceArrow> {
-        let position = info.from.get(world).state.position;
-        position.container.orphan(info.from)?;
-        Ok(SourceArrow::new(position, info.direction, info.precise))
+    pub fn exit(&self, world: &World, info: ExitInfo) -> Option<SourceArrow> {
+        // Exit the upper layers of a stack through its bottom layer.
+        let block = world.stack_base(info.from).get(world);
+
+        // Exit through the two-way alias with the smallest id, if any, or
+        // fall back to the container.
+        let position = block
+            .info
+            .references
+            .iter()
+            .map(|alias| alias.get(world))
+            .filter(|alias| alias.info.two_way && !alias.state.position.is_orphan())
+            .min_by_key(|alias| alias.id)
+            .map_or(block.state.position, |alias| alias.state.position);
+        if position.is_orphan() {
+            return None;
+        }
+        Some(SourceArrow::new(position, info.direction, info.precise))
     }
 
+    /// Returns whether the exit crosses a side wall of the exited block.
     #[trace_func]
     #[instrument(skip(self, world))]
-    pub fn infinity(&self, world: &World, info: ExitInfo) -> MoveResult<ExitInfo> {
-        Ok(ExitInfo {
-            from: info.from.get(world).info.infinity.no_infinity(info.from)?,
+    pub fn exit_blocked(&self, world: &World, info: ExitInfo) -> bool {
+        // The side walls of a stack are the ones of its bottom layer.
+        world
+            .stack_base(info.from)
+            .get(world)
+            .info
+            .side_walls
+            .contains(&info.direction)
+    }
+
+    #[trace_func]
+    #[instrument(skip(self, world))]
+    pub fn infinity(&self, world: &World, info: ExitInfo) -> Option<ExitInfo> {
+        Some(ExitInfo {
+            from: info.from.get(world).info.infinity?,
             direction: info.direction,
             precise: info.precise,
         })
@@ -215,32 +497,82 @@ impl MoveProcessor {
         }
     }
 
+    /// Resolves the target arrow of the block `key` entering the block, or
+    /// `None` if the block is solid, the entered side is a wall, or the entry
+    /// filter rejects `key`.
+    ///
+    /// The entered cell is determined by [EntryPolicy], and ties on cell
+    /// boundaries by [TieBreak].
     #[trace_func]
     #[instrument(skip(self, world))]
-    pub fn enter(&self, world: &World, info: EnterInfo) -> Option<TargetArrow> {
+    pub fn enter(&self, world: &World, key: BlockKey, info: EnterInfo) -> Option<TargetArrow> {
         let container = info.into.get(world);
 
         if container.proto.is_solid() {
             return None;
         }
 
-        let tangent = match info.direction {
-            Direction::North => 0,
-            Direction::South => container.proto.height() - 1,
-            Direction::East => 0,
-            Direction::West => container.proto.width() - 1,
+        // Forbid entering through a side wall.
+        if container
+            .info
+            .side_walls
+            .contains(&info.direction.opposite())
+        {
+            return None;
+        }
+
+        // Forbid entering blocks rejected by the entry filter.
+        if !world.accepts(info.into, key) {
+            return None;
+        }
+
+        // Enter the center of the bottom layer of a stack when moving up, or
+        // of its top layer when moving down.
+        if info.direction.is_depth() {
+            let layers = world.layers(info.into);
+            let layer = match info.direction {
+                Direction::Up => layers.first(),
+                _ => layers.last(),
+            };
+            let (width, height) = container.proto.size();
+            return Some(TargetArrow::new(
+                Position::inside(layer.copied().unwrap_or(info.into), (width / 2, height / 2)),
+                info.direction,
+                Rational::HALF,
+            ));
+        }
+
+        // Enter from the side opposite to the direction.
+        let tangent = match info.direction.delta_for(world.orientation()) {
+            (0, 1) => 0,
+            (0, _) => container.proto.height() - 1,
+            (1, _) => 0,
+            _ => container.proto.width() - 1,
         };
 
-        let normal = match info.direction {
-            Direction::North | Direction::South => container.proto.width(),
-            Direction::East | Direction::West => container.proto.height(),
+        let normal = match info.direction.is_vertical() {
+            true => container.proto.width(),
+            false => container.proto.height(),
         };
 
-        let (offset, precise) = (info.precise * normal).split();
+        let (offset, precise) = match world.rule_config().entry {
+            EntryPolicy::Proportional => {
+                let (offset, precise) = (info.precise * normal).split();
+
+                // Break the tie when hitting exactly the boundary between two cells.
+                let tie = offset > 0 && precise.numerator == 0;
+                match world.rule_config().tie_break {
+                    TieBreak::Lower if tie => (offset - 1, Rational::HALF),
+                    _ => (offset, precise),
+                }
+            }
+            EntryPolicy::Center => (normal / 2, Rational::HALF),
+            EntryPolicy::Edge => (0, Rational::HALF),
+        };
 
-        let pos = match info.direction {
-            Direction::North | Direction::South => (offset, tangent),
-            Direction::East | Direction::West => (tangent, offset),
+        let pos = match info.direction.is_vertical() {
+            true => (offset, tangent),
+            false => (tangent, offset),
         };
 
         Some(TargetArrow::new(
@@ -252,9 +584,9 @@ impl MoveProcessor {
 
     #[trace_func]
     #[instrument(skip(self, world))]
-    pub fn epsilon(&self, world: &World, info: EnterInfo) -> MoveResult<EnterInfo> {
-        Ok(EnterInfo {
-            into: info.into.get(world).info.epsilon.no_epsilon(info.into)?,
+    pub fn epsilon(&self, world: &World, info: EnterInfo) -> Option<EnterInfo> {
+        Some(EnterInfo {
+            into: info.into.get(world).info.epsilon?,
             direction: info.direction,
             precise: info.precise,
         })
@@ -270,3 +602,28 @@ impl MoveProcessor {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_direction() {
+        for direction in Direction::ALL {
+            assert_eq!(direction.rotate_cw().rotate_ccw(), direction);
+            assert_eq!(direction.rotate_cw().rotate_cw(), direction.opposite());
+            assert_ne!(direction.is_horizontal(), direction.is_vertical());
+            assert_eq!(format!("{:?}", direction).parse(), Ok(direction));
+        }
+
+        assert_eq!("w".parse(), Ok(Direction::West));
+        assert_eq!("up".parse(), Ok(Direction::Up));
+        assert!("forward".parse::<Direction>().is_err());
+        for direction in Direction::DEPTH {
+            assert_eq!(direction.rotate_cw(), direction);
+            assert_eq!(direction.opposite().opposite(), direction);
+            assert_eq!(direction.delta(), (0, 0));
+        }
+        assert_eq!(Direction::South * 3, (0, -3));
+    }
+}
@@ -151,7 +151,7 @@ impl PartialEq<usize> for Rational {
 
 impl PartialOrd for Rational {
     fn partial_cmp(&self, other: &Self) -> Option<std::cmp::Ordering> {
-        Some((self.numerator * other.denominator).cmp(&(other.numerator * self.denominator)))
+        Some(self.cmp(other))
     }
 }
 
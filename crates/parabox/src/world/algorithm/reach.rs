@@ -0,0 +1,230 @@
+use super::algorithm::Algorithm;
+use crate::{BlockKey, Direction, MoveResult, Position, World};
+use std::collections::{HashMap, HashSet, VecDeque};
+
+/// How other blocks are treated by [World::reachable_positions].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum ReachMode {
+    /// Other blocks never move. Pushes that would move other blocks are
+    /// considered blocked.
+    Static,
+    /// Other blocks move as they are pushed by the block.
+    ///
+    /// This explores every reachable state of the world, which can be
+    /// expensive for large worlds.
+    Dynamic,
+}
+
+/// The report of [World::push_toward].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct TowardReport {
+    /// The directions of the executed pushes.
+    pub directions: Vec<Direction>,
+    /// The final position of the block, i.e. where it got stuck if the
+    /// target is not reached.
+    pub position: Position,
+    /// Whether the block reached the target.
+    pub reached: bool,
+}
+
+impl World {
+    /// Returns all the positions the block can reach by any sequence of its
+    /// own pushes, including its current position.
+    ///
+    /// Pushes resulting in a [MoveError](crate::MoveError) are considered
+    /// blocked.
+    pub fn reachable_positions(&self, key: BlockKey, mode: ReachMode) -> HashSet<Position> {
+        match mode {
+            ReachMode::Static => self.reachable_static(key),
+            ReachMode::Dynamic => self.reachable_dynamic(key),
+        }
+    }
+
+    fn reachable_static(&self, key: BlockKey) -> HashSet<Position> {
+        self.search_static(key, |_| true).into_keys().collect()
+    }
+
+    /// Searches the positions the block can reach when other blocks never
+    /// move, only entering positions accepted by the filter.
+    ///
+    /// Returns the reached positions, each mapped to its previous position and
+    /// the direction of the push from there.
+    fn search_static(
+        &self,
+        key: BlockKey,
+        filter: impl Fn(Position) -> bool,
+    ) -> HashMap<Position, Option<(Position, Direction)>> {
+        let mut world = self.clone();
+        let mut reached = HashMap::from([(self.position(key), None)]);
+        let mut queue = VecDeque::from([self.position(key)]);
+
+        while let Some(position) = queue.pop_front() {
+            world.place(key, position);
+
+            for direction in Direction::ALL {
+                let mut algorithm = Algorithm::new(key);
+                if !algorithm.push(&world, key, direction).unwrap_or(false) {
+                    continue;
+                }
+
+                // Only the block itself may move.
+                let &[movement] = algorithm.movements() else {
+                    continue;
+                };
+                if movement.key != key
+                    || !filter(movement.target)
+                    || reached.contains_key(&movement.target)
+                {
+                    continue;
+                }
+
+                reached.insert(movement.target, Some((position, direction)));
+                queue.push_back(movement.target);
+            }
+        }
+
+        reached
+    }
+
+    /// Moves the block toward the target position inside its container.
+    ///
+    /// The path is computed treating other blocks as static, see
+    /// [ReachMode::Static]. If the target cannot be reached, the block moves
+    /// to the reachable position closest to the target instead. The pushes
+    /// stop as soon as the block does not move as planned.
+    pub fn push_toward(&mut self, key: BlockKey, target: Position) -> MoveResult<TowardReport> {
+        let container = self.position(key).container;
+        let reached = self.search_static(key, |position| position.container == container);
+
+        // Find the closest reachable position, preferring shorter paths.
+        let distance = |position: Position| {
+            let ((x, y), (tx, ty)) = (position.pos, target.pos);
+            x.abs_diff(tx) + y.abs_diff(ty)
+        };
+        let steps = |mut position: Position| {
+            let mut steps = 0;
+            while let Some((previous, _)) = reached[&position] {
+                (position, steps) = (previous, steps + 1);
+            }
+            steps
+        };
+        let closest = if target.container == container {
+            reached
+                .keys()
+                .copied()
+                .min_by_key(|&position| (distance(position), steps(position), position.pos))
+                .unwrap()
+        } else {
+            self.position(key)
+        };
+
+        // Trace the path back to the current position.
+        let mut path = Vec::new();
+        let mut current = closest;
+        while let Some((previous, direction)) = reached[&current] {
+            path.push((direction, current));
+            current = previous;
+        }
+
+        let mut directions = Vec::new();
+        for (direction, expected) in path.into_iter().rev() {
+            self.push(key, direction)?;
+            directions.push(direction);
+            if self.position(key) != expected {
+                break;
+            }
+        }
+
+        Ok(TowardReport {
+            directions,
+            position: self.position(key),
+            reached: self.position(key) == target,
+        })
+    }
+
+    fn reachable_dynamic(&self, key: BlockKey) -> HashSet<Position> {
+        let fingerprint = |world: &World| -> Vec<Position> {
+            world
+                .blocks_ordered()
+                .map(|(_, block)| block.state.position)
+                .collect()
+        };
+
+        let mut reached = HashSet::from([self.position(key)]);
+        let mut visited = HashSet::from([fingerprint(self)]);
+        let mut queue = VecDeque::from([self.clone()]);
+
+        while let Some(world) = queue.pop_front() {
+            for direction in Direction::ALL {
+                let mut next = world.clone();
+                if !next.push(key, direction).unwrap_or(false) {
+                    continue;
+                }
+
+                if visited.insert(fingerprint(&next)) {
+                    reached.insert(next.position(key));
+                    queue.push_back(next);
+                }
+            }
+        }
+
+        reached
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProtoType;
+
+    #[test]
+    fn test_reachable_positions() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let root = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(container, Position::inside(root, (0, 0)));
+
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        for key in [player, block] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, (0, 0)));
+        }
+        world.place(player, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (2, 0)));
+
+        let inside = |x| Position::inside(container, (x, 0));
+        assert_eq!(
+            world.reachable_positions(player, ReachMode::Static),
+            HashSet::from([inside(0), inside(1)])
+        );
+        assert_eq!(
+            world.reachable_positions(player, ReachMode::Dynamic),
+            HashSet::from([inside(0), inside(1), inside(2)])
+        );
+    }
+
+    #[test]
+    fn test_push_toward() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 3) });
+        let wall = world.insert(ProtoType::Wall);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(wall, Position::inside(container, (1, 1)));
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let report = world
+            .push_toward(block, Position::inside(container, (2, 2)))
+            .unwrap();
+        assert!(report.reached);
+        assert_eq!(report.directions.len(), 4);
+        assert_eq!(world.position(block), Position::inside(container, (2, 2)));
+
+        let report = world
+            .push_toward(block, Position::inside(container, (1, 1)))
+            .unwrap();
+        assert!(!report.reached);
+        assert_eq!(report.directions, vec![Direction::West]);
+        assert_eq!(report.position, world.position(block));
+    }
+}
@@ -0,0 +1,75 @@
+use super::world::World;
+use crate::{BlockKey, Direction, MoveResult};
+use std::num::NonZeroUsize;
+use std::thread;
+
+impl World {
+    /// Pushes a block in each of the worlds in the same direction, returning
+    /// the results of the pushes in the order of the worlds.
+    ///
+    /// The block to push is selected by `select` for each world. If
+    /// `parallel` is `true`, the worlds are split into chunks stepped on
+    /// separate threads, one per available CPU.
+    pub fn step_batch<F>(
+        worlds: &mut [World],
+        select: F,
+        direction: Direction,
+        parallel: bool,
+    ) -> Vec<MoveResult<bool>>
+    where
+        F: Fn(&World) -> BlockKey + Sync,
+    {
+        let step = |world: &mut World| {
+            let key = select(world);
+            world.push(key, direction)
+        };
+
+        let threads = thread::available_parallelism().map_or(1, NonZeroUsize::get);
+        if !parallel || threads == 1 || worlds.len() <= 1 {
+            return worlds.iter_mut().map(step).collect();
+        }
+
+        let chunk = worlds.len().div_ceil(threads);
+        thread::scope(|scope| {
+            let handles: Vec<_> = worlds
+                .chunks_mut(chunk)
+                .map(|worlds| scope.spawn(|| worlds.iter_mut().map(step).collect::<Vec<_>>()))
+                .collect();
+
+            handles
+                .into_iter()
+                .flat_map(|handle| handle.join().unwrap())
+                .collect()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Direction, Position, ProtoType, World};
+
+    #[test]
+    fn test_step_batch() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (0, 0)));
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (1, 0)));
+
+        let mut worlds = vec![world; 16];
+        for parallel in [false, true] {
+            let results = World::step_batch(&mut worlds, |_| block, Direction::East, parallel);
+
+            assert_eq!(results.len(), 16);
+            assert!(results.iter().all(|result| matches!(result, Ok(true))));
+            assert!(worlds
+                .iter()
+                .all(|world| world.position(block) == Position::inside(container, (2, 0))));
+
+            for world in &mut worlds {
+                world.place(block, Position::inside(container, (1, 0)));
+            }
+        }
+    }
+}
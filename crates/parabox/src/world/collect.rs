@@ -0,0 +1,79 @@
+use super::world::World;
+use crate::BlockKey;
+use std::collections::HashSet;
+
+impl World {
+    /// Removes the blocks unreachable from the roots, returning the removed
+    /// blocks in insertion order.
+    ///
+    /// A block is reachable if it is a root, or it is inside a reachable
+    /// block, or it is referred to by a reachable block. The infinity and
+    /// epsilon blocks of reachable blocks are also reachable, since they are
+    /// needed to resolve the movements, and so are all the layers of the
+    /// stack of a reachable block.
+    pub fn collect_unreachable(&mut self, roots: &[BlockKey]) -> Vec<BlockKey> {
+        let mut reachable: HashSet<BlockKey> = HashSet::new();
+        let mut stack = roots.to_vec();
+
+        while let Some(key) = stack.pop() {
+            if !reachable.insert(key) {
+                continue;
+            }
+
+            let block = &self.blocks[key];
+            stack.extend(block.state.interior.iter().flatten().flatten());
+            stack.extend(block.proto.reference());
+            stack.extend(block.info.infinity);
+            stack.extend(block.info.epsilon);
+            stack.extend(self.layers(key));
+        }
+
+        let unreachable: Vec<BlockKey> = self
+            .blocks_ordered()
+            .map(|(key, _)| key)
+            .filter(|key| !reachable.contains(key))
+            .collect();
+
+        for &key in &unreachable {
+            // The block may be already removed with the block it refers to.
+            if self.blocks.contains_key(key) {
+                self.remove(key);
+            }
+        }
+
+        unreachable
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Position, ProtoType, World};
+
+    #[test]
+    fn test_collect_unreachable() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (2, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let alias = world.insert(ProtoType::Alias { reference: block });
+        let debris = world.insert(ProtoType::Box { size: (1, 1) });
+        let child = world.insert(ProtoType::Wall);
+        let stray = world.insert(ProtoType::Alias { reference: block });
+        world.place(alias, Position::inside(root, (0, 0)));
+        world.place(child, Position::inside(debris, (0, 0)));
+
+        assert_eq!(
+            world.collect_unreachable(&[root]),
+            vec![debris, child, stray]
+        );
+        assert_eq!(world.blocks().len(), 3);
+        assert!(world[block].info.references.contains(&alias));
+        assert!(!world[block].info.references.contains(&stray));
+        assert_eq!(world.validate(), vec![]);
+
+        // The layers of a reachable stack are kept, even though they are
+        // orphans.
+        let layers = world.stack(block, 1);
+        assert!(world.collect_unreachable(&[root]).is_empty());
+        assert_eq!(world.layers(block), layers);
+    }
+}
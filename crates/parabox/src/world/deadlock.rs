@@ -0,0 +1,200 @@
+use super::query::PositionState;
+use super::world::World;
+use crate::{BlockKey, Direction, Goal, Position, ProtoType};
+use std::collections::HashSet;
+
+/// What is next to a block on one of its sides.
+enum Neighbor {
+    /// A wall, which never moves and never lets blocks through.
+    Wall,
+    /// A frozen block.
+    Frozen(BlockKey),
+    /// The boundary of the container.
+    Boundary,
+    /// Anything else, which may let the block move.
+    Open,
+}
+
+impl World {
+    /// Returns the target blocks of the goal that can never reach their
+    /// targets, in the order of the targets.
+    ///
+    /// See [World::detect_deadlocks_with_players], where no block is a player.
+    pub fn detect_deadlocks(&self, goal: &Goal) -> Vec<BlockKey> {
+        self.detect_deadlocks_with_players(goal, &[])
+    }
+
+    /// Returns the target blocks of the goal that can never reach their
+    /// targets, given that only the players are pushed directly.
+    ///
+    /// A target block is deadlocked if it is removed, static or _frozen_
+    /// outside its target. A block is frozen if no block can ever push it out
+    /// of its cell, e.g. a box in a corner of walls, or a cluster of boxes
+    /// blocking each other. Frozen blocks are only detected when the
+    /// [RuleConfig](crate::RuleConfig) disables enough mechanics for walls and
+    /// boundaries to hold them.
+    ///
+    /// Only pushes are considered, so blocks moved by ticks or triggers may
+    /// be wrongly reported. Linked and fragile blocks are never frozen.
+    pub fn detect_deadlocks_with_players(
+        &self,
+        goal: &Goal,
+        players: &[BlockKey],
+    ) -> Vec<BlockKey> {
+        let frozen = self.frozen_blocks(players);
+
+        goal.targets()
+            .iter()
+            .filter(|&&(key, target)| {
+                let Some(block) = self.blocks.get(key) else {
+                    return true;
+                };
+                block.state.position != target && (block.proto.is_static() || frozen.contains(&key))
+            })
+            .map(|&(key, _)| key)
+            .collect()
+    }
+
+    /// Returns the frozen blocks, as the greatest set of blocks held by walls,
+    /// boundaries and each other.
+    fn frozen_blocks(&self, players: &[BlockKey]) -> HashSet<BlockKey> {
+        let mut frozen: HashSet<_> = self
+            .blocks
+            .iter()
+            .filter(|&(key, block)| {
+                block.state.position.container.is_some()
+                    && !block.proto.is_static()
+                    && !block.info.fragile
+                    && block.info.group.is_none()
+                    && !players.contains(&key)
+            })
+            .map(|(key, _)| key)
+            .collect();
+
+        loop {
+            let thawed: Vec<_> = frozen
+                .iter()
+                .copied()
+                .filter(|&key| !self.is_held(key, &frozen))
+                .collect();
+            if thawed.is_empty() {
+                return frozen;
+            }
+            for key in thawed {
+                frozen.remove(&key);
+            }
+        }
+    }
+
+    /// Returns whether the block can never move, given the frozen blocks.
+    fn is_held(&self, key: BlockKey, frozen: &HashSet<BlockKey>) -> bool {
+        let config = self.rule_config();
+
+        // Whether no block can arrive at the cell of the block from the side,
+        // and push or eat the block.
+        let closed = |direction| match self.neighbor(key, direction, frozen) {
+            Neighbor::Wall => true,
+            Neighbor::Frozen(other) => !config.exit || !self[other].proto.is_hollow(),
+            Neighbor::Boundary => !config.enter,
+            Neighbor::Open => false,
+        };
+        // Whether the block cannot move to the side.
+        let blocked = |direction| match self.neighbor(key, direction, frozen) {
+            Neighbor::Wall => true,
+            Neighbor::Frozen(other) => {
+                !config.eat && (!config.enter || self[other].proto.is_sealed())
+            }
+            Neighbor::Boundary => !config.exit,
+            Neighbor::Open => false,
+        };
+
+        if config.eat && !Direction::ALL.into_iter().all(closed) {
+            return false;
+        }
+        Direction::ALL
+            .into_iter()
+            .all(|direction| blocked(direction) || closed(direction.opposite()))
+    }
+
+    fn neighbor(
+        &self,
+        key: BlockKey,
+        direction: Direction,
+        frozen: &HashSet<BlockKey>,
+    ) -> Neighbor {
+        let position = self.position(key);
+        let (dx, dy) = direction.delta();
+        let (x, y) = (position.pos.0 as isize + dx, position.pos.1 as isize + dy);
+        if x < 0 || y < 0 {
+            return Neighbor::Boundary;
+        }
+
+        match self.position_state(Position::new(position.container, (x as usize, y as usize))) {
+            PositionState::Present(other) if self[other].proto == ProtoType::Wall => Neighbor::Wall,
+            PositionState::Present(other) if frozen.contains(&other) => Neighbor::Frozen(other),
+            PositionState::OutofBound => Neighbor::Boundary,
+            _ => Neighbor::Open,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RuleConfig;
+
+    /// Returns a Sokoban world with a 4x4 container, a wall in its corner and
+    /// a box next to the wall.
+    fn setup() -> (World, BlockKey, BlockKey) {
+        let mut world = World::new();
+        world.set_rule_config(RuleConfig::sokoban());
+        let container = world.insert(ProtoType::Box { size: (4, 4) });
+        let wall = world.insert(ProtoType::Wall);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(wall, Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(container, (1, 0)));
+        (world, container, block)
+    }
+
+    #[test]
+    fn test_corner() {
+        let (world, container, block) = setup();
+        let goal = Goal::new().with_target(block, Position::inside(container, (2, 2)));
+        assert_eq!(world.detect_deadlocks(&goal), [block]);
+
+        let goal = Goal::new().with_target(block, Position::inside(container, (1, 0)));
+        assert!(world.detect_deadlocks(&goal).is_empty());
+
+        // Exiting frees the block.
+        let mut world = world;
+        world.set_rule_config(RuleConfig {
+            exit: true,
+            ..RuleConfig::sokoban()
+        });
+        let goal = Goal::new().with_target(block, Position::inside(container, (2, 2)));
+        assert!(world.detect_deadlocks(&goal).is_empty());
+    }
+
+    #[test]
+    fn test_frozen_cluster() {
+        let (mut world, container, block) = setup();
+        world.place(block, Position::inside(container, (1, 1)));
+        let goal = Goal::new().with_target(block, Position::inside(container, (2, 3)));
+        assert!(world.detect_deadlocks(&goal).is_empty());
+
+        // A square of boxes in the corner holds each other.
+        let mut others = Vec::new();
+        for pos in [(0, 1), (1, 0)] {
+            let other = world.insert(ProtoType::Box { size: (1, 1) });
+            world.place(other, Position::inside(container, pos));
+            others.push(other);
+        }
+        assert_eq!(world.detect_deadlocks(&goal), [block]);
+        assert!(world
+            .detect_deadlocks_with_players(&goal, &others[..1])
+            .is_empty());
+
+        world.remove(block);
+        assert_eq!(world.detect_deadlocks(&goal), [block]);
+    }
+}
@@ -0,0 +1,101 @@
+use super::world::World;
+use crate::{Orientation, ProtoType};
+use std::fmt::{Debug, Display, Formatter};
+
+/// Formats the interiors of the hollow blocks as ASCII grids.
+///
+/// Each block is named by its [BlockId](crate::BlockId), followed by its
+/// label if any. The upper layers of a stack also name their index and the
+/// bottom layer, see [World::stack]. Empty cells are printed as `.`, and the
+/// rows are printed from top to bottom according to the [Orientation] of the
+/// world.
+impl Display for World {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let id_width = self.next_id.saturating_sub(1).to_string().len();
+
+        for (key, block) in self.blocks_ordered() {
+            if !block.proto.is_hollow() {
+                continue;
+            }
+
+            let (width, height) = block.proto.size();
+            let kind = match block.proto {
+                ProtoType::Box { .. } => "box",
+                ProtoType::Epsilon { .. } => "epsilon",
+                ProtoType::Void { .. } => "void",
+                ProtoType::Room { .. } => "room",
+                ProtoType::Sealed { .. } => "sealed",
+                _ => unreachable!("the block is hollow"),
+            };
+
+            write!(f, "{}", block.id.get())?;
+            if let Some(label) = self.label(key) {
+                write!(f, " {:?}", label)?;
+            }
+            write!(f, " ({} {}x{}", kind, width, height)?;
+            let base = self.stack_base(key);
+            if base != key {
+                let index = self.layers(key).iter().position(|&layer| layer == key);
+                write!(f, ", layer {} of {}", index.unwrap(), self[base].id.get())?;
+            }
+            writeln!(f, "):")?;
+
+            let rows: Vec<usize> = match self.orientation {
+                Orientation::YUp => (0..height).rev().collect(),
+                Orientation::YDown => (0..height).collect(),
+            };
+
+            for y in rows {
+                write!(f, " ")?;
+                for column in &block.state.interior {
+                    match column[y] {
+                        Some(child) => write!(f, " {:>id_width$}", self[child].id.get())?,
+                        None => write!(f, " {:>id_width$}", ".")?,
+                    }
+                }
+                writeln!(f)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+impl Debug for World {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        Display::fmt(self, f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Position, ProtoType, World};
+
+    #[test]
+    fn test_display() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 2) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.set_label(container, "root");
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(wall, Position::inside(container, (2, 1)));
+
+        assert_eq!(
+            world.to_string(),
+            "0 \"root\" (box 3x2):\n  . . 2\n  1 . .\n1 (box 1x1):\n  .\n"
+        );
+    }
+
+    #[test]
+    fn test_display_stack() {
+        let mut world = World::new();
+        let base = world.insert(ProtoType::Box { size: (1, 1) });
+        world.stack(base, 1);
+
+        assert_eq!(
+            world.to_string(),
+            "0 (box 1x1):\n  .\n1 (box 1x1, layer 1 of 0):\n  .\n"
+        );
+    }
+}
@@ -0,0 +1,270 @@
+use super::world::World;
+use crate::{BlockKey, ProtoType};
+use std::collections::HashMap;
+
+/// A channel of a grid encoding, see [World::encode_grid].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub enum Channel {
+    /// [ProtoType::Wall] blocks.
+    Wall,
+    /// [ProtoType::Box], [ProtoType::Void], [ProtoType::Room] and
+    /// [ProtoType::Sealed] blocks.
+    Box,
+    /// The player block, see [EncodingOptions::player].
+    Player,
+    /// Blocks referring to other blocks, i.e. [ProtoType::Alias],
+    /// [ProtoType::Infinity] and [ProtoType::Epsilon] blocks.
+    Reference,
+}
+
+impl Channel {
+    /// All the channels, in the order of the encoding.
+    pub const ALL: [Channel; 4] = [
+        Channel::Wall,
+        Channel::Box,
+        Channel::Player,
+        Channel::Reference,
+    ];
+
+    /// Returns the index of the channel in the encoding.
+    pub fn index(self) -> usize {
+        self as usize
+    }
+
+    fn of(proto: ProtoType) -> Channel {
+        match proto {
+            ProtoType::Wall => Channel::Wall,
+            ProtoType::Box { .. }
+            | ProtoType::Void { .. }
+            | ProtoType::Room { .. }
+            | ProtoType::Sealed { .. } => Channel::Box,
+            ProtoType::Alias { .. } | ProtoType::Infinity { .. } | ProtoType::Epsilon { .. } => {
+                Channel::Reference
+            }
+        }
+    }
+}
+
+/// The options of [World::encode_grid].
+#[derive(Copy, Clone, Default, Debug)]
+pub struct EncodingOptions {
+    /// The block encoded in the [Channel::Player] channel instead of its own.
+    pub player: Option<BlockKey>,
+    /// Whether the channel is the innermost dimension, i.e. the layout is
+    /// `[y][x][channel]` instead of `[channel][y][x]`.
+    pub channels_last: bool,
+}
+
+impl World {
+    /// Encodes the interior of the container into channel layers of `0` and
+    /// `1`, one layer per [Channel].
+    ///
+    /// The result has `Channel::ALL.len() * width * height` values. By default
+    /// the layout is `[channel][y][x]`, see [EncodingOptions::channels_last].
+    pub fn encode_grid(&self, key: BlockKey, options: EncodingOptions) -> Vec<u8> {
+        let (width, height) = key.get(self).proto.size();
+        let channels = Channel::ALL.len();
+        let mut grid = vec![0; channels * width * height];
+
+        for (x, column) in key.get(self).state.interior.iter().enumerate() {
+            for (y, cell) in column.iter().enumerate() {
+                let Some(block) = *cell else {
+                    continue;
+                };
+
+                let channel = if options.player == Some(block) {
+                    Channel::Player
+                } else {
+                    Channel::of(block.get(self).proto)
+                };
+
+                let index = if options.channels_last {
+                    (y * width + x) * channels + channel.index()
+                } else {
+                    (channel.index() * height + y) * width + x
+                };
+                grid[index] = 1;
+            }
+        }
+
+        grid
+    }
+
+    /// Encodes the positions of all the blocks into a compact byte string.
+    ///
+    /// The blocks are numbered by their insertion order, so the encoding does
+    /// not depend on the keys or ids of the blocks. Two worlds with the same
+    /// blocks have the same encoding if and only if every block is at the same
+    /// position, which makes it suitable for deduplicating states in solvers.
+    ///
+    /// Each block is encoded as its container number plus one, or `0` for
+    /// orphans, followed by its coordinates if placed, all as LEB128 varints.
+    pub fn encode_state(&self) -> Vec<u8> {
+        let numbers: HashMap<BlockKey, usize> = self
+            .blocks_ordered()
+            .enumerate()
+            .map(|(number, (key, _))| (key, number))
+            .collect();
+
+        let mut bytes = Vec::with_capacity(numbers.len() * 3);
+        for (_, block) in self.blocks_ordered() {
+            let position = block.state.position;
+            match position.container {
+                Some(container) => {
+                    let (x, y) = position.pos;
+                    write_varint(&mut bytes, numbers[&container] + 1);
+                    write_varint(&mut bytes, x);
+                    write_varint(&mut bytes, y);
+                }
+                None => write_varint(&mut bytes, 0),
+            }
+        }
+
+        bytes
+    }
+
+    /// Encodes every state of the world that may change with pushes or ticks,
+    /// and affects the future ones.
+    ///
+    /// This extends [World::encode_state] with the ids and prototypes of the
+    /// blocks, which change when blocks are removed or promoted into the
+    /// root, followed by the root, the number of ticks and the settings of
+    /// the world. Unlike [World::encode_state], worlds with different blocks
+    /// never have the same encoding.
+    pub fn encode_full_state(&self) -> Vec<u8> {
+        let numbers: HashMap<BlockKey, usize> = self
+            .blocks_ordered()
+            .enumerate()
+            .map(|(number, (key, _))| (key, number))
+            .collect();
+
+        let mut bytes = self.encode_state();
+        for (_, block) in self.blocks_ordered() {
+            write_varint(&mut bytes, block.id.get() as usize);
+            let (kind, reference) = match block.proto {
+                ProtoType::Wall => (0, None),
+                ProtoType::Box { .. } => (1, None),
+                ProtoType::Alias { reference } => (2, Some(reference)),
+                ProtoType::Infinity { reference } => (3, Some(reference)),
+                ProtoType::Epsilon { reference, .. } => (4, Some(reference)),
+                ProtoType::Void { .. } => (5, None),
+                ProtoType::Room { .. } => (6, None),
+                ProtoType::Sealed { .. } => (7, None),
+            };
+            bytes.push(kind);
+            if let Some(reference) = reference {
+                write_varint(&mut bytes, numbers[&reference]);
+            }
+            if block.proto.is_hollow() {
+                let (width, height) = block.proto.size();
+                write_varint(&mut bytes, width);
+                write_varint(&mut bytes, height);
+            }
+        }
+
+        write_varint(&mut bytes, self.root().map_or(0, |root| numbers[&root] + 1));
+        write_varint(&mut bytes, self.ticks);
+        let config = self.config;
+        bytes.extend([
+            self.orientation as u8,
+            self.orphan_policy as u8,
+            u8::from(config.enter),
+            u8::from(config.eat),
+            u8::from(config.exit),
+            config.entry as u8,
+            config.tie_break as u8,
+        ]);
+
+        bytes
+    }
+}
+
+/// Writes the value as an unsigned LEB128 varint.
+fn write_varint(bytes: &mut Vec<u8>, mut value: usize) {
+    while value >= 0x80 {
+        bytes.push((value & 0x7f) as u8 | 0x80);
+        value >>= 7;
+    }
+    bytes.push(value as u8);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Position;
+
+    #[test]
+    fn test_encode_state() {
+        let mut world = World::new();
+        let removed = world.insert(ProtoType::Wall);
+        let container = world.insert(ProtoType::Box { size: (200, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.remove(removed);
+        world.place(block, Position::inside(container, (0, 0)));
+
+        assert_eq!(world.encode_state(), vec![0, 1, 0, 0]);
+
+        let forked = world.fork();
+        world.place(block, Position::inside(container, (150, 0)));
+        assert_eq!(world.encode_state(), vec![0, 1, 150, 1, 0]);
+        assert_ne!(world.encode_state(), forked.encode_state());
+
+        world.place(block, Position::inside(container, (0, 0)));
+        assert_eq!(world.encode_state(), forked.encode_state());
+    }
+
+    #[test]
+    fn test_encode_full_state() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        // Removing either orphan gives the same positions, but not the same
+        // blocks.
+        let (mut first, mut second) = (world.fork(), world.fork());
+        first.remove(container);
+        first.remove(block);
+        second.remove(container);
+        second.remove(other);
+        assert_eq!(first.encode_state(), second.encode_state());
+        assert_ne!(first.encode_full_state(), second.encode_full_state());
+
+        let mut ticked = world.fork();
+        ticked.tick().unwrap();
+        assert_eq!(ticked.encode_state(), world.encode_state());
+        assert_ne!(ticked.encode_full_state(), world.encode_full_state());
+        assert_eq!(world.fork().encode_full_state(), world.encode_full_state());
+    }
+
+    #[test]
+    fn test_encode_grid() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 2) });
+        let wall = world.insert(ProtoType::Wall);
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        let alias = world.insert(ProtoType::Alias { reference: player });
+        world.place(wall, Position::inside(container, (0, 0)));
+        world.place(player, Position::inside(container, (1, 0)));
+        world.place(alias, Position::inside(container, (1, 1)));
+
+        let options = EncodingOptions {
+            player: Some(player),
+            channels_last: false,
+        };
+        assert_eq!(
+            world.encode_grid(container, options),
+            [[1, 0, 0, 0], [0, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 1]].concat()
+        );
+
+        let options = EncodingOptions {
+            player: None,
+            channels_last: true,
+        };
+        assert_eq!(
+            world.encode_grid(container, options),
+            [[1, 0, 0, 0], [0, 1, 0, 0], [0, 0, 0, 0], [0, 0, 0, 1]].concat()
+        );
+    }
+}
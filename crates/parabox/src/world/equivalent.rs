@@ -0,0 +1,209 @@
+use super::world::World;
+use crate::{BlockKey, ProtoType};
+use std::collections::HashMap;
+use std::mem::discriminant;
+
+/// A partial bijection between the blocks of two worlds.
+#[derive(Clone, Default)]
+struct Mapping {
+    forward: HashMap<BlockKey, BlockKey>,
+    backward: HashMap<BlockKey, BlockKey>,
+}
+
+impl World {
+    /// Returns whether the two worlds are the same up to renaming the blocks,
+    /// i.e. there is a one-to-one correspondence between their blocks with the
+    /// same prototypes, the same positions and the same stacks, layer by
+    /// layer.
+    ///
+    /// Only the prototypes and positions are compared, not the labels, history
+    /// or other metadata. Connected blocks are matched in one go, so the cost
+    /// mostly depends on the number of similar disconnected structures.
+    pub fn equivalent(&self, other: &World) -> bool {
+        self.blocks.len() == other.blocks.len() && self.extend(other, &mut Mapping::default())
+    }
+
+    /// Extends the mapping to all the blocks, backtracking over the candidates
+    /// of the first unmapped block.
+    fn extend(&self, other: &World, mapping: &mut Mapping) -> bool {
+        let Some((key, block)) = self
+            .blocks_ordered()
+            .find(|(key, _)| !mapping.forward.contains_key(key))
+        else {
+            return true;
+        };
+
+        for (candidate, _) in other.blocks_ordered().filter(|(candidate, other_block)| {
+            !mapping.backward.contains_key(candidate) && same_shape(block.proto, other_block.proto)
+        }) {
+            let mut attempt = mapping.clone();
+            if self.propagate(other, &mut attempt, key, candidate)
+                && self.extend(other, &mut attempt)
+            {
+                *mapping = attempt;
+                return true;
+            }
+        }
+
+        false
+    }
+
+    /// Maps the block to the candidate, along with all the blocks connected to
+    /// them. Returns whether the mapping is consistent.
+    fn propagate(
+        &self,
+        other: &World,
+        mapping: &mut Mapping,
+        key: BlockKey,
+        candidate: BlockKey,
+    ) -> bool {
+        let mut stack = vec![(key, candidate)];
+
+        while let Some((key, candidate)) = stack.pop() {
+            match mapping.forward.get(&key) {
+                Some(&mapped) if mapped == candidate => continue,
+                Some(_) => return false,
+                None if mapping.backward.contains_key(&candidate) => return false,
+                None => {
+                    mapping.forward.insert(key, candidate);
+                    mapping.backward.insert(candidate, key);
+                }
+            }
+
+            let (block, other_block) = (&self[key], &other[candidate]);
+            if !same_shape(block.proto, other_block.proto) {
+                return false;
+            }
+            if let (Some(reference), Some(other_reference)) =
+                (block.proto.reference(), other_block.proto.reference())
+            {
+                stack.push((reference, other_reference));
+            }
+
+            let (position, other_position) = (block.state.position, other_block.state.position);
+            match (position.container, other_position.container) {
+                (None, None) => {}
+                (Some(container), Some(other_container)) if position.pos == other_position.pos => {
+                    stack.push((container, other_container));
+                }
+                _ => return false,
+            }
+
+            let (layers, other_layers) = (self.layers(key), other.layers(candidate));
+            if layers.len() != other_layers.len() {
+                return false;
+            }
+            stack.extend(layers.iter().copied().zip(other_layers.iter().copied()));
+
+            let cells = block.state.interior.iter().flatten();
+            let other_cells = other_block.state.interior.iter().flatten();
+            for (cell, other_cell) in cells.zip(other_cells) {
+                match (cell, other_cell) {
+                    (None, None) => {}
+                    (Some(child), Some(other_child)) => stack.push((*child, *other_child)),
+                    _ => return false,
+                }
+            }
+        }
+
+        true
+    }
+}
+
+/// Returns whether the prototypes are the same, except for the references.
+fn same_shape(proto: ProtoType, other: ProtoType) -> bool {
+    discriminant(&proto) == discriminant(&other) && proto.size() == other.size()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, Position};
+
+    /// Builds a world with a box and a wall in a container, inserting the
+    /// blocks in the given order.
+    fn build(reversed: bool) -> (World, BlockKey, BlockKey) {
+        let mut world = World::new();
+        let (wall, container) = if reversed {
+            let wall = world.insert(ProtoType::Wall);
+            (wall, world.insert(ProtoType::Box { size: (3, 1) }))
+        } else {
+            let container = world.insert(ProtoType::Box { size: (3, 1) });
+            (world.insert(ProtoType::Wall), container)
+        };
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let alias = world.insert(ProtoType::Alias { reference: block });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(wall, Position::inside(container, (2, 0)));
+        world.place(alias, Position::inside(block, (0, 0)));
+        (world, container, block)
+    }
+
+    #[test]
+    fn test_equivalent() {
+        let (mut world, container, block) = build(false);
+        let (other, _, _) = build(true);
+        assert!(world.equivalent(&other));
+        assert!(other.equivalent(&world));
+
+        assert!(world.push(block, Direction::East).unwrap());
+        assert!(!world.equivalent(&other));
+
+        world.place(block, Position::inside(container, (0, 0)));
+        assert!(world.equivalent(&other));
+
+        world.insert(ProtoType::Wall);
+        assert!(!world.equivalent(&other));
+    }
+
+    #[test]
+    fn test_equivalent_self_containing() {
+        let mut world = World::new();
+        let mut other = World::new();
+        for world in [&mut world, &mut other] {
+            let first = world.insert(ProtoType::Box { size: (2, 1) });
+            let second = world.insert(ProtoType::Box { size: (2, 1) });
+            world.place(first, Position::inside(first, (0, 0)));
+            world.place(second, Position::inside(first, (1, 0)));
+        }
+        assert!(world.equivalent(&other));
+
+        // The self-containing block is the second one in `other`.
+        let mut other = World::new();
+        let second = other.insert(ProtoType::Box { size: (2, 1) });
+        let first = other.insert(ProtoType::Box { size: (2, 1) });
+        other.place(first, Position::inside(first, (0, 0)));
+        other.place(second, Position::inside(first, (1, 0)));
+        assert!(world.equivalent(&other));
+
+        other.place(second, Position::orphan());
+        assert!(!world.equivalent(&other));
+    }
+
+    #[test]
+    fn test_equivalent_stack() {
+        let mut world = World::new();
+        let mut other = World::new();
+        for world in [&mut world, &mut other] {
+            let base = world.insert(ProtoType::Box { size: (3, 3) });
+            world.stack(base, 1);
+            world.insert(ProtoType::Box { size: (3, 3) });
+        }
+        assert!(world.equivalent(&other));
+
+        // The same blocks, with the stack on top of the other base.
+        let mut other = World::new();
+        let block = other.insert(ProtoType::Box { size: (3, 3) });
+        let base = other.insert(ProtoType::Box { size: (3, 3) });
+        other.insert(ProtoType::Box { size: (3, 3) });
+        other.join_stack(base, block);
+        assert!(world.equivalent(&other));
+
+        // Stacks of different heights.
+        let mut other = World::new();
+        for _ in 0..3 {
+            other.insert(ProtoType::Box { size: (3, 3) });
+        }
+        assert!(!world.equivalent(&other));
+    }
+}
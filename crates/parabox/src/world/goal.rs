@@ -0,0 +1,53 @@
+use super::world::World;
+use crate::{BlockKey, Position};
+
+/// The goal of a level.
+///
+/// A goal consists of targets, each requiring a block to be at a given
+/// position. The goal is satisfied when all the targets are reached.
+#[derive(Clone, Default, Debug)]
+pub struct Goal {
+    targets: Vec<(BlockKey, Position)>,
+}
+
+impl Goal {
+    /// Creates a new goal without targets.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a target requiring the block to be at the position.
+    pub fn add_target(&mut self, key: BlockKey, position: Position) {
+        self.targets.push((key, position));
+    }
+
+    /// Returns the goal with an additional target, see [Goal::add_target].
+    pub fn with_target(mut self, key: BlockKey, position: Position) -> Self {
+        self.add_target(key, position);
+        self
+    }
+
+    /// Returns the targets of the goal.
+    pub fn targets(&self) -> &[(BlockKey, Position)] {
+        &self.targets
+    }
+
+    /// Returns the number of targets reached in the world. The targets of
+    /// removed blocks are never reached.
+    pub fn reached(&self, world: &World) -> usize {
+        self.targets
+            .iter()
+            .filter(|&&(key, position)| {
+                world
+                    .blocks()
+                    .get(key)
+                    .is_some_and(|block| block.state.position == position)
+            })
+            .count()
+    }
+
+    /// Returns whether all the targets are reached in the world.
+    pub fn is_satisfied(&self, world: &World) -> bool {
+        self.reached(world) == self.targets.len()
+    }
+}
@@ -0,0 +1,183 @@
+use super::world::World;
+use crate::BlockKey;
+
+/// The identifier of a group of linked blocks, see [World::link].
+#[derive(Copy, Clone, Eq, PartialEq, Ord, PartialOrd, Hash, Debug)]
+pub struct GroupId(u64);
+
+impl World {
+    /// Links the blocks into a new group, which moves as a rigid unit.
+    ///
+    /// Whenever a member is pushed, either directly or by another block, the
+    /// other members are pushed in the same direction, in the order given
+    /// here. The push fails if any of the members is blocked. Being eaten does
+    /// not move the other members.
+    ///
+    /// Blocks already in a group are moved out of their old group.
+    pub fn link(&mut self, keys: impl IntoIterator<Item = BlockKey>) -> GroupId {
+        let id = GroupId(self.next_group);
+        self.next_group += 1;
+
+        let mut members = Vec::new();
+        for key in keys {
+            if !members.contains(&key) {
+                self.unlink(key);
+                self.blocks[key].info.group = Some(id);
+                members.push(key);
+            }
+        }
+        self.groups.insert(id, members);
+
+        id
+    }
+
+    /// Removes the block from its group, if any. Returns whether it was in a
+    /// group.
+    pub fn unlink(&mut self, key: BlockKey) -> bool {
+        let Some(id) = self.blocks[key].info.group.take() else {
+            return false;
+        };
+
+        let members = self.groups.get_mut(&id).unwrap();
+        members.retain(|&member| member != key);
+        if members.is_empty() {
+            self.groups.remove(&id);
+        }
+
+        true
+    }
+
+    /// Returns the group of the block, if any.
+    pub fn group(&self, key: BlockKey) -> Option<GroupId> {
+        self.blocks[key].info.group
+    }
+
+    /// Returns the members of the group, which is empty if the group does
+    /// not exist.
+    pub fn members(&self, id: GroupId) -> &[BlockKey] {
+        self.groups.get(&id).map(Vec::as_slice).unwrap_or_default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, OrphanPolicy, Position, ProtoType};
+
+    /// Returns a world with a 4x2 container and two boxes in the first column.
+    fn setup() -> (World, BlockKey, BlockKey, BlockKey) {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 2) });
+        let lower = world.insert(ProtoType::Box { size: (1, 1) });
+        let upper = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(lower, Position::inside(container, (0, 0)));
+        world.place(upper, Position::inside(container, (0, 1)));
+        for key in [lower, upper] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, (0, 0)));
+        }
+        (world, container, lower, upper)
+    }
+
+    #[test]
+    fn test_link() {
+        let (mut world, container, lower, upper) = setup();
+        let group = world.link([lower, upper]);
+        assert_eq!(world.group(upper), Some(group));
+        assert_eq!(world.members(group), [lower, upper]);
+
+        assert!(world.push(lower, Direction::East).unwrap());
+        assert_eq!(world.position(lower), Position::inside(container, (1, 0)));
+        assert_eq!(world.position(upper), Position::inside(container, (1, 1)));
+
+        // Pushing a member by another block also moves the group.
+        let pusher = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(pusher, Position::inside(container, (0, 1)));
+        assert!(world.push(pusher, Direction::East).unwrap());
+        assert_eq!(world.position(lower), Position::inside(container, (2, 0)));
+        assert_eq!(world.position(upper), Position::inside(container, (2, 1)));
+
+        assert!(world.unlink(upper));
+        assert!(!world.unlink(upper));
+        assert_eq!(world.members(group), [lower]);
+    }
+
+    #[test]
+    fn test_link_blocked() {
+        let (mut world, container, lower, upper) = setup();
+        let wall = world.insert(ProtoType::Wall);
+        world.place(wall, Position::inside(container, (1, 1)));
+        world.link([lower, upper]);
+
+        assert!(!world.push(lower, Direction::East).unwrap());
+        assert_eq!(world.position(lower), Position::inside(container, (0, 0)));
+        assert_eq!(world.position(upper), Position::inside(container, (0, 1)));
+
+        // Members in a row push each other.
+        world.remove(wall);
+        world.place(upper, Position::inside(container, (1, 0)));
+        assert!(world.push(lower, Direction::East).unwrap());
+        assert_eq!(world.position(lower), Position::inside(container, (1, 0)));
+        assert_eq!(world.position(upper), Position::inside(container, (2, 0)));
+    }
+
+    #[test]
+    fn test_link_collision() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let outer = world.insert(ProtoType::Box { size: (3, 1) });
+        let inner = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(outer, Position::inside(container, (1, 0)));
+        world.place(inner, Position::inside(outer, (2, 0)));
+        world.link([outer, inner]);
+
+        // Both members would move into the same cell.
+        assert!(!world.push(outer, Direction::East).unwrap());
+        assert_eq!(world.position(outer), Position::inside(container, (1, 0)));
+        assert_eq!(world.position(inner), Position::inside(outer, (2, 0)));
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_link_alias() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let alias = world.insert(ProtoType::Alias {
+            reference: container,
+        });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(alias, Position::inside(container, (1, 0)));
+        world.place(block, Position::inside(container, (2, 0)));
+        world.place(wall, Position::inside(block, (0, 0)));
+        world.link([alias, block]);
+        world.set_orphan_policy(OrphanPolicy::Stay);
+
+        // The members pushing each other do not push each other again
+        // through the group, so the push resolves as without the group.
+        let mut unlinked = world.clone();
+        unlinked.unlink(alias);
+        assert!(world.push(alias, Direction::East).unwrap());
+        assert!(unlinked.push(alias, Direction::East).unwrap());
+        assert_eq!(world.history(), unlinked.history());
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_link_despawn() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 4) });
+        let first = world.insert(ProtoType::Box { size: (1, 1) });
+        let second = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(first, Position::inside(container, (1, 0)));
+        world.place(second, Position::inside(container, (2, 0)));
+        world.link([first, second]);
+        world.set_orphan_policy(OrphanPolicy::Despawn);
+
+        // Both members exit the orphan container and are despawned once.
+        assert!(world.push(first, Direction::South).unwrap());
+        assert!(!world.blocks().contains_key(first));
+        assert!(!world.blocks().contains_key(second));
+        assert!(world.validate().is_empty());
+    }
+}
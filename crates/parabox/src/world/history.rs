@@ -0,0 +1,88 @@
+use super::world::World;
+use crate::{BlockKey, Position};
+
+/// A movement of a block committed by [World::push].
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct Move {
+    /// The turn in which the movement occurred, starting from `1`.
+    pub turn: usize,
+    /// The moved block.
+    pub key: BlockKey,
+    /// The position before the movement.
+    pub from: Position,
+    /// The position after the movement.
+    pub to: Position,
+}
+
+impl World {
+    /// Returns the current turn, i.e. the number of pushes that have moved
+    /// some blocks.
+    pub fn turn(&self) -> usize {
+        self.turn
+    }
+
+    /// Returns all the movements committed in the world, in order.
+    pub fn history(&self) -> &[Move] {
+        &self.history
+    }
+
+    /// Takes the committed movements out of the world, keeping the turn and
+    /// the checksum, e.g. to clone the world without them.
+    pub fn take_history(&mut self) -> Vec<Move> {
+        std::mem::take(&mut self.history)
+    }
+
+    /// Replaces the committed movements of the world, e.g. by the ones taken
+    /// by [World::take_history]. The turn and the checksum are unchanged.
+    pub fn set_history(&mut self, history: Vec<Move>) {
+        self.history = history;
+    }
+
+    /// Returns the movements committed in the turns after `turn`.
+    ///
+    /// For example, `world.moves_since(world.turn().saturating_sub(1))`
+    /// returns the movements of the last turn, or none before the first turn.
+    pub fn moves_since(&self, turn: usize) -> &[Move] {
+        let start = self
+            .history
+            .partition_point(|movement| movement.turn <= turn);
+        &self.history[start..]
+    }
+
+    /// Returns the running checksum of the committed movements.
+    ///
+    /// The checksum is updated by each push that moves some blocks, and only
+    /// depends on the [BlockId](crate::BlockId)s and positions of the moved
+    /// blocks. Replaying the same pushes on clones of a world gives the same
+    /// checksum, unless the engine behaves differently, see
+    /// [Replay::verify_into](crate::Replay::verify_into).
+    pub fn replay_checksum(&self) -> u64 {
+        self.checksum
+    }
+
+    /// Updates the checksum with the movements, which must be committed
+    /// before the moved blocks are removed.
+    pub(crate) fn update_checksum(&mut self, moves: &[Move]) {
+        let id = |world: &World, key: BlockKey| world[key].id.get();
+        let mut values = vec![moves.len() as u64];
+        for movement in moves {
+            values.push(id(self, movement.key));
+            for position in [movement.from, movement.to] {
+                let (x, y) = position.pos;
+                let container = position.container.map_or(u64::MAX, |key| id(self, key));
+                values.extend([container, x as u64, y as u64]);
+            }
+        }
+
+        for value in values {
+            for byte in value.to_le_bytes() {
+                self.checksum = (self.checksum ^ byte as u64).wrapping_mul(FNV_PRIME);
+            }
+        }
+    }
+}
+
+/// The offset basis of the 64-bit FNV-1a hash, i.e. the initial checksum.
+pub(crate) const FNV_OFFSET: u64 = 0xcbf29ce484222325;
+/// The prime of the 64-bit FNV-1a hash.
+const FNV_PRIME: u64 = 0x100000001b3;
@@ -0,0 +1,321 @@
+use super::world::World;
+use crate::{BlockKey, Direction, Position};
+
+impl World {
+    /// Stacks `count` new layers on top of the block, turning it into a
+    /// layered container, and returns all the layers from the bottom up.
+    ///
+    /// The block itself is the bottom layer. The new layers are orphans of
+    /// the same prototype, so they are entered only by pushes in
+    /// [Direction::Up] or [Direction::Down], and blocks exiting them sideways
+    /// emerge from the position of the bottom layer. Stacking an already
+    /// stacked block adds the layers to the top of its stack.
+    ///
+    /// A block pushed up or down moves to the same cell of the adjacent
+    /// layer. Blocks entered up or down are entered at the center cell of
+    /// their bottom or top layer respectively, and a block moving beyond the
+    /// top or the bottom of its stack exits through the bottom layer, which
+    /// also holds the side walls of the whole stack.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the block is not hollow, refers to another block, or is an
+    /// upper layer of a stack.
+    pub fn stack(&mut self, base: BlockKey, count: usize) -> Vec<BlockKey> {
+        let proto = self.blocks[base].proto;
+        assert!(
+            proto.is_hollow() && proto.reference().is_none(),
+            "{:?} cannot be stacked",
+            base
+        );
+        assert!(
+            self.blocks[base]
+                .info
+                .stack
+                .is_none_or(|stack| stack == base),
+            "{:?} is an upper layer",
+            base
+        );
+
+        for _ in 0..count {
+            let layer = self.insert(proto);
+            self.join_stack(base, layer);
+        }
+
+        self.layers(base).to_vec()
+    }
+
+    /// Adds the block on top of the stack of the base.
+    pub(crate) fn join_stack(&mut self, base: BlockKey, layer: BlockKey) {
+        self.blocks[base].info.stack = Some(base);
+        self.blocks[layer].info.stack = Some(base);
+        self.stacks
+            .entry(base)
+            .or_insert_with(|| vec![base])
+            .push(layer);
+    }
+
+    /// Returns the layers of the stack the block belongs to, from the bottom
+    /// up, which is empty if the block is not stacked.
+    pub fn layers(&self, key: BlockKey) -> &[BlockKey] {
+        self.blocks[key]
+            .info
+            .stack
+            .and_then(|base| self.stacks.get(&base))
+            .map(Vec::as_slice)
+            .unwrap_or_default()
+    }
+
+    /// Returns the bottom layer of the stack the block belongs to, or the
+    /// block itself if it is not stacked.
+    pub fn stack_base(&self, key: BlockKey) -> BlockKey {
+        self.blocks[key].info.stack.unwrap_or(key)
+    }
+
+    /// Returns the position in the adjacent layer of the position in the
+    /// depth direction, or `None` if the position is the top or the bottom of
+    /// its stack, outside of any stack, or the direction is planar.
+    pub(crate) fn adjacent_layer(
+        &self,
+        position: Position,
+        direction: Direction,
+    ) -> Option<Position> {
+        let delta = match direction {
+            Direction::Up => 1,
+            Direction::Down => -1,
+            _ => return None,
+        };
+        let container = position.container?;
+        let layers = self.layers(container);
+        let index = layers.iter().position(|&layer| layer == container)?;
+        let layer = layers.get(index.checked_add_signed(delta)?)?;
+        Some(Position::inside(*layer, position.pos))
+    }
+
+    /// Removes the block from its stack, if any. The other layers of a
+    /// removed bottom layer are no longer stacked.
+    pub(crate) fn unstack(&mut self, key: BlockKey) {
+        let Some(base) = self.blocks[key].info.stack.take() else {
+            return;
+        };
+
+        let Some(layers) = self.stacks.get_mut(&base) else {
+            return;
+        };
+        layers.retain(|&layer| layer != key);
+        if key == base || layers.len() < 2 {
+            for layer in self.stacks.remove(&base).unwrap() {
+                self.blocks[layer].info.stack = None;
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Attempt, MoveError, OrphanPolicy, Outcome, ProtoType};
+
+    /// Returns a world with a 3x3 container stacked into three layers, and a
+    /// box at the center of the bottom layer. Exits from orphans are blocked.
+    fn setup() -> (World, Vec<BlockKey>, BlockKey) {
+        let mut world = World::new();
+        world.set_orphan_policy(OrphanPolicy::Stay);
+        let base = world.insert(ProtoType::Box { size: (3, 3) });
+        let layers = world.stack(base, 2);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(base, (1, 1)));
+        (world, layers, block)
+    }
+
+    #[test]
+    fn test_stack() {
+        let mut world = World::new();
+        let base = world.insert(ProtoType::Box { size: (3, 3) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        assert!(world.layers(base).is_empty());
+        assert_eq!(world.stack_base(base), base);
+
+        let layers = world.stack(base, 2);
+        assert_eq!(layers.len(), 3);
+        assert_eq!(layers[0], base);
+        assert_eq!(world.layers(layers[2]), layers);
+        assert_eq!(world.stack_base(layers[1]), base);
+        assert_eq!(world[layers[1]].proto, ProtoType::Box { size: (3, 3) });
+        assert!(world.layers(block).is_empty());
+
+        let position = Position::inside(layers[1], (0, 2));
+        assert_eq!(
+            world.adjacent_layer(position, Direction::Up),
+            Some(Position::inside(layers[2], (0, 2)))
+        );
+        assert_eq!(
+            world.adjacent_layer(position, Direction::Down),
+            Some(Position::inside(base, (0, 2)))
+        );
+        assert_eq!(
+            world.adjacent_layer(Position::inside(base, (0, 2)), Direction::Down),
+            None
+        );
+
+        // Removing a layer keeps the rest of the stack, removing the bottom
+        // layer dissolves it.
+        world.remove(layers[1]);
+        assert_eq!(world.layers(base), [base, layers[2]]);
+        world.remove(base);
+        assert!(world.layers(layers[2]).is_empty());
+        assert!(world.validate().is_empty());
+    }
+
+    #[test]
+    fn test_stack_exit() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let base = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(base, Position::inside(container, (1, 0)));
+        let layers = world.stack(base, 1);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(layers[1], (0, 0)));
+
+        // Exiting an upper layer sideways emerges beside the bottom layer.
+        assert!(world.push(block, Direction::East).unwrap());
+        assert_eq!(world.position(block), Position::inside(container, (2, 0)));
+    }
+
+    #[test]
+    fn test_push_depth() {
+        let (mut world, layers, block) = setup();
+        assert!(world.push(block, Direction::Up).unwrap());
+        assert_eq!(world.position(block), Position::inside(layers[1], (1, 1)));
+
+        // Pushing a block down also pushes the block below it.
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(other, Position::inside(layers[2], (1, 1)));
+        assert!(world.push(other, Direction::Down).unwrap());
+        assert_eq!(world.position(block), Position::inside(layers[0], (1, 1)));
+        assert_eq!(world.position(other), Position::inside(layers[1], (1, 1)));
+        assert_eq!(world.history().len(), 3);
+
+        // The bottom of an orphan stack cannot be exited, so the block above
+        // enters the block instead.
+        assert!(!world.push(block, Direction::Down).unwrap());
+        assert!(world.push(other, Direction::Down).unwrap());
+        assert_eq!(world.position(other), Position::inside(block, (0, 0)));
+        assert!(world.validate().is_empty());
+
+        // Exits from an orphan stack follow the orphan policy, where orphans
+        // are never promoted in depth.
+        world.set_orphan_policy(OrphanPolicy::Error);
+        assert!(matches!(
+            world.push(block, Direction::Down),
+            Err(MoveError::Orphan(key, _)) if key == layers[0]
+        ));
+        world.set_orphan_policy(OrphanPolicy::Promote);
+        assert!(!world.push(block, Direction::Down).unwrap());
+        assert!(world.root().is_none());
+        world.set_orphan_policy(OrphanPolicy::Despawn);
+        assert!(world.push(block, Direction::Down).unwrap());
+        assert!(!world.blocks.contains_key(block));
+    }
+
+    #[test]
+    fn test_push_depth_stacked() {
+        let (mut world, layers, block) = setup();
+        let wall = world.insert(ProtoType::Wall);
+        world.place(wall, Position::inside(layers[1], (1, 1)));
+        let stacked = world.insert(ProtoType::Room { size: (3, 3) });
+        world.place(stacked, Position::inside(layers[1], (0, 0)));
+        let inner = world.stack(stacked, 1);
+
+        // Blocked by a wall.
+        assert!(!world.push(block, Direction::Up).unwrap());
+
+        // Entering the bottom layer of a stacked block from below.
+        world.place(block, Position::inside(layers[0], (0, 0)));
+        assert!(world.push(block, Direction::Up).unwrap());
+        assert_eq!(world.position(block), Position::inside(stacked, (1, 1)));
+
+        // Exiting its top layer emerges above the stacked block.
+        assert!(world.push(block, Direction::Up).unwrap());
+        assert_eq!(world.position(block), Position::inside(inner[1], (1, 1)));
+        assert!(world.push(block, Direction::Up).unwrap());
+        assert_eq!(world.position(block), Position::inside(layers[2], (0, 0)));
+
+        // Pushing out of the whole stack fails for an orphan stack.
+        assert!(!world.push(block, Direction::Up).unwrap());
+
+        // Sideways pushes stay in the layer.
+        assert!(world.push(block, Direction::East).unwrap());
+        assert_eq!(world.position(block), Position::inside(layers[2], (1, 0)));
+
+        // Entering a stacked block from above enters its top layer.
+        world.place(block, Position::inside(layers[2], (0, 0)));
+        assert!(world.push(block, Direction::Down).unwrap());
+        assert_eq!(world.position(block), Position::inside(inner[1], (1, 1)));
+    }
+
+    #[test]
+    fn test_push_depth_side_wall() {
+        let (mut world, layers, block) = setup();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(layers[0], Position::inside(container, (0, 0)));
+        world.place(block, Position::inside(layers[2], (1, 1)));
+
+        // The ceiling of a stack is a side wall of its bottom layer.
+        world.set_side_wall(layers[0], Direction::Up, true);
+        let trace = world.explain(block, Direction::Up);
+        assert!(matches!(trace.result, Ok(false)));
+        let step = trace.steps.last().unwrap();
+        assert_eq!(
+            step.attempt,
+            Attempt::Exit {
+                key: block,
+                from: layers[2],
+            }
+        );
+        assert_eq!(step.outcome, Outcome::SideWall);
+        world.set_side_wall(layers[0], Direction::Up, false);
+
+        // A block with a floor cannot be entered from below, so it is eaten.
+        let eater = world.insert(ProtoType::Box { size: (3, 3) });
+        world.place(eater, Position::inside(layers[1], (1, 1)));
+        world.set_side_wall(block, Direction::Down, true);
+        world.set_orphan_policy(OrphanPolicy::Stay);
+        world.place(layers[0], Position::orphan());
+        assert!(world.push(eater, Direction::Up).unwrap());
+        assert_eq!(world.position(eater), Position::inside(layers[2], (1, 1)));
+        assert_eq!(world.position(block), Position::inside(eater, (1, 1)));
+    }
+
+    #[test]
+    fn test_push_depth_cycle() {
+        let (mut world, layers, block) = setup();
+
+        // The stack is inside its own top layer, so exiting it up never ends.
+        world.place(layers[0], Position::inside(layers[2], (0, 0)));
+        world.place(block, Position::inside(layers[2], (1, 1)));
+        assert!(matches!(
+            world.push(block, Direction::Up),
+            Err(MoveError::NoInfinity(key, _)) if key == layers[2]
+        ));
+    }
+
+    #[test]
+    fn test_push_depth_linked_alias() {
+        let (mut world, layers, block) = setup();
+        let target = world.insert(ProtoType::Box { size: (3, 3) });
+        let alias = world.insert(ProtoType::Alias { reference: target });
+        let outer = world.insert(ProtoType::Alias { reference: alias });
+
+        // The alias cannot move out of the top layer, so the block enters the
+        // end of the alias chain, and the block linked to it moves along.
+        let linked = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(linked, Position::inside(layers[0], (0, 0)));
+        world.place(outer, Position::inside(layers[2], (1, 1)));
+        world.place(block, Position::inside(layers[1], (1, 1)));
+        world.link([block, linked]);
+        assert!(world.push(block, Direction::Up).unwrap());
+        assert_eq!(world.position(block), Position::inside(target, (1, 1)));
+        assert_eq!(world.position(linked), Position::inside(layers[1], (0, 0)));
+    }
+}
@@ -1,6 +1,42 @@
 mod algorithm;
+mod batch;
+mod collect;
+mod deadlock;
+mod display;
+mod encode;
+mod equivalent;
+mod goal;
+mod group;
+mod history;
+mod layer;
 mod query;
+mod replay;
+mod root;
+mod sequence;
+mod slot;
+mod stats;
+mod tag;
+mod tick;
+mod trigger;
+mod validate;
+#[allow(clippy::module_inception)]
 mod world;
 
-pub use algorithm::{Direction, MoveError, MoveResult};
+pub use algorithm::{
+    Attempt, Direction, EntryPolicy, MoveContext, MoveError, MoveResult, Orientation, OrphanPolicy,
+    Outcome, ParseDirectionError, PushLimit, PushStats, PushTrace, ReachMode, RuleConfig, TieBreak,
+    TowardReport, TraceStep,
+};
+pub use encode::{Channel, EncodingOptions};
+pub use goal::Goal;
+pub use group::GroupId;
+pub use history::Move;
+pub use query::RayHit;
+pub use replay::{ParseReplayError, Replay};
+pub use sequence::{BlockedPolicy, SequenceReport};
+pub use stats::WorldStats;
+pub use tag::EntryFilter;
+pub use tick::{Conveyor, Rule, Spawner, TimedDoor};
+pub use trigger::{TriggerCallback, TriggerEvent, TriggerId, TriggerKind};
+pub use validate::Violation;
 pub use world::World;
@@ -1,5 +1,6 @@
 use super::world::World;
 use crate::block::{Block, BlockKey, Position};
+use crate::Direction;
 
 #[derive(Debug)]
 pub(crate) enum PositionState {
@@ -11,17 +12,11 @@ pub(crate) enum PositionState {
 
 impl PositionState {
     pub(crate) fn is_present(&self) -> bool {
-        match self {
-            PositionState::Present(_) => true,
-            _ => false,
-        }
+        matches!(self, PositionState::Present(_))
     }
 
     pub(crate) fn is_empty(&self) -> bool {
-        match self {
-            PositionState::Empty => true,
-            _ => false,
-        }
+        matches!(self, PositionState::Empty)
     }
 
     pub(crate) fn as_option(&self) -> Option<BlockKey> {
@@ -32,7 +27,60 @@ impl PositionState {
     }
 }
 
+/// The result of a [World::raycast].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct RayHit {
+    /// The first block hit by the ray, if any.
+    pub block: Option<BlockKey>,
+    /// Whether the ray leaves the container without hitting any block.
+    pub exits: bool,
+    /// The empty cells traversed by the ray, in order.
+    ///
+    /// The starting cell and the cell of the hit block are not included.
+    pub cells: Vec<Position>,
+}
+
 impl World {
+    /// Casts a ray from the position in the direction, inside the container of
+    /// the position.
+    ///
+    /// The ray walks cell by cell until it hits a block or leaves the
+    /// container. It never exits or enters blocks. If the position is
+    /// orphan, or the direction is [Direction::Up] or [Direction::Down], the
+    /// ray exits immediately.
+    pub fn raycast(&self, from: Position, direction: Direction) -> RayHit {
+        let mut cells = Vec::new();
+        if direction.is_depth() {
+            return RayHit {
+                block: None,
+                exits: true,
+                cells,
+            };
+        }
+        let (dx, dy) = direction.delta_for(self.orientation);
+        let (mut x, mut y) = (from.pos.0 as isize, from.pos.1 as isize);
+
+        let block = loop {
+            (x, y) = (x + dx, y + dy);
+            if x < 0 || y < 0 {
+                break None;
+            }
+
+            let position = Position::new(from.container, (x as usize, y as usize));
+            match self.position_state(position) {
+                PositionState::Empty => cells.push(position),
+                PositionState::Present(key) => break Some(key),
+                PositionState::Void | PositionState::OutofBound => break None,
+            }
+        };
+
+        RayHit {
+            block,
+            exits: block.is_none(),
+            cells,
+        }
+    }
+
     /// Gets the position of a block.
     pub fn position(&self, block: BlockKey) -> Position {
         self.blocks[block].state.position
@@ -69,3 +117,33 @@ impl BlockKey {
         &mut world.blocks[self]
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ProtoType;
+
+    #[test]
+    fn test_raycast() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        world.place(wall, Position::inside(container, (3, 0)));
+
+        let hit = world.raycast(Position::inside(container, (0, 0)), Direction::East);
+        assert_eq!(hit.block, Some(wall));
+        assert!(!hit.exits);
+        assert_eq!(
+            hit.cells,
+            vec![
+                Position::inside(container, (1, 0)),
+                Position::inside(container, (2, 0)),
+            ]
+        );
+
+        let hit = world.raycast(Position::inside(container, (2, 0)), Direction::West);
+        assert_eq!(hit.block, None);
+        assert!(hit.exits);
+        assert_eq!(hit.cells.len(), 2);
+    }
+}
@@ -0,0 +1,584 @@
+use super::history::FNV_OFFSET;
+use super::world::World;
+use crate::{
+    BlockId, BlockKey, Direction, EntryPolicy, MoveResult, Orientation, OrphanPolicy, Position,
+    ProtoType, TieBreak,
+};
+use std::fmt::{Display, Formatter};
+use std::str::FromStr;
+
+/// A recording of pushes from an initial world.
+///
+/// Pushes are recorded by [BlockId], so that a replay remains valid after
+/// being serialized and deserialized.
+///
+/// A replay can be serialized with [Display] and deserialized with
+/// [FromStr], in a line-based text format:
+///
+/// ```text
+/// block 0 box 3 1
+/// block 1 wall
+/// place 1 0 2 0
+/// push 1 east cbf29ce484222325
+/// ```
+///
+/// Each push is followed by the [World::replay_checksum] after it, which is
+/// optional when parsing. The settings of the initial world different from
+/// the defaults are written before the pushes, e.g. `orphan promote` for the
+/// [World::orphan_policy] or `disable exit` for the [World::rule_config].
+///
+/// Only the pushes are recorded, so the replay is only faithful if the world
+/// is not changed otherwise between them, e.g. a setting changed after the
+/// first push is not replayed. Create a new replay after such a change. The
+/// tags, side walls, groups and stacks of the blocks are serialized, but not
+/// the entry filters, the triggers and the tick rules of the initial world,
+/// which are closures. A tag is written up to the end of its line, so its
+/// surrounding whitespace is lost.
+#[derive(Clone)]
+pub struct Replay {
+    initial: World,
+    pushes: Vec<(BlockId, Direction)>,
+    checksums: Vec<Option<u64>>,
+}
+
+impl Replay {
+    /// Creates an empty replay starting from a snapshot of the world.
+    pub fn new(world: &World) -> Self {
+        Self {
+            initial: world.clone(),
+            pushes: Vec::new(),
+            checksums: Vec::new(),
+        }
+    }
+
+    /// Returns the initial world.
+    pub fn initial(&self) -> &World {
+        &self.initial
+    }
+
+    /// Returns the recorded pushes.
+    pub fn pushes(&self) -> &[(BlockId, Direction)] {
+        &self.pushes
+    }
+
+    /// Pushes the block in the world, recording the push unless it fails.
+    ///
+    /// The block is recorded by its id, read before the push, as it may be
+    /// removed by the push.
+    pub fn record(
+        &mut self,
+        world: &mut World,
+        key: BlockKey,
+        direction: Direction,
+    ) -> MoveResult<bool> {
+        let id = world[key].id;
+        let result = world.push(key, direction)?;
+        self.pushes.push((id, direction));
+        self.checksums.push(Some(world.replay_checksum()));
+        Ok(result)
+    }
+
+    /// Replaces the world with the initial world and replays the pushes.
+    pub fn play_into(&self, world: &mut World) -> MoveResult<()> {
+        *world = self.initial.clone();
+
+        for &(id, direction) in &self.pushes {
+            if let Some(key) = world.key_of(id) {
+                world.push(key, direction)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Replaces the world with the initial world and replays the pushes,
+    /// stopping at the first push after which the [World::replay_checksum]
+    /// differs from the recorded one.
+    ///
+    /// Returns the index of the diverging push, if any. Pushes of blocks
+    /// missing from the world, e.g. removed by an earlier push, always
+    /// diverge. Otherwise, pushes without a recorded checksum are not
+    /// verified.
+    pub fn verify_into(&self, world: &mut World) -> MoveResult<Option<usize>> {
+        *world = self.initial.clone();
+
+        for (index, (&(id, direction), &checksum)) in
+            self.pushes.iter().zip(&self.checksums).enumerate()
+        {
+            let Some(key) = world.key_of(id) else {
+                return Ok(Some(index));
+            };
+            world.push(key, direction)?;
+            if checksum.is_some_and(|checksum| checksum != world.replay_checksum()) {
+                return Ok(Some(index));
+            }
+        }
+
+        Ok(None)
+    }
+}
+
+impl Display for Replay {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        let world = &self.initial;
+        let id = |key: BlockKey| world[key].id.get();
+
+        for (_, block) in world.blocks_ordered() {
+            write!(f, "block {}", block.id.get())?;
+            match block.proto {
+                ProtoType::Wall => writeln!(f, " wall")?,
+                ProtoType::Box { size: (w, h) } => writeln!(f, " box {} {}", w, h)?,
+                ProtoType::Alias { reference } if block.info.two_way => {
+                    writeln!(f, " alias {} two-way", id(reference))?
+                }
+                ProtoType::Alias { reference } => writeln!(f, " alias {}", id(reference))?,
+                ProtoType::Infinity { reference } => writeln!(f, " infinity {}", id(reference))?,
+                ProtoType::Epsilon {
+                    size: (w, h),
+                    reference,
+                } => writeln!(f, " epsilon {} {} {}", w, h, id(reference))?,
+                ProtoType::Void { size: (w, h) } => writeln!(f, " void {} {}", w, h)?,
+                ProtoType::Room { size: (w, h) } => writeln!(f, " room {} {}", w, h)?,
+                ProtoType::Sealed { size: (w, h) } => writeln!(f, " sealed {} {}", w, h)?,
+            }
+        }
+
+        for (_, block) in world.blocks_ordered() {
+            if let Some(container) = block.state.position.container {
+                let (x, y) = block.state.position.pos;
+                writeln!(f, "place {} {} {} {}", block.id.get(), id(container), x, y)?;
+            }
+        }
+
+        for (_, block) in world.blocks_ordered() {
+            if block.info.fragile {
+                writeln!(f, "fragile {}", block.id.get())?;
+            }
+            let mut tags: Vec<_> = block.info.tags.iter().collect();
+            tags.sort();
+            for tag in tags {
+                writeln!(f, "tag {} {}", block.id.get(), tag)?;
+            }
+            for &side in Direction::ALL.iter().chain(&Direction::DEPTH) {
+                if block.info.side_walls.contains(&side) {
+                    writeln!(f, "side {} {}", block.id.get(), side.name())?;
+                }
+            }
+        }
+
+        for members in world.groups.values() {
+            write!(f, "link")?;
+            for &member in members {
+                write!(f, " {}", id(member))?;
+            }
+            writeln!(f)?;
+        }
+
+        for layers in world.stacks.values() {
+            write!(f, "stack")?;
+            for &layer in layers {
+                write!(f, " {}", id(layer))?;
+            }
+            writeln!(f)?;
+        }
+
+        match world.orientation {
+            Orientation::YUp => {}
+            Orientation::YDown => writeln!(f, "orientation y-down")?,
+        }
+        match world.orphan_policy {
+            OrphanPolicy::Error => {}
+            OrphanPolicy::Stay => writeln!(f, "orphan stay")?,
+            OrphanPolicy::Despawn => writeln!(f, "orphan despawn")?,
+            OrphanPolicy::Promote => writeln!(f, "orphan promote")?,
+        }
+        if let Some(root) = world.root() {
+            writeln!(f, "root {}", id(root))?;
+        }
+        let config = world.config;
+        for (enabled, rule) in [
+            (config.enter, "enter"),
+            (config.eat, "eat"),
+            (config.exit, "exit"),
+        ] {
+            if !enabled {
+                writeln!(f, "disable {}", rule)?;
+            }
+        }
+        match config.entry {
+            EntryPolicy::Proportional => {}
+            EntryPolicy::Center => writeln!(f, "entry center")?,
+            EntryPolicy::Edge => writeln!(f, "entry edge")?,
+        }
+        match config.tie_break {
+            TieBreak::Upper => {}
+            TieBreak::Lower => writeln!(f, "tie-break lower")?,
+        }
+
+        if world.checksum != FNV_OFFSET {
+            writeln!(f, "checksum {:016x}", world.checksum)?;
+        }
+
+        for (&(block, direction), checksum) in self.pushes.iter().zip(&self.checksums) {
+            write!(f, "push {} {}", block.get(), direction.name())?;
+            match checksum {
+                Some(checksum) => writeln!(f, " {:016x}", checksum)?,
+                None => writeln!(f)?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// An error when parsing a [Replay].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct ParseReplayError {
+    /// The line where the error occurs, starting from `1`.
+    pub line: usize,
+    /// The description of the error.
+    pub message: String,
+}
+
+impl Display for ParseReplayError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl std::error::Error for ParseReplayError {}
+
+impl FromStr for Replay {
+    type Err = ParseReplayError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut world = World::new();
+        let mut pushes = Vec::new();
+        let mut checksums = Vec::new();
+
+        for (index, line) in s.lines().enumerate() {
+            let error = |message: &str| ParseReplayError {
+                line: index + 1,
+                message: message.to_string(),
+            };
+
+            let words: Vec<&str> = line.split_whitespace().collect();
+            let number = |i: usize| -> Result<u64, ParseReplayError> {
+                let word = words.get(i).ok_or_else(|| error("missing argument"))?;
+                word.parse().map_err(|_| error("expected a number"))
+            };
+            let size = |i: usize| Ok((number(i)? as usize, number(i + 1)? as usize));
+            let checksum = |word: &str| {
+                u64::from_str_radix(word, 16).map_err(|_| error("expected a checksum"))
+            };
+            let key = |world: &World, i: usize| {
+                world
+                    .key_of(BlockId::new(number(i)?))
+                    .ok_or_else(|| error("undefined block"))
+            };
+
+            match words.as_slice() {
+                [] => {}
+                ["block", _, kind, ..] => {
+                    let id = BlockId::new(number(1)?);
+                    if world.key_of(id).is_some() {
+                        return Err(error("duplicate block"));
+                    }
+
+                    let proto = match *kind {
+                        "wall" => ProtoType::Wall,
+                        "box" => ProtoType::Box { size: size(3)? },
+                        "alias" => ProtoType::Alias {
+                            reference: key(&world, 3)?,
+                        },
+                        "infinity" => ProtoType::Infinity {
+                            reference: key(&world, 3)?,
+                        },
+                        "epsilon" => ProtoType::Epsilon {
+                            size: size(3)?,
+                            reference: key(&world, 5)?,
+                        },
+                        "void" => ProtoType::Void { size: size(3)? },
+                        "room" => ProtoType::Room { size: size(3)? },
+                        "sealed" => ProtoType::Sealed { size: size(3)? },
+                        _ => return Err(error("unknown block kind")),
+                    };
+                    let key = world.insert_with_id(id, proto);
+                    match words.get(4) {
+                        Some(&"two-way") if *kind == "alias" => world.set_two_way(key, true),
+                        Some(_) if *kind == "alias" => return Err(error("unknown alias mode")),
+                        _ => {}
+                    }
+                }
+                ["place", ..] => {
+                    let (block, container) = (key(&world, 1)?, key(&world, 2)?);
+                    let pos = size(3)?;
+                    if !world[container].proto.contains(pos) {
+                        return Err(error("position out of bounds"));
+                    }
+                    world.place(block, Position::inside(container, pos));
+                }
+                ["fragile", _] => {
+                    let block = key(&world, 1)?;
+                    world.set_fragile(block, true);
+                }
+                ["tag", id, ..] => {
+                    let block = key(&world, 1)?;
+                    let (_, tag) = line.trim_start()["tag".len()..]
+                        .trim_start()
+                        .split_at(id.len());
+                    if tag.trim().is_empty() {
+                        return Err(error("missing argument"));
+                    }
+                    world.add_tag(block, tag.trim());
+                }
+                ["side", _, side] => {
+                    let block = key(&world, 1)?;
+                    let side = side.parse().map_err(|_| error("unknown direction"))?;
+                    world.set_side_wall(block, side, true);
+                }
+                ["link", ..] => {
+                    let members = (1..words.len())
+                        .map(|i| key(&world, i))
+                        .collect::<Result<Vec<_>, _>>()?;
+                    world.link(members);
+                }
+                ["stack", _, _, ..] => {
+                    let base = key(&world, 1)?;
+                    let proto = world[base].proto;
+                    if !proto.is_hollow()
+                        || proto.reference().is_some()
+                        || world[base].info.stack.is_some()
+                    {
+                        return Err(error("invalid stack"));
+                    }
+                    for i in 2..words.len() {
+                        let layer = key(&world, i)?;
+                        if layer == base
+                            || world[layer].proto != proto
+                            || world[layer].info.stack.is_some()
+                        {
+                            return Err(error("invalid stack"));
+                        }
+                        world.join_stack(base, layer);
+                    }
+                }
+                ["orientation", "y-down"] => world.set_orientation(Orientation::YDown),
+                ["orphan", policy] => {
+                    let policy = match *policy {
+                        "stay" => OrphanPolicy::Stay,
+                        "despawn" => OrphanPolicy::Despawn,
+                        "promote" => OrphanPolicy::Promote,
+                        _ => return Err(error("unknown orphan policy")),
+                    };
+                    world.set_orphan_policy(policy);
+                }
+                ["root", _] => world.root = Some(key(&world, 1)?),
+                ["disable", rule] => {
+                    match *rule {
+                        "enter" => world.config.enter = false,
+                        "eat" => world.config.eat = false,
+                        "exit" => world.config.exit = false,
+                        _ => return Err(error("unknown rule")),
+                    };
+                }
+                ["entry", "center"] => world.config.entry = EntryPolicy::Center,
+                ["entry", "edge"] => world.config.entry = EntryPolicy::Edge,
+                ["tie-break", "lower"] => world.config.tie_break = TieBreak::Lower,
+                ["checksum", word] => {
+                    world.checksum = checksum(word)?;
+                }
+                ["push", _, direction, rest @ ..] if rest.len() <= 1 => {
+                    let block = key(&world, 1)?;
+                    let direction = direction.parse().map_err(|_| error("unknown direction"))?;
+                    pushes.push((world[block].id, direction));
+                    checksums.push(rest.first().map(|word| checksum(word)).transpose()?);
+                }
+                _ => return Err(error("unknown statement")),
+            }
+        }
+
+        Ok(Self {
+            initial: world,
+            pushes,
+            checksums,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_replay() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let removed = world.insert(ProtoType::Wall);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let alias = world.insert(ProtoType::Alias { reference: block });
+        world.remove(removed);
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(alias, Position::inside(block, (0, 0)));
+
+        let mut replay = Replay::new(&world);
+        replay.record(&mut world, block, Direction::East).unwrap();
+        let checksum = world.replay_checksum();
+        replay.record(&mut world, block, Direction::East).unwrap();
+
+        let text = replay.to_string();
+        assert_eq!(
+            text,
+            format!(
+                "block 0 box 3 1\nblock 2 box 1 1\nblock 3 alias 2\n\
+                 place 2 0 0 0\nplace 3 2 0 0\npush 2 east {:016x}\npush 2 east {:016x}\n",
+                checksum,
+                world.replay_checksum()
+            )
+        );
+
+        let parsed: Replay = text.parse().unwrap();
+        assert_eq!(parsed.to_string(), text);
+
+        let mut played = World::new();
+        parsed.play_into(&mut played).unwrap();
+        let block = played.key_of(world[block].id).unwrap();
+        let container = played.key_of(world[container].id).unwrap();
+        assert_eq!(played.position(block), Position::inside(container, (2, 0)));
+        assert_eq!(played.turn(), 2);
+        assert_eq!(parsed.verify_into(&mut played).unwrap(), None);
+    }
+
+    #[test]
+    fn test_record_despawn() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.set_orphan_policy(OrphanPolicy::Despawn);
+
+        // The despawned block is still recorded by its id.
+        let id = world[block].id;
+        let mut replay = Replay::new(&world);
+        assert!(replay.record(&mut world, block, Direction::East).unwrap());
+        assert_eq!(replay.pushes(), [(id, Direction::East)]);
+    }
+
+    #[test]
+    fn test_verify() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.push(block, Direction::East).unwrap();
+
+        let mut replay = Replay::new(&world);
+        replay.record(&mut world, block, Direction::West).unwrap();
+        replay.record(&mut world, block, Direction::East).unwrap();
+        replay.record(&mut world, block, Direction::East).unwrap();
+
+        // The checksum of the initial world is kept through serialization.
+        let text = replay.to_string();
+        let parsed: Replay = text.parse().unwrap();
+        let mut played = World::new();
+        assert_eq!(parsed.verify_into(&mut played).unwrap(), None);
+        assert_eq!(played.replay_checksum(), world.replay_checksum());
+
+        // A diverging engine is detected at the first diverging push.
+        let lines: Vec<_> = text.lines().collect();
+        let diverged = text.replace(lines[5], "push 1 east 0123456789abcdef");
+        let parsed: Replay = diverged.parse().unwrap();
+        assert_eq!(parsed.verify_into(&mut played).unwrap(), Some(1));
+        let block = played.key_of(world[block].id).unwrap();
+        let container = played.key_of(world[container].id).unwrap();
+        assert_eq!(played.position(block), Position::inside(container, (1, 0)));
+
+        // Pushes without a checksum are not verified.
+        let parsed: Replay = "block 0 box 2 1\nblock 1 wall\nplace 1 0 0 0\npush 1 east\n"
+            .parse()
+            .unwrap();
+        assert_eq!(parsed.verify_into(&mut played).unwrap(), None);
+
+        // Pushes of missing blocks diverge.
+        let mut replay = Replay::new(&World::new());
+        replay.pushes.push((BlockId::new(0), Direction::East));
+        replay.checksums.push(None);
+        assert_eq!(replay.verify_into(&mut played).unwrap(), Some(0));
+    }
+
+    #[test]
+    fn test_block_metadata() {
+        let text = "block 0 box 1 1\nblock 1 alias 0 two-way\nblock 2 alias 0\n\
+                    fragile 0\ntag 0 heavy\ntag 0 red box\nside 0 north\nside 0 west\nlink 2 0\n";
+        let replay: Replay = text.parse().unwrap();
+        let world = replay.initial();
+        assert!(world.is_two_way(world.key_of(BlockId::new(1)).unwrap()));
+        assert!(!world.is_two_way(world.key_of(BlockId::new(2)).unwrap()));
+        assert!(world.has_tag(world.key_of(BlockId::new(0)).unwrap(), "red box"));
+        assert!(world
+            .group(world.key_of(BlockId::new(0)).unwrap())
+            .is_some());
+        assert_eq!(replay.to_string(), text);
+    }
+
+    #[test]
+    fn test_stack() {
+        let mut world = World::new();
+        let base = world.insert(ProtoType::Box { size: (3, 3) });
+        let layers = world.stack(base, 2);
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(base, (1, 1)));
+        world.set_side_wall(base, Direction::Up, true);
+
+        let mut replay = Replay::new(&world);
+        replay.record(&mut world, block, Direction::Up).unwrap();
+        let text = replay.to_string();
+        assert!(text.contains("side 0 up\nstack 0 1 2\n"));
+
+        let parsed: Replay = text.parse().unwrap();
+        assert_eq!(parsed.to_string(), text);
+        let mut played = World::new();
+        assert_eq!(parsed.verify_into(&mut played).unwrap(), None);
+        let layer = played.key_of(world[layers[1]].id).unwrap();
+        let block = played.key_of(world[block].id).unwrap();
+        assert_eq!(played.position(block), Position::inside(layer, (1, 1)));
+
+        let Err(error) = "block 0 box 1 1\nblock 1 box 2 2\nstack 0 1\n".parse::<Replay>() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.message, "invalid stack");
+    }
+
+    #[test]
+    fn test_settings() {
+        let text = "block 0 box 2 1\norientation y-down\norphan promote\nroot 0\n\
+                    disable eat\ndisable exit\nentry edge\ntie-break lower\n";
+        let replay: Replay = text.parse().unwrap();
+        let world = replay.initial();
+        assert_eq!(world.orientation(), Orientation::YDown);
+        assert_eq!(world.orphan_policy(), OrphanPolicy::Promote);
+        assert_eq!(world.root(), world.key_of(BlockId::new(0)));
+        let config = world.rule_config();
+        assert_eq!(
+            (config.enter, config.eat, config.exit),
+            (true, false, false)
+        );
+        assert_eq!(config.entry, EntryPolicy::Edge);
+        assert_eq!(config.tie_break, TieBreak::Lower);
+        assert_eq!(replay.to_string(), text);
+
+        let Err(error) = "orphan away\n".parse::<Replay>() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.message, "unknown orphan policy");
+    }
+
+    #[test]
+    fn test_parse_error() {
+        let Err(error) = "block 0 wall\npush 1 east\n".parse::<Replay>() else {
+            panic!("expected an error");
+        };
+        assert_eq!(error.line, 2);
+        assert_eq!(error.message, "undefined block");
+    }
+}
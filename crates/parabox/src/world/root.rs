@@ -0,0 +1,146 @@
+use super::world::World;
+use crate::{BlockKey, Direction, Interior, Position, ProtoType};
+
+impl World {
+    /// Returns the root block created by [OrphanPolicy::Promote], if any.
+    ///
+    /// The root is a [ProtoType::Room] that grows on demand, so it behaves as
+    /// an unbounded container around the orphans promoted into it. It is no
+    /// longer the root once it is removed or placed into another block.
+    ///
+    /// [OrphanPolicy::Promote]: crate::OrphanPolicy::Promote
+    pub fn root(&self) -> Option<BlockKey> {
+        self.root.filter(|&root| {
+            self.blocks
+                .get(root)
+                .is_some_and(|block| block.state.position.is_orphan())
+        })
+    }
+
+    /// Promotes the orphan exited in the direction into the root, creating the
+    /// root if there is none.
+    ///
+    /// Exiting the root grows it by one cell in the direction of the exit. An
+    /// orphan other than the root is placed into a new row or column on the
+    /// side of the root opposite to the exit, at its first cell.
+    pub(crate) fn promote(&mut self, orphan: BlockKey, direction: Direction) {
+        match self.root() {
+            Some(root) if root == orphan => self.grow_root(root, direction),
+            Some(root) => {
+                let side = direction.opposite();
+                self.grow_root(root, side);
+                let (width, height) = self.blocks[root].proto.size();
+                let (dx, dy) = side.delta_for(self.orientation);
+                let x = if dx > 0 { width - 1 } else { 0 };
+                let y = if dy > 0 { height - 1 } else { 0 };
+                self.place(orphan, Position::inside(root, (x, y)));
+            }
+            None => {
+                let root = self.insert(ProtoType::Room { size: (1, 1) });
+                self.place(orphan, Position::inside(root, (0, 0)));
+                self.root = Some(root);
+            }
+        }
+    }
+
+    /// Grows the root by one cell in the direction. Growing toward the
+    /// negative coordinates shifts the blocks inside the root.
+    fn grow_root(&mut self, root: BlockKey, direction: Direction) {
+        let (width, height) = self.blocks[root].proto.size();
+        let (dx, dy) = direction.delta_for(self.orientation);
+        let size = (width + dx.unsigned_abs(), height + dy.unsigned_abs());
+        let shift = (usize::from(dx < 0), usize::from(dy < 0));
+
+        let children: Vec<_> = self.blocks[root]
+            .state
+            .interior
+            .iter()
+            .flatten()
+            .flatten()
+            .copied()
+            .collect();
+        let block = &mut self.blocks[root];
+        block.proto = ProtoType::Room { size };
+        block.state.interior = Interior::new(size);
+        for child in children {
+            let (x, y) = self.blocks[child].state.position.pos;
+            let pos = (x + shift.0, y + shift.1);
+            self.blocks[root].state.interior[pos.0][pos.1] = Some(child);
+            self.blocks[child].state.position = Position::inside(root, pos);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrphanPolicy, RuleConfig};
+
+    #[test]
+    fn test_root() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.set_orphan_policy(OrphanPolicy::Promote);
+
+        // The root grows as the block moves further west.
+        assert!(world.push(block, Direction::West).unwrap());
+        let root = world.root().unwrap();
+        assert_eq!(world.position(container), Position::inside(root, (1, 0)));
+        assert_eq!(world.position(block), Position::inside(root, (0, 0)));
+        assert!(world.push(block, Direction::West).unwrap());
+        assert_eq!(world[root].proto, ProtoType::Room { size: (3, 1) });
+        assert_eq!(world.position(container), Position::inside(root, (2, 0)));
+        assert_eq!(world.position(block), Position::inside(root, (0, 0)));
+        assert!(world.validate().is_empty());
+
+        // Another orphan is placed on the side opposite to the exit.
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        let inner = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(inner, Position::inside(other, (0, 0)));
+        assert!(world.push(inner, Direction::North).unwrap());
+        assert_eq!(world[root].proto, ProtoType::Room { size: (3, 3) });
+        assert_eq!(world.position(other), Position::inside(root, (0, 0)));
+        assert_eq!(world.position(inner), Position::inside(root, (0, 1)));
+        assert_eq!(world.position(block), Position::inside(root, (0, 2)));
+
+        // The root itself is static.
+        assert!(!world.push(root, Direction::East).unwrap());
+    }
+
+    #[test]
+    fn test_root_rollback() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.set_orphan_policy(OrphanPolicy::Promote);
+        world.set_rule_config(RuleConfig {
+            enter: false,
+            eat: false,
+            ..RuleConfig::default()
+        });
+        assert!(world.push(block, Direction::West).unwrap());
+        let root = world.root().unwrap();
+        world.set_side_wall(root, Direction::North, true);
+
+        // The inner block would push `block` through the north wall of the
+        // root, so the promotion of `other` is discarded.
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        let inner = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(inner, Position::inside(other, (0, 0)));
+        assert!(!world.push(inner, Direction::North).unwrap());
+        assert_eq!(world[root].proto, ProtoType::Room { size: (2, 1) });
+        assert!(world.position(other).is_orphan());
+        assert_eq!(world.position(block), Position::inside(root, (0, 0)));
+
+        // The legal pushes take the promotion into account, without applying
+        // it.
+        assert_eq!(
+            world.legal_pushes(inner),
+            vec![Direction::South, Direction::East, Direction::West]
+        );
+        assert!(world.position(other).is_orphan());
+    }
+}
@@ -0,0 +1,92 @@
+use super::world::World;
+use crate::{BlockKey, Direction, MoveResult};
+
+/// What [World::push_sequence] does when a push is blocked.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum BlockedPolicy {
+    /// Stop the sequence at the blocked push.
+    Stop,
+    /// Skip the blocked push and continue the sequence.
+    Continue,
+}
+
+/// The report of [World::push_sequence].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub struct SequenceReport {
+    /// The results of the executed pushes, i.e. whether each push moved some
+    /// blocks.
+    pub steps: Vec<bool>,
+    /// Whether the sequence was stopped before its end by a blocked push.
+    pub stopped: bool,
+}
+
+impl SequenceReport {
+    /// Returns the number of pushes that moved some blocks.
+    pub fn moved(&self) -> usize {
+        self.steps.iter().filter(|&&moved| moved).count()
+    }
+}
+
+impl World {
+    /// Pushes the block in each of the directions in order.
+    ///
+    /// Blocked pushes are handled according to the `policy`. If a push fails,
+    /// the error is returned and the previous pushes remain applied.
+    pub fn push_sequence(
+        &mut self,
+        key: BlockKey,
+        directions: &[Direction],
+        policy: BlockedPolicy,
+    ) -> MoveResult<SequenceReport> {
+        let mut report = SequenceReport {
+            steps: Vec::with_capacity(directions.len()),
+            stopped: false,
+        };
+
+        for (index, &direction) in directions.iter().enumerate() {
+            let moved = self.push(key, direction)?;
+            report.steps.push(moved);
+
+            if !moved && policy == BlockedPolicy::Stop {
+                report.stopped = index + 1 < directions.len();
+                break;
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Position, ProtoType};
+
+    #[test]
+    fn test_push_sequence() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 2) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (1, 1)));
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let directions = [Direction::East, Direction::North, Direction::West];
+
+        let mut stopped = world.clone();
+        let report = stopped
+            .push_sequence(block, &directions, BlockedPolicy::Stop)
+            .unwrap();
+        assert_eq!(report.steps, vec![true, false]);
+        assert!(report.stopped);
+        assert_eq!(stopped.position(block), Position::inside(container, (1, 0)));
+
+        let report = world
+            .push_sequence(block, &directions, BlockedPolicy::Continue)
+            .unwrap();
+        assert_eq!(report.steps, vec![true, false, true]);
+        assert!(!report.stopped);
+        assert_eq!(report.moved(), 2);
+        assert_eq!(world.position(block), Position::inside(container, (0, 0)));
+    }
+}
@@ -0,0 +1,74 @@
+use super::world::World;
+use std::sync::Arc;
+
+impl World {
+    /// Saves a snapshot of the world into the named slot, replacing the old
+    /// snapshot in the slot, if any.
+    ///
+    /// The snapshot covers everything but the slots themselves, and shares the
+    /// unchanged interiors with the world, see [World::fork].
+    pub fn save_slot(&mut self, name: impl Into<String>) {
+        let mut snapshot = self.fork();
+        snapshot.slots.clear();
+        self.slots.insert(name.into(), Arc::new(snapshot));
+    }
+
+    /// Restores the world from the snapshot in the named slot. Returns whether
+    /// the slot exists.
+    ///
+    /// The keys of the blocks are the same as when the snapshot was saved, so
+    /// no remapping is needed. Blocks inserted after saving are discarded.
+    pub fn load_slot(&mut self, name: &str) -> bool {
+        let Some(snapshot) = self.slots.get(name).cloned() else {
+            return false;
+        };
+
+        let slots = std::mem::take(&mut self.slots);
+        *self = World::clone(&snapshot);
+        self.slots = slots;
+
+        true
+    }
+
+    /// Deletes the named slot. Returns whether the slot existed.
+    pub fn delete_slot(&mut self, name: &str) -> bool {
+        self.slots.remove(name).is_some()
+    }
+
+    /// Returns the names of the slots, in alphabetical order.
+    pub fn slots(&self) -> impl Iterator<Item = &str> + '_ {
+        self.slots.keys().map(String::as_str)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, Position, ProtoType};
+
+    #[test]
+    fn test_slots() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        world.save_slot("start");
+        assert!(world.push(block, Direction::East).unwrap());
+        world.save_slot("middle");
+        let inserted = world.insert(ProtoType::Wall);
+
+        assert!(world.load_slot("start"));
+        assert_eq!(world.position(block), Position::inside(container, (0, 0)));
+        assert!(!world.blocks().contains_key(inserted));
+        assert_eq!(world.turn(), 0);
+        assert_eq!(world.slots().collect::<Vec<_>>(), ["middle", "start"]);
+
+        assert!(world.load_slot("middle"));
+        assert_eq!(world.position(block), Position::inside(container, (1, 0)));
+
+        assert!(world.delete_slot("start"));
+        assert!(!world.load_slot("start"));
+        assert!(!world.delete_slot("start"));
+    }
+}
@@ -0,0 +1,136 @@
+use super::history::Move;
+use super::world::World;
+use crate::{Block, BlockKey, ProtoType};
+use std::collections::HashSet;
+use std::mem::size_of;
+
+/// Statistics of a world, see [World::stats].
+#[derive(Copy, Clone, Default, Eq, PartialEq, Debug)]
+pub struct WorldStats {
+    /// The number of [ProtoType::Wall] blocks.
+    pub walls: usize,
+    /// The number of [ProtoType::Box] blocks.
+    pub boxes: usize,
+    /// The number of [ProtoType::Alias] blocks.
+    pub aliases: usize,
+    /// The number of [ProtoType::Infinity] blocks.
+    pub infinities: usize,
+    /// The number of [ProtoType::Epsilon] blocks.
+    pub epsilons: usize,
+    /// The number of [ProtoType::Void] blocks.
+    pub voids: usize,
+    /// The number of [ProtoType::Room] blocks.
+    pub rooms: usize,
+    /// The number of [ProtoType::Sealed] blocks.
+    pub sealed: usize,
+    /// The number of orphan blocks.
+    pub orphans: usize,
+    /// The maximum number of containers above a block.
+    ///
+    /// Containers in a cycle of containment are counted once.
+    pub max_depth: usize,
+    /// The total number of interior cells.
+    pub interior_cells: usize,
+    /// The number of occupied interior cells.
+    pub occupied_cells: usize,
+    /// A rough estimate of the heap and inline memory used by the blocks and
+    /// the history, in bytes.
+    pub memory: usize,
+}
+
+impl WorldStats {
+    /// Returns the total number of blocks.
+    pub fn blocks(&self) -> usize {
+        self.walls
+            + self.boxes
+            + self.aliases
+            + self.infinities
+            + self.epsilons
+            + self.voids
+            + self.rooms
+            + self.sealed
+    }
+}
+
+impl World {
+    /// Returns the statistics of the world.
+    pub fn stats(&self) -> WorldStats {
+        let mut stats = WorldStats::default();
+
+        for (key, block) in self.blocks_ordered() {
+            match block.proto {
+                ProtoType::Wall => stats.walls += 1,
+                ProtoType::Box { .. } => stats.boxes += 1,
+                ProtoType::Alias { .. } => stats.aliases += 1,
+                ProtoType::Infinity { .. } => stats.infinities += 1,
+                ProtoType::Epsilon { .. } => stats.epsilons += 1,
+                ProtoType::Void { .. } => stats.voids += 1,
+                ProtoType::Room { .. } => stats.rooms += 1,
+                ProtoType::Sealed { .. } => stats.sealed += 1,
+            }
+
+            if block.state.position.is_orphan() {
+                stats.orphans += 1;
+            }
+            stats.max_depth = stats.max_depth.max(self.depth(key));
+
+            let (width, height) = block.proto.size();
+            stats.interior_cells += width * height;
+            stats.occupied_cells += block.state.interior.iter().flatten().flatten().count();
+
+            stats.memory += size_of::<Block>()
+                + width * size_of::<Vec<Option<BlockKey>>>()
+                + width * height * size_of::<Option<BlockKey>>()
+                + block.info.references.capacity() * size_of::<BlockKey>()
+                + block.info.label.as_ref().map_or(0, |label| label.len());
+        }
+
+        stats.memory += self.history.capacity() * size_of::<Move>();
+        stats
+    }
+
+    /// Returns the number of distinct containers above the block.
+    fn depth(&self, key: BlockKey) -> usize {
+        let mut visited = HashSet::new();
+        let mut current = self.blocks[key].state.position.container;
+
+        while let Some(container) = current {
+            if !visited.insert(container) {
+                break;
+            }
+            current = self.blocks[container].state.position.container;
+        }
+
+        visited.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{Position, ProtoType, World};
+
+    #[test]
+    fn test_stats() {
+        let mut world = World::new();
+        let root = world.insert(ProtoType::Box { size: (2, 2) });
+        let inner = world.insert(ProtoType::Box { size: (1, 1) });
+        let wall = world.insert(ProtoType::Wall);
+        let alias = world.insert(ProtoType::Alias { reference: root });
+        world.place(inner, Position::inside(root, (0, 0)));
+        world.place(wall, Position::inside(inner, (0, 0)));
+        world.place(alias, Position::inside(root, (1, 1)));
+
+        let stats = world.stats();
+        assert_eq!(stats.blocks(), 4);
+        assert_eq!((stats.boxes, stats.walls, stats.aliases), (2, 1, 1));
+        assert_eq!(stats.orphans, 1);
+        assert_eq!(stats.max_depth, 2);
+        assert_eq!(stats.interior_cells, 5);
+        assert_eq!(stats.occupied_cells, 3);
+        assert!(stats.memory > 0);
+
+        // A block inside itself.
+        world.place(root, Position::inside(root, (1, 0)));
+        assert_eq!(world.stats().max_depth, 2);
+    }
+}
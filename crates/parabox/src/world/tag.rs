@@ -0,0 +1,93 @@
+use super::world::World;
+use crate::{Block, BlockKey};
+use std::sync::Arc;
+
+/// A predicate deciding which blocks may enter a container, see
+/// [World::set_entry_filter].
+pub type EntryFilter = Arc<dyn Fn(&Block) -> bool + Send + Sync>;
+
+impl World {
+    /// Adds the tag to the block. Returns whether the tag is newly added.
+    pub fn add_tag(&mut self, key: BlockKey, tag: impl Into<Arc<str>>) -> bool {
+        self.blocks[key].info.tags.insert(tag.into())
+    }
+
+    /// Removes the tag from the block. Returns whether the block had the tag.
+    pub fn remove_tag(&mut self, key: BlockKey, tag: &str) -> bool {
+        self.blocks[key].info.tags.remove(tag)
+    }
+
+    /// Returns whether the block has the tag.
+    pub fn has_tag(&self, key: BlockKey, tag: &str) -> bool {
+        self.blocks[key].info.tags.contains(tag)
+    }
+
+    /// Sets the predicate deciding which blocks may enter the container.
+    ///
+    /// Blocks rejected by the filter cannot enter the container, nor be eaten
+    /// by it, just like it were solid. Blocks already inside and blocks placed
+    /// with [World::place] are not affected. Cloned worlds share the filters,
+    /// but unlike the tags, they are not serialized in a
+    /// [Replay](crate::Replay).
+    pub fn set_entry_filter(
+        &mut self,
+        key: BlockKey,
+        filter: impl Fn(&Block) -> bool + Send + Sync + 'static,
+    ) {
+        self.blocks[key].info.entry_filter = Some(Arc::new(filter));
+    }
+
+    /// Only allows blocks with the tag to enter the container, see
+    /// [World::set_entry_filter].
+    pub fn require_tag(&mut self, key: BlockKey, tag: impl Into<Arc<str>>) {
+        let tag = tag.into();
+        self.set_entry_filter(key, move |block| block.info.tags.contains(&tag));
+    }
+
+    /// Removes the entry filter of the container, if any.
+    pub fn clear_entry_filter(&mut self, key: BlockKey) {
+        self.blocks[key].info.entry_filter = None;
+    }
+
+    /// Returns whether the filter of the container accepts the block.
+    pub(crate) fn accepts(&self, container: BlockKey, key: BlockKey) -> bool {
+        match &self.blocks[container].info.entry_filter {
+            Some(filter) => filter(&self.blocks[key]),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, Position, ProtoType};
+
+    #[test]
+    fn test_require_tag() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let room = world.insert(ProtoType::Box { size: (3, 3) });
+        let player = world.insert(ProtoType::Box { size: (1, 1) });
+        for (key, pos) in [(player, (0, 0)), (container, (3, 0))] {
+            let wall = world.insert(ProtoType::Wall);
+            world.place(wall, Position::inside(key, pos));
+        }
+        world.place(player, Position::inside(container, (1, 0)));
+        world.place(room, Position::inside(container, (2, 0)));
+        world.require_tag(room, "small");
+
+        assert!(!world.push(player, Direction::East).unwrap());
+
+        assert!(world.add_tag(player, "small"));
+        assert!(!world.add_tag(player, "small"));
+        assert!(world.has_tag(player, "small"));
+        assert!(world.push(player, Direction::East).unwrap());
+        assert_eq!(world.position(player), Position::inside(room, (0, 1)));
+
+        assert!(world.remove_tag(player, "small"));
+        assert!(!world.has_tag(player, "small"));
+        world.clear_entry_filter(room);
+        assert!(world.accepts(room, player));
+    }
+}
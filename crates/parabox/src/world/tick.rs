@@ -0,0 +1,186 @@
+use super::world::World;
+use crate::{BlockKey, Direction, MoveResult, Position, ProtoType};
+use std::sync::Arc;
+
+/// A world-level mechanic advanced by [World::tick].
+pub trait Rule: Send + Sync {
+    /// Applies the rule to the world at the given tick, starting from `1`.
+    fn apply(&self, world: &mut World, tick: usize) -> MoveResult<()>;
+}
+
+/// Pushes the block on the cell in a direction every tick.
+#[derive(Copy, Clone, Debug)]
+pub struct Conveyor {
+    /// The cell of the conveyor.
+    pub position: Position,
+    /// The direction of the conveyor.
+    pub direction: Direction,
+}
+
+/// Returns whether the container of the position is still in the world.
+fn exists(world: &World, position: Position) -> bool {
+    position
+        .container
+        .is_none_or(|container| world.blocks().contains_key(container))
+}
+
+impl Rule for Conveyor {
+    fn apply(&self, world: &mut World, _tick: usize) -> MoveResult<()> {
+        if !exists(world, self.position) {
+            return Ok(());
+        }
+        if let Some(key) = world.position_state(self.position).as_option() {
+            world.push(key, self.direction)?;
+        }
+        Ok(())
+    }
+}
+
+/// Opens and closes a door every `period` ticks.
+///
+/// The door is closed by placing the door block at the position, and opened
+/// by detaching it. A door does not close while the cell is taken, and does
+/// nothing once the door block or its container is removed, e.g. crushed.
+#[derive(Copy, Clone, Debug)]
+pub struct TimedDoor {
+    /// The door block, usually a [ProtoType::Wall].
+    pub door: BlockKey,
+    /// The position of the door.
+    pub position: Position,
+    /// The number of ticks between toggles.
+    pub period: usize,
+}
+
+impl Rule for TimedDoor {
+    fn apply(&self, world: &mut World, tick: usize) -> MoveResult<()> {
+        if !tick.is_multiple_of(self.period)
+            || !world.blocks().contains_key(self.door)
+            || !exists(world, self.position)
+        {
+            return Ok(());
+        }
+
+        if world.position(self.door) == self.position {
+            world.place(self.door, Position::orphan());
+        } else if world.position_state(self.position).is_empty() {
+            world.place(self.door, self.position);
+        }
+        Ok(())
+    }
+}
+
+/// Spawns a block at the position every `period` ticks, if the cell is
+/// empty.
+#[derive(Copy, Clone, Debug)]
+pub struct Spawner {
+    /// The prototype of the spawned blocks.
+    pub proto: ProtoType,
+    /// The position of the spawned blocks.
+    pub position: Position,
+    /// The number of ticks between spawns.
+    pub period: usize,
+}
+
+impl Rule for Spawner {
+    fn apply(&self, world: &mut World, tick: usize) -> MoveResult<()> {
+        if tick.is_multiple_of(self.period)
+            && exists(world, self.position)
+            && world.position_state(self.position).is_empty()
+        {
+            let key = world.insert(self.proto);
+            world.place(key, self.position);
+        }
+        Ok(())
+    }
+}
+
+impl World {
+    /// Adds a rule to the end of the tick pipeline.
+    pub fn add_rule(&mut self, rule: impl Rule + 'static) {
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// Returns the number of ticks advanced.
+    pub fn ticks(&self) -> usize {
+        self.ticks
+    }
+
+    /// Advances the world-level mechanics one step, applying the rules in the
+    /// order they were added.
+    ///
+    /// This is intended to be called after each player push. If a rule fails,
+    /// the error is returned and the remaining rules are skipped.
+    pub fn tick(&mut self) -> MoveResult<()> {
+        self.ticks += 1;
+
+        for rule in self.rules.clone() {
+            rule.apply(self, self.ticks)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tick() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (4, 1) });
+        let door = world.insert(ProtoType::Wall);
+        let inside = |x| Position::inside(container, (x, 0));
+
+        world.add_rule(Spawner {
+            proto: ProtoType::Box { size: (1, 1) },
+            position: inside(0),
+            period: 2,
+        });
+        world.add_rule(Conveyor {
+            position: inside(0),
+            direction: Direction::East,
+        });
+        world.add_rule(TimedDoor {
+            door,
+            position: inside(3),
+            period: 1,
+        });
+
+        world.tick().unwrap();
+        assert_eq!(world.position(door), inside(3));
+
+        world.tick().unwrap();
+        let spawned = world.position_state(inside(1)).as_option().unwrap();
+        assert!(world.position(door).is_orphan());
+
+        world.tick().unwrap();
+        assert_eq!(world.position(spawned), inside(1));
+        assert_eq!(world.position(door), inside(3));
+        assert_eq!(world.ticks(), 3);
+    }
+
+    #[test]
+    fn test_tick_removed() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let door = world.insert(ProtoType::Wall);
+        let position = Position::inside(container, (0, 0));
+        world.add_rule(TimedDoor {
+            door,
+            position,
+            period: 1,
+        });
+        world.add_rule(Conveyor {
+            position,
+            direction: Direction::East,
+        });
+
+        world.remove(door);
+        world.tick().unwrap();
+        assert!(world.position_state(position).is_empty());
+
+        world.remove(container);
+        world.tick().unwrap();
+        assert_eq!(world.ticks(), 2);
+    }
+}
@@ -0,0 +1,130 @@
+use super::history::Move;
+use super::world::World;
+use crate::{BlockKey, Position};
+use std::sync::Arc;
+
+/// The identifier of a trigger, see [World::add_trigger].
+#[derive(Copy, Clone, Eq, PartialEq, Hash, Debug)]
+pub struct TriggerId(u64);
+
+/// Whether a block enters or leaves the cell of a trigger.
+#[allow(missing_docs)]
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub enum TriggerKind {
+    Enter,
+    Leave,
+}
+
+/// An event passed to the callback of a trigger.
+#[derive(Copy, Clone, Eq, PartialEq, Debug)]
+pub struct TriggerEvent {
+    /// The fired trigger.
+    pub trigger: TriggerId,
+    /// The block entering or leaving the cell.
+    pub key: BlockKey,
+    /// The position of the cell.
+    pub position: Position,
+    /// Whether the block enters or leaves the cell.
+    pub kind: TriggerKind,
+    /// The turn of the movement.
+    pub turn: usize,
+}
+
+/// The callback of a trigger.
+pub type TriggerCallback = Arc<dyn Fn(&TriggerEvent) + Send + Sync>;
+
+#[derive(Clone)]
+pub(crate) struct Trigger {
+    id: TriggerId,
+    position: Position,
+    callback: TriggerCallback,
+}
+
+impl World {
+    /// Adds a trigger calling the callback whenever a block enters or leaves
+    /// the cell at the position, as part of a movement committed by
+    /// [World::push].
+    ///
+    /// The callbacks are called after all the movements of the push are
+    /// committed, in the order of the movements. Cloned worlds share the
+    /// callbacks.
+    pub fn add_trigger(
+        &mut self,
+        position: Position,
+        callback: impl Fn(&TriggerEvent) + Send + Sync + 'static,
+    ) -> TriggerId {
+        let id = TriggerId(self.next_trigger);
+        self.next_trigger += 1;
+        self.triggers.push(Trigger {
+            id,
+            position,
+            callback: Arc::new(callback),
+        });
+        id
+    }
+
+    /// Removes the trigger, returning whether it existed.
+    pub fn remove_trigger(&mut self, id: TriggerId) -> bool {
+        let len = self.triggers.len();
+        self.triggers.retain(|trigger| trigger.id != id);
+        self.triggers.len() != len
+    }
+
+    /// Fires the triggers for the committed movements.
+    pub(crate) fn fire_triggers(&self, moves: &[Move]) {
+        if self.triggers.is_empty() {
+            return;
+        }
+
+        for movement in moves {
+            let cells = [
+                (movement.from, TriggerKind::Leave),
+                (movement.to, TriggerKind::Enter),
+            ];
+
+            for (position, kind) in cells {
+                for trigger in self.triggers.iter().filter(|t| t.position == position) {
+                    (trigger.callback)(&TriggerEvent {
+                        trigger: trigger.id,
+                        key: movement.key,
+                        position,
+                        kind,
+                        turn: movement.turn,
+                    });
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Direction, ProtoType};
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_trigger() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (3, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let trigger = world.add_trigger(Position::inside(container, (1, 0)), {
+            let events = events.clone();
+            move |event| events.lock().unwrap().push(event.kind)
+        });
+
+        world.push(block, Direction::East).unwrap();
+        world.push(block, Direction::East).unwrap();
+        assert_eq!(
+            *events.lock().unwrap(),
+            vec![TriggerKind::Enter, TriggerKind::Leave]
+        );
+
+        assert!(world.remove_trigger(trigger));
+        world.push(block, Direction::West).unwrap();
+        assert_eq!(events.lock().unwrap().len(), 2);
+    }
+}
@@ -0,0 +1,194 @@
+use super::world::World;
+use crate::{BlockKey, Position, ProtoType};
+
+/// A violated invariant of a world, see [World::validate].
+#[derive(Clone, Eq, PartialEq, Debug)]
+pub enum Violation {
+    /// The block refers to a block that does not exist.
+    DanglingKey {
+        /// The referring block.
+        key: BlockKey,
+        /// The missing block.
+        missing: BlockKey,
+    },
+    /// The position of the block is out of the bounds of its container.
+    OutOfBounds {
+        /// The block.
+        key: BlockKey,
+        /// The position of the block.
+        position: Position,
+    },
+    /// The cell at the position of the block does not hold the block.
+    Detached {
+        /// The block.
+        key: BlockKey,
+        /// The position of the block.
+        position: Position,
+    },
+    /// The cell holds a block whose position is not the cell.
+    Misplaced {
+        /// The block in the cell.
+        key: BlockKey,
+        /// The position of the cell.
+        cell: Position,
+    },
+    /// The interior of the block does not match the size of its prototype.
+    InteriorSize {
+        /// The block.
+        key: BlockKey,
+    },
+    /// The reference information of the blocks is inconsistent with their
+    /// prototypes.
+    BrokenReference {
+        /// The referred block.
+        key: BlockKey,
+        /// The referring block.
+        referrer: BlockKey,
+    },
+}
+
+impl World {
+    /// Checks the invariants of the world, returning all the violations.
+    ///
+    /// The invariants are:
+    /// - every block referred to exists;
+    /// - the position of every block is in the bounds of its container, and
+    ///   the cell at the position holds the block;
+    /// - every block in a cell is positioned at the cell;
+    /// - the interior of every block matches its size;
+    /// - the [Info](crate::Info) of every block matches the prototypes of the
+    ///   blocks referring to it.
+    ///
+    /// A world modified only through the methods of [World] never has
+    /// violations, unless [World::place] is misused.
+    pub fn validate(&self) -> Vec<Violation> {
+        let mut violations = Vec::new();
+        let exists = |key: BlockKey| self.blocks.contains_key(key);
+
+        for (key, block) in self.blocks_ordered() {
+            let dangling = |violations: &mut Vec<Violation>, missing: BlockKey| {
+                let dangling = !exists(missing);
+                if dangling {
+                    violations.push(Violation::DanglingKey { key, missing });
+                }
+                dangling
+            };
+
+            // Check the references.
+            if let Some(reference) = block.proto.reference() {
+                if !dangling(&mut violations, reference) && !self.refers_back(reference, key) {
+                    violations.push(Violation::BrokenReference {
+                        key: reference,
+                        referrer: key,
+                    });
+                }
+            }
+
+            let info = &block.info;
+            for &referrer in info
+                .references
+                .iter()
+                .chain(&info.infinity)
+                .chain(&info.epsilon)
+            {
+                if !dangling(&mut violations, referrer)
+                    && self.blocks[referrer].proto.reference() != Some(key)
+                {
+                    violations.push(Violation::BrokenReference { key, referrer });
+                }
+            }
+
+            // Check the position.
+            let position = block.state.position;
+            if let Some(container) = position.container {
+                if dangling(&mut violations, container) {
+                    // Reported above.
+                } else if !self.blocks[container].proto.contains(position.pos) {
+                    violations.push(Violation::OutOfBounds { key, position });
+                } else {
+                    let (x, y) = position.pos;
+                    if self.blocks[container].state.interior[x][y] != Some(key) {
+                        violations.push(Violation::Detached { key, position });
+                    }
+                }
+            }
+
+            // Check the interior.
+            let (width, height) = block.proto.size();
+            let interior = &block.state.interior;
+            if interior.len() != width || interior.iter().any(|column| column.len() != height) {
+                violations.push(Violation::InteriorSize { key });
+                continue;
+            }
+
+            for (x, column) in interior.iter().enumerate() {
+                for (y, &cell) in column.iter().enumerate() {
+                    let Some(child) = cell else {
+                        continue;
+                    };
+
+                    let position = Position::inside(key, (x, y));
+                    if dangling(&mut violations, child) {
+                        // Reported above.
+                    } else if self.blocks[child].state.position != position {
+                        violations.push(Violation::Misplaced {
+                            key: child,
+                            cell: position,
+                        });
+                    }
+                }
+            }
+        }
+
+        violations
+    }
+
+    /// Whether the information of the referred block records the referrer.
+    fn refers_back(&self, key: BlockKey, referrer: BlockKey) -> bool {
+        let info = &self.blocks[key].info;
+        match self.blocks[referrer].proto {
+            ProtoType::Alias { .. } => info.references.contains(&referrer),
+            ProtoType::Infinity { .. } => info.infinity == Some(referrer),
+            ProtoType::Epsilon { .. } => info.epsilon == Some(referrer),
+            _ => false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_validate() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let block = world.insert(ProtoType::Box { size: (1, 1) });
+        let alias = world.insert(ProtoType::Alias { reference: block });
+        world.place(block, Position::inside(container, (0, 0)));
+        world.place(alias, Position::inside(block, (0, 0)));
+        assert_eq!(world.validate(), vec![]);
+
+        // Corrupt the world by hand.
+        world.blocks[container].state.interior[1][0] = Some(alias);
+        world.blocks[block].state.position = Position::inside(container, (5, 0));
+
+        assert_eq!(
+            world.validate(),
+            vec![
+                Violation::Misplaced {
+                    key: block,
+                    cell: Position::inside(container, (0, 0)),
+                },
+                Violation::Misplaced {
+                    key: alias,
+                    cell: Position::inside(container, (1, 0)),
+                },
+                Violation::OutOfBounds {
+                    key: block,
+                    position: Position::inside(container, (5, 0)),
+                },
+            ]
+        );
+    }
+}
@@ -1,12 +1,69 @@
-use crate::{Block, BlockKey, Position, ProtoType};
+use super::group::GroupId;
+use super::history::{Move, FNV_OFFSET};
+use super::tick::Rule;
+use super::trigger::Trigger;
+use crate::{
+    Block, BlockId, BlockKey, Direction, Interior, Orientation, OrphanPolicy, Position, ProtoType,
+    RuleConfig,
+};
 use slotmap::SlotMap;
+use std::collections::BTreeMap;
 use std::ops::Index;
+use std::sync::Arc;
 
 pub type Blocks = SlotMap<BlockKey, Block>;
 
 /// Controls the game world and implements the game logic.
+///
+/// # Thread safety
+///
+/// `World` is [Send] and [Sync], and cloning it produces a world with the same
+/// [BlockKey]s and [BlockId]s whose blocks, history and settings are
+/// independent of the original. This makes it possible to clone a world into
+/// multiple threads and explore different moves in parallel.
+///
+/// The [Rule]s added by [World::add_rule], the callbacks of the triggers added
+/// by [World::add_trigger], the filters set by [World::set_entry_filter] and
+/// the snapshots saved by [World::save_slot] are shared behind [Arc]s. So a
+/// rule or a callback with interior mutability, e.g. counting the events,
+/// observes all the clones.
+#[derive(Clone)]
 pub struct World {
     pub(crate) blocks: Blocks,
+    /// The keys of the blocks by their ids, i.e. in insertion order.
+    ids: BTreeMap<BlockId, BlockKey>,
+    /// The id of the next inserted block.
+    pub(crate) next_id: u64,
+    /// The current turn.
+    pub(crate) turn: usize,
+    /// The committed movements.
+    pub(crate) history: Vec<Move>,
+    /// The orientation of the y-axis.
+    pub(crate) orientation: Orientation,
+    /// The policy of exits from orphans.
+    pub(crate) orphan_policy: OrphanPolicy,
+    /// The root created by [OrphanPolicy::Promote].
+    pub(crate) root: Option<BlockKey>,
+    /// The enabled mechanics.
+    pub(crate) config: RuleConfig,
+    /// The triggers of cells.
+    pub(crate) triggers: Vec<Trigger>,
+    /// The id of the next added trigger.
+    pub(crate) next_trigger: u64,
+    /// The rules of the tick pipeline.
+    pub(crate) rules: Vec<Arc<dyn Rule>>,
+    /// The number of ticks advanced.
+    pub(crate) ticks: usize,
+    /// The members of the groups of linked blocks.
+    pub(crate) groups: BTreeMap<GroupId, Vec<BlockKey>>,
+    /// The id of the next linked group.
+    pub(crate) next_group: u64,
+    /// The layers of the stacked blocks by their bases, from the bottom up.
+    pub(crate) stacks: BTreeMap<BlockKey, Vec<BlockKey>>,
+    /// The running checksum of the committed movements.
+    pub(crate) checksum: u64,
+    /// The named snapshots of the world.
+    pub(crate) slots: BTreeMap<String, Arc<World>>,
 }
 
 impl World {
@@ -14,14 +71,44 @@ impl World {
     pub fn new() -> Self {
         Self {
             blocks: SlotMap::with_key(),
+            ids: BTreeMap::new(),
+            next_id: 0,
+            turn: 0,
+            history: Vec::new(),
+            orientation: Orientation::default(),
+            orphan_policy: OrphanPolicy::default(),
+            root: None,
+            config: RuleConfig::default(),
+            triggers: Vec::new(),
+            next_trigger: 0,
+            rules: Vec::new(),
+            ticks: 0,
+            groups: BTreeMap::new(),
+            next_group: 0,
+            stacks: BTreeMap::new(),
+            checksum: FNV_OFFSET,
+            slots: BTreeMap::new(),
         }
     }
 
     /// Inserts a block with the given prototype into the world, returning the
     /// key to the block.
     pub fn insert(&mut self, proto: ProtoType) -> BlockKey {
+        self.insert_with_id(BlockId::new(self.next_id), proto)
+    }
+
+    /// Inserts a block with the given id, which must not be taken.
+    ///
+    /// Used when restoring exported worlds, so that the ids are preserved.
+    pub(crate) fn insert_with_id(&mut self, id: BlockId, proto: ProtoType) -> BlockKey {
+        debug_assert!(!self.ids.contains_key(&id), "id {:?} is taken", id);
+
         // Insert the block and get the key.
-        let key = self.blocks.insert_with_key(|key| Block::new(key, proto));
+        self.next_id = self.next_id.max(id.get() + 1);
+        let key = self
+            .blocks
+            .insert_with_key(|key| Block::new(key, id, proto));
+        self.ids.insert(id, key);
 
         // Update reference relationships.
         match proto {
@@ -49,14 +136,28 @@ impl World {
             interior[x][y] = None;
         }
 
+        self.unlink(key);
+        self.unstack(key);
+
         // Remove the block
         let block = self.blocks.remove(key).unwrap();
+        self.ids.remove(&block.id);
+
+        // Remove the children, making them orphans.
+        for &child in block.state.interior.iter().flatten().flatten() {
+            self.blocks[child].state.position = Position::orphan();
+        }
 
-        // Remove the children
-        for row in block.state.interior {
-            for cell in row {
-                if let Some(child) = cell {
-                    self.place(child, Position::default());
+        // Remove the reference from the referred block.
+        if let Some(reference) = block.proto.reference() {
+            if let Some(referred) = self.blocks.get_mut(reference) {
+                let info = &mut referred.info;
+                info.references.remove(&key);
+                if info.infinity == Some(key) {
+                    info.infinity = None;
+                }
+                if info.epsilon == Some(key) {
+                    info.epsilon = None;
                 }
             }
         }
@@ -73,6 +174,92 @@ impl World {
         }
     }
 
+    /// Removes the block from the world, keeping the blocks referring to it.
+    ///
+    /// The alias with the smallest [BlockId] takes over the prototype and the
+    /// interior of the block, and the other referring blocks are re-pointed
+    /// to it. Without aliases, the infinity block is detached into a
+    /// [ProtoType::Wall], and the epsilon block into a [ProtoType::Box] of the
+    /// same size. Returns the alias taking over, if any.
+    pub(crate) fn crush(&mut self, key: BlockKey) -> Option<BlockKey> {
+        let info = &mut self.blocks[key].info;
+        let mut aliases = std::mem::take(&mut info.references);
+        let infinity = info.infinity.take();
+        let epsilon = info.epsilon.take();
+
+        let Some(successor) = aliases
+            .iter()
+            .copied()
+            .min_by_key(|&alias| self.blocks[alias].id)
+        else {
+            if let Some(infinity) = infinity {
+                self.blocks[infinity].proto = ProtoType::Wall;
+            }
+            if let Some(epsilon) = epsilon {
+                let size = self.blocks[epsilon].proto.size();
+                self.blocks[epsilon].proto = ProtoType::Box { size };
+            }
+            self.remove(key);
+            return None;
+        };
+        aliases.remove(&successor);
+
+        // Move the interior to the successor before removing the block, so
+        // that the children are not made orphans. The block leaves its
+        // container first, as it may be inside itself.
+        self.place(key, Position::orphan());
+        let proto = self.blocks[key].proto;
+        let interior =
+            std::mem::replace(&mut self.blocks[key].state.interior, Interior::new((0, 0)));
+        self.remove(key);
+        for &child in interior.iter().flatten().flatten() {
+            self.blocks[child].state.position.container = Some(successor);
+        }
+        let block = &mut self.blocks[successor];
+        block.proto = proto;
+        block.state.interior = interior;
+        block.info.two_way = false;
+
+        // Take over the reference of the block, if any.
+        match proto {
+            ProtoType::Alias { reference } => {
+                self.blocks[reference].info.references.insert(successor);
+            }
+            ProtoType::Infinity { reference } => {
+                self.blocks[reference].info.infinity = Some(successor);
+            }
+            ProtoType::Epsilon { reference, .. } => {
+                self.blocks[reference].info.epsilon = Some(successor);
+            }
+            _ => {}
+        }
+
+        // Re-point the other referring blocks to the successor.
+        for &alias in &aliases {
+            self.blocks[alias].proto = ProtoType::Alias {
+                reference: successor,
+            };
+        }
+        if let Some(infinity) = infinity {
+            self.blocks[infinity].proto = ProtoType::Infinity {
+                reference: successor,
+            };
+        }
+        if let Some(epsilon) = epsilon {
+            let size = self.blocks[epsilon].proto.size();
+            self.blocks[epsilon].proto = ProtoType::Epsilon {
+                size,
+                reference: successor,
+            };
+        }
+        let info = &mut self.blocks[successor].info;
+        info.references = aliases;
+        info.infinity = infinity;
+        info.epsilon = epsilon;
+
+        Some(successor)
+    }
+
     /// Places the block at the given position.
     pub fn place(&mut self, key: BlockKey, position: Position) {
         // Remove the block from its current position.
@@ -93,12 +280,150 @@ impl World {
         self.blocks[key].state.position = position;
     }
 
+    /// Sets the label of the block, used to name it in debugging output, i.e.
+    /// the [Display](std::fmt::Display) of the world and
+    /// [MoveError::display](crate::MoveError::display).
+    pub fn set_label(&mut self, key: BlockKey, label: impl Into<Arc<str>>) {
+        self.blocks[key].info.label = Some(label.into());
+    }
+
+    /// Returns the label of the block, if any.
+    pub fn label(&self, key: BlockKey) -> Option<&str> {
+        self.blocks[key].info.label.as_deref()
+    }
+
+    /// Sets whether the alias block forwards the exits of its reference.
+    ///
+    /// Blocks exiting the reference emerge from its two-way alias with the
+    /// smallest [BlockId] that is placed in the world, whether the reference
+    /// itself is placed or not. Without such an alias, they exit into the
+    /// container of the reference.
+    pub fn set_two_way(&mut self, key: BlockKey, two_way: bool) {
+        assert!(
+            matches!(self.blocks[key].proto, ProtoType::Alias { .. }),
+            "{:?} is not an alias",
+            key
+        );
+        self.blocks[key].info.two_way = two_way;
+    }
+
+    /// Returns whether the block is a two-way alias.
+    pub fn is_two_way(&self, key: BlockKey) -> bool {
+        self.blocks[key].info.two_way
+    }
+
+    /// Returns a copy of the world for exploring alternative moves.
+    ///
+    /// The interiors are shared between the two worlds, and only copied when
+    /// modified in either of them, so forking is much cheaper than a deep copy
+    /// when most containers do not change.
+    pub fn fork(&self) -> World {
+        self.clone()
+    }
+
+    /// Sets whether the side of the boundary of the block is a wall.
+    ///
+    /// Blocks cannot enter the block through a wall side, nor exit the block
+    /// through it. For example, a box with walls on all sides but north can
+    /// only be entered by pushing south.
+    pub fn set_side_wall(&mut self, key: BlockKey, side: Direction, wall: bool) {
+        let walls = &mut self.blocks[key].info.side_walls;
+        if wall {
+            walls.insert(side);
+        } else {
+            walls.remove(&side);
+        }
+    }
+
+    /// Returns whether the side of the boundary of the block is a wall.
+    pub fn has_side_wall(&self, key: BlockKey, side: Direction) -> bool {
+        self.blocks[key].info.side_walls.contains(&side)
+    }
+
+    /// Sets whether the block is fragile.
+    ///
+    /// A fragile block is removed from the world when it would be eaten, or
+    /// when its movement is blocked in a pushing cycle, instead of being
+    /// moved. The blocks referring to it are kept: its alias with the smallest
+    /// [BlockId] takes its place as the referred block.
+    pub fn set_fragile(&mut self, key: BlockKey, fragile: bool) {
+        self.blocks[key].info.fragile = fragile;
+    }
+
+    /// Returns whether the block is fragile.
+    pub fn is_fragile(&self, key: BlockKey) -> bool {
+        self.blocks[key].info.fragile
+    }
+
+    /// Returns the orientation of the y-axis.
+    pub fn orientation(&self) -> Orientation {
+        self.orientation
+    }
+
+    /// Sets the orientation of the y-axis, which determines the meaning of
+    /// [Direction::North](crate::Direction::North) for pushes and queries.
+    pub fn set_orientation(&mut self, orientation: Orientation) {
+        self.orientation = orientation;
+    }
+
+    /// Returns the policy of exits from orphans.
+    pub fn orphan_policy(&self) -> OrphanPolicy {
+        self.orphan_policy
+    }
+
+    /// Returns the policy of exits from orphans in the direction, where
+    /// [OrphanPolicy::Promote] keeps the block in place in the depth
+    /// directions, since the root has no layers to exit into.
+    pub(crate) fn orphan_policy_for(&self, direction: Direction) -> OrphanPolicy {
+        match self.orphan_policy {
+            OrphanPolicy::Promote if direction.is_depth() => OrphanPolicy::Stay,
+            policy => policy,
+        }
+    }
+
+    /// Sets the policy of exits from orphans, see [OrphanPolicy].
+    pub fn set_orphan_policy(&mut self, policy: OrphanPolicy) {
+        self.orphan_policy = policy;
+    }
+
+    /// Returns the enabled mechanics.
+    pub fn rule_config(&self) -> RuleConfig {
+        self.config
+    }
+
+    /// Sets the enabled mechanics, see [RuleConfig].
+    pub fn set_rule_config(&mut self, config: RuleConfig) {
+        self.config = config;
+    }
+
     /// Returns a reference to the blocks in the world.
     ///
     /// The blocks are stored by [slotmap::SlotMap].
     pub fn blocks(&self) -> &Blocks {
         &self.blocks
     }
+
+    /// Returns an iterator over the blocks in the world in insertion order.
+    ///
+    /// Unlike [World::blocks], the order does not depend on the internals of
+    /// [slotmap::SlotMap], so it is stable across runs and suitable for
+    /// formatting and serialization.
+    pub fn blocks_ordered(&self) -> impl Iterator<Item = (BlockKey, &Block)> + '_ {
+        self.ids.values().map(|&key| (key, &self.blocks[key]))
+    }
+
+    /// Returns the key of the block with the given id, if it still exists.
+    ///
+    /// See [BlockId] for more information.
+    pub fn key_of(&self, id: BlockId) -> Option<BlockKey> {
+        self.ids.get(&id).copied()
+    }
+}
+
+impl Default for World {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Index<BlockKey> for World {
@@ -131,6 +456,113 @@ mod tests {
         assert_eq!(world.blocks.len(), 0);
     }
 
+    #[test]
+    fn test_blocks_ordered() {
+        let mut world = World::new();
+        let first = world.insert(ProtoType::Wall);
+        let second = world.insert(ProtoType::Box { size: (1, 1) });
+        let third = world.insert(ProtoType::Wall);
+        world.remove(second);
+        let fourth = world.insert(ProtoType::Wall);
+
+        let keys = world
+            .blocks_ordered()
+            .map(|(key, _)| key)
+            .collect::<Vec<_>>();
+        assert_eq!(keys, vec![first, third, fourth]);
+    }
+
+    #[test]
+    fn test_send_sync() {
+        fn assert_send_sync<T: Send + Sync>() {}
+
+        assert_send_sync::<World>();
+        assert_send_sync::<Block>();
+        assert_send_sync::<crate::Info>();
+        assert_send_sync::<crate::MoveError>();
+    }
+
+    #[test]
+    fn test_clone() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let mut cloned = world.clone();
+        cloned.place(block, Position::inside(container, (1, 0)));
+
+        assert_eq!(world.position(block), Position::inside(container, (0, 0)));
+        assert_eq!(cloned.position(block), Position::inside(container, (1, 0)));
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (2, 1) });
+        let other = world.insert(ProtoType::Box { size: (1, 1) });
+        let block = world.insert(ProtoType::Wall);
+        world.place(block, Position::inside(container, (0, 0)));
+
+        let mut forked = world.fork();
+        let interior = |world: &World, key: BlockKey| world[key].state.interior.clone();
+        assert!(interior(&world, container).is_shared_with(&interior(&forked, container)));
+
+        forked.place(block, Position::inside(container, (1, 0)));
+        assert!(!interior(&world, container).is_shared_with(&interior(&forked, container)));
+        assert!(interior(&world, other).is_shared_with(&interior(&forked, other)));
+        assert_eq!(world[container].state.interior[0][0], Some(block));
+        assert_eq!(forked[container].state.interior[1][0], Some(block));
+    }
+
+    #[test]
+    fn test_label() {
+        let mut world = World::new();
+        let key = world.insert(ProtoType::Wall);
+        assert_eq!(world.label(key), None);
+
+        world.set_label(key, "wall");
+        assert_eq!(world.label(key), Some("wall"));
+
+        let context = crate::MoveContext {
+            origin: key,
+            direction: crate::Direction::East,
+            position: Position::orphan(),
+        };
+        let error = crate::MoveError::Orphan(key, context);
+        let message = error.display(&world).to_string();
+        assert!(message.starts_with("cannot exit the orphan \"wall\" (pushing \"wall\" East"));
+    }
+
+    #[test]
+    fn test_remove_container() {
+        let mut world = World::new();
+        let container = world.insert(ProtoType::Box { size: (1, 1) });
+        let child = world.insert(ProtoType::Wall);
+        world.place(child, Position::inside(container, (0, 0)));
+
+        world.remove(container);
+
+        assert!(world.position(child).is_orphan());
+    }
+
+    #[test]
+    fn test_key_of() {
+        let mut world = World::new();
+        let first = world.insert(ProtoType::Wall);
+        let second = world.insert(ProtoType::Wall);
+        let (first_id, second_id) = (world[first].id, world[second].id);
+        assert!(first_id < second_id);
+
+        world.remove(first);
+        let third = world.insert(ProtoType::Wall);
+
+        assert_eq!(world.key_of(first_id), None);
+        assert_eq!(world.key_of(second_id), Some(second));
+        assert_eq!(world.key_of(world[third].id), Some(third));
+        assert_ne!(world[third].id, first_id);
+    }
+
     #[test]
     fn test_place() {
         let mut world = World::new();